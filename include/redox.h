@@ -0,0 +1,46 @@
+/*
+ * C API of redox, the Rust to Iron transpiler
+ *
+ * Link against the library built with `cargo build --release --features capi`
+ * (libredox.so, libredox.dylib, or redox.dll).
+ *
+ * Sources are UTF-8 of the given length and need not end in NUL. On return,
+ * *output is a UTF-8 buffer of *output_len bytes followed by a NUL: the converted
+ * code for REDOX_OK, or the error message for any other code except
+ * REDOX_INVALID_ARGUMENT and REDOX_PANIC, which leave *output NULL. Pass every
+ * buffer back to redox_free with its length.
+ */
+
+#ifndef REDOX_H
+#define REDOX_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define REDOX_OK 0
+#define REDOX_PARSE_ERROR 1
+#define REDOX_UNSUPPORTED_SYNTAX 2
+#define REDOX_INTERNAL_ERROR 3
+#define REDOX_INVALID_ARGUMENT 4
+#define REDOX_PANIC 5
+
+/* Reduce Rust source to Iron */
+int32_t redox_transpile(const uint8_t *source, size_t source_len,
+                        uint8_t **output, size_t *output_len);
+
+/* Oxidize Iron source to Rust */
+int32_t redox_oxidize(const uint8_t *source, size_t source_len,
+                      uint8_t **output, size_t *output_len);
+
+/* Free a buffer returned by redox_transpile or redox_oxidize; NULL is ignored */
+void redox_free(uint8_t *buffer, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* REDOX_H */
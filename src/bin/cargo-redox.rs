@@ -0,0 +1,183 @@
+//! `cargo redox` - reduce a whole crate to an Iron mirror
+//!
+//! Reads `cargo metadata`, follows the `mod` declarations of every target from its root
+//! file, and writes the Iron of each source file to the same relative path under the
+//! output directory, along with a manifest recording the mapping.
+
+use clap::{Args, Parser};
+use redox::ReduceOptions;
+use redox::crate_tree::{CrateFile, MANIFEST};
+use serde_json::{Value, json};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+#[derive(Parser)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum Cargo {
+    /// Reduce every target of a crate or workspace to Iron
+    Redox(RedoxArgs),
+}
+
+#[derive(Args)]
+#[command(version = "0.1.0")]
+struct RedoxArgs {
+    /// Path to the Cargo.toml of the crate or workspace
+    #[arg(long, value_name = "PATH")]
+    manifest_path: Option<PathBuf>,
+
+    /// Directory the Iron mirror is written to (default: `iron` in the target directory)
+    #[arg(long, value_name = "DIR")]
+    out_dir: Option<PathBuf>,
+}
+
+/// A build target of a package, such as its library or one of its binaries
+#[derive(Clone)]
+struct Target {
+    package: String,
+    name: String,
+}
+
+/// Run `cargo metadata` for the workspace, without resolving dependencies
+fn cargo_metadata(manifest_path: Option<&Path>) -> Result<Value, Box<dyn std::error::Error>> {
+    // Cargo tells subcommands which cargo ran them
+    let cargo = std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+    let mut command = Command::new(cargo);
+    command.args(["metadata", "--no-deps", "--format-version", "1"]);
+    if let Some(path) = manifest_path {
+        command.arg("--manifest-path").arg(path);
+    }
+
+    let output = command
+        .output()
+        .map_err(|e| format!("Failed to run cargo metadata: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+fn reduce_crate(args: RedoxArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let metadata = cargo_metadata(args.manifest_path.as_deref())?;
+    let workspace_root = PathBuf::from(
+        metadata["workspace_root"]
+            .as_str()
+            .ok_or("cargo metadata has no workspace root")?,
+    );
+    let out_dir = match args.out_dir {
+        Some(dir) => dir,
+        None => Path::new(
+            metadata["target_directory"]
+                .as_str()
+                .ok_or("cargo metadata has no target directory")?,
+        )
+        .join("iron"),
+    };
+
+    // A file shared by several targets is recorded under the first
+    let mut modules: BTreeMap<PathBuf, (Target, CrateFile)> = BTreeMap::new();
+    let mut failures: BTreeMap<PathBuf, String> = BTreeMap::new();
+    let options = ReduceOptions::default();
+    for package in metadata["packages"].as_array().into_iter().flatten() {
+        let package_name = package["name"].as_str().unwrap_or_default();
+        for target in package["targets"].as_array().into_iter().flatten() {
+            let (Some(name), Some(root)) = (target["name"].as_str(), target["src_path"].as_str())
+            else {
+                continue;
+            };
+            let target = Target {
+                package: package_name.to_string(),
+                name: name.to_string(),
+            };
+            let reduced = match redox::transpile_crate(root, &options) {
+                Ok(reduced) => reduced,
+                Err(e) => {
+                    failures.entry(PathBuf::from(root)).or_insert(e.to_string());
+                    continue;
+                }
+            };
+            for file in reduced.files {
+                let path = reduced.root.join(&file.source);
+                if !failures.contains_key(&path) {
+                    modules.entry(path).or_insert((target.clone(), file));
+                }
+            }
+            for failure in reduced.failures {
+                let path = reduced.root.join(&failure.source);
+                if !modules.contains_key(&path) {
+                    failures.entry(path).or_insert(failure.error);
+                }
+            }
+        }
+    }
+
+    // Paths in the mirror and the manifest are relative to the workspace root
+    let relative = |path: &Path| {
+        path.strip_prefix(&workspace_root)
+            .unwrap_or(path)
+            .to_path_buf()
+    };
+    let failures: Vec<Value> = failures
+        .iter()
+        .map(|(path, error)| json!({ "source": relative(path), "error": error }))
+        .collect();
+    let mut files = Vec::new();
+    for (path, (target, file)) in &modules {
+        let source = relative(path);
+        let iron = source.with_extension("iron");
+        let output = out_dir.join(&iron);
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create directory '{}': {}", parent.display(), e))?;
+        }
+        fs::write(&output, &file.iron)
+            .map_err(|e| format!("Failed to write output file '{}': {}", output.display(), e))?;
+        files.push(json!({
+            "source": source,
+            "iron": iron,
+            "package": target.package,
+            "target": target.name,
+            "module": file.module,
+        }));
+    }
+
+    for failure in &failures {
+        eprintln!(
+            "Error: {}: {}",
+            failure["source"].as_str().unwrap_or_default(),
+            failure["error"].as_str().unwrap_or_default()
+        );
+    }
+
+    let manifest = json!({
+        "workspace_root": workspace_root,
+        "files": files,
+        "failures": failures,
+    });
+    fs::create_dir_all(&out_dir)?;
+    fs::write(
+        out_dir.join(MANIFEST),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    eprintln!(
+        "Reduced {} of {} files to {}",
+        files.len(),
+        files.len() + failures.len(),
+        out_dir.display()
+    );
+    Ok(())
+}
+
+fn main() {
+    let Cargo::Redox(args) = Cargo::parse();
+
+    if let Err(e) = reduce_crate(args) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
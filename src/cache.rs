@@ -0,0 +1,417 @@
+//! Caching the Iron of unchanged items
+//!
+//! Reducing a large crate again mostly reduces items that have not changed. With a
+//! [`CacheProvider`] in [`crate::ReduceOptions::cache`], each top-level item's Iron is
+//! stored under a key hashing its tokens, and an item whose key is found is not reduced
+//! again.
+//!
+//! The key covers the item's tokens and where they sit relative to the first, the redox
+//! version, and the settings that change an item's Iron: the fallback policy, verbosity,
+//! indentation, line width, and layout. It does not cover mapping hooks, so each hook
+//! configuration needs a cache of its own.
+//!
+//! An entry keeps what reducing the item recorded along with its Iron: the origins of
+//! its lines, the items in it carried as verbatim Rust, the names renamed in it, and the
+//! names it declares at file scope. A
+//! hit replays them, with positions moved to where the item now starts, so a parser's
+//! [`origins`](crate::parser::IronParser::origins),
+//! [`verbatim_items`](crate::parser::IronParser::verbatim_items), and
+//! [`symbols`](crate::parser::IronParser::symbols) are the same with or without a cache.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::ToTokens;
+
+use crate::emitter::Origin;
+use crate::header::hash;
+use crate::{FallbackPolicy, Indentation, LayoutPolicy, Verbosity};
+
+/// The first line of an entry that records more than the Iron; an entry without it is
+/// Iron alone
+const ENTRY_HEADER: &str = "redox cache entry";
+
+/// Storage for the Iron of reduced items, shared by concurrent reductions
+pub trait CacheProvider: Send + Sync {
+    /// The entry stored under `key`, if any
+    fn get(&self, key: &str) -> Option<String>;
+
+    /// Store `entry` under `key`; failing to store only costs a later reduction
+    fn put(&self, key: &str, entry: &str);
+}
+
+/// The cache key of a top-level item
+pub fn cache_key(
+    item: &syn::Item,
+    fallback: FallbackPolicy,
+    verbosity: Verbosity,
+    indentation: Indentation,
+    max_width: Option<usize>,
+    layout: LayoutPolicy,
+) -> String {
+    let tokens = item.to_token_stream();
+    let mut positions = String::new();
+    write_layout(tokens.clone(), &mut None, &mut positions);
+    let tokens = format!("{}\n{}", tokens, positions);
+    format!(
+        "{}{}",
+        hash(&format!(
+            "redox {} {} {} {} {:?} {:?}",
+            env!("CARGO_PKG_VERSION"),
+            fallback,
+            verbosity,
+            indentation,
+            max_width,
+            layout
+        )),
+        hash(&tokens)
+    )
+}
+
+/// Write where each of `tokens` starts relative to `first`, the start of the first
+fn write_layout(tokens: TokenStream, first: &mut Option<Position>, layout: &mut String) {
+    for token in tokens {
+        let start = match &token {
+            TokenTree::Group(group) => group.span_open().start(),
+            token => token.span().start(),
+        };
+        let first = *first.get_or_insert((start.line, start.column + 1));
+        let (line, column) = relative(first, (start.line, start.column + 1));
+        let _ = write!(layout, "{}:{} ", line, column);
+        if let TokenTree::Group(group) = token {
+            write_layout(group.stream(), &mut Some(first), layout);
+            let end = group.span_close().start();
+            let (line, column) = relative(first, (end.line, end.column + 1));
+            let _ = write!(layout, "{}:{} ", line, column);
+        }
+    }
+}
+
+/// A (line, 1-based column) in Rust source
+pub(crate) type Position = (usize, usize);
+
+/// `position` relative to `start`: the column is counted
+/// from `start`'s on its line and kept as is on later lines
+pub(crate) fn relative(start: Position, position: Position) -> Position {
+    match position.0 == start.0 {
+        true => (0, position.1 - start.1),
+        false => (position.0 - start.0, position.1),
+    }
+}
+
+/// The position that is `position` relative to `start`
+pub(crate) fn absolute(start: Position, position: Position) -> Position {
+    match position.0 {
+        0 => (start.0, start.1 + position.1),
+        line => (start.0 + line, position.1),
+    }
+}
+
+/// What reducing an item gave, with positions relative to where the item starts
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct CachedItem {
+    /// The item's Iron, with the blank lines after it
+    pub iron: String,
+    /// Each origin taking effect in the Iron, at its byte offset there
+    pub origins: Vec<(usize, Origin)>,
+    /// Where each item carried as verbatim Rust starts
+    pub verbatim_items: Vec<Position>,
+    /// The Rust spelling of each name renamed in the Iron
+    pub renamed: Vec<String>,
+    /// The Rust spelling of each name the item declares in the scope around it
+    pub declared: Vec<String>,
+}
+
+impl CachedItem {
+    /// The text stored for the entry: the header, a line for each record, `iron`, and
+    /// the Iron
+    pub fn encode(&self) -> String {
+        let mut entry = format!("{}\n", ENTRY_HEADER);
+        for (offset, origin) in &self.origins {
+            let _ = writeln!(
+                entry,
+                "origin {} {} {} {} {} {}",
+                offset,
+                origin.line,
+                origin.column,
+                origin.end_line,
+                origin.end_column,
+                origin.construct
+            );
+        }
+        for (line, column) in &self.verbatim_items {
+            let _ = writeln!(entry, "verbatim {} {}", line, column);
+        }
+        for name in &self.renamed {
+            let _ = writeln!(entry, "renamed {}", name);
+        }
+        for name in &self.declared {
+            let _ = writeln!(entry, "declared {}", name);
+        }
+        entry.push_str("iron\n");
+        entry.push_str(&self.iron);
+        entry
+    }
+
+    /// Read back an entry made by [`Self::encode`], or Iron stored alone; `None` if the
+    /// records cannot be read, so the item is reduced again
+    pub fn decode(entry: &str) -> Option<CachedItem> {
+        let Some(mut rest) = entry
+            .strip_prefix(ENTRY_HEADER)
+            .and_then(|rest| rest.strip_prefix('\n'))
+        else {
+            return Some(CachedItem {
+                iron: entry.to_string(),
+                ..Default::default()
+            });
+        };
+        let mut item = CachedItem::default();
+        loop {
+            let (line, next) = rest.split_once('\n')?;
+            rest = next;
+            let (record, fields) = line.split_once(' ').unwrap_or((line, ""));
+            match record {
+                "iron" => break,
+                "origin" => {
+                    let mut fields = fields.splitn(6, ' ');
+                    let mut number = || fields.next()?.parse::<usize>().ok();
+                    let (offset, line, column, end_line, end_column) =
+                        (number()?, number()?, number()?, number()?, number()?);
+                    let origin = Origin {
+                        line,
+                        column,
+                        end_line,
+                        end_column,
+                        construct: fields.next()?.to_string(),
+                    };
+                    item.origins.push((offset, origin));
+                }
+                "verbatim" => {
+                    let (line, column) = fields.split_once(' ')?;
+                    item.verbatim_items
+                        .push((line.parse().ok()?, column.parse().ok()?));
+                }
+                "renamed" => item.renamed.push(fields.to_string()),
+                "declared" => item.declared.push(fields.to_string()),
+                _ => return None,
+            }
+        }
+        item.iron = rest.to_string();
+        Some(item)
+    }
+}
+
+/// A cache held in memory, for reducing many sources in one process
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, String>>,
+}
+
+impl MemoryCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of items stored
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    /// Whether no items are stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheProvider for MemoryCache {
+    fn get(&self, key: &str) -> Option<String> {
+        self.entries.lock().unwrap().get(key).cloned()
+    }
+
+    fn put(&self, key: &str, entry: &str) {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), entry.to_string());
+    }
+}
+
+/// A cache of one file per item in a directory, kept between runs
+#[derive(Debug, Clone)]
+pub struct DirectoryCache {
+    dir: PathBuf,
+}
+
+impl DirectoryCache {
+    /// Cache in `dir`, which is created when the first item is stored
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        DirectoryCache { dir: dir.into() }
+    }
+}
+
+impl CacheProvider for DirectoryCache {
+    fn get(&self, key: &str) -> Option<String> {
+        std::fs::read_to_string(self.dir.join(format!("{}.iron", key))).ok()
+    }
+
+    fn put(&self, key: &str, entry: &str) {
+        // Written aside and renamed, so a concurrent reader never sees part of an entry
+        let thread: String = format!("{:?}", std::thread::current().id())
+            .chars()
+            .filter(char::is_ascii_digit)
+            .collect();
+        let temporary = self
+            .dir
+            .join(format!("{}.{}.{}.tmp", key, std::process::id(), thread));
+        let stored = std::fs::create_dir_all(&self.dir)
+            .and_then(|()| std::fs::write(&temporary, entry))
+            .and_then(|()| std::fs::rename(&temporary, self.dir.join(format!("{}.iron", key))));
+        if stored.is_err() {
+            let _ = std::fs::remove_file(&temporary);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ReduceOptions;
+    use crate::parser::IronParser;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_cache_reuses_unchanged_items() {
+        let cache = Arc::new(MemoryCache::new());
+        let options = ReduceOptions {
+            cache: Some(cache.clone()),
+            ..Default::default()
+        };
+        let source = "fn one() -> i32 {\n    1\n}\n\nimpl Point {}\n\nconst TWO: i32 = 2;\n";
+        let iron = crate::transpile_with_options(source, &options).unwrap();
+        assert_eq!(iron, crate::transpile(source).unwrap());
+        assert_eq!(cache.len(), 3);
+
+        // Only the changed item is reduced and stored again
+        let edited = source.replace("TWO: i32 = 2", "TWO: i32 = 3");
+        let iron = crate::transpile_with_options(&edited, &options).unwrap();
+        assert_eq!(iron, crate::transpile(&edited).unwrap());
+        assert_eq!(cache.len(), 4);
+
+        // A stored item is used without reducing it
+        let file = syn::parse_file(source).unwrap();
+        cache.put(
+            &cache_key(
+                &file.items[0],
+                FallbackPolicy::Verbatim,
+                Verbosity::Standard,
+                Indentation::default(),
+                None,
+                LayoutPolicy::default(),
+            ),
+            "function cached\nbegin\nend function\n\n",
+        );
+        let iron = crate::transpile_with_options(source, &options).unwrap();
+        assert!(iron.starts_with("function cached\n"));
+        let key = |fallback, verbosity, indentation| {
+            cache_key(
+                &file.items[0],
+                fallback,
+                verbosity,
+                indentation,
+                None,
+                LayoutPolicy::default(),
+            )
+        };
+        let standard = key(
+            FallbackPolicy::Verbatim,
+            Verbosity::Standard,
+            Indentation::default(),
+        );
+        assert_ne!(
+            standard,
+            key(
+                FallbackPolicy::Error,
+                Verbosity::Standard,
+                Indentation::default()
+            )
+        );
+        assert_ne!(
+            standard,
+            key(
+                FallbackPolicy::Verbatim,
+                Verbosity::Terse,
+                Indentation::default()
+            )
+        );
+        assert_ne!(
+            standard,
+            key(
+                FallbackPolicy::Verbatim,
+                Verbosity::Standard,
+                Indentation::Tab
+            )
+        );
+    }
+
+    #[test]
+    fn test_cache_hits_replay_what_the_item_recorded() {
+        let cache = Arc::new(MemoryCache::new());
+        let sources = [
+            "fn begin(end: i32) -> i32 {\n    end\n}\n\nimpl Point {}\n",
+            "const ONE: i32 = 1;\n\nfn begin(end: i32) -> i32 {\n    end\n}\n\nconst TWO: i32 = 2; impl Point {}\n",
+        ];
+        for source in sources {
+            let file = syn::parse_file(source).unwrap();
+            let mut cached = IronParser::new().with_cache(Some(cache.clone()));
+            let mut plain = IronParser::new();
+            assert_eq!(cached.parse_file(&file), plain.parse_file(&file));
+            assert_eq!(cached.origins(), plain.origins());
+            assert_eq!(cached.verbatim_items(), plain.verbatim_items());
+            assert_eq!(cached.symbols().renamings(), plain.symbols().renamings());
+            assert!(cached.symbols().is_declared("begin"));
+        }
+        // The second source found both moved items
+        assert_eq!(cache.len(), 4);
+
+        // An item laid out differently has other origins, so it is reduced again
+        let key = |source| {
+            let file = syn::parse_file(source).unwrap();
+            cache_key(
+                &file.items[0],
+                FallbackPolicy::Verbatim,
+                Verbosity::Standard,
+                Indentation::default(),
+                None,
+                LayoutPolicy::default(),
+            )
+        };
+        assert_eq!(key("fn f() {}"), key("\n\n  fn f() {}"));
+        assert_ne!(key("fn f() {}"), key("fn f() {\n}"));
+
+        assert_eq!(
+            CachedItem::decode("redox cache entry\norigin 0\niron\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_directory_cache_persists_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = DirectoryCache::new(dir.path().join("cache"));
+        assert_eq!(cache.get("key"), None);
+        cache.put("key", "function f\nbegin\nend function\n\n");
+        let reopened = DirectoryCache::new(dir.path().join("cache"));
+        assert_eq!(
+            reopened.get("key").as_deref(),
+            Some("function f\nbegin\nend function\n\n")
+        );
+        assert_eq!(
+            std::fs::read_dir(dir.path().join("cache")).unwrap().count(),
+            1
+        );
+    }
+}
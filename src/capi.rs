@@ -0,0 +1,236 @@
+//! C API, behind the `capi` feature
+//!
+//! Lets programs in other languages (C++, Go through cgo) embed the transpiler through
+//! the library's cdylib. The declarations are in `include/redox.h`:
+//!
+//! ```c
+//! uint8_t *out;
+//! size_t out_len;
+//! int32_t code = redox_transpile(src, src_len, &out, &out_len);
+//! // code is REDOX_OK and out the Iron, or an error code and out its message
+//! redox_free(out, out_len);
+//! ```
+//!
+//! Input is UTF-8 of the given length, which need not end in NUL. Output is a UTF-8
+//! buffer owned by the caller until passed to [`redox_free`]; it ends in a NUL not
+//! counted in its length, so it can also be read as a C string.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use crate::TranspileError;
+
+/// Success: the output holds the converted code
+pub const REDOX_OK: i32 = 0;
+/// The source does not parse; the output holds the message
+pub const REDOX_PARSE_ERROR: i32 = 1;
+/// The source has no translation; the output holds the message
+pub const REDOX_UNSUPPORTED_SYNTAX: i32 = 2;
+/// The transpiler failed; the output holds the message
+pub const REDOX_INTERNAL_ERROR: i32 = 3;
+/// A pointer is null or the source is not UTF-8; there is no output
+pub const REDOX_INVALID_ARGUMENT: i32 = 4;
+/// The transpiler panicked, which is a bug in it; there is no output
+pub const REDOX_PANIC: i32 = 5;
+
+/// Reduce Rust source to Iron
+///
+/// Returns [`REDOX_OK`] with the Iron in `*output`, or an error code with the error
+/// message in `*output`. Either way `*output` and `*output_len` must later be passed to
+/// [`redox_free`], except after [`REDOX_INVALID_ARGUMENT`] or [`REDOX_PANIC`], which
+/// leave `*output` null.
+///
+/// # Safety
+///
+/// `source` must point to `source_len` readable bytes, and `output` and `output_len`
+/// must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn redox_transpile(
+    source: *const u8,
+    source_len: usize,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> i32 {
+    // SAFETY: the caller upholds this function's contract, which is `convert`'s
+    catch_panics(|| unsafe { convert(source, source_len, output, output_len, crate::transpile) })
+}
+
+/// Oxidize Iron source to Rust
+///
+/// Returns and fills `*output` as [`redox_transpile`] does.
+///
+/// # Safety
+///
+/// As for [`redox_transpile`].
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn redox_oxidize(
+    source: *const u8,
+    source_len: usize,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+) -> i32 {
+    // SAFETY: the caller upholds this function's contract, which is `convert`'s
+    catch_panics(|| unsafe { convert(source, source_len, output, output_len, crate::oxidize) })
+}
+
+/// Free a buffer returned by [`redox_transpile`] or [`redox_oxidize`]; null is ignored
+///
+/// # Safety
+///
+/// `buffer` must be null or a buffer from this library with the length returned
+/// alongside it, not yet freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn redox_free(buffer: *mut u8, len: usize) {
+    if buffer.is_null() {
+        return;
+    }
+    catch_panics(|| {
+        // SAFETY: the buffer was leaked by `into_buffer` as a boxed slice of `len` bytes
+        // and its NUL
+        drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(buffer, len + 1)) });
+        REDOX_OK
+    });
+}
+
+/// Run the body of an entry point, returning [`REDOX_PANIC`] if it panics, since a
+/// panic must not unwind into C
+fn catch_panics(body: impl FnOnce() -> i32) -> i32 {
+    panic::catch_unwind(AssertUnwindSafe(body)).unwrap_or(REDOX_PANIC)
+}
+
+/// The code returned for an error, by its [`TranspileError::code`]
+fn error_code(error: &TranspileError) -> i32 {
+    match error.code() {
+        "parse-error" => REDOX_PARSE_ERROR,
+        "unsupported-syntax" => REDOX_UNSUPPORTED_SYNTAX,
+        _ => REDOX_INTERNAL_ERROR,
+    }
+}
+
+/// # Safety
+///
+/// `source` must point to `source_len` readable bytes, and `output` and `output_len`
+/// must be valid for writes.
+unsafe fn convert(
+    source: *const u8,
+    source_len: usize,
+    output: *mut *mut u8,
+    output_len: *mut usize,
+    convert: fn(&str) -> Result<String, TranspileError>,
+) -> i32 {
+    if output.is_null() || output_len.is_null() {
+        return REDOX_INVALID_ARGUMENT;
+    }
+    // SAFETY: both checked non-null, and the caller guarantees they are writable
+    unsafe {
+        *output = ptr::null_mut();
+        *output_len = 0;
+    }
+    if source.is_null() {
+        return REDOX_INVALID_ARGUMENT;
+    }
+    // SAFETY: the caller guarantees `source_len` readable bytes at `source`
+    let bytes = unsafe { std::slice::from_raw_parts(source, source_len) };
+    let Ok(source) = std::str::from_utf8(bytes) else {
+        return REDOX_INVALID_ARGUMENT;
+    };
+
+    let (code, text) = match convert(source) {
+        Ok(converted) => (REDOX_OK, converted),
+        Err(error) => (error_code(&error), error.to_string()),
+    };
+    let len = text.len();
+    // SAFETY: checked non-null above, and the caller guarantees they are writable
+    unsafe {
+        *output = into_buffer(text);
+        *output_len = len;
+    }
+    code
+}
+
+/// Leak `text` as a NUL-terminated buffer for C, freed by [`redox_free`]
+fn into_buffer(text: String) -> *mut u8 {
+    let mut bytes = text.into_bytes();
+    bytes.push(0);
+    Box::into_raw(bytes.into_boxed_slice()).cast()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(
+        function: unsafe extern "C" fn(*const u8, usize, *mut *mut u8, *mut usize) -> i32,
+        source: &[u8],
+    ) -> (i32, Option<String>) {
+        let mut output = ptr::null_mut();
+        let mut output_len = 0;
+        // SAFETY: the source is a live slice and the outputs are locals
+        let code = unsafe { function(source.as_ptr(), source.len(), &mut output, &mut output_len) };
+        if output.is_null() {
+            return (code, None);
+        }
+        // SAFETY: the library returned this buffer with this length, plus its NUL
+        let text = unsafe { std::slice::from_raw_parts(output, output_len + 1) };
+        assert_eq!(text.last(), Some(&0));
+        let text = String::from_utf8(text[..output_len].to_vec()).unwrap();
+        // SAFETY: freed once, with the length returned alongside it
+        unsafe { redox_free(output, output_len) };
+        (code, Some(text))
+    }
+
+    #[test]
+    fn test_c_api_round_trip_and_errors() {
+        let (code, iron) = call(redox_transpile, b"fn one() -> i32 {\n    1\n}\n");
+        assert_eq!(code, REDOX_OK);
+        let iron = iron.unwrap();
+        assert!(iron.starts_with("function one"));
+
+        let (code, rust) = call(redox_oxidize, iron.as_bytes());
+        assert_eq!(code, REDOX_OK);
+        assert!(rust.unwrap().starts_with("fn one() -> i32"));
+
+        let (code, message) = call(redox_transpile, b"fn main( {}");
+        assert_eq!(code, REDOX_PARSE_ERROR);
+        assert!(message.unwrap().starts_with("Parse error: line 1"));
+
+        let (code, message) = call(
+            redox_oxidize,
+            b"function f\n    takes x of unknown\nbegin\nend function",
+        );
+        assert_eq!(code, REDOX_UNSUPPORTED_SYNTAX);
+        assert!(message.is_some());
+
+        assert_eq!(
+            call(redox_transpile, b"\xff"),
+            (REDOX_INVALID_ARGUMENT, None)
+        );
+        // SAFETY: null pointers are rejected before anything is read or written
+        let code = unsafe { redox_transpile(ptr::null(), 0, ptr::null_mut(), ptr::null_mut()) };
+        assert_eq!(code, REDOX_INVALID_ARGUMENT);
+        // SAFETY: null is ignored
+        unsafe { redox_free(ptr::null_mut(), 0) };
+    }
+
+    #[test]
+    fn test_c_api_panic_does_not_unwind() {
+        let mut output = ptr::null_mut();
+        let mut output_len = 0;
+        let source = b"fn main() {}";
+        let code = catch_panics(|| {
+            // SAFETY: the source is a live slice and the outputs are locals
+            unsafe {
+                convert(
+                    source.as_ptr(),
+                    source.len(),
+                    &mut output,
+                    &mut output_len,
+                    |_| panic!("conversion bug"),
+                )
+            }
+        });
+        assert_eq!(code, REDOX_PANIC);
+        assert!(output.is_null());
+        assert_eq!(output_len, 0);
+    }
+}
@@ -0,0 +1,360 @@
+//! Reducing a whole crate, following its module tree
+//!
+//! [`crate::transpile_crate`] starts at a crate root (`lib.rs` or `main.rs`), finds the
+//! file of each `mod` declaration as rustc does, and reduces every file it reaches. The
+//! resulting [`IronCrate`] keeps each file's path relative to the root's directory, so
+//! [`IronCrate::write`] (with the `files` feature) lays the Iron out as the Rust was,
+//! next to a manifest, and
+//! [`IronCrate::oxidize`] gives back Rust files for the same paths. The `mod`
+//! declarations are kept as verbatim Rust, so the oxidized files form the same module
+//! tree.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "files")]
+use serde_json::{Value, json};
+use syn::ext::IdentExt;
+
+use crate::{OxidizeOptions, ReduceOptions, TranspileError};
+
+/// Name of the manifest written next to the Iron files
+pub const MANIFEST: &str = "redox-manifest.json";
+
+/// One source file of a crate and its Iron
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateFile {
+    /// Path of the Rust file, relative to the directory of the crate root
+    pub source: PathBuf,
+    /// Path of the module the file holds, such as `crate::parser`
+    pub module: String,
+    /// The file's Iron
+    pub iron: String,
+}
+
+impl CrateFile {
+    /// Path of the Iron file, relative to the directory of the crate root
+    pub fn iron_path(&self) -> PathBuf {
+        self.source.with_extension("iron")
+    }
+}
+
+/// A file of the module tree that could not be read, parsed, or reduced
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateFailure {
+    /// Path of the Rust file, relative to the directory of the crate root
+    pub source: PathBuf,
+    pub error: String,
+}
+
+/// The Iron of every file in a crate's module tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IronCrate {
+    /// Directory the paths of the files are relative to
+    pub root: PathBuf,
+    /// Each file that reduced, ordered by module path, so the crate root is first
+    pub files: Vec<CrateFile>,
+    /// Each file that did not, ordered by path
+    pub failures: Vec<CrateFailure>,
+}
+
+impl IronCrate {
+    /// The manifest recording each Iron file's Rust source and module, and each failure
+    #[cfg(feature = "files")]
+    pub fn manifest(&self) -> Value {
+        let files: Vec<Value> = self
+            .files
+            .iter()
+            .map(|file| {
+                json!({
+                    "source": file.source,
+                    "iron": file.iron_path(),
+                    "module": file.module,
+                })
+            })
+            .collect();
+        let failures: Vec<Value> = self
+            .failures
+            .iter()
+            .map(|failure| json!({ "source": failure.source, "error": failure.error }))
+            .collect();
+        json!({ "files": files, "failures": failures })
+    }
+
+    /// Write each Iron file to its path under `out_dir`, and the manifest as
+    /// [`MANIFEST`] at its top
+    #[cfg(feature = "files")]
+    pub fn write(&self, out_dir: &Path) -> Result<(), TranspileError> {
+        for file in &self.files {
+            let output = out_dir.join(file.iron_path());
+            if let Some(parent) = output.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&output, &file.iron)?;
+        }
+        std::fs::create_dir_all(out_dir)?;
+        let manifest = serde_json::to_string_pretty(&self.manifest())
+            .map_err(|e| TranspileError::InternalError(e.to_string()))?;
+        std::fs::write(out_dir.join(MANIFEST), manifest)?;
+        Ok(())
+    }
+
+    /// Read back a crate written by [`IronCrate::write`], rooted at `dir`
+    #[cfg(feature = "files")]
+    pub fn read(dir: &Path) -> Result<Self, TranspileError> {
+        let text = std::fs::read_to_string(dir.join(MANIFEST))?;
+        let manifest: Value = serde_json::from_str(&text)
+            .map_err(|e| TranspileError::ParseError(format!("Invalid {}: {}", MANIFEST, e)))?;
+        let invalid = || TranspileError::ParseError(format!("Invalid {}", MANIFEST));
+        let field = |entry: &Value, name: &str| {
+            entry[name].as_str().map(str::to_string).ok_or_else(invalid)
+        };
+
+        let mut files = Vec::new();
+        for entry in manifest["files"].as_array().ok_or_else(invalid)? {
+            let iron = std::fs::read_to_string(dir.join(field(entry, "iron")?))?;
+            files.push(CrateFile {
+                source: PathBuf::from(field(entry, "source")?),
+                module: field(entry, "module")?,
+                iron,
+            });
+        }
+        let failures = manifest["failures"]
+            .as_array()
+            .ok_or_else(invalid)?
+            .iter()
+            .map(|entry| {
+                Ok(CrateFailure {
+                    source: PathBuf::from(field(entry, "source")?),
+                    error: field(entry, "error")?,
+                })
+            })
+            .collect::<Result<_, TranspileError>>()?;
+        Ok(IronCrate {
+            root: dir.to_path_buf(),
+            files,
+            failures,
+        })
+    }
+
+    /// Oxidize every file, returning the Rust of each with the path of its source
+    pub fn oxidize(
+        &self,
+        options: &OxidizeOptions,
+    ) -> Result<Vec<(PathBuf, String)>, TranspileError> {
+        self.files
+            .iter()
+            .map(|file| {
+                let rust = crate::oxidize_with_options(&file.iron, options)?;
+                Ok((file.source.clone(), rust))
+            })
+            .collect()
+    }
+}
+
+/// The files of a module tree walked so far, keyed by path
+#[derive(Default)]
+struct ModuleTree {
+    /// The module and source text of each file
+    files: BTreeMap<PathBuf, (String, String)>,
+    failures: BTreeMap<PathBuf, String>,
+}
+
+impl ModuleTree {
+    /// Add the file of a module and, recursively, the files of its `mod` declarations
+    fn visit_file(&mut self, path: PathBuf, module: String, mod_rs: bool) {
+        if self.files.contains_key(&path) || self.failures.contains_key(&path) {
+            return;
+        }
+        let (source, syntax) = match std::fs::read_to_string(&path) {
+            Ok(source) => match syn::parse_file(&source) {
+                Ok(syntax) => (source, syntax),
+                Err(e) => {
+                    let start = e.span().start();
+                    let error = format!("line {}, column {}: {}", start.line, start.column + 1, e);
+                    self.failures.insert(path, error);
+                    return;
+                }
+            },
+            Err(e) => {
+                self.failures.insert(path, e.to_string());
+                return;
+            }
+        };
+
+        let file_dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+        // Children of `lib.rs`, `main.rs` or `mod.rs` sit next to it; those of `a.rs` in `a/`
+        let dir = if mod_rs {
+            file_dir.clone()
+        } else {
+            file_dir.join(path.file_stem().unwrap_or_default())
+        };
+        self.files.insert(path, (module.clone(), source));
+        self.visit_items(&syntax.items, &dir, &file_dir, &module, false);
+    }
+
+    fn visit_items(
+        &mut self,
+        items: &[syn::Item],
+        dir: &Path,
+        file_dir: &Path,
+        module: &str,
+        inline: bool,
+    ) {
+        for item in items {
+            let syn::Item::Mod(item_mod) = item else {
+                continue;
+            };
+            let name = item_mod.ident.unraw().to_string();
+            let child = format!("{}::{}", module, name);
+
+            if let Some((_, nested)) = &item_mod.content {
+                let dir = dir.join(&name);
+                self.visit_items(nested, &dir, file_dir, &child, true);
+                continue;
+            }
+
+            // `#[path]` is relative to the file's directory, or to the module directory
+            // inside inline modules; the file it names is treated like a `mod.rs`
+            if let Some(path) = path_attribute(&item_mod.attrs) {
+                let base = if inline { dir } else { file_dir };
+                self.visit_file(base.join(path), child, true);
+                continue;
+            }
+
+            let flat = dir.join(format!("{}.rs", name));
+            let nested = dir.join(&name).join("mod.rs");
+            if flat.is_file() {
+                self.visit_file(flat, child, false);
+            } else if nested.is_file() {
+                self.visit_file(nested, child, true);
+            } else {
+                // Most often a module behind a `cfg` for another platform
+                let error = format!("no file for module {}", child);
+                self.failures.insert(flat, error);
+            }
+        }
+    }
+}
+
+/// The value of a `#[path = "..."]` attribute
+fn path_attribute(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs.iter().find_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(meta) if meta.path.is_ident("path") => match &meta.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(path),
+                ..
+            }) => Some(path.value()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// Reduce the module tree under the crate root at `root`
+pub(crate) fn transpile_crate(
+    root: &Path,
+    options: &ReduceOptions,
+) -> Result<IronCrate, TranspileError> {
+    // The root itself must read and parse; files below it may fail on their own
+    let source = std::fs::read_to_string(root)?;
+    crate::parse_rust(&source)?;
+
+    let mut tree = ModuleTree::default();
+    tree.visit_file(root.to_path_buf(), "crate".to_string(), true);
+
+    let dir = root.parent().unwrap_or(Path::new("")).to_path_buf();
+    let relative = |path: &Path| path.strip_prefix(&dir).unwrap_or(path).to_path_buf();
+    let mut files = Vec::new();
+    let mut failures: BTreeMap<PathBuf, String> = tree
+        .failures
+        .iter()
+        .map(|(path, error)| (relative(path), error.clone()))
+        .collect();
+    for (path, (module, source)) in &tree.files {
+        match crate::transpile_with_options(source, options) {
+            Ok(iron) => files.push(CrateFile {
+                source: relative(path),
+                module: module.clone(),
+                iron,
+            }),
+            Err(e) => {
+                failures.insert(relative(path), e.to_string());
+            }
+        }
+    }
+    files.sort_by(|a, b| a.module.cmp(&b.module));
+
+    Ok(IronCrate {
+        root: dir,
+        files,
+        failures: failures
+            .into_iter()
+            .map(|(source, error)| CrateFailure { source, error })
+            .collect(),
+    })
+}
+
+#[cfg(all(test, feature = "files"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crate_tree_round_trips_through_a_mirror() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        std::fs::create_dir_all(src.join("shapes")).unwrap();
+        std::fs::write(
+            src.join("lib.rs"),
+            "mod shapes;\nmod missing;\n\npub fn one() -> i32 {\n    1\n}\n",
+        )
+        .unwrap();
+        std::fs::write(src.join("shapes.rs"), "pub mod circle;\n").unwrap();
+        std::fs::write(
+            src.join("shapes/circle.rs"),
+            "fn area(r: f64) -> f64 {\n    r * r\n}\n",
+        )
+        .unwrap();
+
+        let reduced =
+            crate::transpile_crate(src.join("lib.rs"), &ReduceOptions::default()).unwrap();
+        let sources: Vec<_> = reduced.files.iter().map(|file| &file.source).collect();
+        assert_eq!(
+            sources,
+            [
+                Path::new("lib.rs"),
+                Path::new("shapes.rs"),
+                Path::new("shapes/circle.rs")
+            ]
+        );
+        assert_eq!(reduced.files[2].module, "crate::shapes::circle");
+        assert_eq!(
+            reduced.files[2].iron_path(),
+            Path::new("shapes/circle.iron")
+        );
+        assert!(reduced.files[2].iron.starts_with("function area"));
+        assert_eq!(reduced.failures[0].source, Path::new("missing.rs"));
+        assert_eq!(
+            reduced.failures[0].error,
+            "no file for module crate::missing"
+        );
+
+        let mirror = dir.path().join("iron");
+        reduced.write(&mirror).unwrap();
+        assert!(mirror.join("shapes/circle.iron").is_file());
+        let read = IronCrate::read(&mirror).unwrap();
+        assert_eq!(read.files, reduced.files);
+        assert_eq!(read.failures, reduced.failures);
+
+        let rust = read.oxidize(&OxidizeOptions::default()).unwrap();
+        assert_eq!(rust[0].0, Path::new("lib.rs"));
+        assert!(rust[0].1.starts_with("mod shapes;\n"));
+        assert_eq!(rust[2].1, "fn area(r: f64) -> f64 {\n    r * r\n}\n");
+
+        std::fs::write(src.join("lib.rs"), "mod (").unwrap();
+        assert!(matches!(
+            crate::transpile_crate(src.join("lib.rs"), &ReduceOptions::default()),
+            Err(TranspileError::RustSyntax { .. })
+        ));
+    }
+}
@@ -0,0 +1,254 @@
+//! Type dictionaries loaded at runtime
+//!
+//! A dictionary gives a project's own Rust types Iron names ("UserId" as "user
+//! identifier") without rebuilding redox. With the `files` feature it is read from TOML
+//! or JSON:
+//!
+//! ```toml
+//! [types]
+//! UserId = "user identifier"
+//! ```
+//!
+//! Each entry is used in both directions: reduction writes the Iron name for the Rust
+//! type, and oxidation writes the Rust type back for the Iron name. Entries must map one
+//! to one and must not redefine a type Iron already names, so the two directions agree.
+//!
+//! Only types can be named. Operators keep their built-in Iron words ("plus", "greater
+//! than"), which the Iron grammar fixes, so a dictionary cannot rename them.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "files")]
+use std::path::Path;
+
+use crate::TranspileError;
+use crate::iron_tokenizer::{Token, Tokenizer};
+use crate::mappings::MappingHook;
+
+/// Rust type names with a built-in Iron name
+const BUILT_IN_RUST: [&str; 25] = [
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize", "f32",
+    "f64", "bool", "char", "str", "String", "Vec", "Box", "Option", "Result", "HashMap", "Rc",
+    "Arc",
+];
+
+/// Iron type names with a built-in Rust type
+const BUILT_IN_IRON: [&str; 13] = [
+    "boolean",
+    "character",
+    "string",
+    "string slice",
+    "list",
+    "optional",
+    "result",
+    "hash map",
+    "box",
+    "reference counted",
+    "atomic reference counted",
+    "unit",
+    "error",
+];
+
+/// Iron names for Rust types, consistent in both directions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TypeDictionary {
+    /// Iron name of each Rust type name
+    iron_names: BTreeMap<String, String>,
+    /// Rust type name of each Iron name
+    rust_names: BTreeMap<String, String>,
+}
+
+impl TypeDictionary {
+    /// Create an empty dictionary
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a dictionary from a `.toml` or `.json` file
+    #[cfg(feature = "files")]
+    pub fn load(path: &Path) -> Result<Self, TranspileError> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            TranspileError::config_source(format!("Failed to read {}: {}", path.display(), e), e)
+        })?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&text),
+            Some("json") => Self::from_json(&text),
+            _ => Err(TranspileError::config(format!(
+                "{}: a dictionary must be a .toml or .json file",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Read a dictionary from TOML with a `[types]` table
+    #[cfg(feature = "files")]
+    pub fn from_toml(text: &str) -> Result<Self, TranspileError> {
+        let value: serde_json::Value = toml::from_str(text)
+            .map_err(|e| TranspileError::config_source(format!("Invalid dictionary: {}", e), e))?;
+        Self::from_value(&value)
+    }
+
+    /// Read a dictionary from JSON with a `types` object
+    #[cfg(feature = "files")]
+    pub fn from_json(text: &str) -> Result<Self, TranspileError> {
+        let value: serde_json::Value = serde_json::from_str(text)
+            .map_err(|e| TranspileError::config_source(format!("Invalid dictionary: {}", e), e))?;
+        Self::from_value(&value)
+    }
+
+    #[cfg(feature = "files")]
+    fn from_value(value: &serde_json::Value) -> Result<Self, TranspileError> {
+        let types = match value.get("types") {
+            Some(serde_json::Value::Object(types)) => types,
+            Some(_) => return Err(TranspileError::config("Dictionary 'types' is not a table")),
+            None => return Err(TranspileError::config("Dictionary has no 'types' table")),
+        };
+
+        let mut dictionary = Self::new();
+        for (rust, iron) in types {
+            let iron = iron.as_str().ok_or_else(|| {
+                TranspileError::config(format!("Dictionary type '{}' is not a string", rust))
+            })?;
+            dictionary.insert(rust, iron)?;
+        }
+        Ok(dictionary)
+    }
+
+    /// Name the Rust type `rust` as `iron` in Iron
+    ///
+    /// The Rust name must be a plain identifier and the Iron name one or more lowercase
+    /// words that are not Iron keywords, separated by single spaces. Neither may already
+    /// have a meaning, built in or in this dictionary.
+    pub fn insert(&mut self, rust: &str, iron: &str) -> Result<(), TranspileError> {
+        if syn::parse_str::<syn::Ident>(rust).is_err() {
+            return Err(TranspileError::config(format!(
+                "'{}' is not a Rust type name",
+                rust
+            )));
+        }
+        if BUILT_IN_RUST.contains(&rust) {
+            return Err(TranspileError::config(format!(
+                "'{}' already has an Iron name",
+                rust
+            )));
+        }
+        check_iron_name(iron)?;
+        if let Some(existing) = self.iron_names.get(rust) {
+            return Err(TranspileError::config(format!(
+                "'{}' is already named '{}'",
+                rust, existing
+            )));
+        }
+        if let Some(existing) = self.rust_names.get(iron) {
+            return Err(TranspileError::config(format!(
+                "'{}' already names '{}'",
+                iron, existing
+            )));
+        }
+
+        self.iron_names.insert(rust.to_string(), iron.to_string());
+        self.rust_names.insert(iron.to_string(), rust.to_string());
+        Ok(())
+    }
+
+    /// The Iron name of a Rust type
+    pub fn iron_name(&self, rust: &str) -> Option<&str> {
+        self.iron_names.get(rust).map(String::as_str)
+    }
+
+    /// The Rust type an Iron name stands for
+    pub fn rust_name(&self, iron: &str) -> Option<&str> {
+        self.rust_names.get(iron).map(String::as_str)
+    }
+
+    /// Every Iron name, which the Iron parser needs to read those of several words
+    pub fn iron_names(&self) -> impl Iterator<Item = &str> {
+        self.rust_names.keys().map(String::as_str)
+    }
+
+    /// Whether the dictionary has no entries
+    pub fn is_empty(&self) -> bool {
+        self.iron_names.is_empty()
+    }
+}
+
+impl MappingHook for TypeDictionary {
+    fn map_type_name(&self, name: &str) -> Option<String> {
+        self.iron_name(name).map(str::to_string)
+    }
+
+    fn type_phrases(&self) -> Vec<String> {
+        self.iron_names().map(str::to_string).collect()
+    }
+}
+
+/// Check that `iron` reads back as the words of a type name
+fn check_iron_name(iron: &str) -> Result<(), TranspileError> {
+    if BUILT_IN_IRON.contains(&iron) {
+        return Err(TranspileError::config(format!(
+            "'{}' is already an Iron type",
+            iron
+        )));
+    }
+    let words: Vec<&str> = iron.split(' ').collect();
+    let mut tokens = Tokenizer::new(iron).tokenize();
+    tokens.pop_if(|token| *token == Token::EndOfFile);
+    let plain_words = tokens.len() == words.len()
+        && words.iter().zip(&tokens).all(|(word, token)| {
+            !word.is_empty()
+                && word.chars().all(|c| c.is_ascii_lowercase() || c == '_')
+                && matches!(token, Token::Identifier(name) if name == word)
+        });
+    if plain_words {
+        Ok(())
+    } else {
+        Err(TranspileError::config(format!(
+            "'{}' is not an Iron type name: use lowercase words that are not Iron keywords",
+            iron
+        )))
+    }
+}
+
+#[cfg(all(test, feature = "files"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dictionary_rejects_inconsistent_entries() {
+        let dictionary =
+            TypeDictionary::from_toml("[types]\nUserId = \"user identifier\"\n").unwrap();
+        assert_eq!(dictionary.iron_name("UserId"), Some("user identifier"));
+        assert_eq!(dictionary.rust_name("user identifier"), Some("UserId"));
+
+        let json = TypeDictionary::from_json(r#"{"types": {"UserId": "user identifier"}}"#);
+        assert_eq!(json.unwrap(), dictionary);
+
+        let mut dictionary = dictionary;
+        for (rust, iron) in [
+            ("GroupId", "user identifier"),
+            ("UserId", "member"),
+            ("String", "text"),
+            ("Text", "string"),
+            ("Order", "order for user"),
+            ("Order", "Order"),
+            ("Order", "order  line"),
+            ("my::Order", "order"),
+        ] {
+            assert!(
+                dictionary.insert(rust, iron).is_err(),
+                "{} as {}",
+                rust,
+                iron
+            );
+        }
+        assert!(TypeDictionary::from_toml("[types]\nUserId = 1\n").is_err());
+        assert!(TypeDictionary::from_json("{}").is_err());
+
+        let error = TypeDictionary::from_json("{").unwrap_err();
+        assert_eq!(error.code(), "invalid-config");
+        let source = std::error::Error::source(&error).unwrap();
+        assert!(source.is::<serde_json::Error>());
+        let error = TypeDictionary::load(Path::new("missing/types.toml")).unwrap_err();
+        assert_eq!(error.code(), "io-error");
+        assert!(error.to_string().contains("missing/types.toml"));
+    }
+}
@@ -3,14 +3,136 @@
 //! This module handles the generation of Iron source code with proper formatting,
 //! indentation, and LLM-optimized output structure.
 
-use crate::keywords::sanitize_identifier;
+use std::ops::Range;
+
+use crate::iron_tokenizer::{CONTINUATION, Token, Tokenizer, is_text_end, is_verbatim_end};
+use crate::symbols::{SymbolCheckpoint, SymbolTable};
+
+/// The Rust construct a run of Iron lines was emitted for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Origin {
+    /// Line of the construct in the Rust source
+    pub line: usize,
+    /// 1-based column of the construct in the Rust source
+    pub column: usize,
+    /// Line the construct ends on in the Rust source
+    pub end_line: usize,
+    /// 1-based column just past the end of the construct
+    pub end_column: usize,
+    /// What the construct is, such as "function add" or "let binding"
+    pub construct: String,
+}
+
+/// One level of Iron indentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indentation {
+    /// A number of spaces
+    Spaces(usize),
+    /// One tab character
+    Tab,
+}
+
+impl Default for Indentation {
+    fn default() -> Self {
+        Indentation::Spaces(4)
+    }
+}
+
+impl Indentation {
+    /// The text of one level
+    pub fn unit(&self) -> String {
+        match self {
+            Indentation::Spaces(width) => " ".repeat(*width),
+            Indentation::Tab => "\t".to_string(),
+        }
+    }
+}
+
+impl std::str::FromStr for Indentation {
+    type Err = String;
+
+    /// A number of spaces from 1 to 16, or `tab`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tab" | "tabs" => Ok(Indentation::Tab),
+            _ => match s.parse() {
+                Ok(width @ 1..=16) => Ok(Indentation::Spaces(width)),
+                _ => Err(format!(
+                    "invalid indentation '{}' (expected a number of spaces from 1 to 16, or tab)",
+                    s
+                )),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Indentation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Indentation::Spaces(width) => write!(f, "{}", width),
+            Indentation::Tab => f.write_str("tab"),
+        }
+    }
+}
+
+/// Where the emitter leaves blank lines
+///
+/// Blank lines come only from this policy, so the same input and options always give
+/// the same bytes: each item is followed by its blank lines unless a block closes or the
+/// file ends right after it, an item nested in a block is set off from the statements
+/// before it unless it opens the block, and the output ends with a single newline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutPolicy {
+    /// Blank lines between top-level items
+    pub between_items: usize,
+    /// Blank lines setting an item nested in a block off from the statements around it
+    pub inside_blocks: usize,
+    /// Blank lines after the header line, when there is one
+    pub after_header: usize,
+}
+
+impl Default for LayoutPolicy {
+    fn default() -> Self {
+        LayoutPolicy {
+            between_items: 1,
+            inside_blocks: 1,
+            after_header: 1,
+        }
+    }
+}
+
+/// A point in the output that writing can be rolled back to
+#[derive(Debug, Clone, Copy)]
+pub struct Checkpoint {
+    output_len: usize,
+    origins_len: usize,
+    indent_level: usize,
+    needs_newline: bool,
+    trailing_blanks: usize,
+    block_start: bool,
+    symbols: SymbolCheckpoint,
+}
 
 /// Builder for generating Iron code with proper formatting
 pub struct IronEmitter {
     output: String,
     indent_level: usize,
-    indent_size: usize,
+    indentation: Indentation,
+    /// Width lines are wrapped to, if any
+    max_width: Option<usize>,
+    /// Where blank lines go
+    layout: LayoutPolicy,
     needs_newline: bool,
+    /// Blank lines at the end of the output, dropped if a block closes after them
+    trailing_blanks: usize,
+    /// Whether the last line written opened a block
+    block_start: bool,
+    /// The construct lines are currently written for
+    origin: Option<Origin>,
+    /// Byte offset in the output where each change of origin takes effect
+    origins: Vec<(usize, Origin)>,
+    /// Identifiers written, and those declared in each indented block
+    symbols: SymbolTable,
 }
 
 impl IronEmitter {
@@ -19,39 +141,284 @@ impl IronEmitter {
         Self {
             output: String::new(),
             indent_level: 0,
-            indent_size: 4,
+            indentation: Indentation::default(),
+            max_width: None,
+            layout: LayoutPolicy::default(),
             needs_newline: false,
+            trailing_blanks: 0,
+            block_start: false,
+            origin: None,
+            origins: Vec::new(),
+            symbols: SymbolTable::new(),
         }
     }
 
     /// Create a new emitter with custom indentation size
     pub fn with_indent_size(indent_size: usize) -> Self {
+        Self::with_indentation(Indentation::Spaces(indent_size))
+    }
+
+    /// Create a new emitter indenting each level by `indentation`
+    pub fn with_indentation(indentation: Indentation) -> Self {
         Self {
             output: String::new(),
             indent_level: 0,
-            indent_size,
+            indentation,
+            max_width: None,
+            layout: LayoutPolicy::default(),
             needs_newline: false,
+            trailing_blanks: 0,
+            block_start: false,
+            origin: None,
+            origins: Vec::new(),
+            symbols: SymbolTable::new(),
         }
     }
 
+    /// Discard everything written, keeping the indentation and the output buffer's
+    /// capacity so the emitter can be reused for another file
+    pub fn clear(&mut self) {
+        self.output.clear();
+        self.indent_level = 0;
+        self.needs_newline = false;
+        self.trailing_blanks = 0;
+        self.block_start = false;
+        self.origin = None;
+        self.origins.clear();
+        self.symbols.clear();
+    }
+
     /// Get the current output as a string (for reading without consuming)
     pub fn output(&self) -> &str {
         &self.output
     }
 
+    /// The identifiers written so far and the names declared in the open blocks
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// The symbol table, for recording the names of expressions built outside the emitter
+    pub fn symbols_mut(&mut self) -> &mut SymbolTable {
+        &mut self.symbols
+    }
+
+    /// One level of indentation
+    pub fn indentation(&self) -> Indentation {
+        self.indentation
+    }
+
+    /// Indent each level by `indentation` from now on
+    pub fn set_indentation(&mut self, indentation: Indentation) {
+        self.indentation = indentation;
+    }
+
+    /// Wrap lines longer than `max_width` characters, indentation included, onto
+    /// `continued` lines; `None` leaves lines as long as they come
+    pub fn set_max_width(&mut self, max_width: Option<usize>) {
+        self.max_width = max_width;
+    }
+
+    /// Width lines are wrapped to, if any
+    pub fn max_width(&self) -> Option<usize> {
+        self.max_width
+    }
+
+    /// Leave blank lines where `layout` says
+    pub fn set_layout(&mut self, layout: LayoutPolicy) {
+        self.layout = layout;
+    }
+
+    /// Where blank lines go
+    pub fn layout(&self) -> LayoutPolicy {
+        self.layout
+    }
+
     /// Get the current indentation string
     fn current_indent(&self) -> String {
-        " ".repeat(self.indent_level * self.indent_size)
+        self.indentation.unit().repeat(self.indent_level)
     }
 
     /// Write a line with proper indentation
+    ///
+    /// Multi-line content (such as block-bodied closures) has every line indented.
     pub fn write_line(&mut self, content: &str) {
         if self.needs_newline {
             self.output.push('\n');
         }
-        self.output.push_str(&self.current_indent());
-        self.output.push_str(content);
+        if let Some(origin) = &self.origin
+            && self.origins.last().is_none_or(|(_, last)| last != origin)
+        {
+            self.origins.push((self.output.len(), origin.clone()));
+        }
+        let indent = self.current_indent();
+        let wrapped = self
+            .max_width
+            .and_then(|width| self.wrap(content, &indent, width));
+        let content = wrapped.as_deref().unwrap_or(content);
+        for (i, line) in content.split('\n').enumerate() {
+            if i > 0 {
+                self.output.push('\n');
+            }
+            self.output.push_str(&indent);
+            self.output.push_str(line);
+        }
         self.needs_newline = true;
+        self.trailing_blanks = 0;
+        self.block_start = false;
+    }
+
+    /// Break the lines of `content` that would be longer than `width` after `indent`,
+    /// or `None` if all fit
+    ///
+    /// A line is broken only where its tokens are separated by spaces, never before a
+    /// comma, at the last such gap that keeps it within the width (or the first, if none
+    /// does). Each piece after the first goes on a line of its own, one level deeper,
+    /// starting with `continued`, and is broken again if it is still too long. Comments
+    /// and the lines of raw blocks are single tokens, so they are never broken.
+    fn wrap(&self, content: &str, indent: &str, width: usize) -> Option<String> {
+        let columns = |prefix: &str, text: &str| {
+            indent.chars().count() + prefix.chars().count() + text.chars().count()
+        };
+        if content.split('\n').all(|line| columns("", line) <= width) {
+            return None;
+        }
+
+        let tokens = Tokenizer::new(content)
+            .with_symbols(true)
+            .tokenize_spanned();
+        let mut words = tokens.iter().filter(|spanned| {
+            !matches!(
+                spanned.token,
+                Token::NewLine | Token::Indent(_) | Token::EndOfFile
+            )
+        });
+        let mut gaps: Vec<Range<usize>> = Vec::new();
+        if let Some(mut previous) = words.next() {
+            for word in words {
+                let gap = previous.byte_range.end..word.byte_range.start;
+                let spaces = content[gap.clone()]
+                    .chars()
+                    .all(|c| matches!(c, ' ' | '\t'));
+                if !gap.is_empty() && spaces && word.token != Token::Comma {
+                    gaps.push(gap);
+                }
+                previous = word;
+            }
+        }
+
+        let mut wrapped = String::new();
+        let mut line_start = 0;
+        for (i, line) in content.split('\n').enumerate() {
+            if i > 0 {
+                wrapped.push('\n');
+            }
+            let line_end = line_start + line.len();
+            let lead = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+            let continuation = format!("{}{}{} ", lead, self.indentation.unit(), CONTINUATION);
+            let line_gaps: Vec<&Range<usize>> = gaps
+                .iter()
+                .filter(|gap| gap.start >= line_start && gap.end <= line_end)
+                .collect();
+
+            let mut piece = line_start;
+            let mut prefix = "";
+            let mut next = 0;
+            while columns(prefix, &content[piece..line_end]) > width {
+                let mut cut = None;
+                while next < line_gaps.len()
+                    && (cut.is_none()
+                        || columns(prefix, &content[piece..line_gaps[next].start]) <= width)
+                {
+                    cut = Some(line_gaps[next]);
+                    next += 1;
+                }
+                let Some(cut) = cut else { break };
+                wrapped.push_str(prefix);
+                wrapped.push_str(&content[piece..cut.start]);
+                wrapped.push('\n');
+                piece = cut.end;
+                prefix = &continuation;
+            }
+            wrapped.push_str(prefix);
+            wrapped.push_str(&content[piece..line_end]);
+            line_start = line_end + 1;
+        }
+        Some(wrapped)
+    }
+
+    /// Attribute the lines written from now on to `origin`, returning the previous one so
+    /// it can be restored once the construct is done
+    pub fn set_origin(&mut self, origin: Option<Origin>) -> Option<Origin> {
+        std::mem::replace(&mut self.origin, origin)
+    }
+
+    /// The (1-based Iron line, origin) of each point where the origin of the output changes
+    pub fn origins(&self) -> Vec<(usize, Origin)> {
+        let mut line = 1;
+        let mut scanned = 0;
+        self.origins
+            .iter()
+            .map(|(offset, origin)| {
+                line += self.output[scanned..*offset].matches('\n').count();
+                scanned = *offset;
+                (line, origin.clone())
+            })
+            .collect()
+    }
+
+    /// Mark the current point in the output
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            output_len: self.output.len(),
+            origins_len: self.origins.len(),
+            indent_level: self.indent_level,
+            needs_newline: self.needs_newline,
+            trailing_blanks: self.trailing_blanks,
+            block_start: self.block_start,
+            symbols: self.symbols.checkpoint(),
+        }
+    }
+
+    /// The output written since `checkpoint`
+    pub fn output_since(&self, checkpoint: &Checkpoint) -> &str {
+        &self.output[checkpoint.output_len..]
+    }
+
+    /// The origins taking effect since `checkpoint`, each with its byte offset in
+    /// [`Self::output_since`]
+    pub fn origins_since(&self, checkpoint: &Checkpoint) -> Vec<(usize, Origin)> {
+        self.origins[checkpoint.origins_len..]
+            .iter()
+            .map(|(offset, origin)| (offset - checkpoint.output_len, origin.clone()))
+            .collect()
+    }
+
+    /// The Rust names renamed in the output since `checkpoint`
+    pub fn renamed_since(&self, checkpoint: &Checkpoint) -> Vec<String> {
+        self.symbols.renamed_since(checkpoint.symbols)
+    }
+
+    /// The Rust names declared since `checkpoint` that are still in scope
+    pub fn declared_since(&self, checkpoint: &Checkpoint) -> Vec<String> {
+        self.symbols.declared_since(checkpoint.symbols)
+    }
+
+    /// Discard everything written since `checkpoint`
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        self.output.truncate(checkpoint.output_len);
+        self.origins.truncate(checkpoint.origins_len);
+        self.indent_level = checkpoint.indent_level;
+        self.needs_newline = checkpoint.needs_newline;
+        self.trailing_blanks = checkpoint.trailing_blanks;
+        self.block_start = checkpoint.block_start;
+        self.symbols.rollback(checkpoint.symbols);
+    }
+
+    /// Whether the last line written is complete, as after a blank line, so output
+    /// written from here does not depend on what came before
+    pub fn at_line_start(&self) -> bool {
+        !self.needs_newline && self.indent_level == 0
     }
 
     /// Write a line without trailing newline
@@ -65,15 +432,37 @@ impl IronEmitter {
         self.output.push_str(content);
     }
 
-    /// Increase indentation level
+    /// Write the Iron of a whole item reduced before, ending with its blank lines, with
+    /// the origins it had at byte offsets into `iron`
+    pub fn write_item(&mut self, iron: &str, origins: Vec<(usize, Origin)>) {
+        let start = self.output.len();
+        self.origins.extend(
+            origins
+                .into_iter()
+                .map(|(offset, origin)| (start + offset, origin)),
+        );
+        self.output.push_str(iron);
+        let lines = iron.len() - iron.trim_end_matches('\n').len();
+        self.trailing_blanks = lines.saturating_sub(1);
+        self.block_start = false;
+    }
+
+    /// Increase indentation level, opening a scope
     pub fn indent(&mut self) {
         self.indent_level += 1;
+        self.block_start = true;
+        self.symbols.enter_scope();
     }
 
-    /// Decrease indentation level
+    /// Decrease indentation level, closing the innermost scope
+    ///
+    /// Blank lines left after the block's last item are dropped, so the block's closing
+    /// line follows it directly.
     pub fn dedent(&mut self) {
+        self.drop_trailing_blanks();
         if self.indent_level > 0 {
             self.indent_level -= 1;
+            self.symbols.exit_scope();
         }
     }
 
@@ -84,6 +473,45 @@ impl IronEmitter {
         }
         self.output.push('\n');
         self.needs_newline = false;
+        self.trailing_blanks += 1;
+    }
+
+    /// End the current line and leave the layout's blank lines after it, between top-level
+    /// items or, inside a block, between an item and its neighbouring statements
+    ///
+    /// Nothing is left at the start of the output or of a block, nor where blank lines
+    /// already are.
+    pub fn separate(&mut self) {
+        if self.needs_newline {
+            self.output.push('\n');
+            self.needs_newline = false;
+        }
+        if self.output.is_empty() || self.block_start || self.trailing_blanks > 0 {
+            return;
+        }
+        let blanks = match self.indent_level {
+            0 => self.layout.between_items,
+            _ => self.layout.inside_blocks,
+        };
+        for _ in 0..blanks {
+            self.output.push('\n');
+        }
+        self.trailing_blanks = blanks;
+    }
+
+    /// The output as it ends a file: its last line finished and no blank lines after it
+    pub fn finished_output(&self) -> String {
+        let mut output = self.output[..self.output.len() - self.trailing_blanks].to_string();
+        if self.needs_newline {
+            output.push('\n');
+        }
+        output
+    }
+
+    fn drop_trailing_blanks(&mut self) {
+        self.output
+            .truncate(self.output.len() - self.trailing_blanks);
+        self.trailing_blanks = 0;
     }
 
     /// Start a block with "begin"
@@ -103,6 +531,14 @@ impl IronEmitter {
         self.write_line(&format!("note that {}", content));
     }
 
+    /// Write a comment as a complete line, so what is written next starts its own line
+    /// just as it would without the comment
+    pub fn write_comment_line(&mut self, content: &str) {
+        self.write_comment(content);
+        self.output.push('\n');
+        self.needs_newline = false;
+    }
+
     /// Get the final output (consumes self)
     pub fn finalize(self) -> String {
         self.output
@@ -113,6 +549,12 @@ impl IronEmitter {
         self.output.clone()
     }
 
+    /// Declare `name` in the innermost scope, returning its Iron spelling
+    fn declare(&mut self, name: &str) -> String {
+        let symbol = self.symbols.declare(name);
+        self.symbols.iron_name(symbol).to_string()
+    }
+
     /// Write a function header
     pub fn write_function_header(
         &mut self,
@@ -121,7 +563,7 @@ impl IronEmitter {
         params: &[(String, String)],
         return_type: &str,
     ) {
-        let sanitized_name = sanitize_identifier(name);
+        let sanitized_name = self.declare(name);
 
         if let Some(generic_info) = generics {
             self.write_line(&format!("function {} {}", sanitized_name, generic_info));
@@ -135,17 +577,21 @@ impl IronEmitter {
                 .map(|(name, ty)| format!("{} of {}", name, ty))
                 .collect::<Vec<_>>()
                 .join(" and ");
-            self.write_line(&format!("    takes {}", param_str));
+            self.write_line(&format!("{}takes {}", self.indentation.unit(), param_str));
         }
 
         if return_type != "unit" {
-            self.write_line(&format!("    returns {}", return_type));
+            self.write_line(&format!(
+                "{}returns {}",
+                self.indentation.unit(),
+                return_type
+            ));
         }
     }
 
     /// Write a variable definition
     pub fn write_variable_def(&mut self, name: &str, is_mutable: bool, value: &str) {
-        let sanitized_name = sanitize_identifier(name);
+        let sanitized_name = self.declare(name);
         if is_mutable {
             self.write_line(&format!("define mutable {} as {}", sanitized_name, value));
         } else {
@@ -155,7 +601,7 @@ impl IronEmitter {
 
     /// Write a struct definition
     pub fn write_struct_header(&mut self, name: &str, generics: Option<&str>) {
-        let sanitized_name = sanitize_identifier(name);
+        let sanitized_name = self.declare(name);
         if let Some(generic_info) = generics {
             self.write_line(&format!(
                 "structure {} {} with fields",
@@ -169,13 +615,13 @@ impl IronEmitter {
 
     /// Write a struct field
     pub fn write_struct_field(&mut self, name: &str, ty: &str) {
-        let sanitized_name = sanitize_identifier(name);
+        let sanitized_name = self.symbols.sanitize(name);
         self.write_line(&format!("{} of {}", sanitized_name, ty));
     }
 
     /// Write enum definition header
     pub fn write_enum_header(&mut self, name: &str, generics: Option<&str>) {
-        let sanitized_name = sanitize_identifier(name);
+        let sanitized_name = self.declare(name);
         if let Some(generic_info) = generics {
             self.write_line(&format!(
                 "enumeration {} {} with variants",
@@ -189,19 +635,19 @@ impl IronEmitter {
 
     /// Write enum variant (simple)
     pub fn write_enum_variant_simple(&mut self, name: &str) {
-        let sanitized_name = sanitize_identifier(name);
+        let sanitized_name = self.symbols.sanitize(name);
         self.write_line(&sanitized_name);
     }
 
     /// Write enum variant with data
     pub fn write_enum_variant_with_data(&mut self, name: &str, data: &str) {
-        let sanitized_name = sanitize_identifier(name);
+        let sanitized_name = self.symbols.sanitize(name);
         self.write_line(&format!("{} of {}", sanitized_name, data));
     }
 
     /// Write enum variant with named fields
     pub fn write_enum_variant_with_fields(&mut self, name: &str, fields: &[(String, String)]) {
-        let sanitized_name = sanitize_identifier(name);
+        let sanitized_name = self.symbols.sanitize(name);
         let field_str = fields
             .iter()
             .map(|(name, ty)| format!("{} of {}", name, ty))
@@ -220,6 +666,11 @@ impl IronEmitter {
         self.write_line("otherwise");
     }
 
+    /// Write an else-if clause
+    pub fn write_else_if(&mut self, condition: &str) {
+        self.write_line(&format!("otherwise if {} then", condition));
+    }
+
     /// Write end if
     pub fn end_if(&mut self) {
         self.dedent();
@@ -239,7 +690,7 @@ impl IronEmitter {
 
     /// Write a for loop header
     pub fn write_for_header(&mut self, var: &str, iterator: &str) {
-        let sanitized_var = sanitize_identifier(var);
+        let sanitized_var = self.symbols.sanitize(var);
         self.write_line(&format!(
             "for each {} in {} repeat",
             sanitized_var, iterator
@@ -252,6 +703,17 @@ impl IronEmitter {
         self.write_line("end for");
     }
 
+    /// Write an infinite loop header
+    pub fn write_loop_header(&mut self) {
+        self.write_line("loop forever");
+    }
+
+    /// Write end loop
+    pub fn end_loop(&mut self) {
+        self.dedent();
+        self.write_line("end loop");
+    }
+
     /// Write a match expression header
     pub fn write_match_header(&mut self, expr: &str) {
         self.write_line(&format!("compare {}", expr));
@@ -259,7 +721,12 @@ impl IronEmitter {
 
     /// Write a match arm
     pub fn write_match_arm(&mut self, pattern: &str, body: &str) {
-        self.write_line(&format!("    case {} then {}", pattern, body));
+        self.write_line(&format!(
+            "{}case {} then {}",
+            self.indentation.unit(),
+            pattern,
+            body
+        ));
     }
 
     /// Write end match
@@ -276,9 +743,22 @@ impl IronEmitter {
         }
     }
 
-    /// Write a verbatim Rust item payload
+    /// Write an import (use declaration)
+    pub fn write_import(&mut self, path: &str) {
+        self.write_line(&format!("import {}", path));
+    }
+
+    /// Write a verbatim Rust item payload as a raw `verbatim begin` ... `verbatim end` block
     pub fn write_verbatim_item(&mut self, rust_item: &str) {
-        self.write_line(&format!("verbatim item \"{}\"", rust_item.escape_default()));
+        // A payload line that reads as the terminator forces the quoted form
+        let fenced = !rust_item
+            .split('\n')
+            .any(|line| is_verbatim_end(line.trim_start_matches([' ', '\t']), true));
+        if fenced {
+            self.write_line(&format!("verbatim begin\n{}\nverbatim end", rust_item));
+        } else {
+            self.write_line(&format!("verbatim item {}", quote_string(rust_item)));
+        }
     }
 
     /// Write an assignment
@@ -287,6 +767,57 @@ impl IronEmitter {
     }
 }
 
+/// Format a string value as an Iron literal
+///
+/// Multi-line values use the raw `text begin` ... `text end` form; everything else is
+/// a quoted string with escapes.
+pub fn format_string_literal(value: &str) -> String {
+    if value.contains('\n') && can_use_text_block(value) {
+        format!("text begin\n{}\ntext end", value)
+    } else {
+        quote_string(value)
+    }
+}
+
+/// A text block cannot hold a line that would read as its terminator, in any
+/// capitalization so the Iron also reads back with case-insensitive keywords
+fn can_use_text_block(value: &str) -> bool {
+    !value
+        .split('\n')
+        .any(|line| is_text_end(line.trim_start_matches([' ', '\t']), true))
+}
+
+/// Quote a string value as a one-line Iron string literal, escaping what the Iron
+/// tokenizer reads back
+pub fn quote_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            _ => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Quote a character as an Iron character literal, escaped as in a string
+pub fn quote_char(value: char) -> String {
+    match value {
+        '\'' => "'\\''".to_string(),
+        '\\' => "'\\\\'".to_string(),
+        '\n' => "'\\n'".to_string(),
+        '\t' => "'\\t'".to_string(),
+        '\r' => "'\\r'".to_string(),
+        _ => format!("'{}'", value),
+    }
+}
+
 impl Default for IronEmitter {
     fn default() -> Self {
         Self::new()
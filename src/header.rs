@@ -0,0 +1,174 @@
+//! Versioned headers on Iron files
+//!
+//! Reduction can start its output with a line recording the Iron specification, the
+//! version of redox, and hashes of the Rust source and of the Iron after the line:
+//!
+//! ```text
+//! iron specification 1, generated by redox 0.1.0, source hash 3b2d1c0e5f4a6978, content hash 9e8d7c6b5a4f3e2d
+//! ```
+//!
+//! Datasets can then record which dialect produced each file. The Iron parser reads
+//! the header and checks the content hash, so an edited file is noticed. The hashes are
+//! 64-bit FNV-1a: they catch edits, but are not a signature.
+
+/// Version of the Iron specification this redox writes and reads
+pub const SPECIFICATION: u32 = 1;
+
+/// How a header line starts
+pub(crate) const HEADER_START: &str = "iron specification ";
+
+/// Provenance of an Iron file, read from or written as its first line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IronHeader {
+    /// Version of the Iron specification the file follows
+    pub specification: u32,
+    /// Tool and version that wrote the file, such as "redox 0.1.0"
+    pub generator: String,
+    /// Hash of the Rust source the file was reduced from
+    pub source_hash: String,
+    /// Hash of the file after the header line
+    pub content_hash: String,
+}
+
+impl IronHeader {
+    /// The header this version of redox writes for `content`, the Iron following the
+    /// header line, reduced from `rust_source`
+    pub fn new(rust_source: &str, content: &str) -> Self {
+        IronHeader {
+            specification: SPECIFICATION,
+            generator: format!("redox {}", env!("CARGO_PKG_VERSION")),
+            source_hash: hash(rust_source),
+            content_hash: hash(content),
+        }
+    }
+
+    /// Read a header line, without its line break
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let malformed = || format!("malformed Iron header '{}'", line);
+        let fields = line.strip_prefix(HEADER_START).ok_or_else(malformed)?;
+        let mut fields = fields.split(", ");
+        let mut field = |name: &str| {
+            fields
+                .next()
+                .and_then(|field| field.strip_prefix(name))
+                .filter(|value| !value.is_empty())
+                .ok_or_else(malformed)
+        };
+        let specification = field("")?.parse().map_err(|_| malformed())?;
+        let generator = field("generated by ")?.to_string();
+        let source_hash = field("source hash ")?.to_string();
+        let content_hash = field("content hash ")?.to_string();
+        if fields.next().is_some() {
+            return Err(malformed());
+        }
+        Ok(IronHeader {
+            specification,
+            generator,
+            source_hash,
+            content_hash,
+        })
+    }
+
+    /// Check that this redox reads the header's specification and that `content`, the
+    /// Iron after the header line, is what the header was written for
+    pub fn verify(&self, content: &str) -> Result<(), String> {
+        if self.specification != SPECIFICATION {
+            return Err(format!(
+                "Iron specification {} is not supported (this redox reads {})",
+                self.specification, SPECIFICATION
+            ));
+        }
+        let actual = hash(content);
+        if actual != self.content_hash {
+            return Err(format!(
+                "content hash {} does not match the header's {}: the file was edited after it was generated",
+                actual, self.content_hash
+            ));
+        }
+        Ok(())
+    }
+
+    /// Whether `rust_source` is the Rust the file was reduced from
+    pub fn matches_source(&self, rust_source: &str) -> bool {
+        hash(rust_source) == self.source_hash
+    }
+}
+
+impl std::fmt::Display for IronHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}{}, generated by {}, source hash {}, content hash {}",
+            HEADER_START, self.specification, self.generator, self.source_hash, self.content_hash
+        )
+    }
+}
+
+/// Put a header line on Iron reduced from `rust_source`, followed by a blank line
+pub fn add_header(iron: &str, rust_source: &str) -> String {
+    add_header_with_blank_lines(iron, rust_source, 1)
+}
+
+/// Put a header line on Iron reduced from `rust_source`, followed by `blank_lines`
+/// blank lines, which its content hash covers
+pub fn add_header_with_blank_lines(iron: &str, rust_source: &str, blank_lines: usize) -> String {
+    let content = format!("{}{}", "\n".repeat(blank_lines), iron);
+    format!("{}\n{}", IronHeader::new(rust_source, &content), content)
+}
+
+/// Split Iron into its header line and the content after it, if it starts with one
+pub fn split_header(iron: &str) -> Option<(&str, &str)> {
+    if !iron.starts_with(HEADER_START) {
+        return None;
+    }
+    Some(iron.split_once('\n').unwrap_or((iron, "")))
+}
+
+/// 64-bit FNV-1a hash of `text`, as 16 hex digits
+pub fn hash(text: &str) -> String {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_round_trip_and_tampering() {
+        let iron = add_header(
+            "constant N of i32\nbegin\n    1\nend constant\n",
+            "const N: i32 = 1;",
+        );
+        let (line, content) = split_header(&iron).unwrap();
+        assert_eq!(content, "\nconstant N of i32\nbegin\n    1\nend constant\n");
+
+        let header = IronHeader::parse(line).unwrap();
+        assert_eq!(header.to_string(), line);
+        assert_eq!(header.specification, SPECIFICATION);
+        assert_eq!(
+            header.generator,
+            format!("redox {}", env!("CARGO_PKG_VERSION"))
+        );
+        assert!(header.matches_source("const N: i32 = 1;"));
+        assert!(header.verify(content).is_ok());
+        assert!(header.verify(&content.replace('1', "2")).is_err());
+
+        let future = IronHeader {
+            specification: SPECIFICATION + 1,
+            ..header
+        };
+        assert!(
+            future
+                .verify(content)
+                .unwrap_err()
+                .contains("not supported")
+        );
+
+        assert_eq!(hash(""), "cbf29ce484222325");
+        assert!(IronHeader::parse("iron specification one, generated by x").is_err());
+        assert_eq!(split_header("function f\n"), None);
+    }
+}
@@ -1,6 +1,7 @@
 //! Iron Abstract Syntax Tree definitions
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IronType {
     Named(String),
     Reference(Box<IronType>),
@@ -16,47 +17,57 @@ pub enum IronType {
     Slice(Box<IronType>),
     Function(Vec<IronType>, Box<IronType>), // params, return
     Generic(String, Vec<IronBound>),
+    Path { name: String, args: Vec<IronType> }, // MyType of A and B
+    Inferred,                                   // no annotation given; left to Rust
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IronBound {
     pub trait_name: String,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IronParam {
     pub name: String,
     pub ty: IronType,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IronField {
     pub name: String,
     pub ty: IronType,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IronVariant {
     pub name: String,
     pub data: Option<IronVariantData>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IronVariantData {
     Type(IronType),
     Fields(Vec<IronField>),
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IronGeneric {
     pub name: String,
     pub bounds: Vec<IronBound>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IronExpr {
     Identifier(String),
     String(String),
+    Char(char),
     Integer(String),
     Float(String),
     Boolean(bool),
@@ -105,6 +116,10 @@ pub enum IronExpr {
         name: String,
         fields: Vec<(IronField, IronExpr)>,
     },
+    TupleStruct {
+        name: String,
+        args: Vec<IronExpr>,
+    },
     Index {
         base: Box<IronExpr>,
         index: Box<IronExpr>,
@@ -118,9 +133,16 @@ pub enum IronExpr {
         params: Vec<IronParam>,
         body: Vec<IronStmt>,
     },
+    If {
+        condition: Box<IronExpr>,
+        then_block: Vec<IronStmt>,
+        else_block: Option<Vec<IronStmt>>,
+    },
+    Group(Box<IronExpr>),
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IronBinaryOp {
     Add,
     Sub,
@@ -143,6 +165,7 @@ pub enum IronBinaryOp {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IronUnaryOp {
     Not,
     Neg,
@@ -150,6 +173,7 @@ pub enum IronUnaryOp {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IronStmt {
     Let {
         name: String,
@@ -160,6 +184,11 @@ pub enum IronStmt {
         target: IronExpr,
         value: IronExpr,
     },
+    CompoundAssign {
+        target: IronExpr,
+        op: IronBinaryOp,
+        value: IronExpr,
+    },
     Expr(IronExpr),
     Return(Option<IronExpr>),
     Break,
@@ -178,13 +207,19 @@ pub enum IronStmt {
         iterator: IronExpr,
         body: Vec<IronStmt>,
     },
+    Loop {
+        body: Vec<IronStmt>,
+    },
     Match {
         expr: IronExpr,
         arms: Vec<(IronPattern, IronExpr)>,
     },
+    Comment(String),     // "note that ..."
+    Item(Box<IronItem>), // local function, structure, constant, ...
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IronPattern {
     Identifier(String),
     Wildcard,
@@ -202,7 +237,9 @@ pub enum IronPattern {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IronFunction {
+    pub line: usize, // where the item starts in the Iron source
     pub name: String,
     pub generics: Vec<IronGeneric>,
     pub params: Vec<IronParam>,
@@ -211,21 +248,27 @@ pub struct IronFunction {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IronStruct {
+    pub line: usize, // where the item starts in the Iron source
     pub name: String,
     pub generics: Vec<IronGeneric>,
     pub fields: Vec<IronField>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IronEnum {
+    pub line: usize, // where the item starts in the Iron source
     pub name: String,
     pub generics: Vec<IronGeneric>,
     pub variants: Vec<IronVariant>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IronStatic {
+    pub line: usize, // where the item starts in the Iron source
     pub name: String,
     pub mutable: bool,
     pub ty: IronType,
@@ -233,20 +276,96 @@ pub struct IronStatic {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IronConst {
+    pub line: usize, // where the item starts in the Iron source
     pub name: String,
     pub ty: IronType,
     pub value: IronExpr,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IronTypeAlias {
+    pub line: usize, // where the item starts in the Iron source
     pub name: String,
     pub generics: Vec<IronGeneric>,
     pub ty: IronType,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IronModule {
+    pub name: String,
+    pub items: Vec<IronItem>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IronUseTree {
+    Path {
+        segment: String,
+        rest: Box<IronUseTree>,
+    },
+    Name {
+        name: String,
+        alias: Option<String>,
+    },
+    Group(Vec<IronUseTree>),
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IronImport {
+    pub tree: IronUseTree,
+}
+
+/// An item carried as verbatim Rust, with what it is so tools can report and filter it
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IronVerbatim {
+    pub line: usize, // where the item starts in the Iron source
+    pub rust: String,
+    pub kind: String, // "impl", "trait", "macro", ..., or "item" if the Rust is not one item
+    pub name: Option<String>, // what the item declares or, for an impl, implements for
+    pub span: Option<RustSpan>, // where the item was in the Rust, when reduction made the tree
+}
+
+/// A stretch of Rust source, as (line, 1-based column) of its start and just past its end
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RustSpan {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+impl IronVerbatim {
+    /// A verbatim item at Iron line `line`, its kind and name read from the Rust
+    pub fn new(line: usize, rust: String) -> Self {
+        let (kind, name) = match syn::parse_str::<syn::Item>(&rust) {
+            Ok(item) => crate::parser::item_kind(&item),
+            Err(_) => ("item", None),
+        };
+        IronVerbatim {
+            line,
+            rust,
+            kind: kind.to_string(),
+            name,
+            span: None,
+        }
+    }
+
+    /// The Rust parsed as an item, or `None` if it is not exactly one item
+    ///
+    /// The item is parsed on each call rather than stored, since `syn` trees cannot be
+    /// shared between threads and syntax trees here can.
+    pub fn syntax(&self) -> Option<syn::Item> {
+        syn::parse_str(&self.rust).ok()
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IronItem {
     Function(IronFunction),
     Struct(IronStruct),
@@ -254,10 +373,32 @@ pub enum IronItem {
     Static(IronStatic),
     Const(IronConst),
     TypeAlias(IronTypeAlias),
-    Verbatim(String),
+    Module(IronModule),
+    Import(IronImport),
+    Verbatim(IronVerbatim),
+    Comment(String), // "note that ..."
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IronFile {
     pub items: Vec<IronItem>,
+    pub warnings: Vec<IronWarning>, // problems the parser worked around
+}
+
+/// A problem worked around during parsing or oxidation rather than reported as an error
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IronWarning {
+    pub line: Option<usize>, // Iron source line, when the problem has one
+    pub message: String,
+}
+
+impl std::fmt::Display for IronWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}", line, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
 }
@@ -0,0 +1,321 @@
+//! Iron source formatter
+//!
+//! Re-emits Iron with canonical indentation, spacing, blank lines, and keyword casing,
+//! so hand-edited or model-generated Iron can be normalized. Formatting works on the
+//! token stream, so comments and raw blocks are kept exactly.
+
+use crate::header::{self, IronHeader};
+use crate::iron_parser::{IronParser, ParseError};
+use crate::iron_tokenizer::{SpannedToken, Token, Tokenizer};
+
+/// Builder for formatting Iron source
+pub struct IronFormatter {
+    indent_size: usize,
+    case_insensitive_keywords: bool,
+}
+
+/// One source line of tokens, with how it changes the block depth
+struct Line<'a> {
+    tokens: &'a [SpannedToken],
+    /// Whether the line starts by closing a block (`end function`, ...)
+    closes_first: bool,
+    /// Whether the line continues a function header (`takes ...`, `returns ...`)
+    continues_header: bool,
+    opened: usize,
+    closed: usize,
+}
+
+impl IronFormatter {
+    /// Create a formatter with the default 4-space indentation
+    pub fn new() -> Self {
+        Self {
+            indent_size: 4,
+            case_insensitive_keywords: false,
+        }
+    }
+
+    /// Read keywords in any capitalization, writing them back in lowercase
+    pub fn with_case_insensitive_keywords(mut self, enabled: bool) -> Self {
+        self.case_insensitive_keywords = enabled;
+        self
+    }
+
+    /// Format Iron source, which must parse
+    ///
+    /// A header that checks out gets the hash of the formatted content; one that does
+    /// not is kept as it is, so formatting cannot make an edited file pass.
+    pub fn format(&self, source: &str) -> Result<String, ParseError> {
+        let mut parser = IronParser::from_tokenizer(self.tokenizer(source));
+        parser.parse()?;
+        let formatted = self.format_content(source);
+
+        let Some((line, _)) = header::split_header(source) else {
+            return Ok(formatted);
+        };
+        let content = format!("\n{}", formatted);
+        let line = match parser.header() {
+            Some(checked) => IronHeader {
+                content_hash: header::hash(&content),
+                ..checked.clone()
+            }
+            .to_string(),
+            None => line.to_string(),
+        };
+        Ok(format!("{}\n{}", line, content))
+    }
+
+    /// Format the Iron after any header line
+    fn format_content(&self, source: &str) -> String {
+        let tokens = self.tokenizer(source).tokenize_spanned();
+
+        let lines: Vec<Option<Line>> = tokens
+            .split(|spanned| spanned.token == Token::NewLine)
+            .map(|tokens| {
+                let tokens = match tokens.first() {
+                    Some(SpannedToken {
+                        token: Token::Indent(_),
+                        ..
+                    }) => &tokens[1..],
+                    _ => tokens,
+                };
+                let tokens = match tokens.last() {
+                    Some(SpannedToken {
+                        token: Token::EndOfFile,
+                        ..
+                    }) => &tokens[..tokens.len() - 1],
+                    _ => tokens,
+                };
+                (!tokens.is_empty()).then(|| Line::new(tokens))
+            })
+            .collect();
+
+        let mut output = String::new();
+        let mut depth = 0usize;
+        let mut blank_pending = false;
+        let mut block_start = true;
+        for (index, line) in lines.iter().enumerate() {
+            let Some(line) = line else {
+                blank_pending = true;
+                continue;
+            };
+
+            // Blank lines are kept between statements and items, but not at the start of
+            // the file or a block, nor at the end of a block
+            if blank_pending && !block_start && !line.closes_first {
+                output.push('\n');
+            }
+            blank_pending = false;
+
+            let level = depth.saturating_sub(usize::from(line.closes_first))
+                + usize::from(line.continues_header);
+            let indent = level * self.indent_size;
+            output.push_str(&" ".repeat(indent));
+            output.push_str(&self.format_line(line.tokens, source, indent));
+            output.push('\n');
+
+            block_start = line.opened > line.closed;
+            depth = (depth + line.opened).saturating_sub(line.closed);
+            if block_start {
+                continue;
+            }
+
+            // Top-level blocks are separated by a blank line
+            let next_is_code = lines.get(index + 1).is_some_and(Option::is_some);
+            if depth == 0 && line.closed > 0 && next_is_code {
+                blank_pending = true;
+            }
+        }
+        output
+    }
+
+    fn tokenizer(&self, source: &str) -> Tokenizer {
+        Tokenizer::new(source).with_case_insensitive_keywords(self.case_insensitive_keywords)
+    }
+
+    /// The tokens of a line separated by single spaces
+    fn format_line(&self, tokens: &[SpannedToken], source: &str, indent: usize) -> String {
+        let mut text = String::new();
+        for spanned in tokens {
+            if !text.is_empty() && spanned.token != Token::Comma {
+                text.push(' ');
+            }
+            text.push_str(&self.format_token(spanned, source, indent));
+        }
+        text
+    }
+
+    fn format_token(&self, spanned: &SpannedToken, source: &str, indent: usize) -> String {
+        let written = &source[spanned.byte_range.clone()];
+        match &spanned.token {
+            Token::Comment(text) if text.is_empty() => "note that".to_string(),
+            Token::Comment(text) => format!("note that {}", text),
+            Token::String(_) | Token::VerbatimBlock(_) if written.contains('\n') => {
+                reindent_raw_block(written, indent)
+            }
+            Token::Identifier(_) | Token::String(_) | Token::Char(_) => written.to_string(),
+            // A fused `negative 5` keeps the digits of the number as written
+            Token::Integer(_) | Token::Float(_) => match written.split_once(char::is_whitespace) {
+                Some((_, number)) => format!("negative {}", number.trim_start()),
+                None => written.to_string(),
+            },
+            _ => keyword_phrase(written),
+        }
+    }
+}
+
+impl Default for IronFormatter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Line<'a> {
+    fn new(tokens: &'a [SpannedToken]) -> Self {
+        let is_closer = |token: &Token| matches!(token, Token::EndOf(_));
+        let mut opened = 0;
+        for (index, spanned) in tokens.iter().enumerate() {
+            let opens = match spanned.token {
+                Token::Begin | Token::Compare | Token::Group => true,
+                // Field and variant lists of structure and enumeration headers
+                Token::Fields | Token::Variants => {
+                    index > 0 && tokens[index - 1].token == Token::With
+                }
+                _ => false,
+            };
+            opened += usize::from(opens);
+        }
+        Line {
+            tokens,
+            // A bare `end` starting a line closes a block with an unrecognized label
+            closes_first: matches!(tokens[0].token, Token::EndOf(_) | Token::End),
+            continues_header: matches!(tokens[0].token, Token::Takes | Token::Returns),
+            opened,
+            closed: tokens
+                .iter()
+                .filter(|spanned| is_closer(&spanned.token))
+                .count()
+                + usize::from(tokens[0].token == Token::End),
+        }
+    }
+}
+
+/// A keyword in lowercase with single spaces between its words ("end function")
+fn keyword_phrase(written: &str) -> String {
+    written
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Move a raw `text begin` / `verbatim begin` block to `indent`, with its closing line
+/// there and its content shifted along with it, so the value it holds is unchanged
+fn reindent_raw_block(written: &str, indent: usize) -> String {
+    let lines: Vec<&str> = written.split('\n').collect();
+    let closing = lines[lines.len() - 1];
+    let closing_indent = closing.len() - closing.trim_start_matches(' ').len();
+    let padding = " ".repeat(indent);
+
+    let mut block = vec![keyword_phrase(lines[0])];
+    for line in &lines[1..lines.len() - 1] {
+        let line_indent = line.len() - line.trim_start_matches(' ').len();
+        let content = &line[line_indent.min(closing_indent)..];
+        if content.is_empty() {
+            block.push(String::new());
+        } else {
+            block.push(format!("{}{}", padding, content));
+        }
+    }
+    block.push(format!("{}{}", padding, keyword_phrase(closing)));
+    block.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_indentation_and_blank_lines() {
+        let source = "\n\nfunction  f\n  takes x of i32\nbegin\n\n      define y as x   plus 1\n\n\n  if y greater than 2 then\n        begin\n  return y\n   end if\n\n  y\nend   function\nstructure P with fields\nx of i32\nend structure\n\n\n";
+        let formatted = IronFormatter::new().format(source).unwrap();
+        assert_eq!(
+            formatted,
+            "function f\n    \
+                 takes x of i32\n\
+             begin\n    \
+                 define y as x plus 1\n\
+             \n    \
+                 if y greater than 2 then\n    \
+                 begin\n        \
+                     return y\n    \
+                 end if\n\
+             \n    \
+                 y\n\
+             end function\n\
+             \n\
+             structure P with fields\n    \
+                 x of i32\n\
+             end structure\n"
+        );
+        assert_eq!(IronFormatter::new().format(&formatted).unwrap(), formatted);
+    }
+
+    #[test]
+    fn test_format_keyword_casing() {
+        let source = "Function Main\nBegin\n    Note That keep Case\n    Call Run With Result_value And negative 0xFF\nEND FUNCTION\n";
+        let formatted = IronFormatter::new()
+            .with_case_insensitive_keywords(true)
+            .format(source)
+            .unwrap();
+        assert_eq!(
+            formatted,
+            "function Main\nbegin\n    note that keep Case\n    call Run with Result_value and negative 0xFF\nend function\n"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_raw_block_values() {
+        let source =
+            "function f\nbegin\ndefine s as text begin\n  a\n\n      b\n  text end\nend function\n";
+        let formatted = IronFormatter::new().format(source).unwrap();
+        assert_eq!(
+            formatted,
+            "function f\nbegin\n    define s as text begin\n    a\n\n        b\n    text end\nend function\n"
+        );
+
+        let value = |iron: &str| {
+            Tokenizer::new(iron)
+                .tokenize()
+                .into_iter()
+                .find(|token| matches!(token, Token::String(_)))
+        };
+        assert_eq!(value(&formatted), value(source));
+    }
+
+    #[test]
+    fn test_format_restamps_header() {
+        let iron = header::add_header("function f\nbegin\nend function\n", "fn f() {}");
+        let formatter = IronFormatter::new().with_case_insensitive_keywords(true);
+
+        // Reformatting checked Iron keeps it checked
+        let messy = iron.replace("\nbegin", "\nBegin\n");
+        let formatted = formatter.format(&messy).unwrap();
+        assert_eq!(formatted, iron);
+        let mut parser = IronParser::new(&formatted);
+        parser.parse().unwrap();
+        assert!(parser.header().is_some());
+
+        // An edit is still caught after formatting
+        let edited = messy.replace("function f", "function g");
+        let formatted = formatter.format(&edited).unwrap();
+        assert!(formatted.starts_with(header::split_header(&iron).unwrap().0));
+        let mut parser = IronParser::new(&formatted);
+        parser.parse().unwrap();
+        assert!(parser.header().is_none());
+    }
+
+    #[test]
+    fn test_format_rejects_invalid_iron() {
+        assert!(IronFormatter::new().format("function f\nbegin\n").is_err());
+    }
+}
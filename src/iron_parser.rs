@@ -2,32 +2,91 @@
 //!
 //! Parses Iron tokens into an AST for transpilation to Rust.
 
+use crate::header::IronHeader;
 use crate::iron_ast::*;
-use crate::iron_tokenizer::{Token, Tokenizer};
+use crate::iron_tokenizer::{Position, SpannedToken, Token, Tokenizer};
 
 pub struct IronParser {
-    tokens: Vec<Token>,
+    tokens: Vec<SpannedToken>,
     position: usize,
+    /// Set while parsing an operand, where calls do not take arguments
+    bare_operand: bool,
+    /// Problems worked around while parsing, handed on with the `IronFile`
+    warnings: Vec<IronWarning>,
+    mode: ParseMode,
+    /// Type names of several words beyond the built-in ones, longest first
+    type_names: Vec<Vec<String>>,
+    /// The input's header line, read and checked against the content after it
+    header: Option<Result<IronHeader, String>>,
 }
 
-#[derive(Debug)]
+/// How the parser treats input outside the Iron grammar
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Reject anything off-grammar, for validating Iron
+    Strict,
+    /// Recover where possible and warn, for model output
+    #[default]
+    Lenient,
+}
+
+impl std::str::FromStr for ParseMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(ParseMode::Strict),
+            "lenient" => Ok(ParseMode::Lenient),
+            _ => Err(format!(
+                "unknown parse mode '{}' (expected strict or lenient)",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for ParseMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ParseMode::Strict => "strict",
+            ParseMode::Lenient => "lenient",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
 pub enum ParseError {
-    UnexpectedToken(Token, String),
-    UnexpectedEndOfInput,
-    InvalidSyntax(String),
+    UnexpectedToken(Token, String, Position),
+    UnexpectedEndOfInput(Position),
+    InvalidSyntax(String, Position),
+}
+
+impl ParseError {
+    /// Where in the Iron source the error was detected
+    pub fn position(&self) -> Position {
+        match self {
+            ParseError::UnexpectedToken(_, _, position)
+            | ParseError::UnexpectedEndOfInput(position)
+            | ParseError::InvalidSyntax(_, position) => *position,
+        }
+    }
 }
 
 impl std::fmt::Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParseError::UnexpectedToken(token, expected) => {
-                write!(f, "Unexpected token {:?}, expected {}", token, expected)
+            ParseError::UnexpectedToken(token, expected, position) => {
+                write!(
+                    f,
+                    "{}: Unexpected token {:?}, expected {}",
+                    position, token, expected
+                )
             }
-            ParseError::UnexpectedEndOfInput => {
-                write!(f, "Unexpected end of input")
+            ParseError::UnexpectedEndOfInput(position) => {
+                write!(f, "{}: Unexpected end of input", position)
             }
-            ParseError::InvalidSyntax(msg) => {
-                write!(f, "Invalid syntax: {}", msg)
+            ParseError::InvalidSyntax(msg, position) => {
+                write!(f, "{}: Invalid syntax: {}", position, msg)
             }
         }
     }
@@ -37,19 +96,95 @@ impl std::error::Error for ParseError {}
 
 impl IronParser {
     pub fn new(input: &str) -> Self {
-        let mut tokenizer = Tokenizer::new(input);
-        let tokens = tokenizer.tokenize();
+        Self::from_tokenizer(Tokenizer::new(input))
+    }
+
+    /// Parse the tokens of a configured tokenizer, e.g. one matching keywords
+    /// case-insensitively
+    pub fn from_tokenizer(mut tokenizer: Tokenizer) -> Self {
+        let tokens = Self::hoist_trailing_comments(tokenizer.tokenize_spanned());
+        let header = tokenizer.header().map(|(line, content)| {
+            let header = IronHeader::parse(line)?;
+            header.verify(content)?;
+            Ok(header)
+        });
         Self {
+            header,
             tokens,
             position: 0,
+            bare_operand: false,
+            warnings: Vec::new(),
+            mode: ParseMode::default(),
+            type_names: Vec::new(),
+        }
+    }
+
+    /// Choose whether off-grammar input is an error or recovered from with a warning
+    pub fn with_mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Read these extra type names, such as those of a
+    /// [`TypeDictionary`](crate::dictionary::TypeDictionary), as one name even when
+    /// they are several words
+    pub fn with_type_names<S: AsRef<str>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        self.type_names = names
+            .into_iter()
+            .map(|name| name.as_ref().split(' ').map(str::to_string).collect())
+            .collect();
+        self.type_names
+            .sort_by_key(|words| std::cmp::Reverse(words.len()));
+        self
+    }
+
+    /// Move comments that follow code on the same line to their own line just above it,
+    /// so they can be kept as comment items or statements
+    fn hoist_trailing_comments(tokens: Vec<SpannedToken>) -> Vec<SpannedToken> {
+        let mut hoisted = Vec::with_capacity(tokens.len());
+        let mut line_start = 0;
+
+        for spanned in tokens {
+            match spanned.token {
+                Token::NewLine => {
+                    hoisted.push(spanned);
+                    line_start = hoisted.len();
+                }
+                Token::Indent(_) if hoisted.len() == line_start => {
+                    hoisted.push(spanned);
+                    line_start = hoisted.len();
+                }
+                Token::Comment(_) if hoisted.len() != line_start => {
+                    let newline = SpannedToken {
+                        token: Token::NewLine,
+                        ..spanned.clone()
+                    };
+                    hoisted.insert(line_start, spanned);
+                    hoisted.insert(line_start + 1, newline);
+                    line_start += 2;
+                }
+                _ => hoisted.push(spanned),
+            }
         }
+
+        hoisted
+    }
+
+    /// The header the input starts with, once it has been checked
+    pub fn header(&self) -> Option<&IronHeader> {
+        self.header.as_ref()?.as_ref().ok()
     }
 
     pub fn parse(&mut self) -> Result<IronFile, ParseError> {
         let mut items = Vec::new();
 
+        // A header that does not check out is off-grammar input on the first line
+        if let Some(Err(message)) = self.header.clone() {
+            self.recover(message)?;
+        }
+
         while !self.is_at_end() {
-            self.skip_newlines();
+            self.skip_blank_lines();
             if self.is_at_end() {
                 break;
             }
@@ -58,7 +193,10 @@ impl IronParser {
             items.push(item);
         }
 
-        Ok(IronFile { items })
+        Ok(IronFile {
+            items,
+            warnings: std::mem::take(&mut self.warnings),
+        })
     }
 
     fn parse_item(&mut self) -> Result<IronItem, ParseError> {
@@ -69,16 +207,29 @@ impl IronParser {
             Some(Token::Static) => self.parse_static(),
             Some(Token::Constant) => self.parse_const(),
             Some(Token::Type) => self.parse_type_alias(),
+            Some(Token::Module) => self.parse_module(),
+            Some(Token::Import) => self.parse_import(),
             Some(Token::Verbatim) => self.parse_verbatim_item(),
-            Some(token) => Err(ParseError::UnexpectedToken(
-                token.clone(),
-                "function, structure, enumeration, static, constant, type, or verbatim".to_string(),
+            Some(Token::VerbatimBlock(payload)) => {
+                let payload = payload.clone();
+                let line = self.current_position().line;
+                self.advance();
+                Ok(IronItem::Verbatim(IronVerbatim::new(line, payload)))
+            }
+            Some(Token::Comment(text)) => {
+                let text = text.clone();
+                self.advance();
+                Ok(IronItem::Comment(text))
+            }
+            _ => Err(self.unexpected(
+                "function, structure, enumeration, static, constant, type, module, import, \
+                 or verbatim",
             )),
-            None => Err(ParseError::UnexpectedEndOfInput),
         }
     }
 
     fn parse_function(&mut self) -> Result<IronItem, ParseError> {
+        let line = self.current_position().line;
         self.expect(Token::Function)?;
 
         // Parse function name
@@ -107,15 +258,10 @@ impl IronParser {
         self.skip_newlines();
         self.expect(Token::Begin)?;
         let body = self.parse_block()?;
-        self.expect(Token::End)?;
-
-        // Expect "function" after end
-        if !self.match_identifier("function") {
-            // Could be another block type, just skip it
-            self.advance();
-        }
+        self.expect_block_end(Token::Function, line)?;
 
         Ok(IronItem::Function(IronFunction {
+            line,
             name,
             generics,
             params,
@@ -125,6 +271,7 @@ impl IronParser {
     }
 
     fn parse_struct(&mut self) -> Result<IronItem, ParseError> {
+        let line = self.current_position().line;
         self.expect(Token::Structure)?;
 
         let name = self.expect_identifier()?;
@@ -134,9 +281,9 @@ impl IronParser {
         self.expect(Token::Fields)?;
 
         let mut fields = Vec::new();
-        while !self.check(Token::End) && !self.is_at_end() {
+        while !self.at_block_end() && !self.is_at_end() {
             self.skip_newlines();
-            if self.check(Token::End) {
+            if self.at_block_end() {
                 break;
             }
 
@@ -150,10 +297,10 @@ impl IronParser {
             });
         }
 
-        self.expect(Token::End)?;
-        self.advance(); // Skip "structure"
+        self.expect_block_end(Token::Structure, line)?;
 
         Ok(IronItem::Struct(IronStruct {
+            line,
             name,
             generics,
             fields,
@@ -161,6 +308,7 @@ impl IronParser {
     }
 
     fn parse_enum(&mut self) -> Result<IronItem, ParseError> {
+        let line = self.current_position().line;
         self.expect(Token::Enumeration)?;
 
         let name = self.expect_identifier()?;
@@ -170,9 +318,9 @@ impl IronParser {
         self.expect(Token::Variants)?;
 
         let mut variants = Vec::new();
-        while !self.check(Token::End) && !self.is_at_end() {
+        while !self.at_block_end() && !self.is_at_end() {
             self.skip_newlines();
-            if self.check(Token::End) {
+            if self.at_block_end() {
                 break;
             }
 
@@ -194,10 +342,10 @@ impl IronParser {
             });
         }
 
-        self.expect(Token::End)?;
-        self.advance(); // Skip "enumeration"
+        self.expect_block_end(Token::Enumeration, line)?;
 
         Ok(IronItem::Enum(IronEnum {
+            line,
             name,
             generics,
             variants,
@@ -205,6 +353,7 @@ impl IronParser {
     }
 
     fn parse_static(&mut self) -> Result<IronItem, ParseError> {
+        let line = self.current_position().line;
         self.expect(Token::Static)?;
 
         let mutable = self.match_token(Token::Mutable);
@@ -213,13 +362,10 @@ impl IronParser {
         self.expect(Token::Of)?;
         let ty = self.parse_type()?;
 
-        self.expect(Token::Begin)?;
-        // For now, parse value as expression - this is simplified
-        let value = IronExpr::Integer("0".to_string());
-        self.expect(Token::End)?;
-        self.advance(); // Skip "static"
+        let value = self.parse_initializer_block(Token::Static, line)?;
 
         Ok(IronItem::Static(IronStatic {
+            line,
             name,
             mutable,
             ty,
@@ -228,6 +374,7 @@ impl IronParser {
     }
 
     fn parse_const(&mut self) -> Result<IronItem, ParseError> {
+        let line = self.current_position().line;
         self.expect(Token::Constant)?;
 
         let name = self.expect_identifier()?;
@@ -235,16 +382,33 @@ impl IronParser {
         self.expect(Token::Of)?;
         let ty = self.parse_type()?;
 
-        self.expect(Token::Begin)?;
-        // For now, parse value as expression - this is simplified
-        let value = IronExpr::Integer("0".to_string());
-        self.expect(Token::End)?;
-        self.advance(); // Skip "constant"
+        let value = self.parse_initializer_block(Token::Constant, line)?;
+
+        Ok(IronItem::Const(IronConst {
+            line,
+            name,
+            ty,
+            value,
+        }))
+    }
 
-        Ok(IronItem::Const(IronConst { name, ty, value }))
+    /// Parse the `begin <expression> end <label>` initializer of the item opened at `line`
+    fn parse_initializer_block(
+        &mut self,
+        item: Token,
+        line: usize,
+    ) -> Result<IronExpr, ParseError> {
+        self.skip_newlines();
+        self.expect(Token::Begin)?;
+        self.skip_newlines();
+        let value = self.parse_expression()?;
+        self.skip_newlines();
+        self.expect_block_end(item, line)?;
+        Ok(value)
     }
 
     fn parse_type_alias(&mut self) -> Result<IronItem, ParseError> {
+        let line = self.current_position().line;
         self.expect(Token::Type)?;
 
         let name = self.expect_identifier()?;
@@ -252,10 +416,100 @@ impl IronParser {
         self.expect(Token::As)?;
         let ty = self.parse_type()?;
 
-        Ok(IronItem::TypeAlias(IronTypeAlias { name, generics, ty }))
+        Ok(IronItem::TypeAlias(IronTypeAlias {
+            line,
+            name,
+            generics,
+            ty,
+        }))
+    }
+
+    fn parse_module(&mut self) -> Result<IronItem, ParseError> {
+        let line = self.current_position().line;
+        self.expect(Token::Module)?;
+
+        let name = self.expect_identifier()?;
+
+        self.skip_newlines();
+        self.expect(Token::Begin)?;
+
+        let mut items = Vec::new();
+        while !self.at_block_end() && !self.is_at_end() {
+            self.skip_blank_lines();
+            if self.at_block_end() {
+                break;
+            }
+
+            items.push(self.parse_item()?);
+        }
+
+        self.expect_block_end(Token::Module, line)?;
+
+        Ok(IronItem::Module(IronModule { name, items }))
+    }
+
+    fn parse_import(&mut self) -> Result<IronItem, ParseError> {
+        self.expect(Token::Import)?;
+
+        let mut prefix = self.parse_import_segments()?;
+
+        let leaf = if self.match_token(Token::Containing) {
+            // Grouped import: import std collections containing HashMap and HashSet
+            let mut entries = Vec::new();
+            loop {
+                let mut segments = self.parse_import_segments()?;
+                let leaf = self.parse_import_name(&mut segments)?;
+                entries.push(Self::wrap_use_path(segments, leaf));
+
+                if !self.match_token(Token::And) {
+                    break;
+                }
+            }
+            IronUseTree::Group(entries)
+        } else {
+            self.parse_import_name(&mut prefix)?
+        };
+
+        Ok(IronItem::Import(IronImport {
+            tree: Self::wrap_use_path(prefix, leaf),
+        }))
+    }
+
+    fn parse_import_segments(&mut self) -> Result<Vec<String>, ParseError> {
+        let mut segments = vec![self.expect_symbol_identifier()?];
+        while let Some(segment) = self.take_symbol_identifier() {
+            segments.push(segment);
+        }
+        Ok(segments)
+    }
+
+    /// Pops the final path segment as the imported name, with an optional `as` rename.
+    fn parse_import_name(&mut self, segments: &mut Vec<String>) -> Result<IronUseTree, ParseError> {
+        let name = segments.pop().ok_or_else(|| {
+            ParseError::InvalidSyntax("import path is empty".to_string(), self.current_position())
+        })?;
+
+        let alias = if self.match_token(Token::As) {
+            Some(self.expect_symbol_identifier()?)
+        } else {
+            None
+        };
+
+        Ok(IronUseTree::Name { name, alias })
+    }
+
+    fn wrap_use_path(segments: Vec<String>, leaf: IronUseTree) -> IronUseTree {
+        segments
+            .into_iter()
+            .rev()
+            .fold(leaf, |rest, segment| IronUseTree::Path {
+                segment,
+                rest: Box::new(rest),
+            })
     }
 
     fn parse_verbatim_item(&mut self) -> Result<IronItem, ParseError> {
+        let line = self.current_position().line;
         self.expect(Token::Verbatim)?;
 
         if self.match_identifier("item") {
@@ -268,16 +522,10 @@ impl IronParser {
                 self.advance();
                 value
             }
-            Some(token) => {
-                return Err(ParseError::UnexpectedToken(
-                    token.clone(),
-                    "string literal payload".to_string(),
-                ));
-            }
-            None => return Err(ParseError::UnexpectedEndOfInput),
+            _ => return Err(self.unexpected("string literal payload")),
         };
 
-        Ok(IronItem::Verbatim(payload))
+        Ok(IronItem::Verbatim(IronVerbatim::new(line, payload)))
     }
 
     fn parse_generics_clause(&mut self) -> Result<Vec<IronGeneric>, ParseError> {
@@ -285,10 +533,7 @@ impl IronParser {
 
         while let Some(&Token::With) = self.peek() {
             // Peek ahead to see if this "with" is followed by "generic"
-            let next_is_generic = match self.tokens.get(self.position + 1) {
-                Some(Token::Generic) => true,
-                _ => false,
-            };
+            let next_is_generic = matches!(self.peek_next(), Some(Token::Generic));
 
             if !next_is_generic {
                 break;
@@ -362,6 +607,39 @@ impl IronParser {
     }
 
     fn parse_type(&mut self) -> Result<IronType, ParseError> {
+        // Mapped std types: hash map from K to V, [atomic] reference counted T
+        if self.check_word(0, "hash") && self.check_word(1, "map") {
+            self.position += 2;
+            if !self.match_token(Token::From) {
+                return Ok(IronType::Named("hash map".to_string()));
+            }
+            let key = self.parse_type()?;
+            self.expect(Token::To)?;
+            let value = self.parse_type()?;
+            return Ok(IronType::Path {
+                name: "hash map".to_string(),
+                args: vec![key, value],
+            });
+        }
+
+        let atomic = self.check_word(0, "atomic");
+        let counted_at = if atomic { 1 } else { 0 };
+        if self.peek_at(counted_at) == Some(&Token::Reference)
+            && self.check_word(counted_at + 1, "counted")
+        {
+            self.position += counted_at + 2;
+            let inner = self.parse_type()?;
+            let name = if atomic {
+                "atomic reference counted"
+            } else {
+                "reference counted"
+            };
+            return Ok(IronType::Path {
+                name: name.to_string(),
+                args: vec![inner],
+            });
+        }
+
         // Complex type parsing
         if self.match_token(Token::Reference) {
             self.expect(Token::To)?;
@@ -461,7 +739,10 @@ impl IronParser {
         }
 
         // Simple type name
-        let name = self.expect_identifier()?;
+        let name = match self.match_type_name() {
+            Some(name) => name,
+            None => self.expect_identifier()?,
+        };
 
         if name == "string" && self.match_token(Token::Slice) {
             return Ok(IronType::Named("string slice".to_string()));
@@ -477,104 +758,80 @@ impl IronParser {
                 }
             }
 
-            let rendered_args = args
-                .iter()
-                .map(Self::render_type_for_rust)
-                .collect::<Vec<_>>()
-                .join(", ");
-            return Ok(IronType::Named(format!("{}<{}>", name, rendered_args)));
+            return Ok(IronType::Path { name, args });
         }
 
         Ok(IronType::Named(name))
     }
 
-    fn render_type_for_rust(ty: &IronType) -> String {
-        match ty {
-            IronType::Named(name) => match name.as_str() {
-                "boolean" => "bool".to_string(),
-                "character" => "char".to_string(),
-                "string" => "String".to_string(),
-                "string slice" => "str".to_string(),
-                "list" => "Vec".to_string(),
-                "optional" => "Option".to_string(),
-                "result" => "Result".to_string(),
-                "hash map" => "HashMap".to_string(),
-                "box" => "Box".to_string(),
-                "reference counted" => "Rc".to_string(),
-                "atomic reference counted" => "Arc".to_string(),
-                "unit" => "()".to_string(),
-                "error" => "dyn std::error::Error".to_string(),
-                _ => name.clone(),
-            },
-            IronType::Reference(inner) => format!("&{}", Self::render_type_for_rust(inner)),
-            IronType::MutableReference(inner) => {
-                format!("&mut {}", Self::render_type_for_rust(inner))
-            }
-            IronType::RawPointer(inner) => format!("*const {}", Self::render_type_for_rust(inner)),
-            IronType::MutableRawPointer(inner) => {
-                format!("*mut {}", Self::render_type_for_rust(inner))
-            }
-            IronType::Optional(inner) => format!("Option<{}>", Self::render_type_for_rust(inner)),
-            IronType::Result(ok, err) => format!(
-                "Result<{}, {}>",
-                Self::render_type_for_rust(ok),
-                Self::render_type_for_rust(err)
-            ),
-            IronType::List(inner) => format!("Vec<{}>", Self::render_type_for_rust(inner)),
-            IronType::BoxType(inner) => format!("Box<{}>", Self::render_type_for_rust(inner)),
-            IronType::Tuple(items) => {
-                let rendered = items
-                    .iter()
-                    .map(Self::render_type_for_rust)
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                format!("({})", rendered)
-            }
-            IronType::Array(inner) => format!("[{}]", Self::render_type_for_rust(inner)),
-            IronType::Slice(inner) => format!("[{}]", Self::render_type_for_rust(inner)),
-            IronType::Function(params, ret) => {
-                let rendered_params = params
-                    .iter()
-                    .map(Self::render_type_for_rust)
-                    .collect::<Vec<_>>()
-                    .join(", ");
-                format!(
-                    "fn({}) -> {}",
-                    rendered_params,
-                    Self::render_type_for_rust(ret)
-                )
-            }
-            IronType::Generic(name, _bounds) => name.clone(),
-        }
-    }
-
     fn parse_block(&mut self) -> Result<Vec<IronStmt>, ParseError> {
         let mut stmts = Vec::new();
 
-        while !self.check(Token::End) && !self.is_at_end() {
-            self.skip_newlines();
+        while !self.at_block_end() && !self.is_at_end() {
+            self.skip_blank_lines();
 
-            if self.check(Token::End) {
+            if self.at_block_end() {
                 break;
             }
 
             let stmt = self.parse_statement()?;
             stmts.push(stmt);
+            self.expect_statement_end()?;
         }
 
         Ok(stmts)
     }
 
+    /// In strict mode, require the line to end after a statement, so a trailing token is
+    /// an error rather than a statement of its own
+    fn expect_statement_end(&mut self) -> Result<(), ParseError> {
+        // A statement may already have read the line break, looking for a continuation
+        let line_ended = self.position > 0
+            && matches!(
+                self.tokens[self.position - 1].token,
+                Token::NewLine | Token::Indent(_)
+            );
+        match self.peek() {
+            _ if self.mode == ParseMode::Lenient || line_ended => Ok(()),
+            None | Some(Token::NewLine | Token::Comment(_) | Token::End | Token::EndOf(_)) => {
+                Ok(())
+            }
+            Some(_) => Err(self.unexpected("end of line after the statement")),
+        }
+    }
+
     fn parse_statement(&mut self) -> Result<IronStmt, ParseError> {
         match self.peek() {
+            Some(Token::Comment(text)) => {
+                let text = text.clone();
+                self.advance();
+                Ok(IronStmt::Comment(text))
+            }
             Some(Token::Define) => self.parse_let(),
             Some(Token::Set) => self.parse_assign(),
+            Some(
+                Token::Increase | Token::Decrease | Token::Multiply | Token::Divide | Token::Update,
+            ) => self.parse_compound_assign(),
             Some(Token::If) => self.parse_if(),
             Some(Token::While) => self.parse_while(),
             Some(Token::For) => self.parse_for(),
+            Some(Token::Loop) => self.parse_loop(),
             Some(Token::Return) => self.parse_return(),
             Some(Token::Exit) => self.parse_break(),
             Some(Token::Continue) => self.parse_continue(),
+            // Rust allows items inside function bodies
+            Some(
+                Token::Function
+                | Token::Structure
+                | Token::Enumeration
+                | Token::Static
+                | Token::Constant
+                | Token::Type
+                | Token::Module
+                | Token::Import
+                | Token::Verbatim
+                | Token::VerbatimBlock(_),
+            ) => Ok(IronStmt::Item(Box::new(self.parse_item()?))),
             _ => {
                 let expr = self.parse_expression()?;
                 Ok(IronStmt::Expr(expr))
@@ -601,15 +858,76 @@ impl IronParser {
     fn parse_assign(&mut self) -> Result<IronStmt, ParseError> {
         self.expect(Token::Set)?;
 
-        let target = IronExpr::Identifier(self.expect_identifier()?);
-        self.expect(Token::Equal)?;
-        self.expect(Token::To)?;
+        let target = self.parse_place()?;
+        self.expect(Token::EqualTo)?;
         let value = self.parse_expression()?;
 
         Ok(IronStmt::Assign { target, value })
     }
 
+    /// Parse an assignable place: a variable, field, index, or dereference
+    fn parse_place(&mut self) -> Result<IronExpr, ParseError> {
+        let position = self.current_position();
+        let target = self.parse_operand()?;
+        if Self::is_place(&target) {
+            Ok(target)
+        } else {
+            Err(ParseError::InvalidSyntax(
+                format!("cannot assign to {:?}", target),
+                position,
+            ))
+        }
+    }
+
+    fn is_place(expr: &IronExpr) -> bool {
+        match expr {
+            IronExpr::Identifier(_) | IronExpr::FieldAccess { .. } | IronExpr::Index { .. } => true,
+            IronExpr::Unary {
+                op: IronUnaryOp::Deref,
+                ..
+            } => true,
+            IronExpr::Group(inner) => Self::is_place(inner),
+            _ => false,
+        }
+    }
+
+    /// Parse a compound assignment: "increase x by 1" or "update x with shift left 2"
+    fn parse_compound_assign(&mut self) -> Result<IronStmt, ParseError> {
+        let verb_op = match self.peek() {
+            Some(Token::Increase) => Some(IronBinaryOp::Add),
+            Some(Token::Decrease) => Some(IronBinaryOp::Sub),
+            Some(Token::Multiply) => Some(IronBinaryOp::Mul),
+            Some(Token::Divide) => Some(IronBinaryOp::Div),
+            _ => None,
+        };
+        self.advance();
+
+        let target = self.parse_place()?;
+
+        let op = match verb_op {
+            Some(op) => {
+                self.expect(Token::By)?;
+                op
+            }
+            None => {
+                self.expect(Token::With)?;
+                match self.peek_binary_op() {
+                    Some(op) => {
+                        self.advance();
+                        op
+                    }
+                    None => return Err(self.unexpected("binary operator")),
+                }
+            }
+        };
+
+        let value = self.parse_expression()?;
+
+        Ok(IronStmt::CompoundAssign { target, op, value })
+    }
+
     fn parse_if(&mut self) -> Result<IronStmt, ParseError> {
+        let line = self.current_position().line;
         self.expect(Token::If)?;
 
         let condition = self.parse_expression()?;
@@ -618,16 +936,19 @@ impl IronParser {
         self.expect(Token::Begin)?;
 
         let then_block = self.parse_block()?;
-        self.expect(Token::End)?;
-        self.advance(); // Skip "if"
+        self.expect_block_end(Token::If, line)?;
 
-        let else_block = if self.match_token(Token::Otherwise) {
-            self.skip_newlines();
-            self.expect(Token::Begin)?;
-            let block = self.parse_block()?;
-            self.expect(Token::End)?;
-            self.advance(); // Skip "if"
-            Some(block)
+        let else_block = if self.match_after_newlines(Token::Otherwise) {
+            if self.check(Token::If) {
+                // Else-if cascade: "otherwise if <condition> then"
+                Some(vec![self.parse_if()?])
+            } else {
+                self.skip_newlines();
+                self.expect(Token::Begin)?;
+                let block = self.parse_block()?;
+                self.expect_block_end(Token::If, line)?;
+                Some(block)
+            }
         } else {
             None
         };
@@ -639,7 +960,46 @@ impl IronParser {
         })
     }
 
+    /// Parse a conditional in value position: `if c then a otherwise b`
+    fn parse_if_expression(&mut self) -> Result<IronExpr, ParseError> {
+        let line = self.current_position().line;
+        self.expect(Token::If)?;
+
+        let condition = self.parse_expression()?;
+        self.expect(Token::Then)?;
+        let then_block = self.parse_conditional_branch(line)?;
+
+        let else_block = if self.match_after_newlines(Token::Otherwise) {
+            if self.check(Token::If) {
+                Some(vec![IronStmt::Expr(self.parse_if_expression()?)])
+            } else {
+                Some(self.parse_conditional_branch(line)?)
+            }
+        } else {
+            None
+        };
+
+        Ok(IronExpr::If {
+            condition: Box::new(condition),
+            then_block,
+            else_block,
+        })
+    }
+
+    /// Parse one branch of a conditional expression, either inline or as a begin/end block
+    fn parse_conditional_branch(&mut self, line: usize) -> Result<Vec<IronStmt>, ParseError> {
+        self.skip_newlines();
+        if self.match_token(Token::Begin) {
+            let block = self.parse_block()?;
+            self.expect_block_end(Token::If, line)?;
+            Ok(block)
+        } else {
+            Ok(vec![IronStmt::Expr(self.parse_expression()?)])
+        }
+    }
+
     fn parse_while(&mut self) -> Result<IronStmt, ParseError> {
+        let line = self.current_position().line;
         self.expect(Token::While)?;
 
         let condition = self.parse_expression()?;
@@ -649,13 +1009,13 @@ impl IronParser {
 
         let body = self.parse_block()?;
 
-        self.expect(Token::End)?;
-        self.advance(); // Skip "while"
+        self.expect_block_end(Token::While, line)?;
 
         Ok(IronStmt::While { condition, body })
     }
 
     fn parse_for(&mut self) -> Result<IronStmt, ParseError> {
+        let line = self.current_position().line;
         self.expect(Token::For)?;
         self.expect(Token::Each)?;
 
@@ -668,8 +1028,7 @@ impl IronParser {
 
         let body = self.parse_block()?;
 
-        self.expect(Token::End)?;
-        self.advance(); // Skip "for"
+        self.expect_block_end(Token::For, line)?;
 
         Ok(IronStmt::For {
             var,
@@ -678,10 +1037,24 @@ impl IronParser {
         })
     }
 
+    fn parse_loop(&mut self) -> Result<IronStmt, ParseError> {
+        let line = self.current_position().line;
+        self.expect(Token::Loop)?;
+        self.expect(Token::Forever)?;
+        self.skip_newlines();
+        self.expect(Token::Begin)?;
+
+        let body = self.parse_block()?;
+
+        self.expect_block_end(Token::Loop, line)?;
+
+        Ok(IronStmt::Loop { body })
+    }
+
     fn parse_return(&mut self) -> Result<IronStmt, ParseError> {
         self.expect(Token::Return)?;
 
-        let value = if self.check(Token::End) || self.check(Token::NewLine) {
+        let value = if self.at_block_end() || self.check(Token::NewLine) {
             None
         } else {
             Some(self.parse_expression()?)
@@ -703,17 +1076,63 @@ impl IronParser {
     }
 
     fn parse_expression(&mut self) -> Result<IronExpr, ParseError> {
-        self.parse_binary_expression(0)
+        self.parse_binary_expression(0, false)
+    }
+
+    /// Parse one element of an `and`-separated list (arguments, fields, elements)
+    ///
+    /// Inside a list, `and` always separates elements rather than acting as logical and.
+    fn parse_list_element(&mut self) -> Result<IronExpr, ParseError> {
+        self.parse_binary_expression(0, true)
+    }
+
+    /// Parse the base of a field access, index, or method call, or an index position.
+    ///
+    /// Bases bind tighter than any binary operator, so "field x of a plus 1" is
+    /// `a.x + 1`. Nested accesses associate to the right: "field a of field b of c"
+    /// is `c.b.a`. Anything looser must be wrapped in `group ... end group`.
+    ///
+    /// A call in operand position takes no `with` arguments, so "call method len on
+    /// call method trim on s with x" passes `x` to `len`; a receiver with arguments
+    /// must be grouped.
+    fn parse_operand(&mut self) -> Result<IronExpr, ParseError> {
+        self.bare_operand = true;
+        let operand = self.parse_primary_expression();
+        self.bare_operand = false;
+        operand
+    }
+
+    /// Parse the `with <arg> and <arg>...` tail of a call, absent for bare operands
+    fn parse_call_args(&mut self, bare: bool) -> Result<Vec<IronExpr>, ParseError> {
+        let mut args = Vec::new();
+        if !bare && self.match_token(Token::With) {
+            // Extra-verbose Iron introduces the arguments: "with parameters x and y"
+            self.match_token(Token::Parameters);
+            loop {
+                args.push(self.parse_list_element()?);
+                if !self.match_token(Token::And) {
+                    break;
+                }
+            }
+        }
+        Ok(args)
     }
 
-    fn parse_binary_expression(&mut self, min_precedence: u8) -> Result<IronExpr, ParseError> {
+    fn parse_binary_expression(
+        &mut self,
+        min_precedence: u8,
+        and_separates: bool,
+    ) -> Result<IronExpr, ParseError> {
         let mut left = self.parse_primary_expression()?;
 
         loop {
-            self.skip_newlines();
+            self.skip_blank_lines();
             let Some(op) = self.peek_binary_op() else {
                 break;
             };
+            if and_separates && matches!(op, IronBinaryOp::And) {
+                break;
+            }
             let precedence = self.get_precedence(&op);
             if precedence < min_precedence {
                 break;
@@ -721,28 +1140,8 @@ impl IronParser {
 
             self.advance();
 
-            // Consume additional tokens for multi-word operators
-            match op {
-                IronBinaryOp::Gt | IronBinaryOp::Lt => {
-                    self.expect(Token::Than)?;
-                    // Check for "or equal to"
-                    if self.match_token(Token::Or) {
-                        self.expect(Token::Equal)?;
-                        self.expect(Token::To)?;
-                    }
-                }
-                IronBinaryOp::Eq => {
-                    self.expect(Token::To)?;
-                }
-                IronBinaryOp::Ne => {
-                    self.expect(Token::Equal)?;
-                    self.expect(Token::To)?;
-                }
-                _ => {}
-            }
-
             self.skip_newlines();
-            let right = self.parse_binary_expression(precedence + 1)?;
+            let right = self.parse_binary_expression(precedence + 1, and_separates)?;
 
             left = IronExpr::Binary {
                 left: Box::new(left),
@@ -754,13 +1153,38 @@ impl IronParser {
         Ok(left)
     }
 
+    fn parse_unary_expression(&mut self, op: IronUnaryOp) -> Result<IronExpr, ParseError> {
+        self.advance();
+        // Unary operators bind tighter than any binary operator, so the operand is a
+        // single primary expression (which may itself be another unary expression).
+        let expr = self.parse_primary_expression()?;
+        Ok(IronExpr::Unary {
+            op,
+            expr: Box::new(expr),
+        })
+    }
+
     fn parse_primary_expression(&mut self) -> Result<IronExpr, ParseError> {
+        // Only the outermost primary of an operand is bare; nested expressions are not
+        let bare = std::mem::take(&mut self.bare_operand);
         match self.peek() {
+            Some(Token::Not) => self.parse_unary_expression(IronUnaryOp::Not),
+            Some(Token::Negate) => self.parse_unary_expression(IronUnaryOp::Neg),
+            Some(Token::Dereference) => self.parse_unary_expression(IronUnaryOp::Deref),
+            Some(Token::If) => self.parse_if_expression(),
+            Some(Token::Group) => {
+                let line = self.current_position().line;
+                self.advance();
+                let inner = self.parse_expression()?;
+                self.skip_newlines();
+                self.expect_block_end(Token::Group, line)?;
+                Ok(IronExpr::Group(Box::new(inner)))
+            }
             Some(Token::Field) => {
                 self.advance();
                 let field_name = self.expect_identifier()?;
                 self.expect(Token::Of)?;
-                let base = self.parse_expression()?;
+                let base = self.parse_operand()?;
                 Ok(IronExpr::FieldAccess {
                     base: Box::new(base),
                     field: field_name,
@@ -775,7 +1199,7 @@ impl IronParser {
                     // Field access: field X of Y
                     if name == "field" {
                         let field_name = self.expect_identifier()?;
-                        let base = self.parse_expression()?;
+                        let base = self.parse_operand()?;
                         return Ok(IronExpr::FieldAccess {
                             base: Box::new(base),
                             field: field_name,
@@ -790,6 +1214,10 @@ impl IronParser {
                 self.advance();
                 Ok(IronExpr::String(s))
             }
+            Some(&Token::Char(ch)) => {
+                self.advance();
+                Ok(IronExpr::Char(ch))
+            }
             Some(Token::Integer(n)) => {
                 let n = n.clone();
                 self.advance();
@@ -833,7 +1261,7 @@ impl IronParser {
 
                 let mut elems = Vec::new();
                 loop {
-                    elems.push(self.parse_expression()?);
+                    elems.push(self.parse_list_element()?);
                     if !self.match_token(Token::And) {
                         break;
                     }
@@ -847,7 +1275,7 @@ impl IronParser {
 
                 let mut elems = Vec::new();
                 loop {
-                    elems.push(self.parse_expression()?);
+                    elems.push(self.parse_list_element()?);
                     if !self.match_token(Token::And) {
                         break;
                     }
@@ -896,19 +1324,24 @@ impl IronParser {
             }
             Some(Token::Index) => {
                 self.advance();
-                let base = self.parse_expression()?;
+                let base = self.parse_operand()?;
                 self.expect(Token::At)?;
-                let index = self.parse_expression()?;
+                let index = self.parse_operand()?;
                 Ok(IronExpr::Index {
                     base: Box::new(base),
                     index: Box::new(index),
                 })
             }
             Some(Token::Closure) => {
+                let line = self.current_position().line;
                 self.advance();
 
                 // Check for move keyword
-                let _is_move = self.match_token(Token::Move);
+                if self.match_token(Token::Move) {
+                    self.warn(
+                        "'move' on closures is not supported yet and was dropped".to_string(),
+                    );
+                }
 
                 // Parse parameters
                 let mut params = Vec::new();
@@ -924,7 +1357,7 @@ impl IronParser {
                                 self.advance(); // consume 'of'
                                 self.parse_type()?
                             } else {
-                                IronType::Named("unknown".to_string())
+                                IronType::Inferred
                             };
 
                             params.push(IronParam {
@@ -948,8 +1381,7 @@ impl IronParser {
                         }
                     } else if self.match_token(Token::Body) {
                         // Zero-parameter closure form: "closure with body ..."
-                        let body_expr = self.parse_expression()?;
-                        let body = vec![IronStmt::Expr(body_expr)];
+                        let body = self.parse_closure_body(line)?;
                         return Ok(IronExpr::Closure { params, body });
                     }
                 }
@@ -958,9 +1390,7 @@ impl IronParser {
                 self.expect(Token::And)?;
                 self.expect(Token::Body)?;
 
-                // For now, parse body as a single expression
-                let body_expr = self.parse_expression()?;
-                let body = vec![IronStmt::Expr(body_expr)];
+                let body = self.parse_closure_body(line)?;
 
                 Ok(IronExpr::Closure { params, body })
             }
@@ -973,10 +1403,7 @@ impl IronParser {
                     let mut arg_parts = Vec::new();
                     let mut uses_brackets = false;
 
-                    while !self.is_at_end()
-                        && !self.check(Token::NewLine)
-                        && !self.check(Token::End)
-                    {
+                    while !self.is_at_end() && !self.check(Token::NewLine) && !self.at_block_end() {
                         // Check for bracket keyword which marks end of args
                         if self.check(Token::Bracket) {
                             uses_brackets = true;
@@ -1002,13 +1429,24 @@ impl IronParser {
                                 self.advance();
                             }
                             Some(Token::String(s)) => {
-                                arg_parts.push(format!("\"{}\"", s));
+                                // As a Rust literal, escaped again
+                                arg_parts.push(format!("{:?}", s));
                                 self.advance();
                             }
-                            _ => {
+                            Some(Token::Char(ch)) => {
+                                arg_parts.push(format!("{:?}", ch));
+                                self.advance();
+                            }
+                            Some(token) => {
                                 // Skip unknown tokens but preserve structure
+                                let message = format!(
+                                    "{:?} is not allowed in the arguments of macro {}",
+                                    token, name
+                                );
+                                self.recover(message)?;
                                 self.advance();
                             }
+                            None => break,
                         }
                     }
                     (arg_parts.join(" "), uses_brackets)
@@ -1027,8 +1465,11 @@ impl IronParser {
             Some(Token::Call) => {
                 self.advance();
 
-                // Check if this is "call associated function X on Y"
-                if self.match_token(Token::Associated) {
+                // Check if this is "call associated function X on Y", or tersely
+                // "call function X on Y"
+                if self.match_token(Token::Associated)
+                    || self.check(Token::Function) && self.peek_at(2) == Some(&Token::On)
+                {
                     self.expect(Token::Function)?;
                     let function_name = self.expect_symbol_identifier()?;
                     self.expect(Token::On)?;
@@ -1039,15 +1480,7 @@ impl IronParser {
                         type_name.push_str(&segment);
                     }
 
-                    let mut args = Vec::new();
-                    if self.match_token(Token::With) {
-                        loop {
-                            args.push(self.parse_expression()?);
-                            if !self.match_token(Token::And) {
-                                break;
-                            }
-                        }
-                    }
+                    let args = self.parse_call_args(bare)?;
 
                     // Associated functions are different from method calls
                     let mut expr = IronExpr::AssociatedFunctionCall {
@@ -1069,21 +1502,13 @@ impl IronParser {
                     return Ok(expr);
                 }
 
-                // Check if this is "call method X on Y"
-                if self.match_token(Token::Method) {
+                // Check if this is "call method X on Y", or tersely "call X on Y"
+                if self.match_token(Token::Method) || self.peek_at(1) == Some(&Token::On) {
                     let method_name = self.expect_symbol_identifier()?;
                     self.expect(Token::On)?;
-                    let receiver = self.parse_expression()?;
+                    let receiver = self.parse_operand()?;
 
-                    let mut args = Vec::new();
-                    if self.match_token(Token::With) {
-                        loop {
-                            args.push(self.parse_expression()?);
-                            if !self.match_token(Token::And) {
-                                break;
-                            }
-                        }
-                    }
+                    let args = self.parse_call_args(bare)?;
 
                     let mut expr = IronExpr::MethodCall {
                         receiver: Box::new(receiver),
@@ -1104,17 +1529,10 @@ impl IronParser {
                     return Ok(expr);
                 }
 
-                // Regular function call
-                let func = self.parse_expression()?;
-                let mut args = Vec::new();
-                if self.match_token(Token::With) {
-                    loop {
-                        args.push(self.parse_expression()?);
-                        if !self.match_token(Token::And) {
-                            break;
-                        }
-                    }
-                }
+                // Regular function call, spelled out as "call function f" when extra verbose
+                self.match_token(Token::Function);
+                let func = self.parse_operand()?;
+                let args = self.parse_call_args(bare)?;
 
                 let mut expr = IronExpr::Call {
                     func: Box::new(func),
@@ -1136,17 +1554,41 @@ impl IronParser {
             Some(Token::Create) => {
                 self.advance();
                 // Create struct: create TypeName [with field1 of value1 and field2 of value2]
-                let type_name = self.expect_identifier()?;
+                // The name may be a path, as in "create Shape Circle with radius of 1"
+                let mut type_name = self.expect_identifier()?;
+                while let Some(Token::Identifier(segment)) = self.peek() {
+                    type_name.push_str("::");
+                    type_name.push_str(segment);
+                    self.advance();
+                }
 
-                // Check for field initialization
-                let fields = if self.match_token(Token::With) {
-                    let mut fields = Vec::new();
+                // Positional construction of a tuple struct: create Wrapper with 42
+                let is_named = matches!(self.peek_next(), Some(Token::Identifier(_)))
+                    && matches!(self.peek_at(2), Some(Token::Of));
+                if self.check(Token::With) && !is_named {
+                    self.advance();
+                    let mut args = Vec::new();
                     loop {
-                        let field_name = self.expect_identifier()?;
-                        self.expect(Token::Of)?;
-                        let value = self.parse_expression()?;
-                        fields.push((
-                            IronField {
+                        args.push(self.parse_list_element()?);
+                        if !self.match_token(Token::And) {
+                            break;
+                        }
+                    }
+                    return Ok(IronExpr::TupleStruct {
+                        name: type_name,
+                        args,
+                    });
+                }
+
+                // Check for field initialization
+                let fields = if self.match_token(Token::With) {
+                    let mut fields = Vec::new();
+                    loop {
+                        let field_name = self.expect_identifier()?;
+                        self.expect(Token::Of)?;
+                        let value = self.parse_list_element()?;
+                        fields.push((
+                            IronField {
                                 name: field_name,
                                 ty: IronType::Named("unknown".to_string()),
                             },
@@ -1166,17 +1608,50 @@ impl IronParser {
                     fields,
                 })
             }
-            Some(token) => Err(ParseError::UnexpectedToken(
-                token.clone(),
-                "expression".to_string(),
-            )),
-            None => Err(ParseError::UnexpectedEndOfInput),
+            _ => Err(self.unexpected("expression")),
+        }
+    }
+
+    /// Parse a closure body: a single expression or a `begin ... end closure` block
+    fn parse_closure_body(&mut self, line: usize) -> Result<Vec<IronStmt>, ParseError> {
+        self.skip_newlines();
+        if self.match_token(Token::Begin) {
+            let body = self.parse_block()?;
+            self.expect_block_end(Token::Closure, line)?;
+            Ok(body)
+        } else {
+            Ok(vec![IronStmt::Expr(self.parse_expression()?)])
         }
     }
 
     // Helper methods
     fn peek(&self) -> Option<&Token> {
-        self.tokens.get(self.position)
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&Token> {
+        self.tokens
+            .get(self.position + offset)
+            .map(|spanned| &spanned.token)
+    }
+
+    /// Position of the current token, or of the last token once input is exhausted
+    fn current_position(&self) -> Position {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map(SpannedToken::position)
+            .unwrap_or_default()
+    }
+
+    /// Build an error for the current token, which did not match `expected`
+    fn unexpected(&self, expected: impl Into<String>) -> ParseError {
+        match self.peek() {
+            Some(token) => {
+                ParseError::UnexpectedToken(token.clone(), expected.into(), self.current_position())
+            }
+            None => ParseError::UnexpectedEndOfInput(self.current_position()),
+        }
     }
 
     fn advance(&mut self) {
@@ -1185,6 +1660,57 @@ impl IronParser {
         }
     }
 
+    /// Whether the current token closes a block ("end function", "end if", ...)
+    fn at_block_end(&self) -> bool {
+        matches!(self.peek(), Some(Token::End | Token::EndOf(_)))
+    }
+
+    /// Consume the terminator of the `block` opened at `line`, whose label must match it
+    fn expect_block_end(&mut self, block: Token, line: usize) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(Token::EndOf(label)) if **label == block => {
+                self.advance();
+                Ok(())
+            }
+            Some(Token::EndOf(label)) => Err(ParseError::InvalidSyntax(
+                format!(
+                    "found 'end {}' but the open block is {} at line {}",
+                    block_keyword(label),
+                    block_description(&block),
+                    line
+                ),
+                self.current_position(),
+            )),
+            Some(Token::End) => {
+                // Missing or unrecognized label: skip it
+                self.advance();
+                let open = format!(
+                    "the open block is {} at line {}",
+                    block_description(&block),
+                    line
+                );
+                match self.peek() {
+                    None | Some(Token::NewLine | Token::Indent(_) | Token::Comment(_)) => {
+                        self.recover(format!("'end' must name the block it closes ({})", open))
+                    }
+                    Some(label) => {
+                        let label = match label {
+                            Token::Identifier(name) => format!(" '{}'", name),
+                            _ => String::new(),
+                        };
+                        self.recover(format!(
+                            "unrecognized block label{} after 'end' ({})",
+                            label, open
+                        ))?;
+                        self.advance();
+                        Ok(())
+                    }
+                }
+            }
+            _ => Err(self.unexpected("end of block")),
+        }
+    }
+
     fn is_at_end(&self) -> bool {
         matches!(self.peek(), Some(Token::EndOfFile) | None)
     }
@@ -1193,6 +1719,25 @@ impl IronParser {
         self.peek() == Some(&token)
     }
 
+    /// Whether the token `offset` ahead is the plain word `word`
+    /// Consume an extra type name of several words, if one is next
+    fn match_type_name(&mut self) -> Option<String> {
+        let words = self.type_names.iter().find(|words| {
+            words.len() > 1
+                && words
+                    .iter()
+                    .enumerate()
+                    .all(|(offset, word)| self.check_word(offset, word))
+        })?;
+        let name = words.join(" ");
+        self.position += words.len();
+        Some(name)
+    }
+
+    fn check_word(&self, offset: usize, word: &str) -> bool {
+        matches!(self.peek_at(offset), Some(Token::Identifier(w)) if w == word)
+    }
+
     fn match_token(&mut self, token: Token) -> bool {
         if self.check(token) {
             self.advance();
@@ -1203,11 +1748,11 @@ impl IronParser {
     }
 
     fn match_identifier(&mut self, name: &str) -> bool {
-        if let Some(Token::Identifier(n)) = self.peek() {
-            if n == name {
-                self.advance();
-                return true;
-            }
+        if let Some(Token::Identifier(n)) = self.peek()
+            && n == name
+        {
+            self.advance();
+            return true;
         }
         false
     }
@@ -1217,10 +1762,7 @@ impl IronParser {
             self.advance();
             Ok(())
         } else {
-            Err(ParseError::UnexpectedToken(
-                self.peek().cloned().unwrap_or(Token::EndOfFile),
-                format!("{:?}", token),
-            ))
+            Err(self.unexpected(format!("{:?}", token)))
         }
     }
 
@@ -1239,24 +1781,14 @@ impl IronParser {
                 self.advance();
                 Ok("error".to_string())
             }
-            Some(token) => Err(ParseError::UnexpectedToken(
-                token.clone(),
-                "identifier".to_string(),
-            )),
-            None => Err(ParseError::UnexpectedEndOfInput),
+            _ => Err(self.unexpected("identifier")),
         }
     }
 
     fn expect_symbol_identifier(&mut self) -> Result<String, ParseError> {
         match self.take_symbol_identifier() {
             Some(name) => Ok(name),
-            None => match self.peek() {
-                Some(token) => Err(ParseError::UnexpectedToken(
-                    token.clone(),
-                    "identifier".to_string(),
-                )),
-                None => Err(ParseError::UnexpectedEndOfInput),
-            },
+            None => Err(self.unexpected("identifier")),
         }
     }
 
@@ -1306,67 +1838,157 @@ impl IronParser {
                 self.advance();
                 Some("box".to_string())
             }
+            Some(Token::Unwrap) => {
+                self.advance();
+                Some("unwrap".to_string())
+            }
             _ => None,
         }
     }
 
+    /// Skip line breaks, dropping any comments where the grammar has no room for them
     fn skip_newlines(&mut self) {
         while let Some(token) = self.peek() {
             match token {
                 Token::NewLine => self.advance(),
                 Token::Indent(_) => self.advance(),
+                Token::Comment(_) => {
+                    self.warn("comment dropped: comments here have no Rust equivalent".to_string());
+                    self.advance();
+                }
                 _ => break,
             }
         }
     }
 
+    /// Record a problem at the current token that parsing works around
+    fn warn(&mut self, message: String) {
+        let line = self.current_position().line;
+        self.warnings.push(IronWarning {
+            line: Some(line),
+            message,
+        });
+    }
+
+    /// Report off-grammar input at the current token: an error when strict, otherwise a
+    /// warning, after which the caller skips it
+    fn recover(&mut self, message: String) -> Result<(), ParseError> {
+        match self.mode {
+            ParseMode::Strict => Err(ParseError::InvalidSyntax(message, self.current_position())),
+            ParseMode::Lenient => {
+                self.warn(format!("{} (skipped)", message));
+                Ok(())
+            }
+        }
+    }
+
+    /// Skip line breaks but stop at comments, which become items or statements
+    fn skip_blank_lines(&mut self) {
+        while let Some(Token::NewLine | Token::Indent(_)) = self.peek() {
+            self.advance();
+        }
+    }
+
+    /// Consume `token` if it is the next token after any line breaks and comments
+    fn match_after_newlines(&mut self, token: Token) -> bool {
+        let mut offset = 0;
+        while let Some(Token::NewLine | Token::Indent(_) | Token::Comment(_)) = self.peek_at(offset)
+        {
+            offset += 1;
+        }
+        if self.peek_at(offset) == Some(&token) {
+            self.position += offset + 1;
+            true
+        } else {
+            false
+        }
+    }
+
     fn peek_binary_op(&self) -> Option<IronBinaryOp> {
         match self.peek() {
             Some(Token::Plus) => Some(IronBinaryOp::Add),
             Some(Token::Minus) => Some(IronBinaryOp::Sub),
             Some(Token::Times) => Some(IronBinaryOp::Mul),
-            Some(Token::Divided) => Some(IronBinaryOp::Div),
+            Some(Token::DividedBy) => Some(IronBinaryOp::Div),
             Some(Token::Modulo) => Some(IronBinaryOp::Mod),
             Some(Token::And) => Some(IronBinaryOp::And),
             Some(Token::Or) => Some(IronBinaryOp::Or),
-            Some(Token::Equal) => Some(IronBinaryOp::Eq),
-            Some(Token::Greater) => {
-                // Check for "greater than" or "greater than or equal to"
-                if self.peek_next() == Some(&Token::Than) {
-                    Some(IronBinaryOp::Gt)
-                } else {
-                    None
-                }
-            }
-            Some(Token::Less) => {
-                // Check for "less than" or "less than or equal to"
-                if self.peek_next() == Some(&Token::Than) {
-                    Some(IronBinaryOp::Lt)
-                } else {
-                    None
-                }
-            }
+            Some(Token::EqualTo) => Some(IronBinaryOp::Eq),
+            Some(Token::NotEqualTo) => Some(IronBinaryOp::Ne),
+            Some(Token::GreaterThan) => Some(IronBinaryOp::Gt),
+            Some(Token::GreaterThanOrEqualTo) => Some(IronBinaryOp::Ge),
+            Some(Token::LessThan) => Some(IronBinaryOp::Lt),
+            Some(Token::LessThanOrEqualTo) => Some(IronBinaryOp::Le),
+            Some(Token::BitwiseAnd) => Some(IronBinaryOp::BitAnd),
+            Some(Token::BitwiseOr) => Some(IronBinaryOp::BitOr),
+            Some(Token::BitwiseXor) => Some(IronBinaryOp::BitXor),
+            Some(Token::ShiftLeft) => Some(IronBinaryOp::Shl),
+            Some(Token::ShiftRight) => Some(IronBinaryOp::Shr),
             _ => None,
         }
     }
 
     fn peek_next(&self) -> Option<&Token> {
-        self.tokens.get(self.position + 1)
+        self.peek_at(1)
     }
 
     fn get_precedence(&self, op: &IronBinaryOp) -> u8 {
+        // Mirrors Rust operator precedence, from loosest to tightest binding
         match op {
             IronBinaryOp::Or => 1,
             IronBinaryOp::And => 2,
             IronBinaryOp::Eq | IronBinaryOp::Ne => 3,
             IronBinaryOp::Lt | IronBinaryOp::Le | IronBinaryOp::Gt | IronBinaryOp::Ge => 4,
-            IronBinaryOp::Add | IronBinaryOp::Sub => 5,
-            IronBinaryOp::Mul | IronBinaryOp::Div | IronBinaryOp::Mod => 6,
-            _ => 7,
+            IronBinaryOp::BitOr => 5,
+            IronBinaryOp::BitXor => 6,
+            IronBinaryOp::BitAnd => 7,
+            IronBinaryOp::Shl | IronBinaryOp::Shr => 8,
+            IronBinaryOp::Add | IronBinaryOp::Sub => 9,
+            IronBinaryOp::Mul | IronBinaryOp::Div | IronBinaryOp::Mod => 10,
         }
     }
 }
 
+/// The keyword that names a block in its "end" label
+fn block_keyword(label: &Token) -> &'static str {
+    match label {
+        Token::Function => "function",
+        Token::Structure => "structure",
+        Token::Enumeration => "enumeration",
+        Token::If => "if",
+        Token::While => "while",
+        Token::For => "for",
+        Token::Loop => "loop",
+        Token::Compare => "compare",
+        Token::Module => "module",
+        Token::Group => "group",
+        Token::Closure => "closure",
+        Token::Static => "static",
+        Token::Constant => "constant",
+        _ => "block",
+    }
+}
+
+/// A block as named in diagnostics ("a while loop")
+fn block_description(block: &Token) -> &'static str {
+    match block {
+        Token::Function => "a function",
+        Token::Structure => "a structure",
+        Token::Enumeration => "an enumeration",
+        Token::If => "an if",
+        Token::While => "a while loop",
+        Token::For => "a for loop",
+        Token::Loop => "a loop",
+        Token::Compare => "a compare",
+        Token::Module => "a module",
+        Token::Group => "a group",
+        Token::Closure => "a closure",
+        Token::Static => "a static",
+        Token::Constant => "a constant",
+        _ => "a block",
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1383,6 +2005,741 @@ end function"#;
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_parse_nested_modules() {
+        let input = r#"module outer
+begin
+    function helper
+    begin
+        return 1
+    end function
+
+    module inner
+    begin
+        type Count as i32
+    end module
+end module
+
+function after
+begin
+    return 2
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("nested modules should parse");
+        assert_eq!(file.items.len(), 2);
+
+        let IronItem::Module(outer) = &file.items[0] else {
+            panic!("expected module, got {:?}", file.items[0]);
+        };
+        assert_eq!(outer.name, "outer");
+        assert_eq!(outer.items.len(), 2);
+        assert!(matches!(&outer.items[0], IronItem::Function(f) if f.name == "helper"));
+        assert!(
+            matches!(&outer.items[1], IronItem::Module(m) if m.name == "inner" && m.items.len() == 1)
+        );
+        assert!(matches!(&file.items[1], IronItem::Function(f) if f.name == "after"));
+    }
+
+    #[test]
+    fn test_parse_grouped_and_renamed_imports() {
+        let input = r#"import std collections HashMap
+import std fmt containing user_self and Display as Show
+import std error Error as StdError"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("imports should parse");
+        assert_eq!(file.items.len(), 3);
+
+        let IronItem::Import(grouped) = &file.items[1] else {
+            panic!("expected import, got {:?}", file.items[1]);
+        };
+        let IronUseTree::Path { segment, rest } = &grouped.tree else {
+            panic!("expected path, got {:?}", grouped.tree);
+        };
+        assert_eq!(segment, "std");
+        let IronUseTree::Path { segment, rest } = rest.as_ref() else {
+            panic!("expected path, got {:?}", rest);
+        };
+        assert_eq!(segment, "fmt");
+        let IronUseTree::Group(entries) = rest.as_ref() else {
+            panic!("expected group, got {:?}", rest);
+        };
+        assert_eq!(entries.len(), 2);
+        assert!(
+            matches!(&entries[1], IronUseTree::Name { name, alias: Some(alias) } if name == "Display" && alias == "Show")
+        );
+    }
+
+    #[test]
+    fn test_parse_unary_binds_tighter_than_binary() {
+        let input = r#"function check
+    takes flag of boolean and count of i32
+    returns boolean
+begin
+    not flag and negate count less than dereference count
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("unary expressions should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        let IronStmt::Expr(IronExpr::Binary { left, op, right }) = &func.body[0] else {
+            panic!("expected binary expression, got {:?}", func.body[0]);
+        };
+        assert!(matches!(op, IronBinaryOp::And));
+        assert!(matches!(
+            left.as_ref(),
+            IronExpr::Unary {
+                op: IronUnaryOp::Not,
+                ..
+            }
+        ));
+        let IronExpr::Binary { left, op, right } = right.as_ref() else {
+            panic!("expected comparison, got {:?}", right);
+        };
+        assert!(matches!(op, IronBinaryOp::Lt));
+        assert!(matches!(
+            left.as_ref(),
+            IronExpr::Unary {
+                op: IronUnaryOp::Neg,
+                ..
+            }
+        ));
+        assert!(matches!(
+            right.as_ref(),
+            IronExpr::Unary {
+                op: IronUnaryOp::Deref,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_not_equal_to_is_binary() {
+        let input = r#"function differs
+    takes a of i32 and b of i32
+    returns boolean
+begin
+    a not equal to b
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("not equal to should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        assert!(matches!(
+            &func.body[0],
+            IronStmt::Expr(IronExpr::Binary {
+                op: IronBinaryOp::Ne,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_parse_if_expression_as_value() {
+        let input = r#"function sign
+    takes n of i32
+    returns i32
+begin
+    define y as if n less than 0 then negate 1 otherwise if n equal to 0 then 0 otherwise 1
+    y
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("if expression should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        assert_eq!(func.body.len(), 2);
+        let IronStmt::Let {
+            value:
+                IronExpr::If {
+                    else_block: Some(else_block),
+                    ..
+                },
+            ..
+        } = &func.body[0]
+        else {
+            panic!("expected let with if value, got {:?}", func.body[0]);
+        };
+        assert!(matches!(
+            else_block.as_slice(),
+            [IronStmt::Expr(IronExpr::If {
+                else_block: Some(_),
+                ..
+            })]
+        ));
+    }
+
+    #[test]
+    fn test_parse_block_bodied_closure() {
+        let input = r#"function make
+    returns i32
+begin
+    define f as closure with parameters x and body
+    begin
+        define doubled as x times 2
+        doubled plus 1
+    end closure
+    call f with 20
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("block-bodied closure should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        assert_eq!(func.body.len(), 2);
+        let IronStmt::Let {
+            value: IronExpr::Closure { params, body },
+            ..
+        } = &func.body[0]
+        else {
+            panic!("expected closure binding, got {:?}", func.body[0]);
+        };
+        assert_eq!(params.len(), 1);
+        assert_eq!(body.len(), 2);
+        assert!(matches!(&body[0], IronStmt::Let { name, .. } if name == "doubled"));
+    }
+
+    #[test]
+    fn test_parse_else_if_chain() {
+        let input = r#"function classify
+    takes n of i32
+    returns i32
+begin
+    if n less than 0 then
+    begin
+        return 1
+    end if
+    otherwise if n equal to 0 then
+    begin
+        return 2
+    end if
+    otherwise if n less than 10 then
+    begin
+        return 3
+    end if
+    otherwise
+    begin
+        return 4
+    end if
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("else-if chain should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        assert_eq!(func.body.len(), 1);
+
+        let mut depth = 0;
+        let mut stmt = &func.body[0];
+        while let IronStmt::If {
+            else_block: Some(else_block),
+            ..
+        } = stmt
+        {
+            depth += 1;
+            match else_block.as_slice() {
+                [nested @ IronStmt::If { .. }] => stmt = nested,
+                _ => break,
+            }
+        }
+        assert_eq!(depth, 3);
+    }
+
+    #[test]
+    fn test_parse_loop_forever() {
+        let input = r#"function spin
+begin
+    loop forever
+    begin
+        exit loop
+    end loop
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("loop forever should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        assert!(matches!(
+            func.body.as_slice(),
+            [IronStmt::Loop { body }] if matches!(body.as_slice(), [IronStmt::Break])
+        ));
+    }
+
+    #[test]
+    fn test_parse_static_and_const_initializers() {
+        let input = r#"constant ORIGIN of Point
+begin
+    create Point with x of 1 and y of 2
+end constant
+
+static mutable COUNTER of i32
+begin
+    40 plus 2
+end static"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("initializers should parse");
+        assert_eq!(file.items.len(), 2);
+
+        let IronItem::Const(cnst) = &file.items[0] else {
+            panic!("expected constant, got {:?}", file.items[0]);
+        };
+        assert!(
+            matches!(&cnst.value, IronExpr::Struct { name, fields } if name == "Point" && fields.len() == 2)
+        );
+
+        let IronItem::Static(stat) = &file.items[1] else {
+            panic!("expected static, got {:?}", file.items[1]);
+        };
+        assert!(stat.mutable);
+        assert!(matches!(
+            &stat.value,
+            IronExpr::Binary {
+                op: IronBinaryOp::Add,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_bitwise_and_shift_precedence() {
+        let input = r#"function mix
+    takes a of u32 and b of u32
+    returns u32
+begin
+    a bitwise or b bitwise and 255 shift left 2 bitwise xor a shift right 1
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("bitwise operators should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        // a | ((b & (255 << 2)) ^ (a >> 1))
+        let IronStmt::Expr(IronExpr::Binary {
+            op: IronBinaryOp::BitOr,
+            right,
+            ..
+        }) = &func.body[0]
+        else {
+            panic!("expected bitwise or at the root, got {:?}", func.body[0]);
+        };
+        let IronExpr::Binary {
+            op: IronBinaryOp::BitXor,
+            left,
+            right,
+        } = right.as_ref()
+        else {
+            panic!("expected bitwise xor, got {:?}", right);
+        };
+        assert!(matches!(
+            left.as_ref(),
+            IronExpr::Binary {
+                op: IronBinaryOp::BitAnd,
+                right,
+                ..
+            } if matches!(right.as_ref(), IronExpr::Binary { op: IronBinaryOp::Shl, .. })
+        ));
+        assert!(matches!(
+            right.as_ref(),
+            IronExpr::Binary {
+                op: IronBinaryOp::Shr,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_or_equal_comparisons() {
+        let input = r#"function within
+    takes n of i32
+    returns boolean
+begin
+    n greater than or equal to 0 and n less than or equal to 9 or n less than 0
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("comparisons should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        let IronStmt::Expr(IronExpr::Binary {
+            op: IronBinaryOp::Or,
+            left,
+            ..
+        }) = &func.body[0]
+        else {
+            panic!("expected or at the root, got {:?}", func.body[0]);
+        };
+        assert!(matches!(
+            left.as_ref(),
+            IronExpr::Binary { op: IronBinaryOp::And, left, right }
+                if matches!(left.as_ref(), IronExpr::Binary { op: IronBinaryOp::Ge, .. })
+                    && matches!(right.as_ref(), IronExpr::Binary { op: IronBinaryOp::Le, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_group_overrides_precedence() {
+        let input = r#"function scale
+    takes a of i32 and b of i32 and c of i32
+    returns i32
+begin
+    group a plus b end group times c
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("grouped expression should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        let IronStmt::Expr(IronExpr::Binary {
+            left,
+            op: IronBinaryOp::Mul,
+            ..
+        }) = &func.body[0]
+        else {
+            panic!(
+                "expected multiplication at the root, got {:?}",
+                func.body[0]
+            );
+        };
+        assert!(matches!(
+            left.as_ref(),
+            IronExpr::Group(inner) if matches!(inner.as_ref(), IronExpr::Binary { op: IronBinaryOp::Add, .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_field_access_binds_tightly() {
+        let input = r#"function get
+begin
+    return field count of call method unwrap on outcome plus 1
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("field access should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        let IronStmt::Return(Some(IronExpr::Binary { left, op, .. })) = &func.body[0] else {
+            panic!("expected binary return, got {:?}", func.body[0]);
+        };
+        assert!(matches!(op, IronBinaryOp::Add));
+        let IronExpr::FieldAccess { base, field } = left.as_ref() else {
+            panic!("expected field access, got {:?}", left);
+        };
+        assert_eq!(field, "count");
+        assert!(matches!(
+            base.as_ref(),
+            IronExpr::MethodCall { method, .. } if method == "unwrap"
+        ));
+    }
+
+    #[test]
+    fn test_parse_assignment_places() {
+        let input = r#"function reset
+begin
+    set field count of user_self equal to 0
+    set index items at 3 equal to x
+    set dereference total equal to 1
+    increase field count of user_self by 2
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("place assignments should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        assert!(matches!(
+            &func.body[0],
+            IronStmt::Assign { target: IronExpr::FieldAccess { field, .. }, .. } if field == "count"
+        ));
+        assert!(matches!(
+            &func.body[1],
+            IronStmt::Assign {
+                target: IronExpr::Index { .. },
+                ..
+            }
+        ));
+        assert!(matches!(
+            &func.body[2],
+            IronStmt::Assign {
+                target: IronExpr::Unary {
+                    op: IronUnaryOp::Deref,
+                    ..
+                },
+                ..
+            }
+        ));
+        assert!(matches!(
+            &func.body[3],
+            IronStmt::CompoundAssign {
+                target: IronExpr::FieldAccess { .. },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_assignment_rejects_non_place() {
+        let input = "function bad\nbegin\n    set 3 equal to x\nend function";
+
+        let mut parser = IronParser::new(input);
+        let err = parser.parse().expect_err("literal target should fail");
+        assert!(matches!(err, ParseError::InvalidSyntax(..)), "{:?}", err);
+    }
+
+    #[test]
+    fn test_parse_generic_type_arguments() {
+        let input = r#"function lookup
+    takes cache of Cache of string and list of Wrapper of i32
+begin
+    return cache
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("generic types should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        let IronType::Path { name, args } = &func.params[0].ty else {
+            panic!("expected type path, got {:?}", func.params[0].ty);
+        };
+        assert_eq!(name, "Cache");
+        assert!(matches!(&args[0], IronType::Named(n) if n == "string"));
+        let IronType::List(inner) = &args[1] else {
+            panic!("expected list argument, got {:?}", args[1]);
+        };
+        assert!(matches!(
+            inner.as_ref(),
+            IronType::Path { name, args } if name == "Wrapper" && args.len() == 1
+        ));
+    }
+
+    #[test]
+    fn test_parse_tuple_struct_construction() {
+        let input = r#"function make
+begin
+    define w as create Wrapper with 42
+    define p as create Point with x of 1 and y of 2
+    return field field0 of w
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser
+            .parse()
+            .expect("tuple struct construction should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        assert!(matches!(
+            &func.body[0],
+            IronStmt::Let { value: IronExpr::TupleStruct { name, args }, .. }
+                if name == "Wrapper" && args.len() == 1
+        ));
+        assert!(matches!(
+            &func.body[1],
+            IronStmt::Let { value: IronExpr::Struct { fields, .. }, .. } if fields.len() == 2
+        ));
+    }
+
+    #[test]
+    fn test_parse_variant_path_construction() {
+        let input = r#"function make
+begin
+    define s as create Shape Circle with radius of 1.0
+    define w as create shapes Wrapper with 42
+    define e as create Shape Empty
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("variant construction should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        assert!(matches!(
+            &func.body[0],
+            IronStmt::Let { value: IronExpr::Struct { name, fields }, .. }
+                if name == "Shape::Circle" && fields.len() == 1
+        ));
+        assert!(matches!(
+            &func.body[1],
+            IronStmt::Let { value: IronExpr::TupleStruct { name, .. }, .. }
+                if name == "shapes::Wrapper"
+        ));
+        assert!(matches!(
+            &func.body[2],
+            IronStmt::Let { value: IronExpr::Struct { name, .. }, .. } if name == "Shape::Empty"
+        ));
+    }
+
+    #[test]
+    fn test_parse_modes() {
+        let input = "function f\nbegin\n    macro println with \"hi\" plus 1\nend bogus";
+
+        let file = IronParser::new(input)
+            .parse()
+            .expect("lenient parsing recovers");
+        let lines: Vec<_> = file.warnings.iter().map(|w| w.line).collect();
+        assert_eq!(lines, [Some(3), Some(4)]);
+
+        let err = IronParser::new(input)
+            .with_mode(ParseMode::Strict)
+            .parse()
+            .expect_err("strict parsing rejects stray tokens");
+        assert_eq!(err.position().line, 3);
+
+        let err = IronParser::new("function f\nbegin\n    define x as 1 banana\nend function")
+            .with_mode(ParseMode::Strict)
+            .parse()
+            .expect_err("strict parsing rejects tokens after a statement");
+        assert_eq!(
+            (err.position().line, err.position().column),
+            (3, 19),
+            "{}",
+            err
+        );
+
+        let err = IronParser::new("function f\nbegin\nend bogus")
+            .with_mode(ParseMode::Strict)
+            .parse()
+            .expect_err("strict parsing rejects unknown end labels");
+        assert!(
+            err.to_string()
+                .contains("unrecognized block label 'bogus' after 'end'"),
+            "{}",
+            err
+        );
+
+        let err = IronParser::new(
+            "function f\nbegin\n    while true repeat\n    begin\n    end\nend function",
+        )
+        .with_mode(ParseMode::Strict)
+        .parse()
+        .expect_err("strict parsing rejects a bare end");
+        assert!(
+            err.to_string().contains(
+                "'end' must name the block it closes (the open block is a while loop at line 3)"
+            ),
+            "{}",
+            err
+        );
+
+        assert_eq!("strict".parse::<ParseMode>(), Ok(ParseMode::Strict));
+        assert!("loose".parse::<ParseMode>().is_err());
+    }
+
+    #[test]
+    fn test_parse_mismatched_end_label() {
+        let input = r#"function f
+begin
+    while true repeat
+    begin
+        if done then
+        begin
+            break
+        end while
+    end if
+end function"#;
+
+        let err = IronParser::new(input)
+            .parse()
+            .expect_err("mismatched end labels should fail");
+        assert_eq!(err.position().line, 8);
+        assert!(
+            err.to_string()
+                .ends_with("found 'end while' but the open block is an if at line 5"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_parse_error_reports_position() {
+        let input = "function broken\nbegin\n    define x as\nend function";
+
+        let mut parser = IronParser::new(input);
+        let err = parser.parse().expect_err("missing initializer should fail");
+        assert_eq!(err.position().line, 3);
+        assert_eq!(err.position().column, 16);
+        assert!(err.to_string().starts_with("line 3, column 16:"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_checks_header() {
+        let iron = crate::header::add_header("function f\nbegin\nend function\n", "fn f() {}");
+        let mut parser = IronParser::new(&iron).with_mode(ParseMode::Strict);
+        let file = parser.parse().expect("a header is not part of the grammar");
+        assert_eq!(file.items.len(), 1);
+        assert!(parser.header().unwrap().matches_source("fn f() {}"));
+
+        let edited = iron.replace("function f", "function g");
+        let mut parser = IronParser::new(&edited).with_mode(ParseMode::Strict);
+        let err = parser
+            .parse()
+            .expect_err("an edited file fails strict parsing");
+        assert_eq!(err.position().line, 1);
+        assert!(err.to_string().contains("content hash"), "{}", err);
+
+        let mut parser = IronParser::new(&edited);
+        let file = parser.parse().expect("lenient parsing warns");
+        assert!(parser.header().is_none());
+        assert!(
+            file.warnings[0]
+                .message
+                .contains("edited after it was generated")
+        );
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        assert_eq!(func.name, "g");
+    }
+
+    #[test]
+    fn test_parse_compound_assignments() {
+        let input = r#"function adjust
+    takes x of i32
+begin
+    increase x by 1
+    divide x by 2
+    update x with shift left 3
+    update x with modulo 7
+end function"#;
+
+        let mut parser = IronParser::new(input);
+        let file = parser.parse().expect("compound assignments should parse");
+        let IronItem::Function(func) = &file.items[0] else {
+            panic!("expected function, got {:?}", file.items[0]);
+        };
+        let ops: Vec<_> = func
+            .body
+            .iter()
+            .map(|stmt| match stmt {
+                IronStmt::CompoundAssign { op, .. } => op.clone(),
+                other => panic!("expected compound assignment, got {:?}", other),
+            })
+            .collect();
+        assert!(matches!(
+            ops.as_slice(),
+            [
+                IronBinaryOp::Add,
+                IronBinaryOp::Div,
+                IronBinaryOp::Shl,
+                IronBinaryOp::Mod
+            ]
+        ));
+    }
+
     #[test]
     fn test_parse_method_call_named_ok() {
         let input = r#"function to_option
@@ -0,0 +1,960 @@
+//! Iron pretty-printer
+//!
+//! Renders an Iron syntax tree as Iron source, in the layout reduction writes. Nested
+//! expressions are wrapped in `group ... end group` wherever the grammar would otherwise
+//! read them differently, so the output parses back to the same tree.
+
+use crate::emitter::{IronEmitter, format_string_literal, quote_char};
+use crate::iron_ast::*;
+use crate::keywords::sanitize_identifier;
+
+/// Printer that writes Iron syntax trees as Iron source
+pub struct IronPrinter {
+    emitter: IronEmitter,
+}
+
+impl IronPrinter {
+    /// Create a printer with the default 4-space indentation
+    pub fn new() -> Self {
+        Self {
+            emitter: IronEmitter::new(),
+        }
+    }
+
+    /// Print a whole file, with a blank line after each item but comments
+    pub fn print(mut self, file: &IronFile) -> String {
+        self.print_items(&file.items);
+        let mut output = self.emitter.finalize();
+        let trimmed = output.trim_end_matches('\n').len();
+        output.truncate(trimmed);
+        output.push('\n');
+        output
+    }
+
+    fn print_items(&mut self, items: &[IronItem]) {
+        for (index, item) in items.iter().enumerate() {
+            self.print_item(item);
+            let last = index + 1 == items.len();
+            if !last && !matches!(item, IronItem::Comment(_)) {
+                self.emitter.write_empty_line();
+            }
+        }
+    }
+
+    fn print_item(&mut self, item: &IronItem) {
+        match item {
+            IronItem::Function(function) => {
+                self.emitter.write_line(&format!(
+                    "function {}{}",
+                    sanitize_identifier(&function.name),
+                    generics(&function.generics)
+                ));
+                if !function.params.is_empty() {
+                    let params: Vec<String> = function
+                        .params
+                        .iter()
+                        .map(|param| {
+                            format!(
+                                "{} of {}",
+                                sanitize_identifier(&param.name),
+                                type_name(&param.ty)
+                            )
+                        })
+                        .collect();
+                    self.emitter
+                        .write_line(&format!("    takes {}", params.join(" and ")));
+                }
+                if let Some(return_type) = &function.return_type {
+                    self.emitter
+                        .write_line(&format!("    returns {}", type_name(return_type)));
+                }
+                self.print_block(&function.body);
+                self.emitter.end_block("function");
+            }
+            IronItem::Struct(structure) => {
+                self.emitter.write_line(&format!(
+                    "structure {}{} with fields",
+                    sanitize_identifier(&structure.name),
+                    generics(&structure.generics)
+                ));
+                self.emitter.indent();
+                for field in &structure.fields {
+                    self.emitter
+                        .write_line(&field_list(std::slice::from_ref(field)));
+                }
+                self.emitter.end_block("structure");
+            }
+            IronItem::Enum(enumeration) => {
+                self.emitter.write_line(&format!(
+                    "enumeration {}{} with variants",
+                    sanitize_identifier(&enumeration.name),
+                    generics(&enumeration.generics)
+                ));
+                self.emitter.indent();
+                for variant in &enumeration.variants {
+                    let name = sanitize_identifier(&variant.name);
+                    let line = match &variant.data {
+                        None => name,
+                        Some(IronVariantData::Type(ty)) => format!("{} of {}", name, type_name(ty)),
+                        Some(IronVariantData::Fields(fields)) => {
+                            format!("{} with {}", name, field_list(fields))
+                        }
+                    };
+                    self.emitter.write_line(&line);
+                }
+                self.emitter.end_block("enumeration");
+            }
+            IronItem::Static(item) => {
+                let mutable = if item.mutable { "mutable " } else { "" };
+                self.emitter.write_line(&format!(
+                    "static {}{} of {}",
+                    mutable,
+                    sanitize_identifier(&item.name),
+                    type_name(&item.ty)
+                ));
+                self.print_initializer(&item.value, "static");
+            }
+            IronItem::Const(item) => {
+                self.emitter.write_line(&format!(
+                    "constant {} of {}",
+                    sanitize_identifier(&item.name),
+                    type_name(&item.ty)
+                ));
+                self.print_initializer(&item.value, "constant");
+            }
+            IronItem::TypeAlias(alias) => {
+                self.emitter.write_line(&format!(
+                    "type {}{} as {}",
+                    sanitize_identifier(&alias.name),
+                    generics(&alias.generics),
+                    type_name(&alias.ty)
+                ));
+            }
+            IronItem::Module(module) => {
+                self.emitter
+                    .write_line(&format!("module {}", sanitize_identifier(&module.name)));
+                self.emitter.begin_block();
+                self.print_items(&module.items);
+                self.emitter.end_block("module");
+            }
+            IronItem::Import(import) => {
+                self.emitter.write_import(&use_tree(&import.tree));
+            }
+            IronItem::Verbatim(verbatim) => self.emitter.write_verbatim_item(&verbatim.rust),
+            IronItem::Comment(text) => self.emitter.write_comment(text),
+        }
+    }
+
+    /// The `begin <value> end <label>` of a static or constant
+    fn print_initializer(&mut self, value: &IronExpr, label: &str) {
+        self.emitter.begin_block();
+        self.emitter.write_line(&expr(value));
+        self.emitter.end_block(label);
+    }
+
+    /// A `begin` line and the statements of a block, indented; the caller ends it
+    fn print_block(&mut self, stmts: &[IronStmt]) {
+        self.emitter.begin_block();
+        for stmt in stmts {
+            self.print_stmt(stmt);
+        }
+    }
+
+    fn print_stmt(&mut self, stmt: &IronStmt) {
+        match stmt {
+            IronStmt::Let {
+                name,
+                mutable,
+                value,
+            } => {
+                let mutable = if *mutable { "mutable " } else { "" };
+                self.emitter.write_line(&format!(
+                    "define {}{} as {}",
+                    mutable,
+                    sanitize_identifier(name),
+                    expr(value)
+                ));
+            }
+            IronStmt::Assign { target, value } => {
+                self.emitter
+                    .write_assignment(&operand(target), &expr(value));
+            }
+            IronStmt::CompoundAssign { target, op, value } => {
+                let verb = match op {
+                    IronBinaryOp::Add => Some("increase"),
+                    IronBinaryOp::Sub => Some("decrease"),
+                    IronBinaryOp::Mul => Some("multiply"),
+                    IronBinaryOp::Div => Some("divide"),
+                    _ => None,
+                };
+                let line = match verb {
+                    Some(verb) => format!("{} {} by {}", verb, operand(target), expr(value)),
+                    None => format!(
+                        "update {} with {} {}",
+                        operand(target),
+                        binary_op(op),
+                        expr(value)
+                    ),
+                };
+                self.emitter.write_line(&line);
+            }
+            IronStmt::Expr(value) => self.emitter.write_line(&expr(value)),
+            IronStmt::Return(value) => {
+                let value = value.as_ref().map(expr);
+                self.emitter.write_return(value.as_deref());
+            }
+            IronStmt::Break => self.emitter.write_line("exit loop"),
+            IronStmt::Continue => self.emitter.write_line("continue loop"),
+            IronStmt::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                self.emitter.write_if_header(&keyword_bounded(condition));
+                self.print_if_branches(then_block, else_block.as_deref());
+            }
+            IronStmt::While { condition, body } => {
+                self.emitter.write_while_header(&keyword_bounded(condition));
+                self.print_block(body);
+                self.emitter.end_while();
+            }
+            IronStmt::For {
+                var,
+                iterator,
+                body,
+            } => {
+                self.emitter.write_line(&format!(
+                    "for each {} in {} repeat",
+                    sanitize_identifier(var),
+                    keyword_bounded(iterator)
+                ));
+                self.print_block(body);
+                self.emitter.end_for();
+            }
+            IronStmt::Loop { body } => {
+                self.emitter.write_loop_header();
+                self.print_block(body);
+                self.emitter.end_loop();
+            }
+            // Not read back by the Iron parser yet, which has no `compare` statement
+            IronStmt::Match { expr: value, arms } => {
+                self.emitter.write_match_header(&expr(value));
+                for (arm_pattern, body) in arms {
+                    self.emitter
+                        .write_match_arm(&pattern(arm_pattern), &expr(body));
+                }
+                self.emitter.end_match();
+            }
+            IronStmt::Comment(text) => self.emitter.write_comment(text),
+            // Set off from the statements around it, as reduction does
+            IronStmt::Item(item) => {
+                self.emitter.separate();
+                self.print_item(item);
+                self.emitter.separate();
+            }
+        }
+    }
+
+    /// The then block and any `otherwise if` / `otherwise` cascade of an if statement
+    fn print_if_branches(&mut self, then_block: &[IronStmt], else_block: Option<&[IronStmt]>) {
+        self.print_block(then_block);
+        self.emitter.end_if();
+        match else_block {
+            Some(
+                [
+                    IronStmt::If {
+                        condition,
+                        then_block,
+                        else_block,
+                    },
+                ],
+            ) => {
+                self.emitter.write_else_if(&keyword_bounded(condition));
+                self.print_if_branches(then_block, else_block.as_deref());
+            }
+            Some(else_block) => {
+                self.emitter.write_else();
+                self.print_block(else_block);
+                self.emitter.end_if();
+            }
+            None => {}
+        }
+    }
+}
+
+impl Default for IronPrinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Writes the file as [`IronPrinter::print`] does, so `to_string` serializes a tree
+impl std::fmt::Display for IronFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&IronPrinter::new().print(self))
+    }
+}
+
+/// Statements as lines one level in, for a block inside an expression
+fn block_lines(stmts: &[IronStmt]) -> String {
+    let mut printer = IronPrinter::new();
+    printer.emitter.indent();
+    for stmt in stmts {
+        printer.print_stmt(stmt);
+    }
+    printer.emitter.finalize()
+}
+
+/// An expression in a position that runs to the end of the line or block
+fn expr(value: &IronExpr) -> String {
+    match value {
+        IronExpr::Identifier(name) => sanitize_identifier(name),
+        IronExpr::String(text) => format_string_literal(text),
+        IronExpr::Char(ch) => quote_char(*ch),
+        IronExpr::Integer(number) | IronExpr::Float(number) => match number.strip_prefix('-') {
+            Some(magnitude) => format!("negative {}", magnitude),
+            None => number.clone(),
+        },
+        IronExpr::Boolean(value) => value.to_string(),
+        IronExpr::Binary { left, op, right } => {
+            let binding = precedence(op);
+            // Operators associate to the left, so only a looser left operand is grouped
+            let left = match &**left {
+                IronExpr::Binary { op: inner, .. } if precedence(inner) < binding => group(left),
+                _ if is_open_ended(left) => group(left),
+                _ => expr(left),
+            };
+            let right = match &**right {
+                IronExpr::Binary { op: inner, .. } if precedence(inner) <= binding => group(right),
+                _ => expr(right),
+            };
+            format!("{} {} {}", left, binary_op(op), right)
+        }
+        IronExpr::Unary { op, expr: inner } => {
+            let op = match op {
+                IronUnaryOp::Not => "not",
+                IronUnaryOp::Neg => "negate",
+                IronUnaryOp::Deref => "dereference",
+            };
+            // The operand is read as one primary expression, calls and all
+            let inner = match &**inner {
+                IronExpr::Binary { .. } => group(inner),
+                _ => expr(inner),
+            };
+            format!("{} {}", op, inner)
+        }
+        IronExpr::Call { func, args } => {
+            format!("call {}{}", operand(func), call_args(args))
+        }
+        IronExpr::MethodCall {
+            receiver,
+            method,
+            args,
+        } => format!(
+            "call method {} on {}{}",
+            sanitize_identifier(method),
+            operand(receiver),
+            call_args(args)
+        ),
+        IronExpr::AssociatedFunctionCall {
+            type_name,
+            function,
+            args,
+        } => format!(
+            "call associated function {} on {}{}",
+            sanitize_identifier(function),
+            path(type_name),
+            call_args(args)
+        ),
+        IronExpr::Macro {
+            name,
+            args,
+            bracket,
+        } => {
+            let mut text = format!("macro {}", sanitize_identifier(name));
+            if !args.is_empty() {
+                // Arguments end with the line, so line breaks in their strings are escaped
+                text.push_str(" with ");
+                text.push_str(&args.replace('\n', "\\n"));
+            }
+            if *bracket {
+                text.push_str(" bracket");
+            }
+            text
+        }
+        IronExpr::FieldAccess { base, field } => {
+            format!("field {} of {}", sanitize_identifier(field), operand(base))
+        }
+        IronExpr::Try { expr: inner } => format!("{} unwrap or return error", expr(inner)),
+        IronExpr::Some(inner) => format!("some of {}", expr(inner)),
+        IronExpr::None => "none".to_string(),
+        IronExpr::Ok(inner) => format!("ok of {}", expr(inner)),
+        IronExpr::Err(inner) => format!("error of {}", expr(inner)),
+        IronExpr::Tuple(elements) => format!("tuple of {}", list(elements)),
+        IronExpr::Array(elements) => format!("array of {}", list(elements)),
+        IronExpr::Struct { name, fields } => {
+            let mut text = format!("create {}", path(name));
+            if !fields.is_empty() {
+                let last = fields.len() - 1;
+                let fields: Vec<String> = fields
+                    .iter()
+                    .enumerate()
+                    .map(|(index, (field, value))| {
+                        format!(
+                            "{} of {}",
+                            sanitize_identifier(&field.name),
+                            list_element(value, index == last)
+                        )
+                    })
+                    .collect();
+                text.push_str(" with ");
+                text.push_str(&fields.join(" and "));
+            }
+            text
+        }
+        IronExpr::TupleStruct { name, args } => {
+            format!("create {}{}", path(name), call_args(args))
+        }
+        IronExpr::Index { base, index } => {
+            format!("index {} at {}", operand(base), operand(index))
+        }
+        IronExpr::Range {
+            start,
+            end,
+            inclusive,
+        } => {
+            let kind = if *inclusive {
+                "inclusive range"
+            } else {
+                "range"
+            };
+            let start = start.as_deref().map_or_else(String::new, keyword_bounded);
+            let end = end.as_deref().map_or_else(|| "end".to_string(), expr);
+            format!("{} from {} to {}", kind, start, end)
+        }
+        IronExpr::Closure { params, body } => {
+            let mut text = "closure with ".to_string();
+            if !params.is_empty() {
+                let params: Vec<String> = params
+                    .iter()
+                    .map(|param| match param.ty {
+                        IronType::Inferred => sanitize_identifier(&param.name),
+                        _ => format!(
+                            "{} of {}",
+                            sanitize_identifier(&param.name),
+                            type_name(&param.ty)
+                        ),
+                    })
+                    .collect();
+                text.push_str(&format!("parameters {} and ", params.join(" and ")));
+            }
+            match body.as_slice() {
+                [IronStmt::Expr(value)] => text.push_str(&format!("body {}", expr(value))),
+                _ => text.push_str(&format!("body\nbegin\n{}\nend closure", block_lines(body))),
+            }
+            text
+        }
+        IronExpr::If {
+            condition,
+            then_block,
+            else_block,
+        } => {
+            let condition = keyword_bounded(condition);
+            let inline = |block: &[IronStmt]| match block {
+                [IronStmt::Expr(value)] if !ends_in_macro(value) => Some(expr(value)),
+                _ => None,
+            };
+            let then_value = inline(then_block);
+            let else_value = match else_block.as_deref() {
+                Some([IronStmt::Expr(nested @ IronExpr::If { .. })]) => Some(Some(expr(nested))),
+                Some([IronStmt::Expr(value)]) => Some(Some(expr(value))),
+                Some(_) => Some(None),
+                None => None,
+            };
+            match (then_value, else_value) {
+                (Some(then_value), None) => format!("if {} then {}", condition, then_value),
+                (Some(then_value), Some(Some(else_value))) => format!(
+                    "if {} then {} otherwise {}",
+                    condition, then_value, else_value
+                ),
+                // A branch holding statements makes the whole conditional a block
+                (_, else_value) => {
+                    let mut text = format!(
+                        "if {} then\nbegin\n{}\nend if",
+                        condition,
+                        block_lines(then_block)
+                    );
+                    if let (Some(_), Some(else_block)) = (else_value, else_block) {
+                        text.push_str(&format!(
+                            "\notherwise\nbegin\n{}\nend if",
+                            block_lines(else_block)
+                        ));
+                    }
+                    text
+                }
+            }
+        }
+        IronExpr::Group(inner) => group(inner),
+    }
+}
+
+fn group(inner: &IronExpr) -> String {
+    let inner = match inner {
+        IronExpr::Group(inner) => inner,
+        _ => inner,
+    };
+    format!("group {} end group", expr(inner))
+}
+
+/// The base of a field access, index, or method call, or the target of an assignment
+///
+/// Operands take no call arguments, so anything that could run on is grouped.
+fn operand(value: &IronExpr) -> String {
+    let atomic = match value {
+        IronExpr::Call { args, .. }
+        | IronExpr::MethodCall { args, .. }
+        | IronExpr::AssociatedFunctionCall { args, .. } => args.is_empty(),
+        IronExpr::Try { expr: inner } => matches!(
+            &**inner,
+            IronExpr::Call { args, .. }
+            | IronExpr::MethodCall { args, .. }
+            | IronExpr::AssociatedFunctionCall { args, .. } if args.is_empty()
+        ),
+        _ => is_closed(value),
+    };
+    if atomic { expr(value) } else { group(value) }
+}
+
+/// Whether an expression reads the same wherever it appears, needing no group
+fn is_closed(value: &IronExpr) -> bool {
+    match value {
+        IronExpr::Identifier(_)
+        | IronExpr::String(_)
+        | IronExpr::Char(_)
+        | IronExpr::Integer(_)
+        | IronExpr::Float(_)
+        | IronExpr::Boolean(_)
+        | IronExpr::None
+        | IronExpr::FieldAccess { .. }
+        | IronExpr::Index { .. }
+        | IronExpr::Group(_) => true,
+        IronExpr::Unary { expr: inner, .. } => is_closed(inner),
+        _ => false,
+    }
+}
+
+/// Whether the rendering of an expression ends in a tail that would absorb a following
+/// operator or list element (call arguments, closure bodies, ...)
+fn is_open_ended(value: &IronExpr) -> bool {
+    match value {
+        IronExpr::Call { args, .. }
+        | IronExpr::MethodCall { args, .. }
+        | IronExpr::AssociatedFunctionCall { args, .. }
+        | IronExpr::TupleStruct { args, .. } => !args.is_empty(),
+        IronExpr::Struct { fields, .. } => !fields.is_empty(),
+        IronExpr::Binary { right, .. } => is_open_ended(right),
+        IronExpr::Unary { expr: inner, .. } => is_open_ended(inner),
+        IronExpr::Macro { .. }
+        | IronExpr::Closure { .. }
+        | IronExpr::If { .. }
+        | IronExpr::Range { .. }
+        | IronExpr::Some(_)
+        | IronExpr::Ok(_)
+        | IronExpr::Err(_)
+        | IronExpr::Tuple(_)
+        | IronExpr::Array(_) => true,
+        _ => false,
+    }
+}
+
+/// Whether an expression ends in macro arguments, which run to the end of the line
+fn ends_in_macro(value: &IronExpr) -> bool {
+    let last = |values: &[IronExpr]| values.last().is_some_and(ends_in_macro);
+    match value {
+        IronExpr::Macro { args, bracket, .. } => !args.is_empty() || *bracket,
+        IronExpr::Binary { right, .. } => ends_in_macro(right),
+        IronExpr::Unary { expr: inner, .. }
+        | IronExpr::Some(inner)
+        | IronExpr::Ok(inner)
+        | IronExpr::Err(inner) => ends_in_macro(inner),
+        IronExpr::Call { args, .. }
+        | IronExpr::MethodCall { args, .. }
+        | IronExpr::AssociatedFunctionCall { args, .. }
+        | IronExpr::TupleStruct { args, .. }
+        | IronExpr::Tuple(args)
+        | IronExpr::Array(args) => last(args),
+        IronExpr::Struct { fields, .. } => fields.last().is_some_and(|(_, v)| ends_in_macro(v)),
+        IronExpr::Range { end, .. } => end.as_deref().is_some_and(ends_in_macro),
+        IronExpr::Closure { body, .. } => {
+            matches!(body.as_slice(), [IronStmt::Expr(value)] if ends_in_macro(value))
+        }
+        IronExpr::If { .. } => true,
+        _ => false,
+    }
+}
+
+/// An expression followed by a keyword on the same line (`then`, `repeat`, `to`)
+fn keyword_bounded(value: &IronExpr) -> String {
+    if ends_in_macro(value) {
+        group(value)
+    } else {
+        expr(value)
+    }
+}
+
+/// The `with <arg> and <arg>...` tail of a call, or nothing
+fn call_args(args: &[IronExpr]) -> String {
+    if args.is_empty() {
+        String::new()
+    } else {
+        format!(" with {}", list(args))
+    }
+}
+
+/// An `and`-separated list (arguments, elements)
+fn list(values: &[IronExpr]) -> String {
+    let last = values.len().saturating_sub(1);
+    values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| list_element(value, index == last))
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// An element of an `and`-separated list
+///
+/// A bare logical and would read as a list separator, and an open-ended element would
+/// swallow the elements after it, so both are wrapped in a group.
+fn list_element(value: &IronExpr, last: bool) -> String {
+    if has_bare_and(value) || (!last && is_open_ended(value)) {
+        group(value)
+    } else {
+        expr(value)
+    }
+}
+
+/// Whether an expression prints a logical `and` outside any group
+fn has_bare_and(value: &IronExpr) -> bool {
+    match value {
+        IronExpr::Binary {
+            op: IronBinaryOp::And,
+            ..
+        } => true,
+        // Only `or` binds looser than `and`, so its operands print ungrouped
+        IronExpr::Binary {
+            op: IronBinaryOp::Or,
+            left,
+            right,
+        } => has_bare_and(left) || has_bare_and(right),
+        _ => false,
+    }
+}
+
+/// Binding strength of a binary operator, as the Iron parser reads it
+fn precedence(op: &IronBinaryOp) -> u8 {
+    match op {
+        IronBinaryOp::Or => 1,
+        IronBinaryOp::And => 2,
+        IronBinaryOp::Eq | IronBinaryOp::Ne => 3,
+        IronBinaryOp::Lt | IronBinaryOp::Le | IronBinaryOp::Gt | IronBinaryOp::Ge => 4,
+        IronBinaryOp::BitOr => 5,
+        IronBinaryOp::BitXor => 6,
+        IronBinaryOp::BitAnd => 7,
+        IronBinaryOp::Shl | IronBinaryOp::Shr => 8,
+        IronBinaryOp::Add | IronBinaryOp::Sub => 9,
+        IronBinaryOp::Mul | IronBinaryOp::Div | IronBinaryOp::Mod => 10,
+    }
+}
+
+fn binary_op(op: &IronBinaryOp) -> &'static str {
+    match op {
+        IronBinaryOp::Add => "plus",
+        IronBinaryOp::Sub => "minus",
+        IronBinaryOp::Mul => "times",
+        IronBinaryOp::Div => "divided by",
+        IronBinaryOp::Mod => "modulo",
+        IronBinaryOp::And => "and",
+        IronBinaryOp::Or => "or",
+        IronBinaryOp::Eq => "equal to",
+        IronBinaryOp::Ne => "not equal to",
+        IronBinaryOp::Lt => "less than",
+        IronBinaryOp::Le => "less than or equal to",
+        IronBinaryOp::Gt => "greater than",
+        IronBinaryOp::Ge => "greater than or equal to",
+        IronBinaryOp::BitAnd => "bitwise and",
+        IronBinaryOp::BitOr => "bitwise or",
+        IronBinaryOp::BitXor => "bitwise xor",
+        IronBinaryOp::Shl => "shift left",
+        IronBinaryOp::Shr => "shift right",
+    }
+}
+
+fn type_name(ty: &IronType) -> String {
+    match ty {
+        IronType::Named(name) => sanitize_identifier(name),
+        IronType::Reference(inner) => format!("reference to {}", type_name(inner)),
+        IronType::MutableReference(inner) => format!("mutable reference to {}", type_name(inner)),
+        IronType::RawPointer(inner) => format!("raw pointer to {}", type_name(inner)),
+        IronType::MutableRawPointer(inner) => {
+            format!("mutable raw pointer to {}", type_name(inner))
+        }
+        IronType::Optional(inner) => format!("optional {}", type_name(inner)),
+        IronType::Result(ok, error) => {
+            format!("result of {} or error {}", type_name(ok), type_name(error))
+        }
+        IronType::List(inner) => format!("list of {}", type_name(inner)),
+        IronType::BoxType(inner) => format!("box containing {}", type_name(inner)),
+        IronType::Tuple(types) => format!("tuple of {}", type_list(types)),
+        IronType::Array(inner) => format!("array of {}", type_name(inner)),
+        IronType::Slice(inner) => format!("slice of {}", type_name(inner)),
+        IronType::Function(params, ret) => format!(
+            "function taking {} returning {}",
+            type_list(params),
+            type_name(ret)
+        ),
+        IronType::Generic(name, _) => sanitize_identifier(name),
+        IronType::Path { name, args } => match (name.as_str(), args.as_slice()) {
+            ("hash map", [key, value]) => {
+                format!("hash map from {} to {}", type_name(key), type_name(value))
+            }
+            ("reference counted" | "atomic reference counted", [inner]) => {
+                format!("{} {}", name, type_name(inner))
+            }
+            _ => format!("{} of {}", sanitize_identifier(name), type_list(args)),
+        },
+        IronType::Inferred => "inferred".to_string(),
+    }
+}
+
+fn type_list(types: &[IronType]) -> String {
+    types
+        .iter()
+        .map(type_name)
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+/// The `with generic type T implementing Bound ...` clause of an item, with a leading
+/// space, or nothing
+fn generics(generics: &[IronGeneric]) -> String {
+    generics
+        .iter()
+        .map(|generic| {
+            if generic.bounds.is_empty() {
+                format!(" with generic type {}", sanitize_identifier(&generic.name))
+            } else {
+                let bounds: Vec<String> = generic
+                    .bounds
+                    .iter()
+                    .map(|bound| sanitize_identifier(&bound.trait_name))
+                    .collect();
+                format!(
+                    " with generic type {} implementing {}",
+                    sanitize_identifier(&generic.name),
+                    bounds.join(" and ")
+                )
+            }
+        })
+        .collect()
+}
+
+fn field_list(fields: &[IronField]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            format!(
+                "{} of {}",
+                sanitize_identifier(&field.name),
+                type_name(&field.ty)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+fn use_tree(tree: &IronUseTree) -> String {
+    match tree {
+        IronUseTree::Path { segment, rest } => match &**rest {
+            IronUseTree::Group(entries) => {
+                let entries: Vec<String> = entries.iter().map(use_tree).collect();
+                format!(
+                    "{} containing {}",
+                    sanitize_identifier(segment),
+                    entries.join(" and ")
+                )
+            }
+            rest => format!("{} {}", sanitize_identifier(segment), use_tree(rest)),
+        },
+        IronUseTree::Name { name, alias: None } => sanitize_identifier(name),
+        IronUseTree::Name {
+            name,
+            alias: Some(alias),
+        } => format!(
+            "{} as {}",
+            sanitize_identifier(name),
+            sanitize_identifier(alias)
+        ),
+        IronUseTree::Group(entries) => {
+            let entries: Vec<String> = entries.iter().map(use_tree).collect();
+            entries.join(" and ")
+        }
+    }
+}
+
+/// A `::`-separated path as the space-separated names Iron spells it with
+fn path(name: &str) -> String {
+    name.split("::")
+        .map(sanitize_identifier)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn pattern(value: &IronPattern) -> String {
+    match value {
+        IronPattern::Identifier(name) => name.clone(),
+        IronPattern::Wildcard => "anything".to_string(),
+        IronPattern::Literal(value) => expr(value),
+        IronPattern::Tuple(patterns) => {
+            let patterns: Vec<String> = patterns.iter().map(pattern).collect();
+            format!("tuple of {}", patterns.join(" and "))
+        }
+        IronPattern::Struct { name, fields } => {
+            let fields: Vec<String> = fields
+                .iter()
+                .map(|(field, value)| format!("{} of {}", field.name, pattern(value)))
+                .collect();
+            format!("{} with {}", name, fields.join(" and "))
+        }
+        IronPattern::Variant {
+            enum_name,
+            variant_name,
+            data,
+        } => match data {
+            Some(data) => format!("{} {} of {}", enum_name, variant_name, pattern(data)),
+            None => format!("{} {}", enum_name, variant_name),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iron_parser::IronParser;
+
+    fn print(source: &str) -> String {
+        IronPrinter::new().print(&IronParser::new(source).parse().unwrap())
+    }
+
+    fn binary(left: IronExpr, op: IronBinaryOp, right: IronExpr) -> IronExpr {
+        IronExpr::Binary {
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+        }
+    }
+
+    fn name(name: &str) -> IronExpr {
+        IronExpr::Identifier(name.to_string())
+    }
+
+    #[test]
+    fn test_print_groups_where_grammar_requires() {
+        let call = |args| IronExpr::Call {
+            func: Box::new(name("f")),
+            args,
+        };
+        let body = vec![
+            // (a + b) * c, a - (b - c), and f(x) + 1
+            IronStmt::Expr(binary(
+                binary(name("a"), IronBinaryOp::Add, name("b")),
+                IronBinaryOp::Mul,
+                name("c"),
+            )),
+            IronStmt::Expr(binary(
+                name("a"),
+                IronBinaryOp::Sub,
+                binary(name("b"), IronBinaryOp::Sub, name("c")),
+            )),
+            IronStmt::Expr(binary(
+                call(vec![name("x")]),
+                IronBinaryOp::Add,
+                IronExpr::Integer("1".to_string()),
+            )),
+            // f(a && b, g(x), y) and a method on a call with arguments
+            IronStmt::Expr(call(vec![
+                binary(name("a"), IronBinaryOp::And, name("b")),
+                IronExpr::Call {
+                    func: Box::new(name("g")),
+                    args: vec![name("x")],
+                },
+                name("y"),
+            ])),
+            IronStmt::Expr(IronExpr::MethodCall {
+                receiver: Box::new(call(vec![name("x")])),
+                method: "len".to_string(),
+                args: vec![],
+            }),
+            IronStmt::Let {
+                name: "loop".to_string(),
+                mutable: false,
+                value: IronExpr::Integer("-1".to_string()),
+            },
+        ];
+        let file = IronFile {
+            items: vec![IronItem::Function(IronFunction {
+                line: 1,
+                name: "main".to_string(),
+                generics: vec![],
+                params: vec![],
+                return_type: None,
+                body,
+            })],
+            warnings: vec![],
+        };
+
+        let iron = IronPrinter::new().print(&file);
+        assert_eq!(
+            iron,
+            "function main\n\
+             begin\n    \
+                 group a plus b end group times c\n    \
+                 a minus group b minus c end group\n    \
+                 group call f with x end group plus 1\n    \
+                 call f with group a and b end group and group call g with x end group and y\n    \
+                 call method len on group call f with x end group\n    \
+                 define user_loop as negative 1\n\
+             end function\n"
+        );
+        assert_eq!(print(&iron), iron);
+    }
+
+    #[test]
+    fn test_print_reparses_to_same_rust() {
+        let rust = "use std::collections::{HashMap, HashSet as Set};\n\n\
+            /// A point\n\
+            struct Point<T> {\n    x: T,\n}\n\n\
+            enum Shape {\n    Dot,\n    Circle(f64),\n    Rect { w: u32, h: u32 },\n}\n\n\
+            const LIMIT: i32 = 10;\n\n\
+            fn f(x: i32) -> i32 {\n\
+                let mut total = 0;\n\
+                for i in 0..=x {\n        total += i * 2;\n    }\n\
+                while total > LIMIT {\n        total -= 1;\n    }\n\
+                if x > 1 {\n        return x;\n    } else if x < 0 {\n        return 0;\n    }\n\
+                let add = |a, b: i32| a + b;\n\
+                let run = || {\n        let y = 1;\n        y\n    };\n\
+                let v = vec![1, 2];\n\
+                let s = \"two\\nlines\";\n\
+                let p = Point { x: (x + 1) * 2 };\n\
+                add(total, run()) + p.x\n\
+            }\n";
+        let ast = crate::reduce_to_ast(rust).unwrap();
+        let iron = IronPrinter::new().print(&ast);
+        assert_eq!(print(&iron), iron);
+        assert_eq!(ast.to_string(), iron);
+        assert_eq!(
+            crate::oxidize(&iron).unwrap(),
+            crate::oxidize(&crate::transpile(rust).unwrap()).unwrap()
+        );
+    }
+}
@@ -2,6 +2,11 @@
 //!
 //! Tokenizes Iron source code into tokens for parsing.
 
+use crate::header::{HEADER_START, split_header};
+use crate::keywords::{COLLISION_PREFIX, is_reserved, is_sanitized};
+use std::ops::Range;
+use unicode_ident::{is_xid_continue, is_xid_start};
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     // Keywords
@@ -98,25 +103,106 @@ pub enum Token {
     Body,
     Pipe,
     Verbatim,
+    Module,
+    Import,
+    Negate,
+    Negative,
+    Dereference,
+    Bitwise,
+    Shift,
+    Group,
+    Increase,
+    Decrease,
+    Multiply,
+    Divide,
+    Update,
+
+    // Fused multi-word keywords
+    GreaterThan,
+    GreaterThanOrEqualTo,
+    LessThan,
+    LessThanOrEqualTo,
+    EqualTo,
+    NotEqualTo,
+    DividedBy,
+    BitwiseAnd,
+    BitwiseOr,
+    BitwiseXor,
+    ShiftLeft,
+    ShiftRight,
+    EndOf(Box<Token>), // "end <block keyword>"
 
     // Literals
     Identifier(String),
     String(String),
+    Char(char),
     Integer(String),
     Float(String),
     Boolean(bool),
 
+    // Comments ("note that ...")
+    Comment(String),
+
+    // Raw Rust item ("verbatim begin" ... "verbatim end")
+    VerbatimBlock(String),
+
+    // A run of characters outside the grammar, such as "::" or "->", kept only when
+    // the tokenizer is asked to (see `with_symbols`)
+    Symbol(String),
+
     // Special
     NewLine,
     Indent(usize),
     EndOfFile,
 }
 
+/// Line and column (both 1-based) where a token starts in the Iron source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// A token together with where it came from in the Iron source
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub line: usize,
+    pub column: usize,
+    pub byte_range: Range<usize>,
+}
+
+impl SpannedToken {
+    pub fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
 pub struct Tokenizer {
     input: String,
+    /// Byte offset of the current character
     position: usize,
     line: usize,
     column: usize,
+    /// Match keywords regardless of capitalization ("Begin", "END FUNCTION")
+    case_insensitive_keywords: bool,
+    /// Keep runs of characters outside the grammar as symbol tokens instead of skipping them
+    symbols: bool,
+}
+
+/// Where a token began: its position and byte offset
+struct TokenStart {
+    position: Position,
+    byte: usize,
 }
 
 impl Tokenizer {
@@ -126,32 +212,78 @@ impl Tokenizer {
             position: 0,
             line: 1,
             column: 1,
+            case_insensitive_keywords: false,
+            symbols: false,
         }
     }
 
+    /// Match keywords in any capitalization, so "Begin" and "END FUNCTION" are read as
+    /// `begin` and `end function`. Identifiers keep their original spelling.
+    pub fn with_case_insensitive_keywords(mut self, enabled: bool) -> Self {
+        self.case_insensitive_keywords = enabled;
+        self
+    }
+
+    /// Keep each run of characters outside the grammar, such as "::" or "->", as a
+    /// `Token::Symbol` instead of skipping it, for checking Iron for Rust symbols
+    pub fn with_symbols(mut self, enabled: bool) -> Self {
+        self.symbols = enabled;
+        self
+    }
+
+    /// The header line the input starts with, if any, and the content after it
+    pub fn header(&self) -> Option<(&str, &str)> {
+        split_header(&self.input)
+    }
+
     pub fn tokenize(&mut self) -> Vec<Token> {
+        self.tokenize_spanned()
+            .into_iter()
+            .map(|spanned| spanned.token)
+            .collect()
+    }
+
+    /// Tokenize the input, keeping the line, column, and byte range of every token
+    pub fn tokenize_spanned(&mut self) -> Vec<SpannedToken> {
         let mut tokens = Vec::new();
-        let mut _current_indent = 0;
+
+        // A header line is metadata, read by the parser through `header`
+        if self.position == 0 && self.input.starts_with(HEADER_START) {
+            while self.position < self.input.len() && self.current_char() != '\n' {
+                self.advance();
+            }
+        }
 
         while self.position < self.input.len() {
             let ch = self.current_char();
+            let start = self.token_start();
 
             // Handle newlines and indentation
             if ch == '\n' {
-                tokens.push(Token::NewLine);
                 self.advance();
+                self.push(&mut tokens, Token::NewLine, start);
 
                 // Count indentation on next line
+                let indent_start = self.token_start();
                 let mut indent = 0;
-                while self.position < self.input.len() && self.current_char() == ' ' {
+                while self.position < self.input.len() && matches!(self.current_char(), ' ' | '\t')
+                {
                     indent += 1;
                     self.advance();
                 }
 
+                // A line starting with `continued` goes on with the line before it
+                if self.at_continuation() {
+                    tokens.pop();
+                    for _ in 0..CONTINUATION.len() {
+                        self.advance();
+                    }
+                    continue;
+                }
+
                 // Only track indentation if there's actual content
                 if self.position < self.input.len() && self.current_char() != '\n' {
-                    tokens.push(Token::Indent(indent));
-                    let _ = indent;
+                    self.push(&mut tokens, Token::Indent(indent), indent_start);
                 }
                 continue;
             }
@@ -162,74 +294,131 @@ impl Tokenizer {
                 continue;
             }
 
-            // Skip comments (note that ...)
-            if self.starts_with("note that") {
+            // Comments (note that ...)
+            if self.starts_with_phrase(COMMENT_START) {
+                let text_start = self.position + COMMENT_START.len();
                 while self.position < self.input.len() && self.current_char() != '\n' {
                     self.advance();
                 }
+                let text = self.input[text_start..self.position].trim().to_string();
+                self.push(&mut tokens, Token::Comment(text), start);
+                continue;
+            }
+
+            // Multi-line raw text: "text begin" ... "text end"
+            if self.at_raw_block(TEXT_BEGIN) {
+                let token = Token::String(self.read_raw_block(TEXT_END));
+                self.push(&mut tokens, token, start);
+                continue;
+            }
+
+            // Raw Rust items: "verbatim begin" ... "verbatim end"
+            if self.at_raw_block(VERBATIM_BEGIN) {
+                let token = Token::VerbatimBlock(self.read_raw_block(VERBATIM_END));
+                self.push(&mut tokens, token, start);
                 continue;
             }
 
             // String literals
             if ch == '"' {
-                tokens.push(self.read_string());
+                let token = self.read_string();
+                self.push(&mut tokens, token, start);
                 continue;
             }
 
             // Character literals
             if ch == '\'' {
-                tokens.push(self.read_char());
+                let token = self.read_char();
+                self.push(&mut tokens, token, start);
                 continue;
             }
 
             // Numbers
             if ch.is_ascii_digit() {
-                tokens.push(self.read_number());
+                let token = self.read_number();
+                self.push(&mut tokens, token, start);
                 continue;
             }
 
             // Identifiers and keywords
-            if ch.is_alphabetic() || ch == '_' {
-                tokens.push(self.read_word());
+            if is_xid_start(ch) || ch == '_' {
+                let token = self.read_word();
+                self.push(&mut tokens, token, start);
                 continue;
             }
 
             // Handle punctuation
-            match ch {
-                ',' => {
-                    tokens.push(Token::Comma);
-                    self.advance();
-                    continue;
-                }
-                _ => {}
+            if ch == ',' {
+                self.advance();
+                self.push(&mut tokens, Token::Comma, start);
+                continue;
             }
 
-            // Unknown character - skip
+            // Unknown character - skip, or keep the run it starts
+            if self.symbols {
+                let token = self.read_symbol();
+                self.push(&mut tokens, token, start);
+                continue;
+            }
             self.advance();
         }
 
-        tokens.push(Token::EndOfFile);
-        tokens
+        let start = self.token_start();
+        self.push(&mut tokens, Token::EndOfFile, start);
+        fuse_keywords(tokens, self.case_insensitive_keywords)
+    }
+
+    fn token_start(&self) -> TokenStart {
+        TokenStart {
+            position: Position {
+                line: self.line,
+                column: self.column,
+            },
+            byte: self.position,
+        }
+    }
+
+    /// Record a token that started at `start` and ends at the current position
+    fn push(&self, tokens: &mut Vec<SpannedToken>, token: Token, start: TokenStart) {
+        tokens.push(SpannedToken {
+            token,
+            line: start.position.line,
+            column: start.position.column,
+            byte_range: start.byte..self.position,
+        });
     }
 
     fn current_char(&self) -> char {
-        self.input.chars().nth(self.position).unwrap_or('\0')
+        self.peek_char(0)
+    }
+
+    /// The character `offset` characters ahead of the current one
+    fn peek_char(&self, offset: usize) -> char {
+        self.input[self.position..]
+            .chars()
+            .nth(offset)
+            .unwrap_or('\0')
     }
 
     fn advance(&mut self) {
-        if self.position < self.input.len() {
-            if self.current_char() == '\n' {
+        if let Some(ch) = self.input[self.position..].chars().next() {
+            if ch == '\n' {
                 self.line += 1;
                 self.column = 1;
             } else {
                 self.column += 1;
             }
-            self.position += 1;
+            self.position += ch.len_utf8();
         }
     }
 
-    fn starts_with(&self, s: &str) -> bool {
-        self.input[self.position..].starts_with(s)
+    fn starts_with_phrase(&self, phrase: &str) -> bool {
+        strip_phrase(
+            &self.input[self.position..],
+            phrase,
+            self.case_insensitive_keywords,
+        )
+        .is_some()
     }
 
     fn read_string(&mut self) -> Token {
@@ -260,12 +449,90 @@ impl Tokenizer {
         Token::String(value)
     }
 
+    /// Whether the input is at the word that continues the line before
+    fn at_continuation(&self) -> bool {
+        strip_phrase(
+            &self.input[self.position..],
+            CONTINUATION,
+            self.case_insensitive_keywords,
+        )
+        .is_some_and(|rest| !rest.starts_with(is_xid_continue))
+    }
+
+    /// Whether the input is at a raw block opener ("text begin") ending its line
+    fn at_raw_block(&self, begin: &str) -> bool {
+        let rest = &self.input[self.position..];
+        match strip_phrase(rest, begin, self.case_insensitive_keywords) {
+            Some(after) => {
+                let line = after.split('\n').next().unwrap_or("");
+                line.trim().is_empty()
+            }
+            None => false,
+        }
+    }
+
+    /// Read the raw lines between a block opener and its closing line (`end`).
+    ///
+    /// Lines are taken verbatim without escapes, minus the indentation of the
+    /// closing line so the block can be nested inside indented code.
+    fn read_raw_block(&mut self, end: &str) -> String {
+        // Skip the opener and the rest of its line
+        while self.position < self.input.len() && self.current_char() != '\n' {
+            self.advance();
+        }
+        self.advance();
+
+        let mut lines = Vec::new();
+        let mut closing_indent = 0;
+        while self.position < self.input.len() {
+            let line = self.input[self.position..]
+                .split('\n')
+                .next()
+                .unwrap_or("")
+                .to_string();
+            let trimmed = line.trim_start_matches([' ', '\t']);
+            if is_block_end(trimmed, end, self.case_insensitive_keywords) {
+                closing_indent = line.len() - trimmed.len();
+                for _ in 0..(closing_indent + end.len()) {
+                    self.advance();
+                }
+                break;
+            }
+            for _ in line.chars() {
+                self.advance();
+            }
+            self.advance(); // newline
+            lines.push(line);
+        }
+
+        lines
+            .iter()
+            .map(|line| {
+                let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+                &line[indent.min(closing_indent)..]
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// A character literal, with the escapes of a string and `\'`; quotes around other
+    /// than one character read as a string
     fn read_char(&mut self) -> Token {
         self.advance(); // skip opening quote
         let mut value = String::new();
 
         while self.position < self.input.len() && self.current_char() != '\'' {
-            value.push(self.current_char());
+            if self.current_char() == '\\' {
+                self.advance();
+                match self.current_char() {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    'r' => value.push('\r'),
+                    c => value.push(c),
+                }
+            } else {
+                value.push(self.current_char());
+            }
             self.advance();
         }
 
@@ -273,10 +540,18 @@ impl Tokenizer {
             self.advance(); // skip closing quote
         }
 
-        Token::String(value) // Represent as string for simplicity
+        let mut chars = value.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => Token::Char(ch),
+            _ => Token::String(value),
+        }
     }
 
     fn read_number(&mut self) -> Token {
+        if let Some(token) = self.read_radix_number() {
+            return token;
+        }
+
         let mut value = String::new();
         let mut is_float = false;
 
@@ -287,10 +562,7 @@ impl Tokenizer {
                 self.advance();
             } else if ch == '.' && !is_float {
                 // Check if next char is digit (to distinguish from method call)
-                let next_pos = self.position + 1;
-                if next_pos < self.input.len()
-                    && self.input.chars().nth(next_pos).unwrap().is_ascii_digit()
-                {
+                if self.peek_char(1).is_ascii_digit() {
                     is_float = true;
                     value.push(ch);
                     self.advance();
@@ -309,12 +581,63 @@ impl Tokenizer {
         }
     }
 
+    /// Read a hexadecimal (`0x`), octal (`0o`), or binary (`0b`) integer, keeping its prefix
+    fn read_radix_number(&mut self) -> Option<Token> {
+        if self.current_char() != '0' {
+            return None;
+        }
+        let radix = match self.peek_char(1) {
+            'x' => 16,
+            'o' => 8,
+            'b' => 2,
+            _ => return None,
+        };
+        if !self.peek_char(2).is_digit(radix) {
+            return None;
+        }
+
+        let mut value = String::new();
+        value.push(self.current_char());
+        self.advance();
+        value.push(self.current_char());
+        self.advance();
+
+        loop {
+            let ch = self.current_char();
+            if ch.is_digit(radix) || ch == '_' {
+                value.push(ch);
+                self.advance();
+            } else {
+                break;
+            }
+        }
+
+        Some(Token::Integer(value))
+    }
+
+    /// Read a run of characters that start no other token
+    fn read_symbol(&mut self) -> Token {
+        let start = self.position;
+        while self.position < self.input.len() {
+            let ch = self.current_char();
+            if ch.is_whitespace()
+                || ch.is_ascii_digit()
+                || is_xid_start(ch)
+                || matches!(ch, '_' | '"' | '\'' | ',')
+            {
+                break;
+            }
+            self.advance();
+        }
+        Token::Symbol(self.input[start..self.position].to_string())
+    }
+
     fn read_word(&mut self) -> Token {
         let mut word = String::new();
 
         while self.position < self.input.len() {
             let ch = self.current_char();
-            if ch.is_alphanumeric() || ch == '_' {
+            if is_xid_continue(ch) {
                 word.push(ch);
                 self.advance();
             } else {
@@ -326,51 +649,22 @@ impl Tokenizer {
     }
 
     fn match_keyword(&self, word: &str) -> Token {
-        // Check for multi-word keywords first
-        let remaining = &self.input[self.position..];
+        let folded = if self.case_insensitive_keywords {
+            word.to_lowercase()
+        } else {
+            word.to_string()
+        };
 
-        match word {
+        match folded.as_str() {
             "function" => Token::Function,
-            "with" => {
-                if remaining.starts_with(" fields") || self.check_context() == "struct" {
-                    Token::With
-                } else if remaining.starts_with(" generic") {
-                    Token::With
-                } else {
-                    Token::With
-                }
-            }
+            "with" => Token::With,
             "generic" => Token::Generic,
-            "type" => {
-                if remaining.starts_with(" T") || remaining.starts_with(" ") {
-                    // Check if this is "type T" in a generic context
-                    Token::Type
-                } else {
-                    Token::Type
-                }
-            }
+            "type" => Token::Type,
             "implementing" => Token::Implementing,
             "takes" => Token::Takes,
             "returns" => Token::Returns,
             "begin" => Token::Begin,
-            "end" => {
-                // Check following word for end block type
-                if remaining.starts_with(" function") {
-                    Token::End
-                } else if remaining.starts_with(" if") {
-                    Token::End
-                } else if remaining.starts_with(" for") {
-                    Token::End
-                } else if remaining.starts_with(" while") {
-                    Token::End
-                } else if remaining.starts_with(" structure") {
-                    Token::End
-                } else if remaining.starts_with(" enumeration") {
-                    Token::End
-                } else {
-                    Token::End
-                }
-            }
+            "end" => Token::End,
             "define" => Token::Define,
             "mutable" => Token::Mutable,
             "as" => Token::As,
@@ -385,13 +679,7 @@ impl Tokenizer {
             "case" => Token::Case,
             "while" => Token::While,
             "repeat" => Token::Repeat,
-            "for" => {
-                if remaining.starts_with(" each") {
-                    Token::For
-                } else {
-                    Token::For
-                }
-            }
+            "for" => Token::For,
             "each" => Token::Each,
             "in" => Token::In,
             "iterator" => Token::Iterator,
@@ -460,23 +748,173 @@ impl Tokenizer {
             "parameters" => Token::Parameters,
             "body" => Token::Body,
             "verbatim" => Token::Verbatim,
+            "module" => Token::Module,
+            "import" => Token::Import,
+            "negate" => Token::Negate,
+            "negative" => Token::Negative,
+            "dereference" => Token::Dereference,
+            "bitwise" => Token::Bitwise,
+            "shift" => Token::Shift,
+            "group" => Token::Group,
+            "increase" => Token::Increase,
+            "decrease" => Token::Decrease,
+            "multiply" => Token::Multiply,
+            "divide" => Token::Divide,
+            "update" => Token::Update,
             "true" => Token::Boolean(true),
             "false" => Token::Boolean(false),
             _ => {
-                // Check for user_ prefix (collision-avoiding identifier)
-                if word.starts_with("user_") {
-                    Token::Identifier(word[5..].to_string())
-                } else {
-                    Token::Identifier(word.to_string())
+                // Strip the user_ prefix only where reduction added it to avoid a collision,
+                // so names like user_id are kept as written
+                match strip_phrase(word, COLLISION_PREFIX, self.case_insensitive_keywords) {
+                    Some(stripped) if is_reserved(stripped) || is_sanitized(stripped) => {
+                        Token::Identifier(stripped.to_string())
+                    }
+                    _ => Token::Identifier(word.to_string()),
                 }
             }
         }
     }
+}
+
+const COMMENT_START: &str = "note that";
+const TEXT_BEGIN: &str = "text begin";
+const TEXT_END: &str = "text end";
+const VERBATIM_BEGIN: &str = "verbatim begin";
+const VERBATIM_END: &str = "verbatim end";
+
+/// The word starting a line that continues the one before, for lines wrapped to fit a
+/// width
+pub const CONTINUATION: &str = "continued";
+
+/// Whether a line (without its indentation) closes a raw text block
+pub fn is_text_end(line: &str, ignore_case: bool) -> bool {
+    is_block_end(line, TEXT_END, ignore_case)
+}
+
+/// Whether a line (without its indentation) closes a raw verbatim block
+pub fn is_verbatim_end(line: &str, ignore_case: bool) -> bool {
+    is_block_end(line, VERBATIM_END, ignore_case)
+}
+
+fn is_block_end(line: &str, end: &str, ignore_case: bool) -> bool {
+    strip_phrase(line, end, ignore_case)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+}
+
+/// Strip a leading keyword phrase, optionally ignoring its capitalization
+fn strip_phrase<'a>(text: &'a str, phrase: &str, ignore_case: bool) -> Option<&'a str> {
+    if !ignore_case {
+        return text.strip_prefix(phrase);
+    }
+    let head = text.get(..phrase.len())?;
+    head.eq_ignore_ascii_case(phrase)
+        .then(|| &text[phrase.len()..])
+}
+
+/// Whether a word token spells `keyword`
+fn is_word(token: &str, keyword: &str, ignore_case: bool) -> bool {
+    if ignore_case {
+        token.eq_ignore_ascii_case(keyword)
+    } else {
+        token == keyword
+    }
+}
+
+/// Replace multi-word keywords ("greater than or equal to", "end function", ...) with
+/// single fused tokens spanning all of their words
+fn fuse_keywords(tokens: Vec<SpannedToken>, ignore_case: bool) -> Vec<SpannedToken> {
+    let mut fused = Vec::with_capacity(tokens.len());
+    let mut i = 0;
 
-    fn check_context(&self) -> &str {
-        // Simple context checking - can be improved
-        ""
+    while i < tokens.len() {
+        let window: Vec<&Token> = tokens[i..].iter().take(5).map(|t| &t.token).collect();
+        match fused_keyword(&window, ignore_case) {
+            Some((token, len)) => {
+                let first = &tokens[i];
+                let last = &tokens[i + len - 1];
+                fused.push(SpannedToken {
+                    token,
+                    line: first.line,
+                    column: first.column,
+                    byte_range: first.byte_range.start..last.byte_range.end,
+                });
+                i += len;
+            }
+            None => {
+                fused.push(tokens[i].clone());
+                i += 1;
+            }
+        }
     }
+
+    fused
+}
+
+/// The fused keyword starting a token window, with the number of tokens it spans
+fn fused_keyword(window: &[&Token], ignore_case: bool) -> Option<(Token, usize)> {
+    let fused = match window {
+        [
+            Token::Greater,
+            Token::Than,
+            Token::Or,
+            Token::Equal,
+            Token::To,
+            ..,
+        ] => (Token::GreaterThanOrEqualTo, 5),
+        [
+            Token::Less,
+            Token::Than,
+            Token::Or,
+            Token::Equal,
+            Token::To,
+            ..,
+        ] => (Token::LessThanOrEqualTo, 5),
+        [Token::Greater, Token::Than, ..] => (Token::GreaterThan, 2),
+        [Token::Less, Token::Than, ..] => (Token::LessThan, 2),
+        [Token::Not, Token::Equal, Token::To, ..] => (Token::NotEqualTo, 3),
+        [Token::Equal, Token::To, ..] => (Token::EqualTo, 2),
+        [Token::Divided, Token::By, ..] => (Token::DividedBy, 2),
+        // "negative 5" is the literal -5, since Iron has no minus sign
+        [Token::Negative, Token::Integer(n), ..] => (Token::Integer(format!("-{}", n)), 2),
+        [Token::Negative, Token::Float(n), ..] => (Token::Float(format!("-{}", n)), 2),
+        [Token::Bitwise, Token::And, ..] => (Token::BitwiseAnd, 2),
+        [Token::Bitwise, Token::Or, ..] => (Token::BitwiseOr, 2),
+        [Token::Bitwise, Token::Identifier(word), ..] if is_word(word, "xor", ignore_case) => {
+            (Token::BitwiseXor, 2)
+        }
+        [Token::Shift, Token::Identifier(word), ..] if is_word(word, "left", ignore_case) => {
+            (Token::ShiftLeft, 2)
+        }
+        [Token::Shift, Token::Identifier(word), ..] if is_word(word, "right", ignore_case) => {
+            (Token::ShiftRight, 2)
+        }
+        [Token::End, label, ..] if is_block_label(label) => {
+            (Token::EndOf(Box::new((*label).clone())), 2)
+        }
+        _ => return None,
+    };
+    Some(fused)
+}
+
+/// Keywords that can follow "end" to close a block
+fn is_block_label(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Function
+            | Token::Structure
+            | Token::Enumeration
+            | Token::If
+            | Token::While
+            | Token::For
+            | Token::Loop
+            | Token::Compare
+            | Token::Module
+            | Token::Group
+            | Token::Closure
+            | Token::Static
+            | Token::Constant
+    )
 }
 
 #[cfg(test)]
@@ -492,7 +930,7 @@ mod tests {
         assert!(tokens.contains(&Token::Function));
         assert!(tokens.contains(&Token::Identifier("hello".to_string())));
         assert!(tokens.contains(&Token::Begin));
-        assert!(tokens.contains(&Token::End));
+        assert!(tokens.contains(&Token::EndOf(Box::new(Token::Function))));
     }
 
     #[test]
@@ -503,5 +941,194 @@ mod tests {
 
         let id_token = tokens.iter().find(|t| matches!(t, Token::Identifier(_)));
         assert!(matches!(id_token, Some(Token::Identifier(name)) if name == "function"));
+
+        // Only prefixes added to avoid a collision are stripped
+        let tokens = Tokenizer::new("user_id plus user_user_self").tokenize();
+        assert_eq!(tokens[0], Token::Identifier("user_id".to_string()));
+        assert_eq!(tokens[2], Token::Identifier("user_self".to_string()));
+    }
+
+    #[test]
+    fn test_tokenize_spans() {
+        let input = "define x as \"é\"\n    set x equal to 7";
+        let mut tokenizer = Tokenizer::new(input);
+        let tokens = tokenizer.tokenize_spanned();
+
+        let string = tokens
+            .iter()
+            .find(|t| matches!(t.token, Token::String(_)))
+            .unwrap();
+        assert_eq!((string.line, string.column), (1, 13));
+        assert_eq!(&input[string.byte_range.clone()], "\"é\"");
+
+        let indent = tokens.iter().find(|t| t.token == Token::Indent(4)).unwrap();
+        assert_eq!((indent.line, indent.column), (2, 1));
+
+        let seven = tokens
+            .iter()
+            .find(|t| t.token == Token::Integer("7".to_string()))
+            .unwrap();
+        assert_eq!((seven.line, seven.column), (2, 20));
+        assert_eq!(&input[seven.byte_range.clone()], "7");
+
+        let eof = tokens.last().unwrap();
+        assert_eq!(eof.token, Token::EndOfFile);
+        assert_eq!(eof.byte_range, input.len()..input.len());
+    }
+
+    #[test]
+    fn test_tokenize_continued_lines() {
+        let joined = Tokenizer::new("define x as 1 plus\n    continued 2\nx").tokenize();
+        assert_eq!(joined, Tokenizer::new("define x as 1 plus 2\nx").tokenize());
+
+        // Only the word itself continues a line
+        let tokens = Tokenizer::new("x\ncontinued_total\nuser_continued").tokenize();
+        assert!(tokens.contains(&Token::Identifier("continued_total".to_string())));
+        assert!(tokens.contains(&Token::Identifier("continued".to_string())));
+        assert_eq!(tokens.iter().filter(|t| **t == Token::NewLine).count(), 2);
+    }
+
+    #[test]
+    fn test_tokenize_char_literals() {
+        let tokens = Tokenizer::new(r"' ' '\n' '\'' 'ab'").tokenize();
+        assert_eq!(
+            tokens[..4],
+            [
+                Token::Char(' '),
+                Token::Char('\n'),
+                Token::Char('\''),
+                Token::String("ab".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_multibyte_input() {
+        let input = "define naïve as \"日本\" note that ünïcode\nset naïve equal to 1.5";
+        let mut tokenizer = Tokenizer::new(input);
+        let tokens = tokenizer.tokenize();
+
+        assert!(tokens.contains(&Token::Identifier("naïve".to_string())));
+        assert!(tokens.contains(&Token::String("日本".to_string())));
+        assert!(tokens.contains(&Token::Float("1.5".to_string())));
+        assert_eq!(tokens.last(), Some(&Token::EndOfFile));
+    }
+
+    #[test]
+    fn test_tokenize_radix_literals() {
+        let input = "0xFF and 0o755 and 0b1010_0101 and 0 and 0.5";
+        let mut tokenizer = Tokenizer::new(input);
+        let tokens = tokenizer.tokenize();
+
+        let numbers: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Integer(n) | Token::Float(n) => Some(n.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(numbers, ["0xFF", "0o755", "0b1010_0101", "0", "0.5"]);
+    }
+
+    #[test]
+    fn test_tokenize_negative_literals() {
+        let input = "define x as negative 5 minus negative 0.5 times negate y";
+        let tokens = Tokenizer::new(input).tokenize();
+
+        assert!(tokens.contains(&Token::Integer("-5".to_string())));
+        assert!(tokens.contains(&Token::Float("-0.5".to_string())));
+        assert!(tokens.contains(&Token::Negate));
+        assert!(!tokens.contains(&Token::Negative));
+    }
+
+    #[test]
+    fn test_tokenize_unicode_identifiers() {
+        // "cafe\u{301}" spells café with a combining accent, which Rust accepts
+        let input = "define cafe\u{301} as 変数 plus user_größe";
+        let mut tokenizer = Tokenizer::new(input);
+        let tokens = tokenizer.tokenize();
+
+        assert!(tokens.contains(&Token::Identifier("cafe\u{301}".to_string())));
+        assert!(tokens.contains(&Token::Identifier("変数".to_string())));
+        assert!(tokens.contains(&Token::Identifier("user_größe".to_string())));
+    }
+
+    #[test]
+    fn test_fuse_multi_word_keywords() {
+        let input =
+            "if a greater than or equal to b divided by c then\nend if\nrange from 1 to end";
+        let mut tokenizer = Tokenizer::new(input);
+        let tokens = tokenizer.tokenize_spanned();
+
+        let kinds: Vec<_> = tokens.iter().map(|t| t.token.clone()).collect();
+        assert!(kinds.contains(&Token::GreaterThanOrEqualTo));
+        assert!(kinds.contains(&Token::DividedBy));
+        assert!(kinds.contains(&Token::EndOf(Box::new(Token::If))));
+        // A bare "end" that does not close a block is left alone
+        assert!(kinds.contains(&Token::End));
+
+        let ge = tokens
+            .iter()
+            .find(|t| t.token == Token::GreaterThanOrEqualTo)
+            .unwrap();
+        assert_eq!(&input[ge.byte_range.clone()], "greater than or equal to");
+    }
+
+    #[test]
+    fn test_tokenize_text_block() {
+        let input = "define s as text begin\n        first \"quoted\"\n          indented \\n\n\n        last\n    text end and 3";
+        let mut tokenizer = Tokenizer::new(input);
+        let tokens = tokenizer.tokenize();
+
+        assert!(tokens.contains(&Token::String(
+            "    first \"quoted\"\n      indented \\n\n\n    last".to_string()
+        )));
+        assert!(tokens.contains(&Token::Integer("3".to_string())));
+        assert!(!tokens.contains(&Token::NewLine));
+    }
+
+    #[test]
+    fn test_tokenize_comments() {
+        let input = "note that  leading text  \ndefine x as 1 note that trailing";
+        let mut tokenizer = Tokenizer::new(input);
+        let tokens = tokenizer.tokenize();
+
+        assert_eq!(tokens[0], Token::Comment("leading text".to_string()));
+        assert_eq!(
+            tokens[tokens.len() - 2],
+            Token::Comment("trailing".to_string())
+        );
+    }
+
+    #[test]
+    fn test_case_insensitive_keywords() {
+        let input = "End Function\nbitwise XOR Begin Point user_Result";
+
+        let strict = Tokenizer::new(input).tokenize();
+        assert_eq!(strict[0], Token::Identifier("End".to_string()));
+
+        let tokens = Tokenizer::new(input)
+            .with_case_insensitive_keywords(true)
+            .tokenize();
+        assert_eq!(tokens[0], Token::EndOf(Box::new(Token::Function)));
+        assert!(tokens.contains(&Token::BitwiseXor));
+        assert!(tokens.contains(&Token::Begin));
+        assert!(tokens.contains(&Token::Identifier("Point".to_string())));
+        assert!(tokens.contains(&Token::Identifier("Result".to_string())));
+    }
+
+    #[test]
+    fn test_tokenize_verbatim_block() {
+        let input = "verbatim begin\nimpl S {\n    fn f() -> &'static str { \"a\\n\" }\n}\nverbatim end\nfunction";
+        let tokens = Tokenizer::new(input).tokenize();
+
+        assert_eq!(
+            tokens[0],
+            Token::VerbatimBlock(
+                "impl S {\n    fn f() -> &'static str { \"a\\n\" }\n}".to_string()
+            )
+        );
+        assert_eq!(tokens[1], Token::NewLine);
+        assert!(tokens.contains(&Token::Function));
     }
 }
@@ -33,6 +33,8 @@ pub const RESERVED_KEYWORDS: &[&str] = &[
     "exit",
     "continue",
     "return",
+    // Line continuation
+    "continued",
     // Functions
     "function",
     "with",
@@ -48,14 +50,30 @@ pub const RESERVED_KEYWORDS: &[&str] = &[
     "method",
     "on",
     "associated",
+    // Operators
+    "not",
+    "negate",
+    "negative",
+    "dereference",
+    "bitwise",
+    "shift",
+    "group",
     // Bindings
     "define",
     "as",
     "set",
+    "increase",
+    "decrease",
+    "multiply",
+    "divide",
+    "update",
     "equal",
     "to",
     "constant",
     "static",
+    // Modules
+    "module",
+    "import",
     // Structs and enums
     "structure",
     "fields",
@@ -94,13 +112,21 @@ pub fn sanitize_identifier(name: &str) -> String {
         return name.to_string();
     }
 
-    if is_reserved(name) {
+    // A name that already reads as sanitized is prefixed again so it survives reversal
+    if is_reserved(name) || is_sanitized(name) {
         format!("{}{}", COLLISION_PREFIX, name)
     } else {
         name.to_string()
     }
 }
 
+/// Check if a name carries a collision prefix added by `sanitize_identifier`, as opposed
+/// to merely starting with it (`user_function` does, `user_id` does not)
+pub fn is_sanitized(name: &str) -> bool {
+    name.strip_prefix(COLLISION_PREFIX)
+        .is_some_and(|rest| is_reserved(rest) || is_sanitized(rest))
+}
+
 /// Check if a name is a standard library variant
 pub fn is_standard_variant(name: &str) -> bool {
     STANDARD_VARIANTS.contains(&name)
@@ -122,5 +148,16 @@ mod tests {
     fn test_sanitize_identifier() {
         assert_eq!(sanitize_identifier("function"), "user_function");
         assert_eq!(sanitize_identifier("my_var"), "my_var");
+        assert_eq!(sanitize_identifier("user_id"), "user_id");
+        assert_eq!(sanitize_identifier("user_self"), "user_user_self");
+    }
+
+    #[test]
+    fn test_is_sanitized() {
+        assert!(is_sanitized("user_function"));
+        assert!(is_sanitized("user_user_self"));
+        assert!(!is_sanitized("user_id"));
+        assert!(!is_sanitized("user_user_id"));
+        assert!(!is_sanitized("function"));
     }
 }
@@ -3,42 +3,287 @@
 //! A deterministic source-to-source transpiler that converts Rust code into Iron,
 //! a verbose, lexically-expanded superset of Rust designed for optimal tokenization
 //! by Large Language Models.
+//!
+//! # Thread safety
+//!
+//! The library keeps no global or thread-local state: every call works on its own
+//! parser and oxidizer. The options, errors, Iron syntax trees, and [`Transpiler`] are
+//! `Send + Sync`, so a server can share one [`Transpiler`] across request threads.
+//! Values holding `syn` syntax trees ([`ReduceItems`], [`ReducedItem`],
+//! [`RoundtripReport`]) stay on the thread that made them.
 
+pub mod cache;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod crate_tree;
+pub mod dictionary;
 pub mod emitter;
+pub mod header;
 pub mod iron_ast;
+pub mod iron_formatter;
 pub mod iron_parser;
+pub mod iron_printer;
 pub mod iron_tokenizer;
 pub mod keywords;
 pub mod mappings;
+pub mod markdown;
 pub mod oxidation;
+pub mod parallel;
 pub mod parser;
+pub mod semantic_diff;
+pub mod source_map;
+pub mod symbols;
+pub mod validation;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
+pub use emitter::{Indentation, LayoutPolicy};
+pub use mappings::MappingHook;
 use parser::IronParser;
+pub use parser::{FallbackPolicy, Verbosity};
+use std::sync::Arc;
 use syn::File;
+pub use validation::ValidationProfile;
 
 /// Error type for transpilation failures
+///
+/// Failures with structured detail carry it, and the error they wrap is their
+/// [`std::error::Error::source`]. [`TranspileError::code`] groups every variant into
+/// the three kinds the first three variants name, failed reads and writes, and unusable
+/// configuration.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum TranspileError {
     ParseError(String),
     UnsupportedSyntax(String),
     InternalError(String),
+    /// The Rust source does not parse
+    ///
+    /// The location is read from the syn span when the error is made, since spans only
+    /// resolve on the thread that parsed the source.
+    RustSyntax {
+        /// Line of the error, starting at 1
+        line: usize,
+        /// Column of the error, starting at 1
+        column: usize,
+        /// What syn reported
+        message: String,
+        /// The syn error itself
+        source: syn::Error,
+    },
+    /// The Iron source does not parse
+    IronSyntax(iron_parser::ParseError),
+    /// The Iron that reduction wrote does not parse back
+    ReducedIronSyntax(iron_parser::ParseError),
+    /// Strict reduction found items with no Iron form
+    UnsupportedItems {
+        /// Each such item, in source order
+        items: Vec<parser::UnsupportedItem>,
+        /// The Iron of every other item
+        partial_iron: String,
+    },
+    /// The Iron has no Rust rendering
+    Oxidize(oxidation::OxidizeError),
+    /// Writing the output failed
+    Io(Arc<std::io::Error>),
+    /// A type dictionary or validation profile cannot be used
+    Config {
+        /// What is wrong with it, naming the file it was read from
+        message: String,
+        /// The failed read or TOML or JSON parse behind it, if any
+        source: Option<Arc<dyn std::error::Error + Send + Sync>>,
+    },
 }
 
 impl std::fmt::Display for TranspileError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.code() {
+            "parse-error" => "Parse error",
+            "unsupported-syntax" => "Unsupported syntax",
+            "io-error" => "I/O error",
+            "invalid-config" => "Invalid configuration",
+            _ => "Internal error",
+        };
+        write!(f, "{}: {}", kind, self.message())
+    }
+}
+
+impl std::error::Error for TranspileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TranspileError::RustSyntax { source, .. } => Some(source),
+            TranspileError::IronSyntax(error) | TranspileError::ReducedIronSyntax(error) => {
+                Some(error)
+            }
+            TranspileError::Oxidize(error) => Some(error),
+            TranspileError::Io(error) => Some(error.as_ref()),
+            TranspileError::Config {
+                source: Some(source),
+                ..
+            } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl TranspileError {
+    /// The kind of error: `parse-error` for source that does not parse,
+    /// `unsupported-syntax` for source with no translation, `io-error` for a file that
+    /// could not be read or written, `invalid-config` for a type dictionary or validation
+    /// profile that cannot be used, and `internal-error`
+    pub fn code(&self) -> &'static str {
+        match self {
+            TranspileError::ParseError(_)
+            | TranspileError::RustSyntax { .. }
+            | TranspileError::IronSyntax(_) => "parse-error",
+            TranspileError::UnsupportedSyntax(_)
+            | TranspileError::UnsupportedItems { .. }
+            | TranspileError::Oxidize(_) => "unsupported-syntax",
+            TranspileError::InternalError(_) | TranspileError::ReducedIronSyntax(_) => {
+                "internal-error"
+            }
+            TranspileError::Io(_) => "io-error",
+            TranspileError::Config {
+                source: Some(source),
+                ..
+            } if source.is::<std::io::Error>() => "io-error",
+            TranspileError::Config { .. } => "invalid-config",
+        }
+    }
+
+    /// Source line and column the error points at, when it has a location; messages
+    /// with one start with "line N, column M: " (or just "line N: ")
+    pub fn location(&self) -> Option<(usize, Option<usize>)> {
         match self {
-            TranspileError::ParseError(msg) => write!(f, "Parse error: {}", msg),
-            TranspileError::UnsupportedSyntax(msg) => {
-                write!(f, "Unsupported syntax: {}", msg)
+            TranspileError::RustSyntax { line, column, .. } => Some((*line, Some(*column))),
+            TranspileError::IronSyntax(error) | TranspileError::ReducedIronSyntax(error) => {
+                let position = error.position();
+                Some((position.line, Some(position.column)))
+            }
+            TranspileError::UnsupportedItems { items, .. } => {
+                let (line, column) = items.first()?.start;
+                Some((line, Some(column)))
             }
-            TranspileError::InternalError(msg) => {
-                write!(f, "Internal error: {}", msg)
+            _ => {
+                let message = self.message();
+                let (position, _) = message.strip_prefix("line ")?.split_once(": ")?;
+                match position.split_once(", column ") {
+                    Some((line, column)) => Some((line.parse().ok()?, Some(column.parse().ok()?))),
+                    None => Some((position.parse().ok()?, None)),
+                }
+            }
+        }
+    }
+
+    /// The message without the kind of error or its location prefix
+    pub fn detail(&self) -> String {
+        let message = self.message();
+        match self.location() {
+            Some(_) => message
+                .split_once(": ")
+                .map_or(message.as_str(), |(_, detail)| detail)
+                .to_string(),
+            None => message,
+        }
+    }
+
+    /// The Iron of the items that did reduce, when strict reduction rejected others
+    pub fn partial_iron(&self) -> Option<&str> {
+        match self {
+            TranspileError::UnsupportedItems { partial_iron, .. } => Some(partial_iron),
+            _ => None,
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            TranspileError::ParseError(msg)
+            | TranspileError::UnsupportedSyntax(msg)
+            | TranspileError::InternalError(msg) => msg.clone(),
+            TranspileError::RustSyntax {
+                line,
+                column,
+                message,
+                ..
+            } => format!(
+                "line {}, column {}: Failed to parse Rust source: {}",
+                line, column, message
+            ),
+            TranspileError::IronSyntax(error) => error.to_string(),
+            TranspileError::ReducedIronSyntax(error) => {
+                let message = error.to_string();
+                let (position, detail) = message.split_once(": ").unwrap_or(("", &message));
+                format!("{}: Reduced Iron does not parse: {}", position, detail)
             }
+            TranspileError::UnsupportedItems { items, .. } => items
+                .iter()
+                .map(|item| item.to_string())
+                .collect::<Vec<_>>()
+                .join("; "),
+            TranspileError::Oxidize(error) => error.to_string(),
+            TranspileError::Io(error) => error.to_string(),
+            TranspileError::Config { message, .. } => message.clone(),
+        }
+    }
+
+    /// A configuration file or entry that cannot be used
+    pub(crate) fn config(message: impl Into<String>) -> Self {
+        TranspileError::Config {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// A configuration file that cannot be read or parsed, with the failure as its source
+    #[cfg(feature = "files")]
+    pub(crate) fn config_source(
+        message: impl Into<String>,
+        source: impl std::error::Error + Send + Sync + 'static,
+    ) -> Self {
+        TranspileError::Config {
+            message: message.into(),
+            source: Some(Arc::new(source)),
+        }
+    }
+
+    /// Reduction refused the items of a failed parse, keeping what it emitted
+    fn unsupported_items(items: Vec<parser::UnsupportedItem>, parser: &IronParser) -> Self {
+        TranspileError::UnsupportedItems {
+            items,
+            partial_iron: parser.output(),
+        }
+    }
+}
+
+impl From<oxidation::OxidizeError> for TranspileError {
+    fn from(error: oxidation::OxidizeError) -> Self {
+        TranspileError::Oxidize(error)
+    }
+}
+
+impl From<std::io::Error> for TranspileError {
+    fn from(error: std::io::Error) -> Self {
+        TranspileError::Io(Arc::new(error))
+    }
+}
+
+impl From<syn::Error> for TranspileError {
+    fn from(error: syn::Error) -> Self {
+        let start = error.span().start();
+        TranspileError::RustSyntax {
+            line: start.line,
+            column: start.column + 1,
+            message: error.to_string(),
+            source: error,
         }
     }
 }
 
-impl std::error::Error for TranspileError {}
+impl From<iron_parser::ParseError> for TranspileError {
+    fn from(error: iron_parser::ParseError) -> Self {
+        TranspileError::IronSyntax(error)
+    }
+}
 
 /// Transpile Rust source code to Iron
 ///
@@ -68,21 +313,313 @@ impl std::error::Error for TranspileError {}
 /// }
 /// ```
 pub fn transpile(source: &str) -> Result<String, TranspileError> {
-    // Parse the Rust source
-    let file = syn::parse_str::<File>(source).map_err(|e| {
-        TranspileError::ParseError(format!(
-            "Failed to parse Rust source at {:?}: {}",
-            e.span(),
-            e
-        ))
-    })?;
+    transpile_with_options(source, &ReduceOptions::default())
+}
 
-    // Create parser and transpile
-    let mut parser = IronParser::new();
+/// Options controlling how Rust source is reduced to Iron
+#[derive(Clone, Default)]
+pub struct ReduceOptions {
+    /// Fail on items with no Iron form instead of carrying them as verbatim Rust, so
+    /// the output is Iron throughout
+    pub strict: bool,
+    /// Custom mappings asked, in order, before the built-in ones
+    pub hooks: Vec<Arc<dyn MappingHook>>,
+    /// Start the output with a [`header::IronHeader`] line recording the specification,
+    /// the redox version, and hashes of the source and the Iron; a whole-file line that
+    /// [`reduce_items`] leaves out
+    pub header: bool,
+    /// Reuse the Iron of items reduced before, looked up by a hash of their tokens
+    pub cache: Option<Arc<dyn cache::CacheProvider>>,
+    /// How much of each call is spelled out; the Iron parser reads every profile
+    pub verbosity: Verbosity,
+    /// What to do with items that have no Iron form; `strict` makes it
+    /// [`FallbackPolicy::Error`]
+    pub fallback: FallbackPolicy,
+    /// One level of indentation in the Iron, four spaces by default
+    pub indentation: Indentation,
+    /// Width in characters that longer Iron lines are wrapped to, onto lines starting
+    /// with `continued`; `None` (the default) leaves lines unwrapped
+    pub max_width: Option<usize>,
+    /// Start each top-level item with a note of the Rust line it comes from, `note that
+    /// this comes from line 42 of src/lib.rs`; [`reduce_items`] leaves the notes out,
+    /// each [`ReducedItem`] carrying its Rust instead
+    pub provenance: bool,
+    /// Path of the Rust source, named in provenance notes, so unused by [`reduce_items`]
+    pub source_path: Option<String>,
+    /// Blank lines between items, around items nested in blocks, and after the header
+    pub layout: LayoutPolicy,
+}
 
-    parser
+impl ReduceOptions {
+    /// Add a custom mapping, asked after those already added
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use redox::{MappingHook, ReduceOptions};
+    ///
+    /// struct Tokio;
+    ///
+    /// impl MappingHook for Tokio {
+    ///     fn map_type(&self, ty: &syn::Type) -> Option<String> {
+    ///         let syn::Type::Path(path) = ty else { return None };
+    ///         let names: Vec<String> = path.path.segments.iter().map(|s| s.ident.to_string()).collect();
+    ///         (names == ["tokio", "sync", "Mutex"]).then(|| "async_mutex".to_string())
+    ///     }
+    /// }
+    ///
+    /// let options = ReduceOptions::default().with_hook(Tokio);
+    /// let rust = "static LOCK: tokio::sync::Mutex = tokio::sync::Mutex::const_new();";
+    /// let iron = redox::transpile_with_options(rust, &options).unwrap();
+    ///
+    /// assert!(iron.starts_with("static LOCK of async_mutex"));
+    /// ```
+    pub fn with_hook(mut self, hook: impl MappingHook + 'static) -> Self {
+        self.hooks.push(Arc::new(hook));
+        self
+    }
+}
+
+impl std::fmt::Debug for ReduceOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReduceOptions")
+            .field("strict", &self.strict)
+            .field("hooks", &self.hooks.len())
+            .field("header", &self.header)
+            .field("cache", &self.cache.is_some())
+            .field("verbosity", &self.verbosity)
+            .field("fallback", &self.fallback)
+            .field("indentation", &self.indentation)
+            .field("max_width", &self.max_width)
+            .field("provenance", &self.provenance)
+            .field("source_path", &self.source_path)
+            .field("layout", &self.layout)
+            .finish()
+    }
+}
+
+/// Transpile Rust source code to Iron with the given options
+///
+/// # Example
+///
+/// ```rust
+/// use redox::{transpile_with_options, ReduceOptions};
+///
+/// let options = ReduceOptions {
+///     strict: true,
+///     ..Default::default()
+/// };
+/// let error = transpile_with_options("trait Shape {}", &options).unwrap_err();
+///
+/// assert_eq!(error.location(), Some((1, Some(1))));
+/// assert!(error.to_string().contains("trait Shape"));
+///
+/// let options = ReduceOptions {
+///     header: true,
+///     ..Default::default()
+/// };
+/// let iron = transpile_with_options("const N: i32 = 1;", &options).unwrap();
+/// assert!(iron.starts_with("iron specification 1, generated by redox"));
+/// assert!(redox::oxidize(&iron).unwrap().starts_with("const N: i32 = 1;"));
+/// ```
+pub fn transpile_with_options(
+    source: &str,
+    options: &ReduceOptions,
+) -> Result<String, TranspileError> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("reduce", bytes = source.len()).entered();
+    reduce_with_parser(&mut reducing_parser(options), source, options)
+}
+
+/// A parser reducing as `options` say
+fn reducing_parser(options: &ReduceOptions) -> IronParser {
+    IronParser::new()
+        .with_indentation(options.indentation)
+        .with_max_width(options.max_width)
+        .with_provenance(options.provenance)
+        .with_source_path(options.source_path.clone())
+        .with_layout(options.layout)
+        .with_strict(options.strict)
+        .with_hooks(options.hooks.clone())
+        .with_cache(options.cache.clone())
+        .with_verbosity(options.verbosity)
+        .with_fallback(options.fallback)
+}
+
+/// Reduce a whole source with a parser made by [`reducing_parser`] for `options`,
+/// resetting it first so it can be reused
+fn reduce_with_parser(
+    parser: &mut IronParser,
+    source: &str,
+    options: &ReduceOptions,
+) -> Result<String, TranspileError> {
+    parser.reset();
+    let file = parse_rust(source)?;
+    let iron = parser
         .parse_file(&file)
-        .map_err(|errors| TranspileError::UnsupportedSyntax(errors.join("; ")))
+        .map_err(|items| TranspileError::unsupported_items(items, parser))?;
+    Ok(match options.header {
+        true => header::add_header_with_blank_lines(&iron, source, options.layout.after_header),
+        false => iron,
+    })
+}
+
+/// Transpile a crate to Iron, following the `mod` declarations from its root file
+/// (`lib.rs` or `main.rs`) to every module file, as rustc finds them
+///
+/// Each file is reduced with `options` and keeps its path relative to the root's
+/// directory, so [`crate_tree::IronCrate::write`] mirrors the crate's layout and
+/// [`crate_tree::IronCrate::oxidize`] gives back Rust for the same paths. A module file
+/// that is missing or fails is recorded in the crate's failures; only a root file that
+/// does not read or parse is an error.
+///
+/// # Example
+///
+/// ```no_run
+/// let iron = redox::transpile_crate("src/lib.rs", &redox::ReduceOptions::default()).unwrap();
+/// iron.write(std::path::Path::new("target/iron")).unwrap();
+///
+/// for file in &iron.files {
+///     println!("{} -> {}", file.module, file.iron_path().display());
+/// }
+/// ```
+pub fn transpile_crate(
+    root: impl AsRef<std::path::Path>,
+    options: &ReduceOptions,
+) -> Result<crate_tree::IronCrate, TranspileError> {
+    crate_tree::transpile_crate(root.as_ref(), options)
+}
+
+/// Transpile Rust source to Iron, writing the Iron of each item to `writer` as soon as
+/// it is reduced, so only one item's Iron is held in memory
+///
+/// The Iron written is the same as [`transpile`] returns. If writing fails part way,
+/// the output is cut short.
+///
+/// # Example
+///
+/// ```rust
+/// let mut iron = Vec::new();
+/// redox::transpile_to_writer("fn one() -> i32 { 1 }", &mut iron).unwrap();
+///
+/// assert!(String::from_utf8(iron).unwrap().starts_with("function one"));
+/// ```
+pub fn transpile_to_writer(
+    source: &str,
+    writer: &mut impl std::io::Write,
+) -> Result<(), TranspileError> {
+    transpile_to_writer_with_options(source, writer, &ReduceOptions::default())
+}
+
+/// Transpile Rust source to Iron with the given options, writing the Iron of each item
+/// to `writer` as soon as it is reduced
+///
+/// The Iron written is the same as [`transpile_with_options`] returns, except that under
+/// [`ReduceOptions::strict`] the error names only the first item with no Iron form. A
+/// [`ReduceOptions::header`] hashes the whole Iron, so with one the Iron is held until
+/// it is all reduced and then written.
+///
+/// # Example
+///
+/// ```rust
+/// use redox::{Indentation, ReduceOptions};
+///
+/// let options = ReduceOptions {
+///     indentation: Indentation::Spaces(2),
+///     ..Default::default()
+/// };
+/// let mut iron = Vec::new();
+/// redox::transpile_to_writer_with_options("fn one() -> i32 { 1 }", &mut iron, &options)
+///     .unwrap();
+///
+/// assert!(String::from_utf8(iron).unwrap().contains("\n  1\n"));
+/// ```
+pub fn transpile_to_writer_with_options(
+    source: &str,
+    writer: &mut impl std::io::Write,
+    options: &ReduceOptions,
+) -> Result<(), TranspileError> {
+    if options.header {
+        writer.write_all(transpile_with_options(source, options)?.as_bytes())?;
+        return Ok(());
+    }
+    let file = parse_rust(source)?;
+    let mut parser = reducing_parser(options);
+    let separator = "\n".repeat(options.layout.between_items);
+    let mut first = true;
+    for item in &file.items {
+        parser.reset();
+        let iron = parser
+            .parse_item(item)
+            .map_err(|items| TranspileError::unsupported_items(items, &parser))?;
+        if iron.is_empty() {
+            continue;
+        }
+        if !std::mem::take(&mut first) {
+            writer.write_all(separator.as_bytes())?;
+        }
+        writer.write_all(iron.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Transpile many Rust sources to Iron on a thread per CPU, returning each source's
+/// result in input order
+///
+/// Each result is the same as [`transpile`] gives for that source alone.
+///
+/// # Example
+///
+/// ```rust
+/// let sources = ["fn one() {}", "fn main( {}", "const TWO: i32 = 2;"];
+/// let results = redox::transpile_many(&sources);
+///
+/// assert_eq!(results.len(), 3);
+/// assert!(results[0].as_ref().unwrap().starts_with("function one"));
+/// assert!(results[1].is_err());
+/// assert!(results[2].as_ref().unwrap().starts_with("constant TWO"));
+/// ```
+pub fn transpile_many<S: AsRef<str> + Sync>(inputs: &[S]) -> Vec<Result<String, TranspileError>> {
+    transpile_many_with_options(inputs, &ReduceOptions::default())
+}
+
+/// Transpile many Rust sources to Iron with the given options on a thread per CPU,
+/// returning each source's result in input order
+///
+/// Each result is the same as [`transpile_with_options`] gives for that source alone;
+/// a [`ReduceOptions::cache`] is shared by every thread.
+///
+/// # Example
+///
+/// ```rust
+/// let options = redox::ReduceOptions {
+///     strict: true,
+///     ..Default::default()
+/// };
+/// let results = redox::transpile_many_with_options(&["fn one() {}", "trait Shape {}"], &options);
+///
+/// assert!(results[0].is_ok());
+/// assert!(results[1].is_err());
+/// ```
+pub fn transpile_many_with_options<S: AsRef<str> + Sync>(
+    inputs: &[S],
+    options: &ReduceOptions,
+) -> Vec<Result<String, TranspileError>> {
+    let jobs = std::thread::available_parallelism().unwrap_or(std::num::NonZeroUsize::MIN);
+    // One parser per thread, reset between sources to reuse its buffers
+    parallel::map_in_order(
+        inputs,
+        jobs,
+        || reducing_parser(options),
+        |parser, input| reduce_with_parser(parser, input.as_ref(), options),
+    )
+}
+
+/// Parse Rust source, locating any syntax error
+fn parse_rust(source: &str) -> Result<File, TranspileError> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("parse_rust", bytes = source.len()).entered();
+    syn::parse_str::<File>(source).map_err(TranspileError::from)
 }
 
 /// Transpile a Rust file to Iron
@@ -100,13 +637,239 @@ pub fn transpile_file(file: &File) -> Result<String, TranspileError> {
 
     parser
         .parse_file(file)
-        .map_err(|errors| TranspileError::UnsupportedSyntax(errors.join("; ")))
+        .map_err(|items| TranspileError::unsupported_items(items, &parser))
+}
+
+/// Transpile a Rust file to Iron, along with where each run of Iron lines came from
+///
+/// Each origin is paired with the 1-based Iron line its run starts at; the run lasts
+/// until the next origin. Lines closing a block belong to the construct that opened it.
+///
+/// # Example
+///
+/// ```rust
+/// let file = syn::parse_file("fn main() {\n    let x = 1;\n}\n").unwrap();
+/// let (iron, origins) = redox::transpile_with_origins(&file).unwrap();
+///
+/// assert_eq!(iron.lines().nth(2), Some("    define x as 1"));
+/// assert_eq!(origins[1].0, 3);
+/// assert_eq!(origins[1].1.line, 2);
+/// assert_eq!(origins[1].1.construct, "let binding x");
+/// ```
+pub fn transpile_with_origins(
+    file: &File,
+) -> Result<(String, Vec<(usize, emitter::Origin)>), TranspileError> {
+    let mut parser = IronParser::new();
+
+    let iron = parser
+        .parse_file(file)
+        .map_err(|items| TranspileError::unsupported_items(items, &parser))?;
+    Ok((iron, parser.origins()))
+}
+
+/// Reduce Rust source code to an Iron syntax tree, for tools that analyze or transform
+/// Iron structurally
+///
+/// Items with no Iron form are kept as [`iron_ast::IronItem::Verbatim`]. The tree is
+/// read back from the reduced Iron text, and printing it with [`print_iron`] gives
+/// that text again; should the text not parse, the error is
+/// [`TranspileError::ReducedIronSyntax`] with the Iron line and column.
+///
+/// # Example
+///
+/// ```rust
+/// use redox::iron_ast::IronItem;
+///
+/// let ast = redox::reduce_to_ast("fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n").unwrap();
+///
+/// let IronItem::Function(function) = &ast.items[0] else {
+///     panic!("expected a function");
+/// };
+/// assert_eq!(function.name, "add");
+/// assert_eq!(function.params.len(), 2);
+/// ```
+pub fn reduce_to_ast(source: &str) -> Result<iron_ast::IronFile, TranspileError> {
+    let file = parse_rust(source)?;
+    let (iron, origins) = transpile_with_origins(&file)?;
+    let mut ast = iron_parser::IronParser::new(&iron)
+        .parse()
+        .map_err(TranspileError::ReducedIronSyntax)?;
+    set_verbatim_spans(&mut ast.items, &origins);
+    Ok(ast)
+}
+
+/// Give each verbatim item the Rust span of the origin its Iron line starts
+fn set_verbatim_spans(items: &mut [iron_ast::IronItem], origins: &[(usize, emitter::Origin)]) {
+    for item in items {
+        match item {
+            iron_ast::IronItem::Verbatim(verbatim) => {
+                verbatim.span =
+                    origins
+                        .iter()
+                        .find(|(line, _)| *line == verbatim.line)
+                        .map(|(_, origin)| iron_ast::RustSpan {
+                            start: (origin.line, origin.column),
+                            end: (origin.end_line, origin.end_column),
+                        });
+            }
+            iron_ast::IronItem::Module(module) => set_verbatim_spans(&mut module.items, origins),
+            _ => {}
+        }
+    }
+}
+
+/// A top-level Rust item and the Iron it was reduced to
+#[derive(Debug, Clone)]
+pub struct ReducedItem {
+    /// The Rust item
+    pub rust: syn::Item,
+    /// The item's Iron, ending in a single newline; the Iron of every item in order,
+    /// separated by [`LayoutPolicy::between_items`] blank lines, is the Iron of the whole
+    /// file
+    pub iron: String,
+}
+
+impl ReducedItem {
+    /// The item's Iron as syntax tree items, which is
+    /// [`iron_ast::IronItem::Verbatim`] holding the Rust if the item has no Iron form;
+    /// Iron that does not parse back is [`TranspileError::ReducedIronSyntax`]
+    pub fn iron_items(&self) -> Result<Vec<iron_ast::IronItem>, TranspileError> {
+        let mut items = iron_parser::IronParser::new(&self.iron)
+            .parse()
+            .map(|file| file.items)
+            .map_err(TranspileError::ReducedIronSyntax)?;
+        if let [iron_ast::IronItem::Verbatim(verbatim)] = &mut items[..] {
+            verbatim.span = Some(parser::rust_span(&self.rust));
+        }
+        Ok(items)
+    }
+}
+
+/// Iterator reducing the top-level items of a Rust file one at a time, from
+/// [`reduce_items`]
+pub struct ReduceItems {
+    items: std::vec::IntoIter<syn::Item>,
+    /// Reset before each item, reusing its buffers
+    parser: IronParser,
+}
+
+impl Iterator for ReduceItems {
+    type Item = Result<ReducedItem, TranspileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rust = self.items.next()?;
+        self.parser.reset();
+        let reduced = match self.parser.parse_item(&rust) {
+            Ok(iron) => Ok(ReducedItem { rust, iron }),
+            Err(items) => Err(TranspileError::unsupported_items(items, &self.parser)),
+        };
+        Some(reduced)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.items.size_hint()
+    }
+}
+
+impl ExactSizeIterator for ReduceItems {}
+
+/// Reduce Rust source to Iron an item at a time, so callers can filter items, report
+/// progress, or stop early on large files
+///
+/// Only parsing the Rust happens up front. An item failing under
+/// [`ReduceOptions::strict`] is an error for that item alone; later items still reduce.
+/// Items are looked up in and stored to [`ReduceOptions::cache`] as a whole-file
+/// reduction does. The whole-file options are left out: there is no
+/// [`ReduceOptions::header`] line, and no provenance notes to name
+/// [`ReduceOptions::source_path`] in, since each item carries its Rust.
+///
+/// # Example
+///
+/// ```rust
+/// use redox::iron_ast::IronItem;
+///
+/// let rust = "fn one() -> i32 {\n    1\n}\n\ntrait Shape {}\n";
+/// let items = redox::reduce_items(rust, &redox::ReduceOptions::default()).unwrap();
+///
+/// for item in items {
+///     let item = item.unwrap();
+///     match &item.iron_items().unwrap()[..] {
+///         [IronItem::Function(function)] => assert_eq!(function.name, "one"),
+///         [IronItem::Verbatim(verbatim)] => assert_eq!(verbatim.name.as_deref(), Some("Shape")),
+///         other => panic!("unexpected items {:?}", other),
+///     }
+/// }
+/// ```
+pub fn reduce_items(source: &str, options: &ReduceOptions) -> Result<ReduceItems, TranspileError> {
+    let file = parse_rust(source)?;
+    Ok(ReduceItems {
+        items: file.items.into_iter(),
+        parser: IronParser::new()
+            .with_indentation(options.indentation)
+            .with_max_width(options.max_width)
+            .with_layout(options.layout)
+            .with_strict(options.strict)
+            .with_hooks(options.hooks.clone())
+            .with_cache(options.cache.clone())
+            .with_verbosity(options.verbosity)
+            .with_fallback(options.fallback),
+    })
+}
+
+/// Print an Iron syntax tree as Iron source that parses back to the same tree, as
+/// its `Display` implementation does
+///
+/// # Example
+///
+/// ```rust
+/// let mut ast = redox::reduce_to_ast("fn one() -> i32 {\n    1\n}\n").unwrap();
+/// if let redox::iron_ast::IronItem::Function(function) = &mut ast.items[0] {
+///     function.name = "two".to_string();
+/// }
+///
+/// let iron = redox::print_iron(&ast);
+/// assert_eq!(iron, "function two\n    returns i32\nbegin\n    1\nend function\n");
+/// ```
+pub fn print_iron(file: &iron_ast::IronFile) -> String {
+    iron_printer::IronPrinter::new().print(file)
+}
+
+/// Transpile Rust source code to Iron, along with a source map between the two
+///
+/// # Example
+///
+/// ```rust
+/// let rust = "fn main() {\n    let x = 1;\n}\n";
+/// let (iron, map) = redox::transpile_with_source_map(rust).unwrap();
+///
+/// let binding = map.rust_for_iron_line(3).unwrap();
+/// assert_eq!(binding.construct, "let binding x");
+/// assert_eq!(binding.rust_start, (2, 5));
+/// assert_eq!(map.iron_lines_for_rust((2, 9), (2, 10)), Some(3..=3));
+/// assert_eq!(iron.lines().nth(2), Some("    define x as 1"));
+/// ```
+pub fn transpile_with_source_map(
+    source: &str,
+) -> Result<(String, source_map::SourceMap), TranspileError> {
+    let file = parse_rust(source)?;
+    let (iron, origins) = transpile_with_origins(&file)?;
+    let map = source_map::SourceMap::from_origins(&origins, iron.lines().count());
+    Ok((iron, map))
+}
+
+/// Where each item that reduction carries as verbatim Rust starts, as (line, 1-based
+/// column), including items nested in others
+pub fn verbatim_item_locations(file: &File) -> Vec<(usize, usize)> {
+    let mut parser = IronParser::new();
+    let _ = parser.parse_file(file);
+    parser.verbatim_items().to_vec()
 }
 
 /// Check if Iron code is valid (basic validation)
 ///
 /// This function checks if the generated Iron code contains any
-/// prohibited symbols that should have been transformed.
+/// prohibited symbols that should have been transformed. Symbols in
+/// comments, strings, and verbatim blocks do not count.
 ///
 /// # Arguments
 ///
@@ -114,22 +877,161 @@ pub fn transpile_file(file: &File) -> Result<String, TranspileError> {
 ///
 /// # Returns
 ///
-/// `true` if valid, `false` otherwise
+/// `true` if valid, `false` otherwise; [`validate_iron_report`] says where it is not
 pub fn validate_iron(iron_code: &str) -> bool {
-    let prohibited_chars = ['&', '-', '>', '<', '*'];
+    validate_iron_report(iron_code).is_valid()
+}
 
-    for ch in prohibited_chars {
-        if iron_code.contains(ch) {
-            return false;
-        }
+/// Check Iron code for the symbols the standard [`ValidationProfile`] prohibits,
+/// reporting each one found
+///
+/// # Example
+///
+/// ```rust
+/// let report = redox::validate_iron_report("define x as 1\ncall std::mem::take with x\n");
+///
+/// assert!(!report.is_valid());
+/// let error = report.errors().next().unwrap();
+/// assert_eq!((error.line, error.column), (2, 9));
+/// assert_eq!(error.rule, "path-separator");
+/// assert_eq!(error.snippet, "std::mem::take");
+/// ```
+pub fn validate_iron_report(iron_code: &str) -> ValidationReport {
+    validate_iron_report_with_profile(iron_code, &ValidationProfile::default())
+}
+
+/// Check Iron code for the symbols `profile` prohibits, reporting each one found
+///
+/// # Example
+///
+/// ```rust
+/// use redox::ValidationProfile;
+///
+/// let iron = "note that a - b\ncall std::mem::take with x\n";
+/// let report = redox::validate_iron_report_with_profile(iron, &ValidationProfile::code());
+///
+/// assert_eq!(report.violations.len(), 2);
+/// assert_eq!(report.violations[0].line, 2);
+/// ```
+pub fn validate_iron_report_with_profile(
+    iron_code: &str,
+    profile: &ValidationProfile,
+) -> ValidationReport {
+    ValidationReport {
+        violations: prohibited_symbols_with_profile(iron_code, profile),
+    }
+}
+
+/// The prohibited symbols in some Iron code, from [`validate_iron_report`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationReport {
+    /// Every symbol found, in source order, including warnings
+    pub violations: Vec<ProhibitedSymbol>,
+}
+
+impl ValidationReport {
+    /// Whether the code is valid, with nothing worse than warnings
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
     }
 
-    // Check for :: pattern (namespace separator)
-    if iron_code.contains("::") {
-        return false;
+    /// The symbols that make the code invalid
+    pub fn errors(&self) -> impl Iterator<Item = &ProhibitedSymbol> {
+        self.violations
+            .iter()
+            .filter(|symbol| symbol.severity == Severity::Error)
     }
+}
+
+/// Whether a problem makes Iron invalid, or is only worth a look
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A Rust symbol found in Iron code, which should have been spelled out in words
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProhibitedSymbol {
+    pub line: usize,   // 1-based
+    pub column: usize, // 1-based, in characters
+    pub symbol: String,
+    /// Which rule the symbol breaks, such as "path-separator" for `::`
+    pub rule: String,
+    /// The whitespace-delimited text around the symbol, such as "std::mem::take"
+    pub snippet: String,
+    /// An error in code; a warning inside a comment, string, or verbatim block, where
+    /// the symbol is carried through unchanged
+    pub severity: Severity,
+}
+
+/// Find every symbol the standard [`ValidationProfile`] prohibits in Iron code, in
+/// source order
+pub fn prohibited_symbols(iron_code: &str) -> Vec<ProhibitedSymbol> {
+    prohibited_symbols_with_profile(iron_code, &ValidationProfile::default())
+}
+
+/// Find every symbol `profile` prohibits in Iron code, in source order
+///
+/// Symbols are found in the tokens the Iron tokenizer reads as symbols, and in
+/// comments, strings, and verbatim blocks if the profile checks them.
+pub fn prohibited_symbols_with_profile(
+    iron_code: &str,
+    profile: &ValidationProfile,
+) -> Vec<ProhibitedSymbol> {
+    use iron_tokenizer::Token;
+
+    let mut found = Vec::new();
+    let tokens = iron_tokenizer::Tokenizer::new(iron_code)
+        .with_symbols(true)
+        .tokenize_spanned();
+    for spanned in tokens {
+        let severity = match spanned.token {
+            Token::Symbol(_) => Severity::Error,
+            Token::Comment(_) | Token::String(_) | Token::Char(_) | Token::VerbatimBlock(_)
+                if profile.checks_literals() =>
+            {
+                Severity::Warning
+            }
+            _ => continue,
+        };
+        let text = &iron_code[spanned.byte_range.clone()];
+        let (mut line, mut column) = (spanned.line, spanned.column);
+        let mut rest = text;
+        while let Some(ch) = rest.chars().next() {
+            let symbol = profile.symbol_at(rest);
+            if let Some((symbol, rule)) = symbol {
+                let offset = spanned.byte_range.start + text.len() - rest.len();
+                found.push(ProhibitedSymbol {
+                    line,
+                    column,
+                    symbol: symbol.to_string(),
+                    rule: rule.to_string(),
+                    snippet: snippet_at(iron_code, offset),
+                    severity,
+                });
+            }
+            // A namespace separator is one symbol, not two
+            let len = symbol.map_or(ch.len_utf8(), |(symbol, _)| symbol.len());
+            for ch in rest[..len].chars() {
+                match ch {
+                    '\n' => (line, column) = (line + 1, 1),
+                    _ => column += 1,
+                }
+            }
+            rest = &rest[len..];
+        }
+    }
+    found
+}
 
-    true
+/// The whitespace-delimited text of its line around byte `offset`
+fn snippet_at(code: &str, offset: usize) -> String {
+    let (before, after) = code.split_at(offset);
+    let before = &before[before.rfind('\n').map_or(0, |i| i + 1)..];
+    let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+    let end = after.find(char::is_whitespace).unwrap_or(after.len());
+    format!("{}{}", &before[start..], &after[..end])
 }
 
 /// Oxidize Iron code to Rust
@@ -161,25 +1063,344 @@ pub fn validate_iron(iron_code: &str) -> bool {
 /// }
 /// ```
 pub fn oxidize(iron_source: &str) -> Result<String, TranspileError> {
-    use iron_parser::IronParser;
-    use oxidation::Oxidizer;
+    oxidize_with_options(iron_source, &OxidizeOptions::default())
+}
 
-    // Parse the Iron source
-    let mut parser = IronParser::new(iron_source);
-    let ast = parser
-        .parse()
-        .map_err(|e| TranspileError::ParseError(format!("{:?}", e)))?;
+/// Oxidize Iron code to Rust, writing the Rust to `writer`
+///
+/// The Rust is the same as [`oxidize`] returns. It is formatted as one file, so it is
+/// built in memory, but handed to `writer` without being copied.
+pub fn oxidize_to_writer(
+    iron_source: &str,
+    writer: &mut impl std::io::Write,
+) -> Result<(), TranspileError> {
+    let rust = oxidize(iron_source)?;
+    writer.write_all(rust.as_bytes())?;
+    Ok(())
+}
 
-    // Convert to Rust
-    let mut oxidizer = Oxidizer::new();
-    Ok(oxidizer.oxidize(&ast))
+/// Format Iron code with canonical indentation, blank lines, and keyword casing
+///
+/// # Example
+///
+/// ```rust
+/// use redox::format_iron;
+///
+/// let iron_code = "Function hello\nBegin\nReturn 42\nEnd   Function\n";
+///
+/// assert_eq!(
+///     format_iron(iron_code, true).unwrap(),
+///     "function hello\nbegin\n    return 42\nend function\n"
+/// );
+/// ```
+pub fn format_iron(
+    iron_source: &str,
+    case_insensitive_keywords: bool,
+) -> Result<String, TranspileError> {
+    iron_formatter::IronFormatter::new()
+        .with_case_insensitive_keywords(case_insensitive_keywords)
+        .format(iron_source)
+        .map_err(TranspileError::IronSyntax)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Options controlling how Iron source is read during oxidation
+#[derive(Debug, Clone, Default)]
+pub struct OxidizeOptions {
+    /// Accept keywords in any capitalization ("Begin", "End Function"), as
+    /// language models often produce them
+    pub case_insensitive_keywords: bool,
+    /// Whether off-grammar input is rejected or recovered from with a warning
+    pub parse_mode: iron_parser::ParseMode,
+    /// Annotate untyped closure parameters when the closure is passed directly to a
+    /// function whose parameter type spells out the closure's argument types
+    pub infer_closure_param_types: bool,
+    /// Rust edition the output targets
+    pub edition: oxidation::Edition,
+    /// Render function generic bounds as `where` clauses instead of inline
+    pub where_clauses: bool,
+    /// Indentation, final newline, and brace placement of the output
+    pub layout: oxidation::OxidizerConfig,
+    /// Rust types for Iron type names beyond the built-in ones, the reverse of a
+    /// dictionary given to reduction with [`ReduceOptions::with_hook`]
+    pub types: dictionary::TypeDictionary,
+}
 
-    #[test]
+/// Oxidize Iron code to Rust with the given options
+///
+/// # Example
+///
+/// ```rust
+/// use redox::{oxidize_with_options, OxidizeOptions};
+///
+/// let iron_code = "Function hello\nBegin\n    Return 42\nEnd Function\n";
+/// let options = OxidizeOptions {
+///     case_insensitive_keywords: true,
+///     ..Default::default()
+/// };
+///
+/// assert!(oxidize_with_options(iron_code, &options).is_ok());
+/// ```
+pub fn oxidize_with_options(
+    iron_source: &str,
+    options: &OxidizeOptions,
+) -> Result<String, TranspileError> {
+    oxidize_with_warnings(iron_source, options).map(|(rust_code, _)| rust_code)
+}
+
+/// Oxidize Iron code to Rust, also returning the non-fatal problems that were worked
+/// around (dropped bounds and modifiers, ...) with their Iron line numbers
+///
+/// # Example
+///
+/// ```rust
+/// use redox::{oxidize_with_warnings, OxidizeOptions};
+///
+/// let iron_code = "function hello\nbegin\n    define f as closure move with body 1\nend function\n";
+/// let (_, warnings) = oxidize_with_warnings(iron_code, &OxidizeOptions::default()).unwrap();
+///
+/// assert_eq!(warnings[0].line, Some(3));
+/// ```
+pub fn oxidize_with_warnings(
+    iron_source: &str,
+    options: &OxidizeOptions,
+) -> Result<(String, Vec<iron_ast::IronWarning>), TranspileError> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!("oxidize", bytes = iron_source.len()).entered();
+    let ast = parse_iron_with_options(iron_source, options)?;
+    oxidize_ast_with_warnings(&ast, options)
+}
+
+/// Parse Iron source into a syntax tree, reading it leniently as [`oxidize`] does
+///
+/// The tree can be edited and then oxidized with [`oxidize_ast`] or printed back to
+/// Iron with [`print_iron`]. Problems the parser recovered from are in its `warnings`.
+///
+/// # Example
+///
+/// ```rust
+/// let mut ast = redox::parse_iron("function hello\nbegin\n    return 42\nend function\n").unwrap();
+/// if let redox::iron_ast::IronItem::Function(function) = &mut ast.items[0] {
+///     function.name = "answer".to_string();
+/// }
+///
+/// assert_eq!(redox::oxidize_ast(&ast).unwrap(), "fn answer() {\n    return 42;\n}\n");
+/// ```
+pub fn parse_iron(iron_source: &str) -> Result<iron_ast::IronFile, TranspileError> {
+    parse_iron_with_options(iron_source, &OxidizeOptions::default())
+}
+
+/// Parse Iron source into a syntax tree as oxidation with `options` reads it
+pub fn parse_iron_with_options(
+    iron_source: &str,
+    options: &OxidizeOptions,
+) -> Result<iron_ast::IronFile, TranspileError> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::debug_span!("parse_iron", bytes = iron_source.len()).entered();
+    let tokenizer = iron_tokenizer::Tokenizer::new(iron_source)
+        .with_case_insensitive_keywords(options.case_insensitive_keywords);
+    iron_parser::IronParser::from_tokenizer(tokenizer)
+        .with_mode(options.parse_mode)
+        .with_type_names(options.types.iron_names())
+        .parse()
+        .map_err(TranspileError::IronSyntax)
+}
+
+/// Oxidize an Iron syntax tree to Rust, as [`oxidize`] oxidizes the tree it parses
+pub fn oxidize_ast(file: &iron_ast::IronFile) -> Result<String, TranspileError> {
+    oxidize_ast_with_warnings(file, &OxidizeOptions::default()).map(|(rust_code, _)| rust_code)
+}
+
+/// Oxidize an Iron syntax tree to Rust with the given options, also returning the
+/// tree's parse warnings and the problems oxidation worked around
+pub fn oxidize_ast_with_warnings(
+    file: &iron_ast::IronFile,
+    options: &OxidizeOptions,
+) -> Result<(String, Vec<iron_ast::IronWarning>), TranspileError> {
+    let mut oxidizer = oxidation::Oxidizer::new()
+        .with_closure_param_inference(options.infer_closure_param_types)
+        .with_edition(options.edition)
+        .with_where_clauses(options.where_clauses)
+        .with_config(options.layout.clone())
+        .with_types(options.types.clone());
+    let rust_code = oxidizer.oxidize(file)?;
+    Ok((rust_code, oxidizer.warnings().to_vec()))
+}
+
+/// Rewrite Rust source in canonical form: reduced to Iron and oxidized back
+///
+/// The canonical form is what a round trip makes of the source. It has oxidation's
+/// layout, drops doc and non-doc comments and the type annotations of `let` bindings,
+/// and writes each macro's string literals with plain escapes. Items reduction keeps
+/// verbatim come back unchanged. Canonicalizing canonical source returns it unchanged,
+/// so two versions can be compared by canonicalizing both.
+///
+/// # Example
+///
+/// ```rust
+/// let canonical = redox::canonicalize("fn add(a:i32,b:i32)->i32{ a+b }").unwrap();
+/// assert_eq!(redox::canonicalize(&canonical).unwrap(), canonical);
+/// ```
+pub fn canonicalize(source: &str) -> Result<String, TranspileError> {
+    oxidize(&transpile(source)?)
+}
+
+/// What became of Rust source reduced to Iron and oxidized back, from
+/// [`verify_roundtrip`]
+#[derive(Debug, Clone)]
+pub struct RoundtripReport {
+    /// The Iron the source reduced to
+    pub iron: String,
+    /// The Rust the Iron oxidized back to
+    pub rust: String,
+    /// Problems oxidation worked around
+    pub warnings: Vec<iron_ast::IronWarning>,
+    /// Syntax tree of the source
+    pub original: File,
+    /// Syntax tree of the oxidized Rust
+    pub roundtripped: File,
+}
+
+impl RoundtripReport {
+    /// Whether the Rust came back with the same syntax tree, so differing at most in
+    /// layout and non-doc comments
+    pub fn is_identical(&self) -> bool {
+        self.original == self.roundtripped
+    }
+
+    /// How the round-tripped syntax tree differs from the original, item by item
+    pub fn differences(&self) -> Vec<semantic_diff::Difference> {
+        semantic_diff::semantic_diff(&self.original, &self.roundtripped)
+    }
+}
+
+/// Reduce Rust source to Iron and oxidize it back, comparing the syntax trees of the
+/// two without compiling either
+///
+/// Fails if the source does not parse or a stage fails.
+///
+/// # Example
+///
+/// ```rust
+/// let report = redox::verify_roundtrip("fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n").unwrap();
+/// assert!(report.is_identical());
+///
+/// let report = redox::verify_roundtrip("/// Adds\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n").unwrap();
+/// assert!(!report.is_identical());
+/// ```
+pub fn verify_roundtrip(source: &str) -> Result<RoundtripReport, TranspileError> {
+    verify_roundtrip_with_options(source, &OxidizeOptions::default())
+}
+
+/// Round-trip Rust source as [`verify_roundtrip`] does, oxidizing with the given options
+pub fn verify_roundtrip_with_options(
+    source: &str,
+    options: &OxidizeOptions,
+) -> Result<RoundtripReport, TranspileError> {
+    let original = parse_rust(source)?;
+    let iron = transpile_file(&original)?;
+    let (rust, warnings) = oxidize_with_warnings(&iron, options)?;
+    let roundtripped = syn::parse_file(&rust).map_err(|e| {
+        TranspileError::InternalError(format!("Oxidized Rust does not parse: {}", e))
+    })?;
+    Ok(RoundtripReport {
+        iron,
+        rust,
+        warnings,
+        original,
+        roundtripped,
+    })
+}
+
+/// Reduction and oxidation with fixed options, shareable across threads
+///
+/// Holds only options, so each call starts fresh and calls on several threads at once
+/// do not interfere.
+///
+/// # Example
+///
+/// ```rust
+/// use std::sync::Arc;
+///
+/// let transpiler = Arc::new(redox::Transpiler::new());
+/// let handles: Vec<_> = (0..4)
+///     .map(|n| {
+///         let transpiler = Arc::clone(&transpiler);
+///         std::thread::spawn(move || transpiler.transpile(&format!("const N: i32 = {};", n)))
+///     })
+///     .collect();
+///
+/// for (n, handle) in handles.into_iter().enumerate() {
+///     let iron = handle.join().unwrap().unwrap();
+///     assert_eq!(iron, format!("constant N of i32\nbegin\n    {}\nend constant\n", n));
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Transpiler {
+    reduce: ReduceOptions,
+    oxidize: OxidizeOptions,
+}
+
+impl Transpiler {
+    /// Create a handle with the default options
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reduce with the given options
+    pub fn with_reduce_options(mut self, options: ReduceOptions) -> Self {
+        self.reduce = options;
+        self
+    }
+
+    /// Oxidize with the given options
+    pub fn with_oxidize_options(mut self, options: OxidizeOptions) -> Self {
+        self.oxidize = options;
+        self
+    }
+
+    /// Reduce Rust source to Iron, as [`transpile_with_options`] does
+    pub fn transpile(&self, source: &str) -> Result<String, TranspileError> {
+        transpile_with_options(source, &self.reduce)
+    }
+
+    /// Oxidize Iron source to Rust, as [`oxidize_with_options`] does
+    pub fn oxidize(&self, iron_source: &str) -> Result<String, TranspileError> {
+        oxidize_with_options(iron_source, &self.oxidize)
+    }
+
+    /// Oxidize Iron source to Rust with the problems worked around, as
+    /// [`oxidize_with_warnings`] does
+    pub fn oxidize_with_warnings(
+        &self,
+        iron_source: &str,
+    ) -> Result<(String, Vec<iron_ast::IronWarning>), TranspileError> {
+        oxidize_with_warnings(iron_source, &self.oxidize)
+    }
+
+    /// Find the prohibited symbols in Iron code, as [`validate_iron_report`] does
+    pub fn validate(&self, iron_code: &str) -> ValidationReport {
+        validate_iron_report(iron_code)
+    }
+}
+
+// Embedders rely on these being shareable; a field that is not fails the build here
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Transpiler>();
+    assert_send_sync::<ReduceOptions>();
+    assert_send_sync::<OxidizeOptions>();
+    assert_send_sync::<TranspileError>();
+    assert_send_sync::<ValidationReport>();
+    assert_send_sync::<iron_ast::IronFile>();
+    assert_send_sync::<parser::IronParser>();
+    assert_send_sync::<oxidation::Oxidizer>();
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
     fn test_transpile_simple_function() {
         let rust = r#"
 fn add(a: i32, b: i32) -> i32 {
@@ -196,11 +1417,882 @@ fn add(a: i32, b: i32) -> i32 {
         assert!(validate_iron(&iron));
     }
 
+    #[test]
+    fn test_transpile_strict_rejects_verbatim() {
+        let rust = "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nimpl Point {}\n";
+        let iron = transpile(rust).unwrap();
+        assert!(iron.contains("verbatim begin"));
+
+        let file = syn::parse_file(rust).unwrap();
+        assert_eq!(verbatim_item_locations(&file), [(5, 1)]);
+
+        let options = ReduceOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let error = transpile_with_options(rust, &options).unwrap_err();
+        let TranspileError::UnsupportedItems {
+            items,
+            partial_iron,
+        } = &error
+        else {
+            panic!("expected unsupported items, got {:?}", error);
+        };
+        assert_eq!(
+            items,
+            &[parser::UnsupportedItem {
+                item: "impl Point".to_string(),
+                start: (5, 1),
+                end: (5, 14),
+            }]
+        );
+        assert!(partial_iron.starts_with("function add"));
+        assert_eq!(error.code(), "unsupported-syntax");
+        assert_eq!(error.location(), Some((5, Some(1))));
+        assert_eq!(
+            error.detail(),
+            "impl Point has no Iron form and would be emitted verbatim"
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_spans_cover_each_phase() {
+        use std::sync::Mutex;
+        use tracing::span;
+
+        /// Names of the spans opened and messages of the events recorded
+        #[derive(Default)]
+        struct Recorder {
+            spans: Mutex<Vec<&'static str>>,
+            events: Mutex<Vec<String>>,
+        }
+
+        /// The message of an event
+        struct Message(String);
+
+        impl tracing::field::Visit for Message {
+            fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+                if field.name() == "message" {
+                    self.0 = format!("{:?}", value);
+                }
+            }
+        }
+
+        impl tracing::Subscriber for Recorder {
+            fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+                let mut spans = self.spans.lock().unwrap();
+                spans.push(span.metadata().name());
+                span::Id::from_u64(spans.len() as u64)
+            }
+            fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, event: &tracing::Event<'_>) {
+                let mut message = Message(String::new());
+                event.record(&mut message);
+                self.events.lock().unwrap().push(message.0);
+            }
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let recorder = Arc::new(Recorder::default());
+        tracing::subscriber::with_default(recorder.clone(), || {
+            let iron = transpile_with_options(
+                "fn one() -> i32 {\n    1\n}\n\ntrait Shape {}\n",
+                &ReduceOptions::default(),
+            )
+            .unwrap();
+            oxidize(&iron).unwrap();
+        });
+        assert_eq!(
+            *recorder.spans.lock().unwrap(),
+            [
+                "reduce",
+                "parse_rust",
+                "emit",
+                "oxidize",
+                "parse_iron",
+                "oxidize_ast"
+            ]
+        );
+        assert_eq!(
+            *recorder.events.lock().unwrap(),
+            [
+                "item emitted",
+                "item has no Iron form",
+                "item emitted",
+                "item oxidized",
+                "item oxidized"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_symbol_tables_agree_across_round_trip() {
+        let rust = "fn total(items: Vec<i32>) -> i32 {\n    let mut function = 0;\n    for each in items {\n        function += each;\n    }\n    function\n}\n";
+        let file = parse_rust(rust).unwrap();
+        let mut parser = IronParser::new();
+        let iron = parser.parse_file(&file).unwrap();
+        let renamings = [
+            ("function".to_string(), "user_function".to_string()),
+            ("each".to_string(), "user_each".to_string()),
+        ];
+        assert_eq!(parser.symbols().renamings(), renamings);
+        assert_eq!(parser.symbols().depth(), 0);
+        assert!(parser.symbols().is_declared("total"));
+        assert!(!parser.symbols().is_declared("function"));
+
+        let mut oxidizer = oxidation::Oxidizer::new();
+        oxidizer.oxidize(&parse_iron(&iron).unwrap()).unwrap();
+        assert_eq!(oxidizer.symbols().renamings(), renamings);
+        assert_eq!(oxidizer.symbols().depth(), 0);
+        assert!(oxidizer.symbols().is_declared("total"));
+    }
+
+    #[test]
+    fn test_reduce_to_ast_oxidizes_back() {
+        let rust = "struct Point {\n    x: i32,\n}\n\nimpl Point {}\n\nfn origin() -> i32 {\n    let x = 0;\n    x\n}\n";
+        let ast = reduce_to_ast(rust).unwrap();
+        assert!(matches!(ast.items[0], iron_ast::IronItem::Struct(_)));
+        assert!(matches!(ast.items[2], iron_ast::IronItem::Function(_)));
+        let iron_ast::IronItem::Verbatim(verbatim) = &ast.items[1] else {
+            panic!("expected a verbatim item, got {:?}", ast.items[1]);
+        };
+        assert_eq!(verbatim.kind, "impl");
+        assert_eq!(verbatim.name.as_deref(), Some("Point"));
+        assert_eq!(
+            verbatim.span,
+            Some(iron_ast::RustSpan {
+                start: (5, 1),
+                end: (5, 14)
+            })
+        );
+        assert_eq!(
+            transpile(rust).unwrap().lines().nth(verbatim.line - 1),
+            Some("verbatim begin")
+        );
+        assert!(matches!(verbatim.syntax(), Some(syn::Item::Impl(_))));
+
+        let oxidized = oxidation::Oxidizer::new().oxidize(&ast).unwrap();
+        assert_eq!(oxidized, oxidize(&transpile(rust).unwrap()).unwrap());
+
+        let error = reduce_to_ast("fn main( {}").unwrap_err();
+        assert!(matches!(error, TranspileError::RustSyntax { .. }));
+    }
+
+    #[test]
+    fn test_reduced_iron_syntax_error_has_iron_location() {
+        use std::error::Error;
+
+        let position = iron_tokenizer::Position { line: 4, column: 7 };
+        let error = TranspileError::ReducedIronSyntax(
+            iron_parser::ParseError::UnexpectedEndOfInput(position),
+        );
+        assert_eq!(error.code(), "internal-error");
+        assert_eq!(error.location(), Some((4, Some(7))));
+        assert_eq!(
+            error.detail(),
+            "Reduced Iron does not parse: Unexpected end of input"
+        );
+        assert!(error.source().is_some());
+    }
+
+    #[test]
+    fn test_parse_iron_and_oxidize_ast_match_oxidize() {
+        let iron = "function f\nbegin\n    define g as closure move with body 1\n    return 1\nend function\n";
+        let mut ast = parse_iron(iron).unwrap();
+        assert_eq!(oxidize_ast(&ast).unwrap(), oxidize(iron).unwrap());
+        assert_eq!(
+            oxidize_ast_with_warnings(&ast, &OxidizeOptions::default()).unwrap(),
+            oxidize_with_warnings(iron, &OxidizeOptions::default()).unwrap()
+        );
+
+        if let iron_ast::IronItem::Function(function) = &mut ast.items[0] {
+            function.body.truncate(1);
+        }
+        assert_eq!(
+            oxidize_ast(&ast).unwrap(),
+            "fn f() {\n    let g = || { 1 };\n}\n"
+        );
+
+        let options = OxidizeOptions {
+            parse_mode: iron_parser::ParseMode::Strict,
+            ..Default::default()
+        };
+        assert!(parse_iron_with_options("function f\nbegin\n", &options).is_err());
+        assert!(matches!(
+            parse_iron("define as"),
+            Err(TranspileError::IronSyntax(_))
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_iron_ast_json_round_trip() {
+        let rust = "enum Shape {\n    Circle(f64),\n}\n\nimpl Shape {}\n\nfn area(r: f64) -> f64 {\n    let x = r * r;\n    x\n}\n";
+        let ast = reduce_to_ast(rust).unwrap();
+        let json = serde_json::to_string(&ast).unwrap();
+        assert!(json.contains("\"Function\""));
+        assert!(json.contains("\"kind\":\"impl\",\"name\":\"Shape\""));
+
+        let read: iron_ast::IronFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&read).unwrap(), json);
+        assert_eq!(print_iron(&read), print_iron(&ast));
+    }
+
+    #[test]
+    fn test_mapping_hooks_run_before_built_in_mappings() {
+        struct Serde;
+
+        impl MappingHook for Serde {
+            fn map_type(&self, ty: &syn::Type) -> Option<String> {
+                let syn::Type::Path(path) = ty else {
+                    return None;
+                };
+                let last = path.path.segments.last()?;
+                (last.ident == "Value").then(|| "json_value".to_string())
+            }
+
+            fn map_macro(&self, mac: &syn::Macro) -> Option<String> {
+                mac.path
+                    .is_ident("json")
+                    .then(|| format!("call parse_json with \"{}\"", mac.tokens))
+            }
+        }
+
+        let rust = "fn load(values: Vec<serde_json::Value>) -> usize {\n    let v = json!(1);\n    let w = vec![1];\n    values.len()\n}\n";
+        let options = ReduceOptions::default().with_hook(Serde);
+        let iron = transpile_with_options(rust, &options).unwrap();
+        assert!(iron.contains("values of list of json_value"));
+        assert!(iron.contains("define v as call parse_json with \"1\""));
+        assert!(iron.contains("define w as macro vec with 1 bracket"));
+        assert!(!transpile(rust).unwrap().contains("json_value"));
+
+        // Custom Iron that does not parse leaves the item verbatim
+        struct Broken;
+
+        impl MappingHook for Broken {
+            fn map_type(&self, _ty: &syn::Type) -> Option<String> {
+                Some("of of".to_string())
+            }
+        }
+
+        let options = ReduceOptions::default().with_hook(Broken);
+        let iron = transpile_with_options(rust, &options).unwrap();
+        assert!(iron.starts_with("verbatim begin"));
+    }
+
+    #[test]
+    fn test_transpile_many_keeps_input_order() {
+        let sources: Vec<String> = (0..40)
+            .map(|i| match i % 3 {
+                0 => format!("fn f{}() -> i32 {{\n    {}\n}}\n", i, i),
+                1 => format!("const C{}: i32 = {};\n", i, i),
+                _ => format!("fn broken{}( {{}}", i),
+            })
+            .collect();
+        let results = transpile_many(&sources);
+        assert_eq!(results.len(), sources.len());
+        for (source, result) in sources.iter().zip(&results) {
+            assert_eq!(format!("{:?}", result), format!("{:?}", transpile(source)));
+        }
+        assert!(transpile_many::<&str>(&[]).is_empty());
+
+        let options = ReduceOptions {
+            header: true,
+            indentation: Indentation::Spaces(2),
+            ..Default::default()
+        };
+        let results = transpile_many_with_options(&sources, &options);
+        for (source, result) in sources.iter().zip(&results) {
+            assert_eq!(
+                format!("{:?}", result),
+                format!("{:?}", transpile_with_options(source, &options))
+            );
+        }
+    }
+
+    #[test]
+    fn test_transpile_many_locates_rust_syntax_errors() {
+        let sources = [
+            "fn ok() {}
+",
+            "fn main() {}
+
+fn bad( {}\n",
+        ];
+        let results = transpile_many(&sources);
+        let error = results[1].as_ref().unwrap_err();
+        assert_eq!(error.location(), Some((3, Some(7))));
+        assert_eq!(
+            error.to_string(),
+            transpile(sources[1]).unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_reduce_items_one_at_a_time() {
+        let rust = "/// The answer\nfn answer() -> i32 {\n    42\n}\n\ntrait Shape {}\n\nconst ONE: i32 = 1;\n";
+        let items: Vec<ReducedItem> = reduce_items(rust, &ReduceOptions::default())
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(items.len(), 3);
+        assert!(matches!(items[1].rust, syn::Item::Trait(_)));
+        let irons: Vec<&str> = items.iter().map(|item| item.iron.as_str()).collect();
+        assert_eq!(irons.join("\n"), transpile(rust).unwrap());
+
+        let answer = items[0].iron_items().unwrap();
+        assert!(matches!(&answer[..], [iron_ast::IronItem::Function(_)]));
+        let shape = items[1].iron_items().unwrap();
+        let [iron_ast::IronItem::Verbatim(verbatim)] = &shape[..] else {
+            panic!("expected a verbatim item, got {:?}", shape);
+        };
+        assert_eq!(
+            (verbatim.kind.as_str(), verbatim.name.as_deref()),
+            ("trait", Some("Shape"))
+        );
+        assert_eq!(verbatim.span.map(|span| span.start), Some((6, 1)));
+
+        let options = ReduceOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let results: Vec<_> = reduce_items(rust, &options).unwrap().collect();
+        assert!(results[0].is_ok());
+        assert!(
+            results[1]
+                .as_ref()
+                .unwrap_err()
+                .to_string()
+                .contains("trait Shape")
+        );
+        assert!(results[2].is_ok());
+
+        assert!(reduce_items("fn main( {}", &options).is_err());
+
+        // The cache is shared with whole-file reduction, and the header is left out
+        let cache = Arc::new(cache::MemoryCache::new());
+        let options = ReduceOptions {
+            cache: Some(cache.clone()),
+            header: true,
+            ..Default::default()
+        };
+        let cached: Vec<String> = reduce_items(rust, &options)
+            .unwrap()
+            .map(|item| item.unwrap().iron)
+            .collect();
+        assert_eq!(cached, irons);
+        assert_eq!(cache.len(), 3);
+        let again: Vec<String> = reduce_items(rust, &options)
+            .unwrap()
+            .map(|item| item.unwrap().iron)
+            .collect();
+        assert_eq!(again, irons);
+        assert_eq!(cache.len(), 3);
+        transpile_with_options(rust, &options).unwrap();
+        assert_eq!(cache.len(), 3);
+    }
+
+    #[test]
+    fn test_transpiler_shared_across_threads() {
+        let transpiler = Transpiler::new();
+        let sources: Vec<String> = (0..32)
+            .map(|n| format!("fn f{}(x: i32) -> i32 {{\n    x + {}\n}}\n", n, n))
+            .collect();
+        let expected: Vec<String> = sources
+            .iter()
+            .map(|source| oxidize(&transpile(source).unwrap()).unwrap())
+            .collect();
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    for (source, expected) in sources.iter().zip(&expected) {
+                        let iron = transpiler.transpile(source).unwrap();
+                        assert!(transpiler.validate(&iron).is_valid());
+                        assert_eq!(&transpiler.oxidize(&iron).unwrap(), expected);
+                    }
+                });
+            }
+        });
+    }
+
+    #[test]
+    fn test_writers_match_string_results() {
+        let rust = "use std::collections::HashMap;\n\nstruct Point {\n    x: i32,\n}\n\nimpl Point {}\n\nfn origin() -> i32 {\n    let x = 0;\n    x\n}\n";
+        let mut iron = Vec::new();
+        transpile_to_writer(rust, &mut iron).unwrap();
+        let iron = String::from_utf8(iron).unwrap();
+        assert_eq!(iron, transpile(rust).unwrap());
+
+        let mut oxidized = Vec::new();
+        oxidize_to_writer(&iron, &mut oxidized).unwrap();
+        assert_eq!(
+            String::from_utf8(oxidized).unwrap(),
+            oxidize(&iron).unwrap()
+        );
+
+        struct Full;
+        impl std::io::Write for Full {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::StorageFull.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        let options = ReduceOptions {
+            indentation: Indentation::Tab,
+            max_width: Some(30),
+            layout: LayoutPolicy {
+                between_items: 2,
+                ..Default::default()
+            },
+            provenance: true,
+            ..Default::default()
+        };
+        for header in [false, true] {
+            let options = ReduceOptions {
+                header,
+                ..options.clone()
+            };
+            let mut iron = Vec::new();
+            transpile_to_writer_with_options(rust, &mut iron, &options).unwrap();
+            assert_eq!(
+                String::from_utf8(iron).unwrap(),
+                transpile_with_options(rust, &options).unwrap()
+            );
+        }
+
+        let error = transpile_to_writer(rust, &mut Full).unwrap_err();
+        assert_eq!(error.code(), "io-error");
+        assert!(std::error::Error::source(&error).is_some());
+        assert!(matches!(
+            transpile_to_writer("fn main( {}", &mut Full),
+            Err(TranspileError::RustSyntax { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reset_reuses_parser_and_oxidizer() {
+        let sources = [
+            "/// Doc\nfn one() -> i32 {\n    1\n}\n",
+            "struct Point {\n    x: HashMap<String, i32>,\n}\n",
+            "fn main() {\n    let v: Vec<i32> = vec![1];\n}\n",
+        ];
+        let mut parser = IronParser::new();
+        let mut oxidizer = oxidation::Oxidizer::new();
+        for source in sources {
+            let file = syn::parse_file(source).unwrap();
+            parser.reset();
+            let iron = parser.parse_file(&file).unwrap();
+            assert_eq!(iron, transpile(source).unwrap());
+            assert_eq!(parser.origins(), transpile_with_origins(&file).unwrap().1);
+
+            let ast = iron_parser::IronParser::new(&iron).parse().unwrap();
+            oxidizer.reset();
+            assert_eq!(oxidizer.oxidize(&ast).unwrap(), oxidize(&iron).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_verbosity_profiles_oxidize_alike() {
+        let rust = "fn main() {\n    let v = Vec::new();\n    v.push(parse(1, 2));\n    v.clear();\n    run();\n}\n";
+        let reduce = |verbosity| {
+            let options = ReduceOptions {
+                strict: true,
+                verbosity,
+                ..Default::default()
+            };
+            transpile_with_options(rust, &options).unwrap()
+        };
+
+        let terse = reduce(Verbosity::Terse);
+        assert!(terse.contains("call function new on Vec\n"));
+        assert!(terse.contains("call push on v with call parse with 1 and 2\n"));
+        assert!(terse.contains("call clear on v\n"));
+
+        let standard = reduce(Verbosity::Standard);
+        assert_eq!(standard, transpile(rust).unwrap());
+        assert!(standard.contains("call method push on v with call parse with 1 and 2\n"));
+
+        let verbose = reduce(Verbosity::ExtraVerbose);
+        assert!(verbose.contains(
+            "call method push on v with parameters call function parse with parameters 1 and 2\n"
+        ));
+        assert!(verbose.contains("call function run\n"));
+
+        assert!(terse.len() < standard.len() && standard.len() < verbose.len());
+        for iron in [&terse, &verbose] {
+            assert_eq!(oxidize(iron).unwrap(), oxidize(&standard).unwrap());
+        }
+        assert_eq!("extra-verbose".parse(), Ok(Verbosity::ExtraVerbose));
+        assert!("loud".parse::<Verbosity>().is_err());
+    }
+
+    #[test]
+    fn test_indentation_oxidizes_alike() {
+        let rust = "fn main() {\n    for x in xs {\n        if x > 1 {\n            print(\"one\\ntwo\\n\");\n        }\n    }\n}\n\nimpl Shape {\n    fn area(&self) -> f64 {\n        self.w * self.h\n    }\n}\n";
+        let reduce = |indentation| {
+            let options = ReduceOptions {
+                indentation,
+                ..Default::default()
+            };
+            transpile_with_options(rust, &options).unwrap()
+        };
+
+        let standard = reduce(Indentation::default());
+        assert_eq!(standard, transpile(rust).unwrap());
+        let tabs = reduce(Indentation::Tab);
+        assert!(
+            tabs.contains("\n\t\t\tcall print with text begin"),
+            "{}",
+            tabs
+        );
+        let narrow = reduce(Indentation::Spaces(2));
+        assert!(
+            narrow.contains("\n      call print with text begin"),
+            "{}",
+            narrow
+        );
+        for iron in [&tabs, &narrow] {
+            assert_eq!(oxidize(iron).unwrap(), oxidize(&standard).unwrap());
+        }
+        assert_eq!("tab".parse(), Ok(Indentation::Tab));
+        assert_eq!("2".parse(), Ok(Indentation::Spaces(2)));
+        assert!("0".parse::<Indentation>().is_err());
+    }
+
+    #[test]
+    fn test_max_width_wraps_lines_that_oxidize_alike() {
+        let rust = "fn main() {\n    let total = items.iter().map(|item| item.price * item.quantity).filter(|value| *value > minimum).sum();\n    if total > 100 {\n        print(\"a text\\nthat spans lines, each longer than the width of the wrapped Iron\\n\");\n    }\n}\n\nimpl Shape {\n    fn area(&self) -> f64 {\n        self.width * self.height * self.depth * self.scale * self.factor\n    }\n}\n";
+        let options = ReduceOptions {
+            max_width: Some(40),
+            ..Default::default()
+        };
+        let wrapped = transpile_with_options(rust, &options).unwrap();
+        let standard = transpile(rust).unwrap();
+        assert_ne!(wrapped, standard);
+        assert!(wrapped.contains("\n        continued "), "{}", wrapped);
+        assert_eq!(oxidize(&wrapped).unwrap(), oxidize(&standard).unwrap());
+
+        // Raw blocks are kept as they are; every other line fits
+        let raw = ["that spans lines", "impl Shape"];
+        for line in wrapped.lines() {
+            let kept = raw.iter().any(|text| line.contains(text));
+            assert!(kept || line.chars().count() <= 40, "{:?}", line);
+        }
+        for text in raw {
+            assert!(
+                standard
+                    .lines()
+                    .any(|line| line.contains(text) && wrapped.contains(line))
+            );
+        }
+    }
+
+    #[test]
+    fn test_provenance_notes_name_item_lines() {
+        let rust =
+            "fn one() -> i32 {\n    1\n}\n\nimpl Point {}\n\n#[derive(Debug)]\nstruct Unit;\n";
+        let options = ReduceOptions {
+            provenance: true,
+            source_path: Some("src/lib.rs".to_string()),
+            cache: Some(Arc::new(cache::MemoryCache::new())),
+            ..Default::default()
+        };
+        let iron = transpile_with_options(rust, &options).unwrap();
+        assert!(iron.starts_with("note that this comes from line 1 of src/lib.rs\nfunction one\n"));
+        assert!(
+            iron.contains("\nnote that this comes from line 5 of src/lib.rs\nverbatim begin\n")
+        );
+        assert!(iron.contains("\nnote that this comes from line 8 of src/lib.rs\n"));
+        let plain = ReduceOptions {
+            provenance: false,
+            ..options.clone()
+        };
+        let unnoted: String = iron
+            .split_inclusive('\n')
+            .filter(|line| !line.starts_with("note that this comes from"))
+            .collect();
+        assert_eq!(unnoted, transpile_with_options(rust, &plain).unwrap());
+
+        // Items from the cache are noted at the lines they have moved to
+        let moved = transpile_with_options(&format!("\n\n{}", rust), &options).unwrap();
+        assert!(
+            moved.starts_with("note that this comes from line 3 of src/lib.rs\nfunction one\n")
+        );
+        assert!(
+            oxidize(&moved)
+                .unwrap()
+                .starts_with("// this comes from line 3 of src/lib.rs\n")
+        );
+    }
+
+    #[test]
+    fn test_layout_policy_places_blank_lines() {
+        let rust = "use std::io;\n\nfn main() {\n    fn helper() {}\n    let x = 1;\n    const Y: i32 = 2;\n}\n";
+        let iron = transpile(rust).unwrap();
+        assert_eq!(
+            iron,
+            "import std io\n\
+             \n\
+             function main\n\
+             begin\n    \
+                 function helper\n    \
+                 begin\n    \
+                 end function\n\
+             \n    \
+                 define x as 1\n\
+             \n    \
+                 constant Y of i32\n    \
+                 begin\n        \
+                     2\n    \
+                 end constant\n\
+             end function\n"
+        );
+
+        let options = ReduceOptions {
+            header: true,
+            layout: LayoutPolicy {
+                between_items: 2,
+                inside_blocks: 0,
+                after_header: 0,
+            },
+            ..Default::default()
+        };
+        let spaced = transpile_with_options(rust, &options).unwrap();
+        let (_, content) = header::split_header(&spaced).unwrap();
+        assert!(content.starts_with("import std io\n\n\nfunction main\n"));
+        assert!(content.contains("end function\n    define x as 1\n    constant Y"));
+        assert!(content.ends_with("end constant\nend function\n"));
+        assert_eq!(oxidize(&spaced).unwrap(), oxidize(&iron).unwrap());
+
+        // The same input and options give the same bytes, however the parser is reused
+        let mut parser = IronParser::new().with_layout(options.layout);
+        let file = syn::parse_file(rust).unwrap();
+        let first = parser.parse_file(&file).unwrap();
+        parser.reset();
+        assert_eq!(parser.parse_file(&file).unwrap(), first);
+        assert_eq!(header::add_header_with_blank_lines(&first, rust, 0), spaced);
+        let cached = ReduceOptions {
+            cache: Some(Arc::new(cache::MemoryCache::new())),
+            ..options.clone()
+        };
+        for _ in 0..2 {
+            assert_eq!(transpile_with_options(rust, &cached).unwrap(), spaced);
+        }
+    }
+
+    #[test]
+    fn test_transpile_falls_back_when_iron_does_not_parse() {
+        // Iron has no reference expressions yet, so `&x` would not read back
+        let rust = "fn f(x: i32) -> bool {\n    g(&x)\n}\n\nfn h() -> i32 {\n    1\n}\n";
+        let iron = transpile(rust).unwrap();
+        assert!(iron.starts_with("verbatim begin\nfn f"));
+        assert!(iron.contains("function h"));
+        assert!(oxidize(&iron).is_ok());
+
+        let options = ReduceOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let error = transpile_with_options(rust, &options).unwrap_err();
+        assert_eq!(error.location(), Some((1, Some(1))));
+    }
+
+    #[test]
+    fn test_fallback_policies() {
+        struct Unions;
+
+        impl MappingHook for Unions {
+            fn map_unsupported_item(&self, item: &syn::Item) -> Option<String> {
+                match item {
+                    syn::Item::Union(union) => Some(format!(
+                        "structure {} with fields\n    bits of u32\nend structure\n",
+                        union.ident
+                    )),
+                    // Not Iron, so the item stays verbatim
+                    syn::Item::Trait(_) => Some("trait T {}".to_string()),
+                    _ => None,
+                }
+            }
+        }
+
+        let rust = "union Bits {\n    n: u32,\n}\n\ntrait T {}\n\nfn one() -> i32 {\n    1\n}\n";
+        let reduce = |fallback, strict| {
+            let options = ReduceOptions {
+                fallback,
+                strict,
+                ..Default::default()
+            }
+            .with_hook(Unions);
+            transpile_with_options(rust, &options)
+        };
+
+        let verbatim = reduce(FallbackPolicy::Verbatim, false).unwrap();
+        assert_eq!(verbatim, transpile(rust).unwrap());
+        assert_eq!(verbatim.matches("verbatim begin").count(), 2);
+
+        let placeholder = reduce(FallbackPolicy::Placeholder, false).unwrap();
+        assert!(placeholder.starts_with(
+            "note that union Bits has no Iron form and was left out\n\n\
+             note that trait T has no Iron form and was left out\n\nfunction one"
+        ));
+        assert!(validate_iron(&placeholder));
+        assert_eq!(
+            oxidize(&placeholder).unwrap(),
+            "// union Bits has no Iron form and was left out\n\
+             // trait T has no Iron form and was left out\n\
+             fn one() -> i32 {\n    1\n}\n"
+        );
+
+        let error = reduce(FallbackPolicy::Error, false).unwrap_err();
+        assert_eq!(error.location(), Some((1, Some(1))));
+        assert!(reduce(FallbackPolicy::Placeholder, true).is_err());
+
+        let hooked = reduce(FallbackPolicy::Hook, false).unwrap();
+        assert!(
+            hooked.starts_with("structure Bits with fields\n    bits of u32\nend structure\n\n")
+        );
+        assert!(hooked.contains("verbatim begin\ntrait T { }\nverbatim end\n"));
+        assert!(oxidize(&hooked).is_ok());
+
+        assert_eq!("placeholder".parse(), Ok(FallbackPolicy::Placeholder));
+        assert!("skip".parse::<FallbackPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_verify_roundtrip_compares_syntax_trees() {
+        // Layout and plain comments do not count
+        let rust = "fn add(a: i32,b: i32)->i32{\n    // sum\n    a+b\n}\n";
+        let report = verify_roundtrip(rust).unwrap();
+        assert!(report.is_identical());
+        assert_eq!(report.iron, transpile(rust).unwrap());
+        assert!(report.rust.contains("fn add(a: i32, b: i32) -> i32"));
+
+        let report =
+            verify_roundtrip("fn f(x: i32) -> i32 {\n    let y: i32 = x;\n    y\n}\n").unwrap();
+        assert!(!report.is_identical());
+        assert_eq!(
+            report.differences(),
+            [semantic_diff::Difference::ChangedBody {
+                item: "function f".to_string(),
+                original: "let y : i32 = x ;".to_string(),
+                roundtripped: "let y = x ;".to_string(),
+            }]
+        );
+
+        let error = verify_roundtrip("fn main( {}").unwrap_err();
+        assert!(matches!(error, TranspileError::RustSyntax { .. }));
+    }
+
+    #[test]
+    fn test_canonicalize_is_idempotent() {
+        let source = r#"
+fn report(value: &str) -> String {
+    println!("tab\t\u{41} \"quoted\" \\ {}", value);
+    eprintln!(r"raw \d+");
+    format!("first
+second {}", value)
+}
+"#;
+        let canonical = canonicalize(source).unwrap();
+        assert!(canonical.contains(r#"println!("tab\tA \"quoted\" \\ {}", value);"#));
+        assert!(canonical.contains(r#"format!("first\nsecond {}", value)"#));
+        assert_eq!(canonicalize(&canonical).unwrap(), canonical);
+    }
+
     #[test]
     fn test_validate_iron() {
         assert!(validate_iron("function foo begin end function"));
         assert!(!validate_iron("function &foo begin end function"));
         assert!(!validate_iron("function -> foo begin end function"));
+        assert!(validate_iron(
+            "function foo\nbegin\n    note that a -> b\n    \"x < y\"\nend function\n"
+        ));
+
+        let report = validate_iron_report("note that a -> b\nset x to a->b\n");
+        assert!(!report.is_valid());
+        assert_eq!(report.violations.len(), 4);
+        let errors: Vec<_> = report
+            .errors()
+            .map(|s| (s.line, s.column, s.rule.as_str(), s.snippet.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (2, 11, "minus-sign", "a->b"),
+                (2, 12, "angle-bracket", "a->b")
+            ]
+        );
+
+        // Comments are left alone by the code profile, and symbols match token-wise
+        let code = ValidationProfile::code();
+        let report = validate_iron_report_with_profile("note that a - b\n\"x - y\"\n", &code);
+        assert!(report.violations.is_empty());
+        let mut arrows = ValidationProfile::new("arrows");
+        arrows.insert("->", "arrow").unwrap();
+        let found =
+            prohibited_symbols_with_profile("note that a -> b\nset x to a->b - c\n", &arrows);
+        let found: Vec<_> = found
+            .iter()
+            .map(|s| (s.line, s.column, s.rule.as_str(), s.severity))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (1, 13, "arrow", Severity::Warning),
+                (2, 11, "arrow", Severity::Error)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prohibited_symbols() {
+        let found = prohibited_symbols("define x as 1\ncall std::mem::take with é & y");
+        let positions: Vec<_> = found
+            .iter()
+            .map(|s| (s.line, s.column, s.symbol.as_str()))
+            .collect();
+        assert_eq!(positions, [(2, 9, "::"), (2, 14, "::"), (2, 28, "&")]);
+
+        let snippets: Vec<_> = found
+            .iter()
+            .map(|s| (s.rule.as_str(), s.snippet.as_str()))
+            .collect();
+        assert_eq!(
+            snippets,
+            [
+                ("path-separator", "std::mem::take"),
+                ("path-separator", "std::mem::take"),
+                ("ampersand", "&")
+            ]
+        );
+
+        let found = prohibited_symbols("note that a -> b\ndefine s as \"&\" & t\n");
+        let severities: Vec<_> = found
+            .iter()
+            .map(|s| (s.line, s.column, s.severity))
+            .collect();
+        assert_eq!(
+            severities,
+            [
+                (1, 13, Severity::Warning),
+                (1, 14, Severity::Warning),
+                (2, 14, Severity::Warning),
+                (2, 17, Severity::Error)
+            ]
+        );
+
+        // A symbol just before a string is in code
+        let found = prohibited_symbols("define s as &\"x\"\n");
+        assert_eq!(found[0].severity, Severity::Error);
     }
 
     #[test]
@@ -218,4 +2310,75 @@ fn identity<T>(x: T) -> T {
         assert!(iron.contains("generic"));
         assert!(iron.contains("type T"));
     }
+
+    #[test]
+    fn test_error_location() {
+        let error = transpile("fn main() {\n    let = 1;\n}").unwrap_err();
+        assert_eq!(error.location(), Some((2, Some(9))));
+
+        let error = oxidize("function f\nbegin\n    define x as\nend function").unwrap_err();
+        assert_eq!(error.location(), Some((3, Some(16))));
+        assert_eq!(
+            error.detail(),
+            "Unexpected token NewLine, expected expression"
+        );
+
+        let error = oxidize("function f\n    takes x of unknown\nbegin\nend function").unwrap_err();
+        assert_eq!(error.location(), Some((1, None)));
+
+        assert_eq!(
+            TranspileError::InternalError("oops".into()).location(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_error_sources_chain() {
+        use std::error::Error;
+
+        let error = transpile("fn main() {\n    let = 1;\n}").unwrap_err();
+        assert_eq!(error.code(), "parse-error");
+        let source = error.source().unwrap();
+        assert!(source.downcast_ref::<syn::Error>().is_some());
+        assert!(error.to_string().ends_with(&source.to_string()));
+
+        let error = oxidize("function f\nbegin\n    define x as\nend function").unwrap_err();
+        assert!(matches!(
+            error.source().unwrap().downcast_ref(),
+            Some(iron_parser::ParseError::UnexpectedToken(..))
+        ));
+
+        let error = oxidize("function f\n    takes x of unknown\nbegin\nend function").unwrap_err();
+        assert_eq!(error.code(), "unsupported-syntax");
+        assert!(matches!(
+            error.source().unwrap().downcast_ref(),
+            Some(oxidation::OxidizeError::UnknownType { line: Some(1), .. })
+        ));
+
+        let error = TranspileError::InternalError("oops".into());
+        assert!(error.source().is_none());
+        assert_eq!(error.partial_iron(), None);
+    }
+
+    #[test]
+    fn test_oxidize_case_insensitive_keywords() {
+        let iron = r#"Function Add
+    Takes a of i32 AND b of i32
+    Returns i32
+Begin
+    Note That mixed case
+    a PLUS b
+End Function"#;
+
+        assert!(oxidize(iron).is_err());
+
+        let options = OxidizeOptions {
+            case_insensitive_keywords: true,
+            ..Default::default()
+        };
+        let rust = oxidize_with_options(iron, &options).unwrap();
+        assert!(rust.contains("fn Add(a: i32, b: i32) -> i32"));
+        assert!(rust.contains("// mixed case"));
+        assert!(rust.contains("a + b"));
+    }
 }
@@ -1,9 +1,25 @@
 //! Redox CLI - Command line interface for the Rust to Iron transpiler
 
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use quote::ToTokens;
+use redox::cache::DirectoryCache;
+use redox::dictionary::TypeDictionary;
+use redox::iron_ast::IronWarning;
+use redox::iron_parser::ParseMode;
+use redox::iron_tokenizer::{Token, Tokenizer};
+use redox::oxidation::Edition;
+use redox::{LayoutPolicy, OxidizeOptions, ReduceOptions, Severity, TranspileError};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread;
+use std::time::Instant;
+use syn::spanned::Spanned;
 
 #[derive(Parser)]
 #[command(name = "redox")]
@@ -12,191 +28,3158 @@ use std::process;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// How errors, warnings, and results are printed
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Exit with status 6 when any warning was reported, even if nothing failed
+    #[arg(long, global = true)]
+    warnings_as_errors: bool,
+
+    /// Print no progress or summary for batch jobs, only errors and results
+    #[arg(short, long, global = true)]
+    quiet: bool,
+}
+
+/// Exit status of the process, so scripts can tell kinds of failure apart
+///
+/// Status 2 is left to clap, which uses it for bad command lines.
+#[derive(Clone, Copy)]
+enum Exit {
+    /// Anything not covered below, such as a round trip that changed the code
+    Failure = 1,
+    /// The input is not valid Rust or Iron
+    ParseError = 3,
+    /// The input uses syntax with no form in the other language
+    UnsupportedSyntax = 4,
+    /// Validation, checking, or `--verify` found errors
+    Invalid = 5,
+    /// Nothing failed, but warnings were reported under `--warnings-as-errors`
+    Warnings = 6,
+    /// A file or stream could not be read or written
+    Io = 7,
+}
+
+impl Exit {
+    fn of(error: &(dyn std::error::Error + 'static)) -> Self {
+        let transpile_error = match error.downcast_ref::<StageError>() {
+            Some(stage_error) => Some(&stage_error.error),
+            None => error.downcast_ref::<TranspileError>(),
+        };
+        match transpile_error.map(TranspileError::code) {
+            Some("parse-error") => Exit::ParseError,
+            Some("unsupported-syntax") => Exit::UnsupportedSyntax,
+            Some("io-error") => Exit::Io,
+            Some(_) => Exit::Failure,
+            None if error.is::<IoError>() || error.is::<io::Error>() => Exit::Io,
+            None if error.is::<VerifyError>() => Exit::Invalid,
+            None => Exit::Failure,
+        }
+    }
+}
+
+/// Whether any warning has been reported, for `--warnings-as-errors`
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Output format for diagnostics
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    /// Human-readable lines on stderr
+    Text,
+    /// One JSON object per diagnostic on stderr, and results on stdout
+    Json,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Transpile Rust source to Iron
     Reduce {
-        /// Input Rust source file
-        #[arg(value_name = "INPUT")]
-        input: PathBuf,
+        /// Input Rust source files (`-` for stdin), or directories to reduce recursively
+        #[arg(value_name = "INPUT", required = true)]
+        inputs: Vec<PathBuf>,
 
-        /// Output Iron file (default: stdout)
-        #[arg(short, long, value_name = "OUTPUT")]
+        /// Output Iron file for a single input file (default or `-`: stdout)
+        #[arg(short, long, value_name = "OUTPUT", conflicts_with = "out_dir")]
         output: Option<PathBuf>,
 
+        /// Directory the Iron files are written to: directory inputs mirror their tree
+        /// there, and file inputs go at its top
+        #[arg(long, value_name = "DIR")]
+        out_dir: Option<PathBuf>,
+
+        /// Extension of the Iron files written to the output directory, such as `fe`
+        #[arg(long, value_name = "EXT", default_value = "iron")]
+        extension: String,
+
+        /// Files of a directory input to reduce, relative to it
+        #[arg(long, value_name = "PATTERN", default_value = "**/*.rs")]
+        glob: String,
+
         /// Validate output contains no prohibited symbols
         #[arg(short, long)]
         validate: bool,
 
+        /// Fail on items with no Iron form instead of emitting them as verbatim Rust
+        #[arg(long)]
+        strict: bool,
+
+        /// TOML or JSON dictionary of Iron names for project types, as a `types` table
+        /// of Rust name to Iron name
+        #[arg(long, value_name = "FILE")]
+        types: Option<PathBuf>,
+
+        /// Start each Iron file with a line recording the Iron specification, the redox
+        /// version, and hashes of the Rust source and the Iron
+        #[arg(long, conflicts_with = "markdown")]
+        header: bool,
+
+        /// Directory caching the Iron of each item between runs, so only changed items
+        /// are reduced again
+        #[arg(long, value_name = "DIR")]
+        cache_dir: Option<PathBuf>,
+
+        /// How much of each call to spell out: `terse`, `standard`, or `extra-verbose`
+        #[arg(long, value_name = "PROFILE", default_value = "standard")]
+        verbosity: redox::Verbosity,
+
+        /// What to do with items that have no Iron form: carry them as `verbatim` Rust,
+        /// leave a `placeholder` comment, or fail with an `error` (as `--strict` does)
+        #[arg(long, value_name = "POLICY", default_value = "verbatim")]
+        fallback: redox::FallbackPolicy,
+
+        /// One level of indentation in the Iron: a number of spaces from 1 to 16, or `tab`
+        #[arg(long, value_name = "WIDTH", default_value = "4")]
+        indent: redox::Indentation,
+
+        /// Wrap Iron lines longer than this many characters onto lines starting with
+        /// `continued`
+        #[arg(long, value_name = "WIDTH")]
+        max_width: Option<usize>,
+
+        /// Start each item with a note of the Rust file and line it comes from
+        #[arg(long)]
+        provenance: bool,
+
+        /// Blank lines between top-level items
+        #[arg(long, value_name = "LINES", default_value = "1")]
+        item_spacing: usize,
+
+        /// Blank lines setting an item nested in a block off from the statements around it
+        #[arg(long, value_name = "LINES", default_value = "1")]
+        block_spacing: usize,
+
+        /// Blank lines after the `--header` line
+        #[arg(long, value_name = "LINES", default_value = "1")]
+        header_spacing: usize,
+
+        /// Treat the input as Markdown, rewriting its `rust` code blocks as `iron` blocks
+        #[arg(long, conflicts_with = "out_dir")]
+        markdown: bool,
+
+        /// Write nothing, only check whether the existing output files would change,
+        /// failing if any would
+        #[arg(long, conflicts_with = "markdown")]
+        check: bool,
+
+        /// Number of files to reduce at once into the output directory (default: one per CPU)
+        #[arg(short, long, value_name = "N")]
+        jobs: Option<NonZeroUsize>,
+
         /// Show verbose error messages
         #[arg(short = 'V', long)]
         verbose: bool,
     },
 
-    /// Validate Iron code
+    /// Check Iron code for leftover Rust symbols (`check` also parses it)
     Validate {
-        /// Input Iron file to validate
+        /// Input Iron file to validate (`-` for stdin)
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Symbols to prohibit: a built-in profile (`standard`, or `code` to leave
+        /// comments, strings, and verbatim blocks alone) or a .toml or .json profile
+        #[arg(long, value_name = "PROFILE", default_value = "standard")]
+        profile: redox::ValidationProfile,
+    },
+
+    /// Check that Iron source parses and oxidizes, reporting problems with their locations
+    Check {
+        /// Input Iron file to check (`-` for stdin)
         #[arg(value_name = "INPUT")]
         input: PathBuf,
+
+        /// How to treat input outside the Iron grammar: reject it (strict) or recover
+        /// with a warning (lenient)
+        #[arg(long, value_name = "MODE", default_value = "strict")]
+        parse_mode: ParseMode,
+
+        /// Accept keywords in any capitalization
+        #[arg(long)]
+        case_insensitive_keywords: bool,
     },
 
     /// Transpile Iron source to Rust
     Oxidize {
-        /// Input Iron source file
+        /// Input Iron source file (`-` for stdin)
         #[arg(value_name = "INPUT")]
         input: PathBuf,
 
-        /// Output Rust file (default: stdout)
+        /// Output Rust file (default or `-`: stdout)
         #[arg(short, long, value_name = "OUTPUT")]
         output: Option<PathBuf>,
 
+        /// Rust edition the output targets (2015, 2018, 2021, or 2024)
+        #[arg(long, value_name = "EDITION", default_value = "2024")]
+        edition: Edition,
+
+        /// How to treat input outside the Iron grammar: reject it (strict) or recover
+        /// with a warning (lenient)
+        #[arg(long, value_name = "MODE", default_value = "lenient")]
+        parse_mode: ParseMode,
+
+        /// TOML or JSON dictionary of Iron names for project types, the one the input was
+        /// reduced with
+        #[arg(long, value_name = "FILE")]
+        types: Option<PathBuf>,
+
+        /// Treat the input as Markdown, rewriting its `iron` code blocks as `rust` blocks
+        #[arg(long)]
+        markdown: bool,
+
+        /// Check that the generated Rust parses before writing it, failing with the
+        /// parse error if it does not
+        #[arg(long, conflicts_with = "markdown")]
+        verify: bool,
+
+        /// With --verify, also compile the generated Rust with rustc, failing with the
+        /// compiler's errors
+        #[arg(long, requires = "verify")]
+        compile: bool,
+
         /// Show verbose error messages
         #[arg(short = 'V', long)]
         verbose: bool,
     },
+
+    /// Rewrite Iron source with canonical indentation, blank lines, and keyword casing
+    Fmt {
+        /// Input Iron source file (`-` for stdin)
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Output Iron file (default or `-`: stdout)
+        #[arg(short, long, value_name = "OUTPUT", conflicts_with = "check")]
+        output: Option<PathBuf>,
+
+        /// Only check whether the input is already formatted, failing if it is not
+        #[arg(long)]
+        check: bool,
+
+        /// Accept keywords in any capitalization, rewriting them in lowercase
+        #[arg(long)]
+        case_insensitive_keywords: bool,
+    },
+
+    /// Report how much of some Rust source reduces to Iron, per file and in total
+    Stats {
+        /// Input Rust source files or directories
+        #[arg(value_name = "INPUT", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Files of a directory input to include, relative to it
+        #[arg(long, value_name = "PATTERN", default_value = "**/*.rs")]
+        glob: String,
+
+        /// Number of files to reduce at once (default: one per CPU)
+        #[arg(short, long, value_name = "N")]
+        jobs: Option<NonZeroUsize>,
+    },
+
+    /// Report the share of items, statements, and expressions reduced natively rather
+    /// than carried as verbatim Rust, by syntax node kind
+    Coverage {
+        /// Input Rust source files or directories
+        #[arg(value_name = "INPUT", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Files of a directory input to include, relative to it
+        #[arg(long, value_name = "PATTERN", default_value = "**/*.rs")]
+        glob: String,
+
+        /// Number of files to reduce at once (default: one per CPU)
+        #[arg(short, long, value_name = "N")]
+        jobs: Option<NonZeroUsize>,
+    },
+
+    /// Export each top-level item's Rust source and Iron reduction as JSON lines
+    Dataset {
+        /// Input Rust source files or directories
+        #[arg(value_name = "INPUT", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Output JSONL file (default or `-`: stdout)
+        #[arg(long, value_name = "OUTPUT")]
+        out: Option<PathBuf>,
+
+        /// Files of a directory input to include, relative to it
+        #[arg(long, value_name = "PATTERN", default_value = "**/*.rs")]
+        glob: String,
+
+        /// Number of files to reduce at once (default: one per CPU)
+        #[arg(short, long, value_name = "N")]
+        jobs: Option<NonZeroUsize>,
+    },
+
+    /// Show Rust source and its Iron reduction item by item, side by side
+    Diff {
+        /// Input Rust source file (`-` for stdin)
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Print each item's Rust and Iron one after the other, marked `-` and `+`
+        #[arg(long)]
+        unified: bool,
+
+        /// Total width of the side-by-side view, in characters
+        #[arg(long, value_name = "COLUMNS", default_value_t = 120)]
+        width: usize,
+    },
+
+    /// Count LLM tokens in Rust source and its Iron reduction, per file and per item
+    #[cfg(feature = "tokens")]
+    Tokens {
+        /// Input Rust source files (`-` for stdin), or directories to search
+        #[arg(value_name = "INPUT", required = true)]
+        inputs: Vec<PathBuf>,
+
+        /// Tokenizer to count with: `cl100k` or `o200k` (GPT-style BPE), or the path of
+        /// a Hugging Face `tokenizer.json`, such as a Llama one. May be repeated.
+        #[arg(short, long, value_name = "TOKENIZER", default_value = "cl100k")]
+        tokenizer: Vec<String>,
+
+        /// Files of directory inputs to count, relative to each
+        #[arg(long, value_name = "PATTERN", default_value = "**/*.rs")]
+        glob: String,
+    },
+
+    /// Reduce Rust source to Iron, annotating it with the Rust each line came from
+    Explain {
+        /// Input Rust source file (`-` for stdin)
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Output file (default: stdout)
+        #[arg(short, long, value_name = "OUTPUT")]
+        output: Option<PathBuf>,
+    },
+
+    /// Run the reductions, oxidations, and validations a manifest lists, with per-file
+    /// options, and report each result as JSON
+    Batch {
+        /// Manifest file (`-` for stdin): JSON, or TOML when its extension is `.toml`
+        #[arg(value_name = "MANIFEST")]
+        manifest: PathBuf,
+
+        /// File the JSON report is written to (default or `-`: stdout)
+        #[arg(short, long, value_name = "REPORT")]
+        report: Option<PathBuf>,
+
+        /// Number of files to process at once (default: one per CPU)
+        #[arg(short, long, value_name = "N")]
+        jobs: Option<NonZeroUsize>,
+    },
+
+    /// Serve reduction, oxidation, and validation as JSON endpoints over HTTP, or as
+    /// JSON-RPC methods over stdio with `--daemon`
+    Serve {
+        /// Port to listen on (0 picks a free one)
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+
+        /// Address to listen on
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1")]
+        host: String,
+
+        /// Number of requests to answer at once (default: one per CPU)
+        #[arg(short, long, value_name = "N")]
+        jobs: Option<NonZeroUsize>,
+
+        /// Speak JSON-RPC on stdin and stdout, each message framed by a `Content-Length`
+        /// header, instead of listening for HTTP
+        #[arg(long, conflicts_with_all = ["port", "host"])]
+        daemon: bool,
+    },
+
+    /// Reduce Rust source to Iron and oxidize it back, reporting what changed
+    Roundtrip {
+        /// Input Rust source file (`-` for stdin)
+        #[arg(value_name = "INPUT")]
+        input: PathBuf,
+
+        /// Also compile the original and the round-tripped code with rustc
+        #[arg(long)]
+        compile: bool,
+
+        /// Rust edition to oxidize and compile for (2015, 2018, 2021, or 2024)
+        #[arg(long, value_name = "EDITION", default_value = "2024")]
+        edition: Edition,
+
+        /// Print the intermediate Iron
+        #[arg(long)]
+        show_iron: bool,
+
+        /// Report what changed item by item (missing items, changed signatures, altered
+        /// literals) instead of a diff of the code
+        #[arg(long)]
+        semantic: bool,
+    },
+
+    /// Print a shell completion script for redox
+    #[command(hide = true)]
+    Completions {
+        /// Shell to complete in
+        #[arg(value_name = "SHELL")]
+        shell: clap_complete::Shell,
+    },
+
+    /// Print the redox man page, in roff
+    #[command(hide = true)]
+    Man,
 }
 
 fn main() {
     let cli = Cli::parse();
+    let reporter = Reporter {
+        format: cli.format,
+        quiet: cli.quiet,
+    };
 
     match cli.command {
         Commands::Reduce {
-            input,
+            inputs,
             output,
+            out_dir,
+            extension,
+            glob,
             validate,
+            strict,
+            types,
+            header,
+            cache_dir,
+            verbosity,
+            fallback,
+            indent,
+            max_width,
+            provenance,
+            item_spacing,
+            block_spacing,
+            header_spacing,
+            markdown,
+            check,
+            jobs,
             verbose,
         } => {
-            if let Err(e) = transpile_file(input, output, validate, verbose) {
-                eprintln!("Error: {}", e);
-                process::exit(1);
+            let mut options = ReduceOptions {
+                strict,
+                header,
+                verbosity,
+                fallback,
+                indentation: indent,
+                max_width,
+                provenance,
+                layout: LayoutPolicy {
+                    between_items: item_spacing,
+                    inside_blocks: block_spacing,
+                    after_header: header_spacing,
+                },
+                ..Default::default()
+            };
+            // Each dictionary changes the Iron of the items it names, so it has its own cache
+            let mut cache_name = "default".to_string();
+            if let Some(types) = &types {
+                let dictionary = load_types(types, reporter);
+                cache_name = redox::header::hash(&format!("{:?}", dictionary));
+                options = options.with_hook(dictionary);
+            }
+            if let Some(cache_dir) = cache_dir {
+                options.cache = Some(Arc::new(DirectoryCache::new(cache_dir.join(cache_name))));
+            }
+            let settings = ReduceSettings {
+                options,
+                validate,
+                check,
+                verbose,
+            };
+            let result = match (&inputs[..], out_dir) {
+                ([input], None) if markdown => {
+                    let options = &settings.options;
+                    convert_markdown(input, output, &["rust", "rs"], "iron", reporter, |code| {
+                        redox::transpile_with_options(code, options)
+                    })
+                }
+                (_, None) if markdown => Err("--markdown takes a single input file".into()),
+                ([input], None) if !input.is_dir() => {
+                    transpile_file(input, output, &settings, reporter)
+                }
+                (_, None) => Err("Reducing a directory or several files needs --out-dir".into()),
+                (_, Some(out_dir)) => {
+                    let extension = extension.trim_start_matches('.');
+                    let jobs = jobs.unwrap_or_else(default_jobs);
+                    output_paths(&inputs, &out_dir, &glob, extension)
+                        .and_then(|files| transpile_files(&files, jobs, &settings, reporter))
+                }
+            };
+            if let Err(e) = result {
+                reporter.fatal(&inputs[0], &*e)
             }
         }
-        Commands::Validate { input } => {
-            if let Err(e) = validate_file(input) {
-                eprintln!("Validation error: {}", e);
-                process::exit(1);
+        Commands::Validate { input, profile } => match validate_file(&input, &profile, reporter) {
+            Ok(true) => {}
+            Ok(false) => process::exit(Exit::Invalid as i32),
+            Err(e) => reporter.fatal(&input, &*e),
+        },
+        Commands::Check {
+            input,
+            parse_mode,
+            case_insensitive_keywords,
+        } => {
+            let options = OxidizeOptions {
+                parse_mode,
+                case_insensitive_keywords,
+                ..Default::default()
+            };
+            match check_file(&input, &options, reporter) {
+                Ok(true) => {}
+                Ok(false) => process::exit(Exit::Invalid as i32),
+                Err(e) => reporter.fatal(&input, &*e),
             }
         }
         Commands::Oxidize {
             input,
             output,
+            edition,
+            parse_mode,
+            types,
+            markdown,
+            verify,
+            compile,
             verbose,
         } => {
-            if let Err(e) = oxidize_file(input, output, verbose) {
-                eprintln!("Error: {}", e);
-                process::exit(1);
+            let options = OxidizeOptions {
+                edition,
+                parse_mode,
+                types: types
+                    .map(|types| load_types(&types, reporter))
+                    .unwrap_or_default(),
+                ..Default::default()
+            };
+            let verify = match (verify, compile) {
+                (_, true) => Verify::Compile,
+                (true, false) => Verify::Parse,
+                (false, false) => Verify::None,
+            };
+            let result = if markdown {
+                convert_markdown(&input, output, &["iron"], "rust", reporter, |code| {
+                    redox::oxidize_with_options(code, &options)
+                })
+            } else {
+                oxidize_file(&input, output, &options, verify, verbose, reporter)
+            };
+            if let Err(e) = result {
+                reporter.fatal(&input, &*e)
+            }
+        }
+        Commands::Fmt {
+            input,
+            output,
+            check,
+            case_insensitive_keywords,
+        } => {
+            if let Err(e) = format_file(&input, output, check, case_insensitive_keywords) {
+                reporter.fatal(&input, &*e)
+            }
+        }
+        Commands::Stats { inputs, glob, jobs } => {
+            let jobs = jobs.unwrap_or_else(default_jobs);
+            if let Err(e) = stats_files(&inputs, &glob, jobs, reporter) {
+                reporter.fatal(&inputs[0], &*e)
+            }
+        }
+        Commands::Coverage { inputs, glob, jobs } => {
+            let jobs = jobs.unwrap_or_else(default_jobs);
+            if let Err(e) = coverage_files(&inputs, &glob, jobs, reporter) {
+                reporter.fatal(&inputs[0], &*e)
+            }
+        }
+        Commands::Dataset {
+            inputs,
+            out,
+            glob,
+            jobs,
+        } => {
+            let jobs = jobs.unwrap_or_else(default_jobs);
+            if let Err(e) = dataset_files(&inputs, out, &glob, jobs, reporter) {
+                reporter.fatal(&inputs[0], &*e)
+            }
+        }
+        Commands::Diff {
+            input,
+            unified,
+            width,
+        } => {
+            if let Err(e) = diff_file(&input, unified, width, cli.format) {
+                reporter.fatal(&input, &*e)
+            }
+        }
+        #[cfg(feature = "tokens")]
+        Commands::Tokens {
+            inputs,
+            tokenizer,
+            glob,
+        } => {
+            if let Err(e) = tokens_files(&inputs, &tokenizer, &glob, reporter) {
+                reporter.fatal(&inputs[0], &*e)
+            }
+        }
+        Commands::Explain { input, output } => {
+            if let Err(e) = explain_file(&input, output, cli.format) {
+                reporter.fatal(&input, &*e)
+            }
+        }
+        Commands::Batch {
+            manifest,
+            report,
+            jobs,
+        } => {
+            let jobs = jobs.unwrap_or_else(default_jobs);
+            match batch_files(&manifest, report, jobs, reporter) {
+                Ok(true) => {}
+                Ok(false) => process::exit(Exit::Invalid as i32),
+                Err(e) => reporter.fatal(&manifest, &*e),
+            }
+        }
+        Commands::Serve {
+            port,
+            host,
+            jobs,
+            daemon,
+        } => {
+            let jobs = jobs.unwrap_or_else(default_jobs);
+            let result = if daemon {
+                serve_stdio(jobs)
+            } else {
+                serve(&host, port, jobs)
+            };
+            // The server reads no file, so its errors name none
+            if let Err(e) = result {
+                reporter.fatal(Path::new("-"), &*e)
+            }
+        }
+        Commands::Roundtrip {
+            input,
+            compile,
+            edition,
+            show_iron,
+            semantic,
+        } => match roundtrip_file(&input, compile, edition, show_iron, semantic, cli.format) {
+            Ok(true) => {}
+            Ok(false) => process::exit(Exit::Failure as i32),
+            Err(e) => reporter.fatal(&input, &*e),
+        },
+        Commands::Completions { shell } => {
+            clap_complete::generate(shell, &mut Cli::command(), "redox", &mut io::stdout());
+        }
+        Commands::Man => {
+            if let Err(e) = clap_mangen::Man::new(Cli::command()).render(&mut io::stdout()) {
+                reporter.fatal(Path::new("-"), &e)
             }
         }
     }
+
+    if cli.warnings_as_errors && WARNED.load(Ordering::Relaxed) {
+        process::exit(Exit::Warnings as i32);
+    }
 }
 
-fn transpile_file(
-    input: PathBuf,
-    output: Option<PathBuf>,
+/// How `reduce` treats each file
+struct ReduceSettings {
+    options: ReduceOptions,
     validate: bool,
+    /// Compare with the output files instead of writing them
+    check: bool,
     verbose: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Read input file
-    let source = fs::read_to_string(&input)
-        .map_err(|e| format!("Failed to read input file '{}': {}", input.display(), e))?;
+}
 
-    if verbose {
+fn transpile_file(
+    input: &Path,
+    output: Option<PathBuf>,
+    settings: &ReduceSettings,
+    reporter: Reporter,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let source = read_input(input)?;
+
+    if settings.verbose {
         eprintln!("Transpiling: {}", input.display());
         eprintln!("Source size: {} bytes", source.len());
     }
 
+    // Provenance notes name the file each item comes from
+    let named;
+    let options = if settings.options.provenance && input != Path::new("-") {
+        named = ReduceOptions {
+            source_path: Some(input.display().to_string()),
+            ..settings.options.clone()
+        };
+        &named
+    } else {
+        &settings.options
+    };
+
     // Transpile
-    let iron_code = match redox::transpile(&source) {
+    let iron_code = match redox::transpile_with_options(&source, options) {
         Ok(code) => code,
-        Err(e) => {
-            return Err(format!("Transpilation failed: {}", e).into());
+        Err(error) => {
+            return Err(StageError {
+                stage: "Transpilation",
+                error,
+            }
+            .into());
         }
     };
 
-    if verbose {
+    if settings.verbose {
         eprintln!("Output size: {} bytes", iron_code.len());
     }
 
     // Validate if requested
-    if validate {
-        if !redox::validate_iron(&iron_code) {
-            eprintln!("Warning: Output contains prohibited symbols!");
-            eprintln!("This indicates a bug in the transpiler.");
-        } else if verbose {
+    if settings.validate {
+        // Verbatim blocks carry Rust symbols on purpose
+        let mut symbols = redox::prohibited_symbols(&iron_code);
+        symbols.retain(|symbol| symbol.severity == Severity::Error);
+        if !symbols.is_empty() {
+            reporter.prohibited_symbols(input, &symbols);
+        } else if settings.verbose {
             eprintln!("Validation passed: No prohibited symbols found");
         }
     }
 
-    // Output result
-    match output {
-        Some(path) => {
-            fs::write(&path, iron_code)
-                .map_err(|e| format!("Failed to write output file '{}': {}", path.display(), e))?;
-            if verbose {
-                eprintln!("Output written to: {}", path.display());
+    if settings.check {
+        return check_output(output, &iron_code, settings.verbose);
+    }
+    write_output(output, &iron_code, settings.verbose)
+}
+
+/// Compare content with what an output file holds, failing with `OutOfDate` if writing
+/// it would change the file
+fn check_output(
+    output: Option<PathBuf>,
+    content: &str,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let Some(path) = output.filter(|path| path != Path::new("-")) else {
+        return Err("--check needs an output file or directory to compare with".into());
+    };
+    let existing = match fs::read_to_string(&path) {
+        Ok(existing) => Some(existing),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+        Err(error) => {
+            return Err(IoError {
+                action: format!("Failed to read output file '{}'", path.display()),
+                error,
             }
+            .into());
         }
-        None => {
-            print!("{}", iron_code);
+    };
+    if existing.as_deref() != Some(content) {
+        return Err(OutOfDate {
+            exists: existing.is_some(),
+            output: path,
         }
+        .into());
+    }
+    if verbose {
+        eprintln!("Up to date: {}", path.display());
     }
-
     Ok(())
 }
 
-fn validate_file(input: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-    let content = fs::read_to_string(&input)
-        .map_err(|e| format!("Failed to read file '{}': {}", input.display(), e))?;
+/// Pair each input file with where its Iron goes under `out_dir`: the files of a
+/// directory matching `pattern` at the same relative paths, and single files at the top
+///
+/// Fails before anything is written if two inputs would share an output, or an output
+/// would overwrite an input.
+fn output_paths(
+    inputs: &[PathBuf],
+    out_dir: &Path,
+    pattern: &str,
+    extension: &str,
+) -> Result<Vec<(PathBuf, PathBuf)>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            for file in matching_files(input, pattern)? {
+                let relative = file.strip_prefix(input).unwrap_or(&file);
+                let output = out_dir.join(relative).with_extension(extension);
+                files.push((file, output));
+            }
+        } else {
+            let name = input
+                .file_name()
+                .filter(|_| input != Path::new("-"))
+                .ok_or_else(|| format!("'{}' has no file name to write", input.display()))?;
+            let output = out_dir.join(name).with_extension(extension);
+            files.push((input.clone(), output));
+        }
+    }
 
-    if redox::validate_iron(&content) {
-        println!("✓ Valid Iron code");
-        Ok(())
+    let sources: HashSet<PathBuf> = files
+        .iter()
+        .filter_map(|(source, _)| fs::canonicalize(source).ok())
+        .collect();
+    let mut written: HashMap<&Path, &Path> = HashMap::new();
+    for (source, output) in &files {
+        if let Some(other) = written.insert(output, source) {
+            return Err(format!(
+                "Both '{}' and '{}' would be written to '{}'",
+                other.display(),
+                source.display(),
+                output.display()
+            )
+            .into());
+        }
+        if fs::canonicalize(output).is_ok_and(|output| sources.contains(&output)) {
+            return Err(format!(
+                "Writing '{}' would overwrite an input file",
+                output.display()
+            )
+            .into());
+        }
+    }
+    Ok(files)
+}
+
+/// An input file and the output it is reduced to, named by its input in progress
+#[derive(Clone, Copy)]
+struct OutputJob<'a> {
+    input: &'a Path,
+    output: &'a Path,
+}
+
+impl AsRef<Path> for OutputJob<'_> {
+    fn as_ref(&self) -> &Path {
+        self.input
+    }
+}
+
+/// Reduce each (input, output) pair, creating the directories the outputs need
+fn transpile_files(
+    files: &[(PathBuf, PathBuf)],
+    jobs: NonZeroUsize,
+    settings: &ReduceSettings,
+    reporter: Reporter,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let started = Instant::now();
+    // An input can repeat, named both directly and through its directory, so each job
+    // carries its own output
+    let file_jobs: Vec<OutputJob> = files
+        .iter()
+        .map(|(input, output)| OutputJob { input, output })
+        .collect();
+    let label = if settings.check {
+        "Checking"
     } else {
-        Err("Invalid Iron code: contains prohibited symbols".into())
+        "Reducing"
+    };
+    let progress = reporter.progress(label, files.len());
+    let results = for_each_file(&file_jobs, jobs, &progress, |job| {
+        let OutputJob {
+            input: file,
+            output,
+        } = *job;
+        if let Some(parent) = output.parent().filter(|_| !settings.check) {
+            fs::create_dir_all(parent).map_err(|error| IoError {
+                action: format!("Failed to create directory '{}'", parent.display()),
+                error,
+            })?;
+        }
+        transpile_file(file, Some(output.to_path_buf()), settings, reporter)
+    });
+
+    // Keep going past failures so one bad file does not stop a whole project
+    let mut failures = 0;
+    let mut out_of_date = 0;
+    for ((file, _), result) in files.iter().zip(results) {
+        match result {
+            Err(e) if e.is::<OutOfDate>() => {
+                reporter.file_error(file, &*e);
+                out_of_date += 1;
+            }
+            Err(e) => {
+                reporter.file_error(file, &*e);
+                failures += 1;
+            }
+            Ok(()) => {}
+        }
     }
+
+    let done = files.len() - failures - out_of_date;
+    let mut counts = vec![("reduced", done), ("failed", failures)];
+    if settings.check {
+        counts = vec![
+            ("unchanged", done),
+            ("changed", out_of_date),
+            ("failed", failures),
+        ];
+    }
+    counts.push(("total", files.len()));
+    reporter.summary(&counts, started);
+    if failures > 0 {
+        return Err(format!("{} of {} files failed to reduce", failures, files.len()).into());
+    }
+    if out_of_date > 0 {
+        return Err(format!(
+            "{} of {} output files would change",
+            out_of_date,
+            files.len()
+        )
+        .into());
+    }
+    Ok(())
 }
 
-fn oxidize_file(
-    input: PathBuf,
-    output: Option<PathBuf>,
-    verbose: bool,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Read input file
-    let source = fs::read_to_string(&input)
-        .map_err(|e| format!("Failed to read input file '{}': {}", input.display(), e))?;
+/// Run `task` on each file with up to `jobs` threads, returning the results in file order
+fn for_each_file<F: AsRef<Path> + Sync, T: Send>(
+    files: &[F],
+    jobs: NonZeroUsize,
+    progress: &Progress,
+    task: impl Fn(&F) -> T + Sync,
+) -> Vec<T> {
+    let results = redox::parallel::map_in_order(
+        files,
+        jobs,
+        || (),
+        |_, file| {
+            let result = task(file);
+            progress.file_done(file.as_ref());
+            result
+        },
+    );
+    progress.clear();
+    results
+}
 
-    if verbose {
-        eprintln!("Oxidizing: {}", input.display());
-        eprintln!("Source size: {} bytes", source.len());
+/// A status line on stderr counting the files a batch job has finished
+///
+/// It is only drawn on a terminal, so logs and pipes get no control characters.
+struct Progress {
+    label: &'static str,
+    total: usize,
+    done: AtomicUsize,
+    visible: bool,
+}
+
+impl Progress {
+    fn file_done(&self, file: &Path) {
+        let done = self.done.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.visible {
+            let mut stderr = io::stderr().lock();
+            let _ = write!(
+                stderr,
+                "\r\x1b[K{} [{}/{}] {}",
+                self.label,
+                done,
+                self.total,
+                file.display()
+            );
+            let _ = stderr.flush();
+        }
     }
 
-    // Oxidize
-    let rust_code = match redox::oxidize(&source) {
-        Ok(code) => code,
-        Err(e) => {
-            return Err(format!("Oxidation failed: {}", e).into());
+    /// Erase the status line, before results and errors are printed
+    fn clear(&self) {
+        if self.visible {
+            eprint!("\r\x1b[K");
         }
+    }
+}
+
+/// Load a type dictionary given with --types, exiting if it cannot be used
+fn load_types(path: &Path, reporter: Reporter) -> TypeDictionary {
+    TypeDictionary::load(path).unwrap_or_else(|e| reporter.fatal(path, &e))
+}
+
+/// One job per CPU, or a single job when that cannot be determined
+fn default_jobs() -> NonZeroUsize {
+    thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+}
+
+/// The files under `dir` whose path relative to it matches the glob `pattern`
+fn matching_files(
+    dir: &Path,
+    pattern: &str,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error + Send + Sync>> {
+    let glob = glob::Pattern::new(pattern)
+        .map_err(|e| format!("Invalid glob pattern '{}': {}", pattern, e))?;
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
     };
 
-    if verbose {
-        eprintln!("Output size: {} bytes", rust_code.len());
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    files.retain(|file| {
+        let relative = file.strip_prefix(dir).unwrap_or(file);
+        glob.matches_path_with(relative, options)
+    });
+    if files.is_empty() {
+        return Err(format!("No files in '{}' match '{}'", dir.display(), pattern).into());
     }
+    Ok(files)
+}
 
-    // Output result
-    match output {
-        Some(path) => {
-            fs::write(&path, rust_code)
-                .map_err(|e| format!("Failed to write output file '{}': {}", path.display(), e))?;
-            if verbose {
-                eprintln!("Output written to: {}", path.display());
+/// Every file under `dir`, recursively, in a stable order
+fn collect_files(
+    dir: &Path,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let mut entries = fs::read_dir(dir)
+        .map_err(|error| IoError {
+            action: format!("Failed to read directory '{}'", dir.display()),
+            error,
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.path());
+
+    for entry in entries {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Format an Iron file, or with `check` only report whether it is formatted
+fn format_file(
+    input: &Path,
+    output: Option<PathBuf>,
+    check: bool,
+    case_insensitive_keywords: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let source = read_input(input)?;
+
+    let formatted = match redox::format_iron(&source, case_insensitive_keywords) {
+        Ok(formatted) => formatted,
+        Err(error) => {
+            return Err(StageError {
+                stage: "Formatting",
+                error,
             }
+            .into());
         }
-        None => {
-            print!("{}", rust_code);
+    };
+
+    if check {
+        if formatted != source {
+            return Err(format!("'{}' is not formatted", input.display()).into());
         }
+        return Ok(());
     }
+    write_output(output, &formatted, false)
+}
 
+/// Rewrite the code blocks of a Markdown document tagged with one of `from` as `to`
+/// blocks, reporting each block that fails and keeping it as it was
+fn convert_markdown(
+    input: &Path,
+    output: Option<PathBuf>,
+    from: &[&str],
+    to: &str,
+    reporter: Reporter,
+    convert: impl FnMut(&str) -> Result<String, TranspileError>,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let markdown = read_input(input)?;
+    let (converted, errors) = redox::markdown::convert_code_blocks(&markdown, from, to, convert);
+
+    for block in &errors {
+        let error: Box<dyn std::error::Error> =
+            format!("code block at line {}: {}", block.line, block.error).into();
+        reporter.file_error(input, &*error);
+    }
+    write_output(output, &converted, false)?;
+    if !errors.is_empty() {
+        return Err(format!("{} code blocks failed to convert", errors.len()).into());
+    }
     Ok(())
 }
+
+/// Sizes and item counts of reduced Rust source
+#[derive(Default)]
+struct ReductionStats {
+    files: usize,
+    items: BTreeMap<&'static str, usize>,
+    verbatim: usize,
+    input_bytes: usize,
+    output_bytes: usize,
+}
+
+impl ReductionStats {
+    /// Reduce one source file and measure the result
+    fn of_source(source: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let file = syn::parse_file(source).map_err(redox::TranspileError::from)?;
+        let iron = redox::transpile_file(&file)?;
+
+        let mut stats = ReductionStats {
+            files: 1,
+            input_bytes: source.len(),
+            output_bytes: iron.len(),
+            ..Default::default()
+        };
+        stats.count_items(&file.items);
+        // Items the reducer could not express are carried as verbatim Rust
+        stats.verbatim = Tokenizer::new(&iron)
+            .tokenize()
+            .iter()
+            .filter(|token| matches!(token, Token::Verbatim | Token::VerbatimBlock(_)))
+            .count();
+        Ok(stats)
+    }
+
+    fn count_items(&mut self, items: &[syn::Item]) {
+        for item in items {
+            let kind = match item {
+                syn::Item::Fn(_) => "fn",
+                syn::Item::Struct(_) => "struct",
+                syn::Item::Enum(_) => "enum",
+                syn::Item::Impl(_) => "impl",
+                syn::Item::Trait(_) => "trait",
+                syn::Item::Use(_) => "use",
+                syn::Item::Const(_) => "const",
+                syn::Item::Static(_) => "static",
+                syn::Item::Type(_) => "type",
+                syn::Item::Mod(_) => "mod",
+                syn::Item::Macro(_) => "macro",
+                _ => "other",
+            };
+            *self.items.entry(kind).or_default() += 1;
+            if let syn::Item::Mod(module) = item
+                && let Some((_, nested)) = &module.content
+            {
+                self.count_items(nested);
+            }
+        }
+    }
+
+    fn add(&mut self, other: &ReductionStats) {
+        self.files += other.files;
+        for (kind, count) in &other.items {
+            *self.items.entry(kind).or_default() += count;
+        }
+        self.verbatim += other.verbatim;
+        self.input_bytes += other.input_bytes;
+        self.output_bytes += other.output_bytes;
+    }
+}
+
+impl std::fmt::Display for ReductionStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total: usize = self.items.values().sum();
+        let kinds: Vec<String> = self
+            .items
+            .iter()
+            .map(|(kind, count)| format!("{} {}", kind, count))
+            .collect();
+        write!(
+            f,
+            "{} items ({}), {} verbatim, {} -> {} bytes",
+            total,
+            kinds.join(", "),
+            self.verbatim,
+            self.input_bytes,
+            self.output_bytes
+        )?;
+        if self.input_bytes > 0 {
+            write!(
+                f,
+                " ({:.2}x)",
+                self.output_bytes as f64 / self.input_bytes as f64
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The input files, with directories expanded to the files in them matching `pattern`
+fn input_files(
+    inputs: &[PathBuf],
+    pattern: &str,
+) -> Result<Vec<PathBuf>, Box<dyn std::error::Error + Send + Sync>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if input.is_dir() {
+            files.extend(matching_files(input, pattern)?);
+        } else {
+            files.push(input.clone());
+        }
+    }
+    Ok(files)
+}
+
+/// Syntax nodes reduced natively and carried as verbatim Rust, by category and kind
+#[derive(Default)]
+struct Coverage {
+    /// (native, verbatim) per kind, for items, statements, and expressions
+    counts: [BTreeMap<&'static str, (usize, usize)>; 3],
+}
+
+const COVERAGE_CATEGORIES: [&str; 3] = ["items", "statements", "expressions"];
+
+impl Coverage {
+    fn of_source(source: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let file = syn::parse_file(source).map_err(redox::TranspileError::from)?;
+        let mut counter = CoverageCounter {
+            verbatim_items: redox::verbatim_item_locations(&file),
+            in_verbatim: false,
+            coverage: Coverage::default(),
+        };
+        syn::visit::visit_file(&mut counter, &file);
+        Ok(counter.coverage)
+    }
+
+    fn record(&mut self, category: usize, kind: &'static str, verbatim: bool) {
+        let (native, fallback) = self.counts[category].entry(kind).or_default();
+        if verbatim {
+            *fallback += 1;
+        } else {
+            *native += 1;
+        }
+    }
+
+    fn add(&mut self, other: &Coverage) {
+        for (counts, other) in self.counts.iter_mut().zip(&other.counts) {
+            for (kind, (native, verbatim)) in other {
+                let entry = counts.entry(kind).or_default();
+                entry.0 += native;
+                entry.1 += verbatim;
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Coverage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let percent = |native: usize, total: usize| {
+            if total == 0 {
+                100.0
+            } else {
+                native as f64 * 100.0 / total as f64
+            }
+        };
+        for (name, counts) in COVERAGE_CATEGORIES.iter().zip(&self.counts) {
+            let native: usize = counts.values().map(|(native, _)| native).sum();
+            let total: usize = counts
+                .values()
+                .map(|(native, verbatim)| native + verbatim)
+                .sum();
+            writeln!(
+                f,
+                "{}: {} of {} native ({:.1}%)",
+                name,
+                native,
+                total,
+                percent(native, total)
+            )?;
+            for (kind, (native, verbatim)) in counts {
+                let total = native + verbatim;
+                writeln!(
+                    f,
+                    "  {:<14} {:>6} / {:<6} {:>5.1}%",
+                    kind,
+                    native,
+                    total,
+                    percent(*native, total)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Counts syntax nodes, marking everything inside a verbatim item as verbatim
+struct CoverageCounter {
+    verbatim_items: Vec<(usize, usize)>,
+    in_verbatim: bool,
+    coverage: Coverage,
+}
+
+impl<'ast> syn::visit::Visit<'ast> for CoverageCounter {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        let start = item.span().start();
+        let outer = self.in_verbatim;
+        self.in_verbatim |= self
+            .verbatim_items
+            .contains(&(start.line, start.column + 1));
+        self.coverage.record(0, item_kind(item), self.in_verbatim);
+        syn::visit::visit_item(self, item);
+        self.in_verbatim = outer;
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast syn::Stmt) {
+        let kind = match stmt {
+            syn::Stmt::Local(_) => "let",
+            syn::Stmt::Item(_) => "item",
+            syn::Stmt::Expr(_, None) => "expression",
+            syn::Stmt::Expr(_, Some(_)) => "expression;",
+            syn::Stmt::Macro(_) => "macro",
+        };
+        self.coverage.record(1, kind, self.in_verbatim);
+        syn::visit::visit_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        self.coverage.record(2, expr_kind(expr), self.in_verbatim);
+        syn::visit::visit_expr(self, expr);
+    }
+}
+
+fn item_kind(item: &syn::Item) -> &'static str {
+    match item {
+        syn::Item::Const(_) => "const",
+        syn::Item::Enum(_) => "enum",
+        syn::Item::ExternCrate(_) => "extern crate",
+        syn::Item::Fn(_) => "fn",
+        syn::Item::ForeignMod(_) => "extern block",
+        syn::Item::Impl(_) => "impl",
+        syn::Item::Macro(_) => "macro",
+        syn::Item::Mod(_) => "mod",
+        syn::Item::Static(_) => "static",
+        syn::Item::Struct(_) => "struct",
+        syn::Item::Trait(_) => "trait",
+        syn::Item::TraitAlias(_) => "trait alias",
+        syn::Item::Type(_) => "type",
+        syn::Item::Union(_) => "union",
+        syn::Item::Use(_) => "use",
+        _ => "other",
+    }
+}
+
+fn expr_kind(expr: &syn::Expr) -> &'static str {
+    match expr {
+        syn::Expr::Array(_) => "array",
+        syn::Expr::Assign(_) => "assign",
+        syn::Expr::Async(_) => "async",
+        syn::Expr::Await(_) => "await",
+        syn::Expr::Binary(_) => "binary",
+        syn::Expr::Block(_) => "block",
+        syn::Expr::Break(_) => "break",
+        syn::Expr::Call(_) => "call",
+        syn::Expr::Cast(_) => "cast",
+        syn::Expr::Closure(_) => "closure",
+        syn::Expr::Const(_) => "const",
+        syn::Expr::Continue(_) => "continue",
+        syn::Expr::Field(_) => "field",
+        syn::Expr::ForLoop(_) => "for",
+        syn::Expr::Group(_) => "group",
+        syn::Expr::If(_) => "if",
+        syn::Expr::Index(_) => "index",
+        syn::Expr::Infer(_) => "infer",
+        syn::Expr::Let(_) => "let",
+        syn::Expr::Lit(_) => "literal",
+        syn::Expr::Loop(_) => "loop",
+        syn::Expr::Macro(_) => "macro",
+        syn::Expr::Match(_) => "match",
+        syn::Expr::MethodCall(_) => "method call",
+        syn::Expr::Paren(_) => "paren",
+        syn::Expr::Path(_) => "path",
+        syn::Expr::Range(_) => "range",
+        syn::Expr::RawAddr(_) => "raw address",
+        syn::Expr::Reference(_) => "reference",
+        syn::Expr::Repeat(_) => "repeat",
+        syn::Expr::Return(_) => "return",
+        syn::Expr::Struct(_) => "struct",
+        syn::Expr::Try(_) => "try",
+        syn::Expr::TryBlock(_) => "try block",
+        syn::Expr::Tuple(_) => "tuple",
+        syn::Expr::Unary(_) => "unary",
+        syn::Expr::Unsafe(_) => "unsafe",
+        syn::Expr::While(_) => "while",
+        syn::Expr::Yield(_) => "yield",
+        _ => "other",
+    }
+}
+
+/// Print how many syntax nodes of each kind the inputs reduce natively, all files together
+fn coverage_files(
+    inputs: &[PathBuf],
+    pattern: &str,
+    jobs: NonZeroUsize,
+    reporter: Reporter,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let files = input_files(inputs, pattern)?;
+    let progress = reporter.progress("Measuring", files.len());
+    let results = for_each_file(&files, jobs, &progress, |file| {
+        read_input(file).and_then(|source| Coverage::of_source(&source))
+    });
+
+    let mut total = Coverage::default();
+    let mut failures = 0;
+    for (file, result) in files.iter().zip(results) {
+        match result {
+            Ok(coverage) => total.add(&coverage),
+            Err(e) => {
+                println!("{}: failed: {}", file.display(), e);
+                failures += 1;
+            }
+        }
+    }
+
+    println!("{} files ({} failed)", files.len(), failures);
+    print!("{}", total);
+    Ok(())
+}
+
+/// One JSON record per top-level item of a Rust file: its source, its Iron, and metadata
+fn dataset_records(
+    path: &Path,
+    source: &str,
+) -> Result<Vec<serde_json::Value>, Box<dyn std::error::Error + Send + Sync>> {
+    let file = syn::parse_file(source).map_err(redox::TranspileError::from)?;
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+        .collect();
+    // Span positions are lines and character columns; records need byte offsets
+    let offset = |position: proc_macro2::LineColumn| {
+        let line_start = line_starts[(position.line - 1).min(line_starts.len() - 1)];
+        source[line_start..]
+            .char_indices()
+            .nth(position.column)
+            .map_or(source.len(), |(index, _)| line_start + index)
+    };
+
+    let mut records = Vec::new();
+    for item in &file.items {
+        let span = item.span();
+        let item_file = syn::File {
+            shebang: None,
+            attrs: Vec::new(),
+            items: vec![item.clone()],
+        };
+        // Items that do not reduce on their own are left out rather than recorded empty
+        let Ok(iron) = redox::transpile_file(&item_file) else {
+            continue;
+        };
+        let start = span.start();
+        let verbatim =
+            redox::verbatim_item_locations(&item_file).contains(&(start.line, start.column + 1));
+        let iron_tokens = Tokenizer::new(&iron)
+            .tokenize()
+            .iter()
+            .filter(|token| !matches!(token, Token::NewLine | Token::Indent(_) | Token::EndOfFile))
+            .count();
+
+        records.push(serde_json::json!({
+            "path": path.display().to_string(),
+            "line": start.line,
+            "kind": item_kind(item),
+            "rust": &source[offset(start)..offset(span.end())],
+            "iron": iron.trim_end(),
+            "rust_tokens": count_tokens(item.to_token_stream()),
+            "iron_tokens": iron_tokens,
+            "verbatim": verbatim,
+        }));
+    }
+    Ok(records)
+}
+
+/// Rust tokens in a stream, counting both delimiters of each group and multi-character
+/// operators (`->`, `::`) as one token
+fn count_tokens(tokens: proc_macro2::TokenStream) -> usize {
+    tokens
+        .into_iter()
+        .map(|tree| match tree {
+            proc_macro2::TokenTree::Group(group) => 2 + count_tokens(group.stream()),
+            proc_macro2::TokenTree::Punct(punct) => {
+                usize::from(punct.spacing() == proc_macro2::Spacing::Alone)
+            }
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Write the dataset records of every input file as JSON lines
+fn dataset_files(
+    inputs: &[PathBuf],
+    out: Option<PathBuf>,
+    pattern: &str,
+    jobs: NonZeroUsize,
+    reporter: Reporter,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let files = input_files(inputs, pattern)?;
+    let progress = reporter.progress("Exporting", files.len());
+    let results = for_each_file(&files, jobs, &progress, |file| {
+        read_input(file).and_then(|source| dataset_records(file, &source))
+    });
+
+    let mut lines = String::new();
+    let mut records = 0;
+    for (file, result) in files.iter().zip(results) {
+        match result {
+            Ok(file_records) => {
+                for record in &file_records {
+                    lines.push_str(&record.to_string());
+                    lines.push('\n');
+                }
+                records += file_records.len();
+            }
+            Err(e) => reporter.file_error(file, &*e),
+        }
+    }
+
+    write_output(out, &lines, false)?;
+    if !reporter.quiet {
+        eprintln!("Exported {} items from {} files", records, files.len());
+    }
+    Ok(())
+}
+
+/// Print reduction statistics for each input file and for all of them together
+fn stats_files(
+    inputs: &[PathBuf],
+    pattern: &str,
+    jobs: NonZeroUsize,
+    reporter: Reporter,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let files = input_files(inputs, pattern)?;
+    let progress = reporter.progress("Measuring", files.len());
+    let results = for_each_file(&files, jobs, &progress, |file| {
+        read_input(file).and_then(|source| ReductionStats::of_source(&source))
+    });
+
+    let mut total = ReductionStats::default();
+    let mut failures = 0;
+    for (file, result) in files.iter().zip(results) {
+        match result {
+            Ok(stats) => {
+                println!("{}: {}", file.display(), stats);
+                total.add(&stats);
+            }
+            Err(e) => {
+                println!("{}: failed: {}", file.display(), e);
+                failures += 1;
+            }
+        }
+    }
+
+    println!(
+        "total: {} files ({} failed), {}",
+        total.files + failures,
+        failures,
+        total
+    );
+    Ok(())
+}
+
+/// Round-trip a Rust file through Iron and print a report of each stage, followed by a
+/// diff of the pretty-printed original and result, or with `semantic` the structural
+/// differences. Returns whether every stage passed and the code came back unchanged.
+///
+/// In JSON the report is one object with a member for each stage that ran.
+fn roundtrip_file(
+    input: &Path,
+    compile: bool,
+    edition: Edition,
+    show_iron: bool,
+    semantic: bool,
+    format: Format,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let source = read_input(input)?;
+    let text = format == Format::Text;
+    let mut report = serde_json::json!({ "file": input.display().to_string() });
+
+    let iron = match redox::transpile(&source) {
+        Ok(iron) => {
+            if text {
+                println!("reduce: ok ({} lines of Iron)", iron.lines().count());
+            }
+            report["reduce"] = serde_json::json!({ "ok": true, "lines": iron.lines().count() });
+            iron
+        }
+        Err(e) => {
+            if text {
+                println!("reduce: failed\n  {}", e);
+            }
+            report["reduce"] = serde_json::json!({
+                "ok": false,
+                "error": Diagnostic::transpile_error(input, &e).to_json(),
+            });
+            return Ok(finish_roundtrip(report, false, format));
+        }
+    };
+    if show_iron {
+        if text {
+            println!("{}", iron);
+        }
+        report["iron"] = serde_json::json!(iron);
+    }
+
+    let options = OxidizeOptions {
+        edition,
+        ..Default::default()
+    };
+    let roundtrip = match redox::oxidize_with_warnings(&iron, &options) {
+        Ok((rust_code, warnings)) => {
+            if text {
+                println!("oxidize: ok ({} warnings)", warnings.len());
+                for warning in &warnings {
+                    println!("  {}", warning);
+                }
+            }
+            report["oxidize"] = serde_json::json!({
+                "ok": true,
+                "warnings": warnings
+                    .iter()
+                    .map(|warning| Diagnostic::warning(input, warning).to_json())
+                    .collect::<Vec<_>>(),
+            });
+            rust_code
+        }
+        Err(e) => {
+            if text {
+                println!("oxidize: failed\n  {}", e);
+            }
+            report["oxidize"] = serde_json::json!({
+                "ok": false,
+                "error": Diagnostic::transpile_error(input, &e).to_json(),
+            });
+            return Ok(finish_roundtrip(report, false, format));
+        }
+    };
+
+    let mut passed = true;
+    if compile {
+        for (name, code) in [("original", &source), ("roundtrip", &roundtrip)] {
+            match compile_rust(code, edition)? {
+                None => {
+                    if text {
+                        println!("compile {}: ok", name);
+                    }
+                    report["compile"][name] = serde_json::json!({ "ok": true });
+                }
+                Some(errors) => {
+                    if text {
+                        println!("compile {}: failed", name);
+                        for line in errors.lines() {
+                            println!("  {}", line);
+                        }
+                    }
+                    report["compile"][name] = serde_json::json!({ "ok": false, "errors": errors });
+                    passed = false;
+                }
+            }
+        }
+    }
+
+    if semantic {
+        let original = syn::parse_file(&source)?;
+        let roundtripped = syn::parse_file(&roundtrip)?;
+        let differences = redox::semantic_diff::semantic_diff(&original, &roundtripped);
+        if text {
+            if differences.is_empty() {
+                println!("result: identical");
+            } else {
+                println!("result: differs");
+                for difference in &differences {
+                    println!("  {}", difference);
+                }
+            }
+        }
+        report["identical"] = serde_json::json!(differences.is_empty());
+        report["differences"] = differences
+            .iter()
+            .map(|difference| serde_json::json!(difference.to_string()))
+            .collect();
+        passed &= differences.is_empty();
+        return Ok(finish_roundtrip(report, passed, format));
+    }
+
+    // Compare the pretty-printed forms so layout differences do not show up
+    let original = normalize_rust(&source);
+    let result = normalize_rust(&roundtrip);
+    report["identical"] = serde_json::json!(original == result);
+    if original == result {
+        if text {
+            println!("result: identical");
+        }
+    } else {
+        let diff = similar::TextDiff::from_lines(&original, &result)
+            .unified_diff()
+            .context_radius(2)
+            .header("original", "roundtrip")
+            .to_string();
+        if text {
+            println!("result: differs");
+            print!("{}", diff);
+        }
+        report["diff"] = serde_json::json!(diff);
+        passed = false;
+    }
+
+    Ok(finish_roundtrip(report, passed, format))
+}
+
+/// Print a round trip's JSON report once it has passed or failed, returning whether it
+/// passed
+fn finish_roundtrip(mut report: serde_json::Value, passed: bool, format: Format) -> bool {
+    if format == Format::Json {
+        report["passed"] = serde_json::json!(passed);
+        println!("{}", report);
+    }
+    passed
+}
+
+/// The Rust source of one item (with the comments and blank lines before it) and its Iron
+struct AlignedItem {
+    first_line: usize,
+    rust: Vec<String>,
+    iron: Vec<String>,
+}
+
+/// Split Rust source into its items, each paired with the Iron it reduces to
+fn align_items(source: &str) -> Result<Vec<AlignedItem>, Box<dyn std::error::Error + Send + Sync>> {
+    let file = syn::parse_file(source).map_err(|e| {
+        let start = e.span().start();
+        format!("line {}, column {}: {}", start.line, start.column + 1, e)
+    })?;
+    let lines: Vec<&str> = source.lines().collect();
+
+    let mut aligned = Vec::new();
+    let mut next_line = 0;
+    for (index, item) in file.items.iter().enumerate() {
+        // The last item also takes whatever trails it
+        let end = if index + 1 == file.items.len() {
+            lines.len()
+        } else {
+            item.span().end().line.min(lines.len())
+        };
+        let item_file = syn::File {
+            shebang: None,
+            attrs: Vec::new(),
+            items: vec![item.clone()],
+        };
+        let iron = match redox::transpile_file(&item_file) {
+            Ok(iron) => iron,
+            Err(e) => format!("error: {}", e),
+        };
+
+        let rust = &lines[next_line.min(end)..end];
+        let blank = rust
+            .iter()
+            .take_while(|line| line.trim().is_empty())
+            .count();
+        aligned.push(AlignedItem {
+            first_line: next_line + blank + 1,
+            rust: trim_blank_lines(rust[blank..].iter().copied()),
+            iron: trim_blank_lines(iron.lines()),
+        });
+        next_line = end;
+    }
+    Ok(aligned)
+}
+
+/// The lines without blank ones at the end
+fn trim_blank_lines<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut lines: Vec<String> = lines.map(str::to_string).collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+/// Print Rust source next to its Iron reduction, aligned item by item, or in JSON each
+/// item's first line with its Rust and Iron lines
+fn diff_file(
+    input: &Path,
+    unified: bool,
+    width: usize,
+    format: Format,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let source = read_input(input)?;
+    let items = align_items(&source)?;
+
+    if format == Format::Json {
+        let items: Vec<serde_json::Value> = items
+            .iter()
+            .map(|item| {
+                serde_json::json!({
+                    "line": item.first_line,
+                    "rust": item.rust,
+                    "iron": item.iron,
+                })
+            })
+            .collect();
+        let diff = serde_json::json!({ "file": input.display().to_string(), "items": items });
+        println!("{}", diff);
+        return Ok(());
+    }
+
+    let column = width.saturating_sub(3) / 2;
+    for (index, item) in items.iter().enumerate() {
+        if unified {
+            println!(
+                "@@ line {} ({} Rust, {} Iron) @@",
+                item.first_line,
+                item.rust.len(),
+                item.iron.len()
+            );
+            for line in &item.rust {
+                println!("- {}", line);
+            }
+            for line in &item.iron {
+                println!("+ {}", line);
+            }
+            continue;
+        }
+
+        if index > 0 {
+            println!("{}─┼─{}", "─".repeat(column), "─".repeat(column));
+        }
+        for row in 0..item.rust.len().max(item.iron.len()) {
+            let rust = item.rust.get(row).map_or("", String::as_str);
+            let iron = item.iron.get(row).map_or("", String::as_str);
+            let row = format!(
+                "{} │ {}",
+                fit_column(rust, column),
+                fit_column(iron, column)
+            );
+            println!("{}", row.trim_end());
+        }
+    }
+    Ok(())
+}
+
+/// The tokenizer of a language model, used to count the tokens of text
+#[cfg(feature = "tokens")]
+enum LlmTokenizer {
+    Bpe(tiktoken_rs::CoreBPE),
+    HuggingFace(Box<tokenizers::Tokenizer>),
+}
+
+#[cfg(feature = "tokens")]
+impl LlmTokenizer {
+    /// A built-in BPE by name (`cl100k`, `o200k`), or a `tokenizer.json` by path
+    fn load(name: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let failed =
+            |e: &dyn std::fmt::Display| format!("Failed to load tokenizer '{}': {}", name, e);
+        match name.trim_end_matches("_base") {
+            "cl100k" => tiktoken_rs::cl100k_base()
+                .map(Self::Bpe)
+                .map_err(|e| failed(&e).into()),
+            "o200k" => tiktoken_rs::o200k_base()
+                .map(Self::Bpe)
+                .map_err(|e| failed(&e).into()),
+            _ => tokenizers::Tokenizer::from_file(name)
+                .map(|tokenizer| Self::HuggingFace(Box::new(tokenizer)))
+                .map_err(|e| failed(&e).into()),
+        }
+    }
+
+    fn count(&self, text: &str) -> Result<usize, Box<dyn std::error::Error + Send + Sync>> {
+        match self {
+            Self::Bpe(bpe) => Ok(bpe.encode_ordinary(text).len()),
+            Self::HuggingFace(tokenizer) => Ok(tokenizer.encode(text, false)?.len()),
+        }
+    }
+}
+
+/// Token counts of Rust and its Iron, and how many Iron tokens each Rust one became
+#[cfg(feature = "tokens")]
+fn token_counts(rust: usize, iron: usize) -> serde_json::Value {
+    let ratio = (rust > 0).then(|| iron as f64 / rust as f64);
+    serde_json::json!({ "rust": rust, "iron": iron, "ratio": ratio })
+}
+
+#[cfg(feature = "tokens")]
+fn format_token_counts(counts: &serde_json::Value) -> String {
+    let ratio = match counts["ratio"].as_f64() {
+        Some(ratio) => format!("{:.2}x", ratio),
+        None => "-".to_string(),
+    };
+    format!(
+        "{} Rust, {} Iron ({})",
+        counts["rust"], counts["iron"], ratio
+    )
+}
+
+/// Count the tokens of each file and its items, and of all files together, with every
+/// tokenizer
+#[cfg(feature = "tokens")]
+fn tokens_files(
+    inputs: &[PathBuf],
+    tokenizer_names: &[String],
+    pattern: &str,
+    reporter: Reporter,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let tokenizers = tokenizer_names
+        .iter()
+        .map(|name| LlmTokenizer::load(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let files = input_files(inputs, pattern)?;
+
+    let mut totals = vec![(0, 0); tokenizers.len()];
+    for file in &files {
+        let counted = (|| -> Result<_, Box<dyn std::error::Error + Send + Sync>> {
+            let source = read_input(file)?;
+            let iron = redox::transpile(&source).map_err(|error| StageError {
+                stage: "Transpilation",
+                error,
+            })?;
+            let items = align_items(&source)?;
+
+            let mut counts = Vec::new();
+            for (tokenizer, name) in tokenizers.iter().zip(tokenizer_names) {
+                let mut item_counts = Vec::new();
+                for item in &items {
+                    let mut counts = token_counts(
+                        tokenizer.count(&item.rust.join("\n"))?,
+                        tokenizer.count(&item.iron.join("\n"))?,
+                    );
+                    counts["line"] = item.first_line.into();
+                    item_counts.push(counts);
+                }
+                let mut file_counts =
+                    token_counts(tokenizer.count(&source)?, tokenizer.count(&iron)?);
+                file_counts["tokenizer"] = name.as_str().into();
+                file_counts["items"] = item_counts.into();
+                counts.push(file_counts);
+            }
+            Ok(counts)
+        })();
+        let counts = match counted {
+            Ok(counts) => counts,
+            Err(e) => {
+                reporter.file_error(file, &*e);
+                continue;
+            }
+        };
+
+        for (total, counts) in totals.iter_mut().zip(&counts) {
+            total.0 += counts["rust"].as_u64().unwrap_or_default() as usize;
+            total.1 += counts["iron"].as_u64().unwrap_or_default() as usize;
+        }
+        match reporter.format {
+            Format::Text => {
+                println!("{}:", file.display());
+                for counts in &counts {
+                    println!(
+                        "  {}: {}",
+                        counts["tokenizer"].as_str().unwrap_or_default(),
+                        format_token_counts(counts)
+                    );
+                    for item in counts["items"].as_array().into_iter().flatten() {
+                        println!("    line {}: {}", item["line"], format_token_counts(item));
+                    }
+                }
+            }
+            Format::Json => println!("{}", serde_json::json!({ "file": file, "counts": counts })),
+        }
+    }
+
+    if reporter.format == Format::Text && files.len() > 1 {
+        println!("total:");
+        for (name, (rust, iron)) in tokenizer_names.iter().zip(&totals) {
+            println!(
+                "  {}: {}",
+                name,
+                format_token_counts(&token_counts(*rust, *iron))
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Pad or cut a line to exactly `width` characters, marking a cut with `…`
+fn fit_column(line: &str, width: usize) -> String {
+    let line = line.replace('\t', "    ");
+    let length = line.chars().count();
+    if length <= width {
+        format!("{}{}", line, " ".repeat(width - length))
+    } else {
+        let cut: String = line.chars().take(width.saturating_sub(1)).collect();
+        format!("{}…", cut)
+    }
+}
+
+/// Write the Iron reduction of a file with a `note that` line before each run of lines,
+/// naming the Rust line and construct it came from
+fn explain_file(
+    input: &Path,
+    output: Option<PathBuf>,
+    format: Format,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let source = read_input(input)?;
+    let file = syn::parse_file(&source).map_err(|e| {
+        let start = e.span().start();
+        format!("line {}, column {}: {}", start.line, start.column + 1, e)
+    })?;
+    let (iron, origins) = redox::transpile_with_origins(&file)?;
+
+    if format == Format::Json {
+        let origins: Vec<serde_json::Value> = origins
+            .iter()
+            .map(|(iron_line, origin)| {
+                serde_json::json!({
+                    "iron_line": iron_line,
+                    "rust_line": origin.line,
+                    "rust_column": origin.column,
+                    "construct": origin.construct,
+                })
+            })
+            .collect();
+        let explained = serde_json::json!({ "file": input, "iron": iron, "origins": origins });
+        return write_output(output, &format!("{}\n", explained), false);
+    }
+
+    let rust_lines: Vec<&str> = source.lines().collect();
+    let mut annotated = String::new();
+    let mut shown = Vec::new();
+    let mut next = origins.iter().peekable();
+    for (index, line) in iron.lines().enumerate() {
+        while let Some((_, origin)) = next.next_if(|(iron_line, _)| *iron_line == index + 1) {
+            // A block's closing line returns to the construct that opened it, which has
+            // already been explained
+            if shown.contains(&origin) {
+                continue;
+            }
+            shown.push(origin);
+            let indent = &line[..line.len() - line.trim_start().len()];
+            let rust = rust_lines
+                .get(origin.line - 1)
+                .map_or("", |rust| rust.trim());
+            annotated.push_str(&format!(
+                "{}note that line {}, {}: {}\n",
+                indent, origin.line, origin.construct, rust
+            ));
+        }
+        annotated.push_str(line);
+        annotated.push('\n');
+    }
+    write_output(output, &annotated, false)
+}
+
+/// Pretty-print Rust code, or keep it as written if it does not parse
+fn normalize_rust(code: &str) -> String {
+    match syn::parse_file(code) {
+        Ok(file) => prettyplease::unparse(&file),
+        Err(_) => code.to_string(),
+    }
+}
+
+/// Compile Rust code as a library, returning rustc's errors if it fails
+fn compile_rust(
+    code: &str,
+    edition: Edition,
+) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let dir = std::env::temp_dir().join(format!("redox-compile-{}", process::id()));
+    fs::create_dir_all(&dir)?;
+    let source_path = Path::new("input.rs");
+    fs::write(dir.join(source_path), code)?;
+
+    let output = process::Command::new("rustc")
+        .args([
+            "--crate-type",
+            "lib",
+            "--emit",
+            "metadata",
+            "-A",
+            "warnings",
+        ])
+        .arg("--edition")
+        .arg(edition.to_string())
+        .arg(source_path)
+        // Run in the directory so errors name the file without its temporary path
+        .current_dir(&dir)
+        .output()
+        .map_err(|e| format!("Failed to run rustc: {}", e))?;
+    let _ = fs::remove_dir_all(&dir);
+
+    if output.status.success() {
+        Ok(None)
+    } else {
+        Ok(Some(String::from_utf8_lossy(&output.stderr).into_owned()))
+    }
+}
+
+/// Read an input file, or stdin when the path is `-`
+fn read_input(input: &Path) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    if input == Path::new("-") {
+        let mut source = String::new();
+        io::stdin()
+            .read_to_string(&mut source)
+            .map_err(|error| IoError {
+                action: "Failed to read stdin".to_string(),
+                error,
+            })?;
+        return Ok(source);
+    }
+    fs::read_to_string(input).map_err(|error| {
+        IoError {
+            action: format!("Failed to read input file '{}'", input.display()),
+            error,
+        }
+        .into()
+    })
+}
+
+/// Write the result to the output file, or to stdout when there is none or it is `-`
+fn write_output(
+    output: Option<PathBuf>,
+    content: &str,
+    verbose: bool,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    match output {
+        Some(path) if path != Path::new("-") => {
+            fs::write(&path, content).map_err(|error| IoError {
+                action: format!("Failed to write output file '{}'", path.display()),
+                error,
+            })?;
+            if verbose {
+                eprintln!("Output written to: {}", path.display());
+            }
+        }
+        _ => {
+            print!("{}", content);
+        }
+    }
+
+    Ok(())
+}
+
+/// Check an Iron file for the symbols `profile` prohibits and print the result,
+/// returning whether it is valid
+///
+/// Symbols in code are errors; those in comments, strings, and verbatim blocks are
+/// warnings, which leave the file valid.
+fn validate_file(
+    input: &Path,
+    profile: &redox::ValidationProfile,
+    reporter: Reporter,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let content = read_input(input)?;
+    let diagnostics: Vec<_> = redox::prohibited_symbols_with_profile(&content, profile)
+        .iter()
+        .map(|symbol| Diagnostic::prohibited_symbol(input, symbol))
+        .collect();
+    record_warnings(&diagnostics);
+
+    let valid = !diagnostics.iter().any(Diagnostic::is_error);
+    match reporter.format {
+        Format::Text => {
+            for diagnostic in &diagnostics {
+                eprintln!("{}", diagnostic);
+            }
+            if valid {
+                println!("✓ Valid Iron code");
+            } else {
+                eprintln!("Validation error: Invalid Iron code: contains prohibited symbols");
+            }
+        }
+        Format::Json => print_json_result(input, &diagnostics),
+    }
+    Ok(valid)
+}
+
+/// Check that an Iron file parses and oxidizes, printing every problem found with its
+/// location. Returns whether the file is valid.
+fn check_file(
+    input: &Path,
+    options: &OxidizeOptions,
+    reporter: Reporter,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let source = read_input(input)?;
+    let diagnostics = check_diagnostics(input, &source, options);
+    record_warnings(&diagnostics);
+
+    let valid = !diagnostics.iter().any(Diagnostic::is_error);
+    match reporter.format {
+        Format::Text => {
+            for diagnostic in &diagnostics {
+                eprintln!("{}", diagnostic);
+            }
+            if valid {
+                println!("✓ Valid Iron code");
+            }
+        }
+        Format::Json => print_json_result(input, &diagnostics),
+    }
+    Ok(valid)
+}
+
+/// The prohibited symbols, grammar errors, and oxidation warnings of Iron source, in
+/// source order
+fn check_diagnostics<'a>(
+    input: &'a Path,
+    source: &str,
+    options: &OxidizeOptions,
+) -> Vec<Diagnostic<'a>> {
+    let mut diagnostics: Vec<_> = redox::prohibited_symbols(source)
+        .iter()
+        .map(|symbol| Diagnostic::prohibited_symbol(input, symbol))
+        .collect();
+    match redox::oxidize_with_warnings(source, options) {
+        Ok((_, warnings)) => diagnostics.extend(
+            warnings
+                .iter()
+                .map(|warning| Diagnostic::warning(input, warning)),
+        ),
+        Err(error) => diagnostics.push(Diagnostic::transpile_error(input, &error)),
+    }
+    diagnostics.sort_by_key(|diagnostic| (diagnostic.line, diagnostic.column));
+    diagnostics
+}
+
+/// Note for `--warnings-as-errors` whether any of the diagnostics is a warning
+fn record_warnings(diagnostics: &[Diagnostic]) {
+    if diagnostics.iter().any(|diagnostic| !diagnostic.is_error()) {
+        WARNED.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Print whether a file is valid and the diagnostics behind it as one JSON object
+fn print_json_result(input: &Path, diagnostics: &[Diagnostic]) {
+    let result = serde_json::json!({
+        "file": input.display().to_string(),
+        "valid": !diagnostics.iter().any(Diagnostic::is_error),
+        "diagnostics": diagnostics.iter().map(Diagnostic::to_json).collect::<Vec<_>>(),
+    });
+    println!("{}", result);
+}
+
+/// How far `oxidize --verify` checks the Rust it generates
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Verify {
+    None,
+    /// Parse it with syn
+    Parse,
+    /// Parse it, then compile it with rustc
+    Compile,
+}
+
+fn oxidize_file(
+    input: &Path,
+    output: Option<PathBuf>,
+    options: &OxidizeOptions,
+    verify: Verify,
+    verbose: bool,
+    reporter: Reporter,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let source = read_input(input)?;
+
+    if verbose {
+        eprintln!("Oxidizing: {}", input.display());
+        eprintln!("Source size: {} bytes", source.len());
+    }
+
+    // Oxidize
+    let rust_code = match redox::oxidize_with_warnings(&source, options) {
+        Ok((code, warnings)) => {
+            for warning in &warnings {
+                reporter.warning(input, warning);
+            }
+            code
+        }
+        Err(error) => {
+            return Err(StageError {
+                stage: "Oxidation",
+                error,
+            }
+            .into());
+        }
+    };
+
+    if verbose {
+        eprintln!("Output size: {} bytes", rust_code.len());
+    }
+
+    // Broken Rust is reported rather than written
+    if verify != Verify::None
+        && let Err(e) = syn::parse_file(&rust_code)
+    {
+        let start = e.span().start();
+        return Err(VerifyError(format!(
+            "generated Rust does not parse: line {}, column {}: {}",
+            start.line,
+            start.column + 1,
+            e
+        ))
+        .into());
+    }
+    if verify == Verify::Compile
+        && let Some(errors) = compile_rust(&rust_code, options.edition)?
+    {
+        let errors = errors.trim_end();
+        return Err(VerifyError(format!("generated Rust does not compile:\n{}", errors)).into());
+    }
+    if verify != Verify::None && verbose {
+        eprintln!("Verification passed");
+    }
+
+    write_output(output, &rust_code, verbose)
+}
+
+/// Why a `serve` request could not be answered
+enum ServiceError {
+    /// No endpoint has the requested name
+    UnknownMethod,
+    /// The request body is not a JSON object with the fields the endpoint takes
+    InvalidParams(String),
+    /// Reduction or oxidation of the source failed, with the diagnostic saying why
+    Failed(serde_json::Value),
+}
+
+/// Answer a reduce, oxidize, or validate request made of JSON parameters
+///
+/// Every method takes the code as `source`, and an optional `file` name to report in
+/// diagnostics.
+fn call_service(
+    method: &str,
+    params: &serde_json::Value,
+) -> Result<serde_json::Value, ServiceError> {
+    if !["reduce", "oxidize", "validate"].contains(&method) {
+        return Err(ServiceError::UnknownMethod);
+    }
+    let source = params["source"]
+        .as_str()
+        .ok_or_else(|| ServiceError::InvalidParams("missing string field 'source'".into()))?;
+    let file = Path::new(params["file"].as_str().unwrap_or("-"));
+    let flag = |name: &str| params[name].as_bool().unwrap_or(false);
+
+    match method {
+        "reduce" => {
+            let options = ReduceOptions {
+                strict: flag("strict"),
+                ..Default::default()
+            };
+            match redox::transpile_with_options(source, &options) {
+                Ok(iron) => Ok(serde_json::json!({ "iron": iron })),
+                Err(error) => Err(ServiceError::Failed(
+                    Diagnostic::transpile_error(file, &error).to_json(),
+                )),
+            }
+        }
+        "oxidize" => {
+            let options = OxidizeOptions {
+                case_insensitive_keywords: flag("case_insensitive_keywords"),
+                parse_mode: service_param(params, "parse_mode", "lenient")?,
+                edition: service_param(params, "edition", "2024")?,
+                ..Default::default()
+            };
+            match redox::oxidize_with_warnings(source, &options) {
+                Ok((rust, warnings)) => {
+                    let diagnostics: Vec<_> = warnings
+                        .iter()
+                        .map(|warning| Diagnostic::warning(file, warning).to_json())
+                        .collect();
+                    Ok(serde_json::json!({ "rust": rust, "diagnostics": diagnostics }))
+                }
+                Err(error) => Err(ServiceError::Failed(
+                    Diagnostic::transpile_error(file, &error).to_json(),
+                )),
+            }
+        }
+        "validate" => {
+            let options = OxidizeOptions {
+                case_insensitive_keywords: flag("case_insensitive_keywords"),
+                parse_mode: service_param(params, "parse_mode", "strict")?,
+                ..Default::default()
+            };
+            let diagnostics = check_diagnostics(file, source, &options);
+            Ok(serde_json::json!({
+                "valid": !diagnostics.iter().any(Diagnostic::is_error),
+                "diagnostics": diagnostics.iter().map(Diagnostic::to_json).collect::<Vec<_>>(),
+            }))
+        }
+        _ => unreachable!("method names are checked first"),
+    }
+}
+
+/// A string parameter parsed as an option value, or `default` when it is absent
+fn service_param<T: std::str::FromStr<Err = String>>(
+    params: &serde_json::Value,
+    name: &str,
+    default: &str,
+) -> Result<T, ServiceError> {
+    let value = match &params[name] {
+        serde_json::Value::Null => default.to_string(),
+        serde_json::Value::String(value) => value.clone(),
+        // Such as an edition written as a number
+        serde_json::Value::Number(value) => value.to_string(),
+        _ => {
+            let error = format!("'{}' must be a string", name);
+            return Err(ServiceError::InvalidParams(error));
+        }
+    };
+    value
+        .parse()
+        .map_err(|e| ServiceError::InvalidParams(format!("'{}': {}", name, e)))
+}
+
+/// One file of a batch manifest, with its options over the manifest's defaults
+struct BatchEntry {
+    /// `reduce`, `oxidize`, or `validate`
+    command: String,
+    input: PathBuf,
+    /// Where converted code is written; validation writes nothing
+    output: Option<PathBuf>,
+    /// Options in the shape `serve` takes them
+    params: serde_json::Value,
+}
+
+impl AsRef<Path> for BatchEntry {
+    fn as_ref(&self) -> &Path {
+        &self.input
+    }
+}
+
+/// Read the `files` of a batch manifest, with paths relative to the manifest
+///
+/// Each file is a table with an `input`, an optional `command` (default `reduce`) and
+/// `output` (default: the input with an `.iron` or `.rs` extension), and the options of
+/// the command, any of which may also be set for every file in a `defaults` table.
+fn read_manifest(
+    manifest: &Path,
+) -> Result<Vec<BatchEntry>, Box<dyn std::error::Error + Send + Sync>> {
+    let text = read_input(manifest)?;
+    let value: serde_json::Value = if manifest.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&text).map_err(|e| format!("Invalid manifest: {}", e))?
+    } else {
+        serde_json::from_str(&text).map_err(|e| format!("Invalid manifest: {}", e))?
+    };
+    let base = manifest.parent().unwrap_or(Path::new(""));
+    let defaults = match &value["defaults"] {
+        serde_json::Value::Null => serde_json::Map::new(),
+        serde_json::Value::Object(defaults) => defaults.clone(),
+        _ => return Err("Manifest 'defaults' is not a table".into()),
+    };
+    let files = value["files"]
+        .as_array()
+        .ok_or("Manifest has no 'files' list")?;
+
+    let mut entries = Vec::new();
+    let mut written: HashMap<PathBuf, PathBuf> = HashMap::new();
+    for (index, file) in files.iter().enumerate() {
+        let serde_json::Value::Object(options) = file else {
+            return Err(format!("Manifest files[{}] is not a table", index).into());
+        };
+        let mut params = defaults.clone();
+        params.extend(options.clone());
+        let command = params
+            .get("command")
+            .and_then(|command| command.as_str())
+            .unwrap_or("reduce")
+            .to_string();
+        let extension = match command.as_str() {
+            "reduce" => Some("iron"),
+            "oxidize" => Some("rs"),
+            "validate" => None,
+            _ => {
+                let error = format!(
+                    "Manifest files[{}] has unknown command '{}'",
+                    index, command
+                );
+                return Err(error.into());
+            }
+        };
+        let input = match options.get("input").and_then(|input| input.as_str()) {
+            Some(input) => base.join(input),
+            None => return Err(format!("Manifest files[{}] has no 'input'", index).into()),
+        };
+        let output = extension.map(|extension| {
+            match params.get("output").and_then(|output| output.as_str()) {
+                Some(output) => base.join(output),
+                None => input.with_extension(extension),
+            }
+        });
+
+        if let Some(output) = &output {
+            if *output == input {
+                let error = format!("Writing '{}' would overwrite its input", output.display());
+                return Err(error.into());
+            }
+            if let Some(other) = written.insert(output.clone(), input.clone()) {
+                return Err(format!(
+                    "Both '{}' and '{}' would be written to '{}'",
+                    other.display(),
+                    input.display(),
+                    output.display()
+                )
+                .into());
+            }
+        }
+        entries.push(BatchEntry {
+            command,
+            input,
+            output,
+            params: serde_json::Value::Object(params),
+        });
+    }
+    Ok(entries)
+}
+
+/// Run one file of a batch, returning its record in the report
+fn run_batch_entry(entry: &BatchEntry) -> serde_json::Value {
+    let mut record = serde_json::json!({
+        "command": entry.command,
+        "input": entry.input,
+        "output": entry.output,
+    });
+    let failed = |mut record: serde_json::Value, diagnostic: serde_json::Value| {
+        record["status"] = "failed".into();
+        record["diagnostics"] = serde_json::json!([diagnostic]);
+        record
+    };
+    let source = match read_input(&entry.input) {
+        Ok(source) => source,
+        Err(e) => return failed(record, Diagnostic::error(&entry.input, &*e).to_json()),
+    };
+
+    let mut params = entry.params.clone();
+    params["source"] = source.into();
+    params["file"] = entry.input.display().to_string().into();
+    let result = match call_service(&entry.command, &params) {
+        Ok(result) => result,
+        Err(ServiceError::Failed(diagnostic)) => return failed(record, diagnostic),
+        Err(ServiceError::InvalidParams(message)) => {
+            let error: Box<dyn std::error::Error> = message.into();
+            return failed(record, Diagnostic::error(&entry.input, &*error).to_json());
+        }
+        Err(ServiceError::UnknownMethod) => unreachable!("commands are checked on reading"),
+    };
+
+    let code = result["iron"].as_str().or(result["rust"].as_str());
+    if let (Some(code), Some(output)) = (code, &entry.output) {
+        let written = output
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(output, code))
+            .map_err(|error| IoError {
+                action: format!("Failed to write output file '{}'", output.display()),
+                error,
+            });
+        if let Err(e) = written {
+            return failed(record, Diagnostic::error(&entry.input, &e).to_json());
+        }
+    }
+    record["status"] = match result["valid"].as_bool() {
+        Some(false) => "invalid",
+        _ => "ok",
+    }
+    .into();
+    record["diagnostics"] = result
+        .get("diagnostics")
+        .cloned()
+        .unwrap_or_else(|| serde_json::json!([]));
+    record
+}
+
+/// Run every file of a batch manifest and write the report, returning whether every
+/// validation passed
+///
+/// Files that fail do not stop the others; the run fails after the report is written.
+fn batch_files(
+    manifest: &Path,
+    report: Option<PathBuf>,
+    jobs: NonZeroUsize,
+    reporter: Reporter,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+    let started = Instant::now();
+    let entries = read_manifest(manifest)?;
+    let progress = reporter.progress("Processing", entries.len());
+    let records = for_each_file(&entries, jobs, &progress, run_batch_entry);
+
+    let count = |status: &str| {
+        records
+            .iter()
+            .filter(|record| record["status"] == status)
+            .count()
+    };
+    let (invalid, failed) = (count("invalid"), count("failed"));
+    let warned = records
+        .iter()
+        .flat_map(|record| record["diagnostics"].as_array().into_iter().flatten())
+        .any(|diagnostic| diagnostic["severity"] == "warning");
+    if warned {
+        WARNED.store(true, Ordering::Relaxed);
+    }
+
+    let summary = serde_json::json!({
+        "ok": count("ok"),
+        "invalid": invalid,
+        "failed": failed,
+        "total": records.len(),
+    });
+    let report_json = serde_json::json!({ "files": records, "summary": summary });
+    write_output(report, &format!("{}\n", report_json), false)?;
+    reporter.summary(
+        &[
+            ("ok", count("ok")),
+            ("invalid", invalid),
+            ("failed", failed),
+            ("total", records.len()),
+        ],
+        started,
+    );
+    if failed > 0 {
+        return Err(format!("{} of {} files failed", failed, records.len()).into());
+    }
+    Ok(invalid == 0)
+}
+
+/// Listen for `POST /reduce`, `/oxidize`, and `/validate` requests with JSON bodies,
+/// answering up to `jobs` of them at once
+fn serve(
+    host: &str,
+    port: u16,
+    jobs: NonZeroUsize,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let server = tiny_http::Server::http((host, port)).map_err(|error| {
+        let action = format!("Failed to listen on {}:{}", host, port);
+        let error: Box<dyn std::error::Error + Send + Sync> = match error.downcast::<io::Error>() {
+            Ok(error) => Box::new(IoError {
+                action,
+                error: *error,
+            }),
+            Err(error) => format!("{}: {}", action, error).into(),
+        };
+        error
+    })?;
+    match server.server_addr().to_ip() {
+        Some(address) => eprintln!("Listening on http://{}", address),
+        None => eprintln!("Listening on {}", server.server_addr()),
+    }
+
+    // Requests past the busy workers wait in the server's queue
+    thread::scope(|scope| {
+        for _ in 0..jobs.get() {
+            scope.spawn(|| {
+                for mut request in server.incoming_requests() {
+                    let (status, body) = answer_http(&mut request);
+                    let content_type =
+                        tiny_http::Header::from_bytes("Content-Type", "application/json")
+                            .expect("static header is valid");
+                    let response = tiny_http::Response::from_string(format!("{}\n", body))
+                        .with_status_code(status)
+                        .with_header(content_type);
+                    if let Err(e) = request.respond(response) {
+                        eprintln!("Error: {}", e);
+                    }
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// The status and JSON body answering one HTTP request
+fn answer_http(request: &mut tiny_http::Request) -> (u16, serde_json::Value) {
+    let error = |message: String| serde_json::json!({ "error": { "message": message } });
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let method = path.trim_start_matches('/').to_string();
+    if *request.method() != tiny_http::Method::Post {
+        return (405, error(format!("{} takes POST requests", path)));
+    }
+
+    let too_large = || {
+        let message = format!("request body is over the {} byte limit", MAX_REQUEST_BODY);
+        (413, error(message))
+    };
+    // A chunked body has no length up front, so the read is capped as well
+    if request
+        .body_length()
+        .is_some_and(|length| length > MAX_REQUEST_BODY)
+    {
+        return too_large();
+    }
+    let mut body = String::new();
+    let mut reader = request.as_reader().take(MAX_REQUEST_BODY as u64 + 1);
+    if let Err(e) = reader.read_to_string(&mut body) {
+        return (400, error(format!("Failed to read request body: {}", e)));
+    }
+    if body.len() > MAX_REQUEST_BODY {
+        return too_large();
+    }
+    let params: serde_json::Value = match serde_json::from_str(&body) {
+        Ok(params) => params,
+        Err(e) => return (400, error(format!("Request body is not JSON: {}", e))),
+    };
+
+    match call_service(&method, &params) {
+        Ok(result) => (200, result),
+        Err(ServiceError::UnknownMethod) => (404, error(format!("no endpoint {}", path))),
+        Err(ServiceError::InvalidParams(message)) => (400, error(message)),
+        Err(ServiceError::Failed(diagnostic)) => (422, serde_json::json!({ "error": diagnostic })),
+    }
+}
+
+/// JSON-RPC error code for a request answered early because the client cancelled it
+const REQUEST_CANCELLED: i64 = -32800;
+
+/// Answer JSON-RPC 2.0 requests read from stdin on stdout until stdin closes
+///
+/// Messages are framed as in the Language Server Protocol: a `Content-Length` header, a
+/// blank line, then that many bytes of JSON. Up to `jobs` requests run at once, with as
+/// many more queued before reading stops to wait for them. A `$/cancelRequest`
+/// notification with a pending request's `id` answers it at once with a cancellation
+/// error, discarding the result when it arrives, and a request reusing the `id` of one
+/// still pending is refused.
+fn serve_stdio(jobs: NonZeroUsize) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let pending = Mutex::new(HashSet::new());
+    let (sender, receiver) =
+        mpsc::sync_channel::<(serde_json::Value, String, serde_json::Value)>(jobs.get());
+    let receiver = Mutex::new(receiver);
+    let mut input = io::stdin().lock();
+
+    thread::scope(|scope| {
+        for _ in 0..jobs.get() {
+            scope.spawn(|| {
+                loop {
+                    // The lock is released once a request is taken
+                    let Ok((id, method, params)) = receiver.lock().unwrap().recv() else {
+                        break;
+                    };
+                    answer_rpc(&pending, id, &method, &params);
+                }
+            });
+        }
+
+        while let Some(frame) = read_rpc_message(&mut input)? {
+            let body = match frame {
+                Ok(body) => body,
+                Err(reason) => {
+                    let error = rpc_error(-32700, reason, None);
+                    write_rpc_message(&rpc_response(&serde_json::Value::Null, Err(error)))?;
+                    continue;
+                }
+            };
+            let mut message: serde_json::Value = match serde_json::from_slice(&body) {
+                Ok(message) => message,
+                Err(e) => {
+                    let error = rpc_error(-32700, format!("Message is not JSON: {}", e), None);
+                    write_rpc_message(&rpc_response(&serde_json::Value::Null, Err(error)))?;
+                    continue;
+                }
+            };
+            let id = message.get("id").cloned();
+            let Some(method) = message["method"].as_str() else {
+                let error = rpc_error(-32600, "missing string field 'method'".into(), None);
+                let id = id.unwrap_or_default();
+                write_rpc_message(&rpc_response(&id, Err(error)))?;
+                continue;
+            };
+
+            if method == "$/cancelRequest" {
+                let cancelled = &message["params"]["id"];
+                if pending.lock().unwrap().remove(&cancelled.to_string()) {
+                    let error = rpc_error(REQUEST_CANCELLED, "request cancelled".into(), None);
+                    write_rpc_message(&rpc_response(cancelled, Err(error)))?;
+                }
+                continue;
+            }
+            // Notifications get no answer, and no other method takes them
+            let Some(id) = id else {
+                continue;
+            };
+
+            if !pending.lock().unwrap().insert(id.to_string()) {
+                let error = rpc_error(
+                    -32600,
+                    format!("request id {} is already pending", id),
+                    None,
+                );
+                write_rpc_message(&rpc_response(&id, Err(error)))?;
+                continue;
+            }
+            let method = method.to_string();
+            let params = message["params"].take();
+            if sender.send((id, method, params)).is_err() {
+                break;
+            }
+        }
+        drop(sender);
+        Ok(())
+    })
+}
+
+/// Run one daemon request and write its answer, unless it was cancelled while it waited
+/// or ran
+fn answer_rpc(
+    pending: &Mutex<HashSet<String>>,
+    id: serde_json::Value,
+    method: &str,
+    params: &serde_json::Value,
+) {
+    if !pending.lock().unwrap().contains(&id.to_string()) {
+        return;
+    }
+    let result = call_service(method, params).map_err(|e| match e {
+        ServiceError::UnknownMethod => rpc_error(-32601, format!("no method '{}'", method), None),
+        ServiceError::InvalidParams(detail) => rpc_error(-32602, detail, None),
+        ServiceError::Failed(diagnostic) => {
+            let message = diagnostic["message"].as_str().unwrap_or_default();
+            rpc_error(-32000, message.to_string(), Some(diagnostic))
+        }
+    });
+    // A cancelled request has already been answered
+    if pending.lock().unwrap().remove(&id.to_string())
+        && let Err(e) = write_rpc_message(&rpc_response(&id, result))
+    {
+        eprintln!("Error: {}", e);
+    }
+}
+
+/// Largest request body `serve` reads, over HTTP or from the daemon's stdin; a longer one
+/// is answered with an error, so a bad length cannot exhaust memory
+const MAX_REQUEST_BODY: usize = 16 * 1024 * 1024;
+
+/// Read the body of the next framed message, or why the frame is unusable, or `None`
+/// once the input ends
+fn read_rpc_message(input: &mut impl BufRead) -> io::Result<Option<Result<Vec<u8>, String>>> {
+    let mut length = None;
+    loop {
+        let mut header = Vec::new();
+        if input.read_until(b'\n', &mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = String::from_utf8_lossy(&header);
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = Some(
+                value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| value.trim().to_string()),
+            );
+        }
+    }
+
+    let length = match length {
+        Some(Ok(length)) => length,
+        Some(Err(value)) => return Ok(Some(Err(format!("invalid Content-Length '{}'", value)))),
+        None => {
+            return Ok(Some(
+                Err("message has no Content-Length header".to_string()),
+            ));
+        }
+    };
+    if length > MAX_REQUEST_BODY {
+        io::copy(&mut input.take(length as u64), &mut io::sink())?;
+        return Ok(Some(Err(format!(
+            "message of {} bytes is over the {} byte limit",
+            length, MAX_REQUEST_BODY
+        ))));
+    }
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(Ok(body)))
+}
+
+fn write_rpc_message(
+    message: &serde_json::Value,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let body = message.to_string();
+    let mut output = io::stdout().lock();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()?;
+    Ok(())
+}
+
+fn rpc_response(
+    id: &serde_json::Value,
+    result: Result<serde_json::Value, serde_json::Value>,
+) -> serde_json::Value {
+    match result {
+        Ok(result) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+}
+
+fn rpc_error(code: i64, message: String, data: Option<serde_json::Value>) -> serde_json::Value {
+    match data {
+        Some(data) => serde_json::json!({ "code": code, "message": message, "data": data }),
+        None => serde_json::json!({ "code": code, "message": message }),
+    }
+}
+
+/// A failed read or write, kept apart from other errors for its exit status
+#[derive(Debug)]
+struct IoError {
+    action: String,
+    error: io::Error,
+}
+
+impl std::fmt::Display for IoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.action, self.error)
+    }
+}
+
+impl std::error::Error for IoError {}
+
+/// An output file that `--check` found would change if written
+#[derive(Debug)]
+struct OutOfDate {
+    output: PathBuf,
+    exists: bool,
+}
+
+impl std::fmt::Display for OutOfDate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.exists {
+            write!(f, "'{}' would change", self.output.display())
+        } else {
+            write!(f, "'{}' would be created", self.output.display())
+        }
+    }
+}
+
+impl std::error::Error for OutOfDate {}
+
+/// Generated Rust that `oxidize --verify` found does not parse or compile
+#[derive(Debug)]
+struct VerifyError(String);
+
+impl std::fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Verification failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// A failed reduction or oxidation, kept whole so its kind and location can be reported
+#[derive(Debug)]
+struct StageError {
+    stage: &'static str,
+    error: TranspileError,
+}
+
+impl std::fmt::Display for StageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} failed: {}", self.stage, self.error)
+    }
+}
+
+impl std::error::Error for StageError {}
+
+/// One error or warning about an input file, in the shape printed by `--format json`
+struct Diagnostic<'a> {
+    file: &'a Path,
+    line: Option<usize>,
+    column: Option<usize>,
+    severity: &'static str,
+    code: &'static str,
+    message: String,
+}
+
+impl<'a> Diagnostic<'a> {
+    fn error(file: &'a Path, error: &(dyn std::error::Error + 'static)) -> Self {
+        match error.downcast_ref::<StageError>() {
+            Some(StageError { stage, error }) => {
+                let mut diagnostic = Diagnostic::transpile_error(file, error);
+                diagnostic.message = format!("{} failed: {}", stage, diagnostic.message);
+                diagnostic
+            }
+            None => Diagnostic {
+                file,
+                line: None,
+                column: None,
+                severity: "error",
+                code: match Exit::of(error) {
+                    Exit::Io => "io-error",
+                    Exit::Invalid => "verify-error",
+                    _ if error.is::<OutOfDate>() => "out-of-date",
+                    _ => "error",
+                },
+                message: error.to_string(),
+            },
+        }
+    }
+
+    /// A library error, with its location moved out of the message
+    fn transpile_error(file: &'a Path, error: &TranspileError) -> Self {
+        let location = error.location();
+        Diagnostic {
+            file,
+            line: location.map(|(line, _)| line),
+            column: location.and_then(|(_, column)| column),
+            severity: "error",
+            code: error.code(),
+            message: error.detail(),
+        }
+    }
+
+    fn warning(file: &'a Path, warning: &IronWarning) -> Self {
+        Diagnostic {
+            file,
+            line: warning.line,
+            column: None,
+            severity: "warning",
+            code: "warning",
+            message: warning.message.clone(),
+        }
+    }
+
+    fn prohibited_symbol(file: &'a Path, symbol: &redox::ProhibitedSymbol) -> Self {
+        let (severity, place) = match symbol.severity {
+            Severity::Error => ("error", ""),
+            Severity::Warning => ("warning", " in a comment, string, or verbatim block"),
+        };
+        Diagnostic {
+            file,
+            line: Some(symbol.line),
+            column: Some(symbol.column),
+            severity,
+            code: "prohibited-symbol",
+            message: if symbol.snippet == symbol.symbol {
+                format!("prohibited symbol '{}'{}", symbol.symbol, place)
+            } else {
+                format!(
+                    "prohibited symbol '{}' in '{}'{}",
+                    symbol.symbol, symbol.snippet, place
+                )
+            },
+        }
+    }
+
+    fn is_error(&self) -> bool {
+        self.severity == "error"
+    }
+
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "file": self.file.display().to_string(),
+            "line": self.line,
+            "column": self.column,
+            "severity": self.severity,
+            "code": self.code,
+            "message": self.message,
+        })
+    }
+}
+
+/// `file:line:column: severity: message`, the form editors and terminals link to
+impl std::fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.file.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+        }
+        if let Some(column) = self.column {
+            write!(f, ":{}", column)?;
+        }
+        write!(f, ": {}: {}", self.severity, self.message)
+    }
+}
+
+/// Prints errors and warnings to stderr in the selected format
+#[derive(Clone, Copy)]
+struct Reporter {
+    format: Format,
+    /// Leave out progress and summaries
+    quiet: bool,
+}
+
+impl Reporter {
+    fn error(&self, file: &Path, error: &(dyn std::error::Error + 'static)) {
+        match self.format {
+            Format::Text => eprintln!("Error: {}", error),
+            Format::Json => eprintln!("{}", Diagnostic::error(file, error).to_json()),
+        }
+    }
+
+    /// A status line for a batch job over `total` files
+    fn progress(&self, label: &'static str, total: usize) -> Progress {
+        Progress {
+            label,
+            total,
+            done: AtomicUsize::new(0),
+            visible: !self.quiet && io::stderr().is_terminal(),
+        }
+    }
+
+    /// Print how a batch job went as a table of counts and the time it took
+    fn summary(&self, counts: &[(&str, usize)], started: Instant) {
+        if self.quiet {
+            return;
+        }
+        let seconds = started.elapsed().as_secs_f64();
+        match self.format {
+            Format::Text => {
+                for (name, count) in counts {
+                    eprintln!("  {:<10}{:>8} files", name, count);
+                }
+                eprintln!("  {:<10}{:>8.2}s", "time", seconds);
+            }
+            Format::Json => {
+                let mut summary: serde_json::Map<_, _> = counts
+                    .iter()
+                    .map(|(name, count)| (name.to_string(), serde_json::json!(count)))
+                    .collect();
+                summary.insert("seconds".to_string(), serde_json::json!(seconds));
+                eprintln!("{}", serde_json::json!({ "summary": summary }));
+            }
+        }
+    }
+
+    /// Report an error that ends the run, and exit with the status for its kind
+    fn fatal(&self, file: &Path, error: &(dyn std::error::Error + 'static)) -> ! {
+        self.error(file, error);
+        process::exit(Exit::of(error) as i32)
+    }
+
+    /// Like `error`, but naming the file in text output, for commands that process several
+    fn file_error(&self, file: &Path, error: &(dyn std::error::Error + 'static)) {
+        match self.format {
+            Format::Text => eprintln!("Error: {}: {}", file.display(), error),
+            Format::Json => eprintln!("{}", Diagnostic::error(file, error).to_json()),
+        }
+    }
+
+    fn warning(&self, file: &Path, warning: &IronWarning) {
+        WARNED.store(true, Ordering::Relaxed);
+        match self.format {
+            Format::Text => eprintln!("Warning: {}", warning),
+            Format::Json => eprintln!("{}", Diagnostic::warning(file, warning).to_json()),
+        }
+    }
+
+    /// Symbols left in reduced output, which point at a transpiler bug
+    fn prohibited_symbols(&self, file: &Path, symbols: &[redox::ProhibitedSymbol]) {
+        WARNED.store(true, Ordering::Relaxed);
+        match self.format {
+            Format::Text => {
+                eprintln!("Warning: Output contains prohibited symbols!");
+                eprintln!("This indicates a bug in the transpiler.");
+            }
+            Format::Json => {
+                for symbol in symbols {
+                    let mut diagnostic = Diagnostic::prohibited_symbol(file, symbol);
+                    diagnostic.severity = "warning";
+                    eprintln!("{}", diagnostic.to_json());
+                }
+            }
+        }
+    }
+}
@@ -4,15 +4,67 @@
 //! Rust AST constructs to Iron syntax.
 
 use crate::keywords::sanitize_identifier;
+use std::sync::Arc;
 use syn::{FnArg, Pat, PatType, ReturnType, Type};
 
+/// Custom Iron phrasings for Rust constructs, registered on [`crate::ReduceOptions`] to
+/// add domain-specific mappings without changing this module
+///
+/// Each method returns `None` to leave the construct to the next hook, and then to the
+/// built-in mapping. An item whose custom Iron does not parse is emitted as verbatim
+/// Rust instead, and oxidation only reads phrasings it already knows.
+pub trait MappingHook: Send + Sync {
+    /// The Iron for a type, such as "shared mutex of T" for `tokio::sync::Mutex<T>`
+    fn map_type(&self, _ty: &Type) -> Option<String> {
+        None
+    }
+
+    /// The Iron expression for a macro invocation, such as one of `tokio::join!`
+    fn map_macro(&self, _mac: &syn::Macro) -> Option<String> {
+        None
+    }
+
+    /// The Iron name for types named `name`, with any generic arguments following as
+    /// "name of A and B"
+    fn map_type_name(&self, _name: &str) -> Option<String> {
+        None
+    }
+
+    /// The Iron for a top-level item with no Iron form of its own, asked under
+    /// [`crate::FallbackPolicy::Hook`]; it must parse as Iron to be used
+    fn map_unsupported_item(&self, _item: &syn::Item) -> Option<String> {
+        None
+    }
+
+    /// The Iron type names of several words this hook writes, which the Iron parser
+    /// must know to read them back
+    fn type_phrases(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// The first custom Iron the hooks give for a macro invocation
+pub fn map_macro_with_hooks(mac: &syn::Macro, hooks: &[Arc<dyn MappingHook>]) -> Option<String> {
+    hooks.iter().find_map(|hook| hook.map_macro(mac))
+}
+
 /// Maps Rust types to Iron type representations
 pub fn map_type_to_iron(ty: &Type) -> String {
+    map_type_with_hooks(ty, &[])
+}
+
+/// Maps Rust types to Iron type representations, asking `hooks` first for each type,
+/// nested ones included
+pub fn map_type_with_hooks(ty: &Type, hooks: &[Arc<dyn MappingHook>]) -> String {
+    if let Some(iron) = hooks.iter().find_map(|hook| hook.map_type(ty)) {
+        return iron;
+    }
     match ty {
         Type::Path(type_path) => {
             let path = &type_path.path;
             if let Some(segment) = path.segments.last() {
                 let name = segment.ident.to_string();
+                let custom = hooks.iter().find_map(|hook| hook.map_type_name(&name));
 
                 // Handle generics
                 match &segment.arguments {
@@ -21,21 +73,24 @@ pub fn map_type_to_iron(ty: &Type) -> String {
                             .args
                             .iter()
                             .map(|arg| match arg {
-                                syn::GenericArgument::Type(t) => map_type_to_iron(t),
+                                syn::GenericArgument::Type(t) => map_type_with_hooks(t, hooks),
                                 _ => "unknown".to_string(),
                             })
                             .collect();
 
-                        map_generic_type(&name, &generic_args)
+                        match custom {
+                            Some(custom) => format!("{} of {}", custom, generic_args.join(" and ")),
+                            None => map_generic_type(&name, &generic_args),
+                        }
                     }
-                    _ => map_simple_type(&name),
+                    _ => custom.unwrap_or_else(|| map_simple_type(&name)),
                 }
             } else {
                 "unknown".to_string()
             }
         }
         Type::Reference(type_ref) => {
-            let inner = map_type_to_iron(&type_ref.elem);
+            let inner = map_type_with_hooks(&type_ref.elem, hooks);
             if type_ref.mutability.is_some() {
                 format!("mutable reference to {}", inner)
             } else {
@@ -43,7 +98,7 @@ pub fn map_type_to_iron(ty: &Type) -> String {
             }
         }
         Type::Ptr(type_ptr) => {
-            let inner = map_type_to_iron(&type_ptr.elem);
+            let inner = map_type_with_hooks(&type_ptr.elem, hooks);
             if type_ptr.mutability.is_some() {
                 format!("mutable raw pointer to {}", inner)
             } else {
@@ -54,27 +109,31 @@ pub fn map_type_to_iron(ty: &Type) -> String {
             if tuple.elems.is_empty() {
                 "unit".to_string()
             } else {
-                let types: Vec<String> = tuple.elems.iter().map(map_type_to_iron).collect();
+                let types: Vec<String> = tuple
+                    .elems
+                    .iter()
+                    .map(|ty| map_type_with_hooks(ty, hooks))
+                    .collect();
                 format!("tuple of {}", types.join(" and "))
             }
         }
         Type::Array(array) => {
-            let inner = map_type_to_iron(&array.elem);
+            let inner = map_type_with_hooks(&array.elem, hooks);
             format!("array of {}", inner)
         }
         Type::Slice(slice) => {
-            let inner = map_type_to_iron(&slice.elem);
+            let inner = map_type_with_hooks(&slice.elem, hooks);
             format!("slice of {}", inner)
         }
         Type::BareFn(fn_type) => {
             let inputs: Vec<String> = fn_type
                 .inputs
                 .iter()
-                .map(|arg| map_type_to_iron(&arg.ty))
+                .map(|arg| map_type_with_hooks(&arg.ty, hooks))
                 .collect();
             let output = match &fn_type.output {
                 ReturnType::Default => "unit".to_string(),
-                ReturnType::Type(_, ty) => map_type_to_iron(ty),
+                ReturnType::Type(_, ty) => map_type_with_hooks(ty, hooks),
             };
             format!(
                 "function taking {} returning {}",
@@ -82,7 +141,7 @@ pub fn map_type_to_iron(ty: &Type) -> String {
                 output
             )
         }
-        Type::Paren(paren_type) => map_type_to_iron(&paren_type.elem),
+        Type::Paren(paren_type) => map_type_with_hooks(&paren_type.elem, hooks),
         Type::TraitObject(type_trait) => type_trait
             .bounds
             .iter()
@@ -153,22 +212,22 @@ fn map_generic_type(name: &str, args: &[String]) -> String {
 }
 
 /// Maps Rust return types to Iron return type representations
-pub fn map_return_type(return_type: &ReturnType) -> String {
+pub fn map_return_type(return_type: &ReturnType, hooks: &[Arc<dyn MappingHook>]) -> String {
     match return_type {
         ReturnType::Default => "unit".to_string(),
-        ReturnType::Type(_, ty) => map_type_to_iron(ty),
+        ReturnType::Type(_, ty) => map_type_with_hooks(ty, hooks),
     }
 }
 
 /// Maps function arguments to Iron parameter representations
-pub fn map_fn_arg(arg: &FnArg) -> Option<(String, String)> {
+pub fn map_fn_arg(arg: &FnArg, hooks: &[Arc<dyn MappingHook>]) -> Option<(String, String)> {
     match arg {
         FnArg::Typed(PatType { pat, ty, .. }) => {
             let name = match &**pat {
                 Pat::Ident(pat_ident) => sanitize_identifier(&pat_ident.ident.to_string()),
                 _ => "unnamed".to_string(),
             };
-            let ty_str = map_type_to_iron(ty);
+            let ty_str = map_type_with_hooks(ty, hooks);
             Some((name, ty_str))
         }
         FnArg::Receiver(receiver) => {
@@ -221,6 +280,37 @@ pub fn map_binary_op(op: &syn::BinOp) -> String {
     }
 }
 
+/// Maps Rust compound assignment operators to an Iron statement
+/// ("increase x by 1", "update x with shift left 2")
+pub fn map_compound_assign(op: &syn::BinOp, target: &str, value: &str) -> Option<String> {
+    let verb = match op {
+        syn::BinOp::AddAssign(_) => "increase",
+        syn::BinOp::SubAssign(_) => "decrease",
+        syn::BinOp::MulAssign(_) => "multiply",
+        syn::BinOp::DivAssign(_) => "divide",
+        syn::BinOp::RemAssign(_) => {
+            return Some(format!("update {} with modulo {}", target, value));
+        }
+        syn::BinOp::BitXorAssign(_) => {
+            return Some(format!("update {} with bitwise xor {}", target, value));
+        }
+        syn::BinOp::BitAndAssign(_) => {
+            return Some(format!("update {} with bitwise and {}", target, value));
+        }
+        syn::BinOp::BitOrAssign(_) => {
+            return Some(format!("update {} with bitwise or {}", target, value));
+        }
+        syn::BinOp::ShlAssign(_) => {
+            return Some(format!("update {} with shift left {}", target, value));
+        }
+        syn::BinOp::ShrAssign(_) => {
+            return Some(format!("update {} with shift right {}", target, value));
+        }
+        _ => return None,
+    };
+    Some(format!("{} {} by {}", verb, target, value))
+}
+
 /// Maps Rust unary operators to Iron representations
 pub fn map_unary_op(op: &syn::UnOp) -> String {
     match op {
@@ -0,0 +1,174 @@
+//! Code blocks in Markdown documents
+//!
+//! Finds fenced code blocks of one language and rewrites each with a conversion, so a
+//! document's Rust examples can be reduced to Iron (or its Iron oxidized to Rust) while
+//! its prose stays as written.
+
+use crate::TranspileError;
+
+/// A code block whose conversion failed, left in the document as it was
+#[derive(Debug, Clone)]
+pub struct BlockError {
+    /// Line of the block's opening fence
+    pub line: usize,
+    pub error: TranspileError,
+}
+
+/// An open fence: its indentation, character, and length
+struct Fence {
+    indent: usize,
+    marker: char,
+    length: usize,
+}
+
+/// Rewrite every fenced code block tagged with one of `from` as a `to` block holding
+/// `convert` of its contents
+///
+/// The tag is the first word of the info string, so ```` ```rust,ignore ```` counts as
+/// `rust`. A block that fails to convert is kept unchanged and its error returned.
+pub fn convert_code_blocks(
+    markdown: &str,
+    from: &[&str],
+    to: &str,
+    mut convert: impl FnMut(&str) -> Result<String, TranspileError>,
+) -> (String, Vec<BlockError>) {
+    let lines: Vec<&str> = markdown.split_inclusive('\n').collect();
+    let mut output = String::new();
+    let mut errors = Vec::new();
+
+    let mut index = 0;
+    while index < lines.len() {
+        let line = lines[index];
+        let Some((fence, info)) = opening_fence(line) else {
+            output.push_str(line);
+            index += 1;
+            continue;
+        };
+
+        // An unclosed block runs to the end of the document
+        let body_start = index + 1;
+        let mut body_end = body_start;
+        while body_end < lines.len() && !closes(lines[body_end], &fence) {
+            body_end += 1;
+        }
+        let closing = lines.get(body_end).copied();
+        let block_end = (body_end + 1).min(lines.len());
+
+        let tag = info.split([',', ' ', '\t']).next().unwrap_or_default();
+        if !from.contains(&tag) {
+            output.extend(lines[index..block_end].iter().copied());
+            index = block_end;
+            continue;
+        }
+
+        let body: String = lines[body_start..body_end]
+            .iter()
+            .map(|line| strip_indent(line, fence.indent))
+            .collect();
+        match convert(&body) {
+            Ok(converted) => {
+                let indent = " ".repeat(fence.indent);
+                let fence_text = fence.marker.to_string().repeat(fence.length);
+                output.push_str(&format!("{}{}{}\n", indent, fence_text, to));
+                for converted_line in converted.trim_end_matches('\n').lines() {
+                    if converted_line.is_empty() {
+                        output.push('\n');
+                    } else {
+                        output.push_str(&format!("{}{}\n", indent, converted_line));
+                    }
+                }
+                match closing {
+                    Some(closing) => output.push_str(closing),
+                    None => output.push_str(&format!("{}{}\n", indent, fence_text)),
+                }
+            }
+            Err(error) => {
+                errors.push(BlockError {
+                    line: index + 1,
+                    error,
+                });
+                output.extend(lines[index..block_end].iter().copied());
+            }
+        }
+        index = block_end;
+    }
+    (output, errors)
+}
+
+/// The fence a line opens and its info string, if it opens one
+fn opening_fence(line: &str) -> Option<(Fence, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    let indent = line.len() - trimmed.len();
+    let marker = trimmed.chars().next()?;
+    if indent > 3 || (marker != '`' && marker != '~') {
+        return None;
+    }
+    let length = trimmed.chars().take_while(|&c| c == marker).count();
+    let info = trimmed[length..].trim();
+    // A backtick fence's info string cannot hold backticks
+    if length < 3 || (marker == '`' && info.contains('`')) {
+        return None;
+    }
+    Some((
+        Fence {
+            indent,
+            marker,
+            length,
+        },
+        info,
+    ))
+}
+
+fn closes(line: &str, fence: &Fence) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    let length = trimmed.chars().take_while(|&c| c == fence.marker).count();
+    line.len() - trimmed.len() <= 3 && length >= fence.length && trimmed[length..].trim().is_empty()
+}
+
+/// Remove up to `indent` leading spaces, as the contents of an indented fence do
+fn strip_indent(line: &str, indent: usize) -> &str {
+    let spaces = line.len() - line.trim_start_matches(' ').len();
+    &line[spaces.min(indent)..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shout(code: &str) -> Result<String, TranspileError> {
+        if code.contains("bad") {
+            Err(TranspileError::ParseError("bad code".to_string()))
+        } else {
+            Ok(code.to_uppercase())
+        }
+    }
+
+    #[test]
+    fn test_convert_tagged_blocks_only() {
+        let markdown = "# Title\n\n```rust\nfn f() {}\n```\n\n```text\nfn g() {}\n```\n\n~~~~rust,ignore\nlet x = 1;\n\n~~~~\n";
+        let (converted, errors) = convert_code_blocks(markdown, &["rust"], "iron", shout);
+        assert!(errors.is_empty());
+        assert_eq!(
+            converted,
+            "# Title\n\n```iron\nFN F() {}\n```\n\n```text\nfn g() {}\n```\n\n~~~~iron\nLET X = 1;\n~~~~\n"
+        );
+    }
+
+    #[test]
+    fn test_convert_keeps_failed_and_indented_blocks() {
+        let markdown = "- item\n\n  ```rust\n  fn f() {\n  }\n  ```\n\n```rust\nbad\n```\ntail";
+        let (converted, errors) = convert_code_blocks(markdown, &["rust"], "iron", shout);
+        assert_eq!(
+            converted,
+            "- item\n\n  ```iron\n  FN F() {\n  }\n  ```\n\n```rust\nbad\n```\ntail"
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 8);
+    }
+
+    #[test]
+    fn test_convert_unclosed_block() {
+        let (converted, _) = convert_code_blocks("```rust\nfn f() {}\n", &["rust"], "iron", shout);
+        assert_eq!(converted, "```iron\nFN F() {}\n```\n");
+    }
+}
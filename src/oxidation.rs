@@ -2,11 +2,159 @@
 //!
 //! Converts Iron AST into valid Rust source code.
 
+use crate::dictionary::TypeDictionary;
 use crate::iron_ast::*;
+use crate::symbols::SymbolTable;
+use std::collections::{BTreeSet, HashMap};
+
+/// Placeholder macro that carries comments through syn, which drops real comments
+const COMMENT_MARKER: &str = "__redox_comment";
 
 pub struct Oxidizer {
     output: String,
     indent_level: usize,
+    /// Comment texts, referenced by index from comment markers in `output`
+    comments: Vec<String>,
+    /// Std paths of mapped types used in the item list being oxidized
+    std_imports: BTreeSet<&'static str>,
+    /// Annotate untyped closure arguments from the signature of the called function
+    infer_closure_param_types: bool,
+    /// Parameter types of the functions in the file, keyed by unique name
+    signatures: HashMap<String, Vec<IronType>>,
+    /// Rust edition the output targets
+    edition: Edition,
+    /// Put function generic bounds in a `where` clause instead of inline
+    where_clauses: bool,
+    /// Problems worked around so far, including those found by the parser
+    warnings: Vec<IronWarning>,
+    /// Iron line of the item being oxidized, for warnings
+    line: Option<usize>,
+    /// House style applied to the formatted output
+    config: OxidizerConfig,
+    /// Rust types for Iron type names beyond the built-in ones
+    types: TypeDictionary,
+    /// First problem that makes the output unusable, reported by `oxidize`
+    error: Option<OxidizeError>,
+    /// Names written, and those declared in each open block
+    symbols: SymbolTable,
+}
+
+/// Why an Iron AST could not be turned into Rust
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OxidizeError {
+    /// An AST node with no Rust rendering, such as an inferred type in a signature
+    Unsupported {
+        construct: String,
+        line: Option<usize>,
+    },
+    /// A type name with no Rust equivalent, e.g. `unknown_type` from a failed reduction
+    UnknownType { name: String, line: Option<usize> },
+    /// A verbatim item whose payload is not valid Rust
+    InvalidVerbatim { message: String },
+}
+
+impl std::fmt::Display for OxidizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (message, line) = match self {
+            OxidizeError::Unsupported { construct, line } => {
+                (format!("unsupported construct: {}", construct), line)
+            }
+            OxidizeError::UnknownType { name, line } => {
+                (format!("type '{}' has no Rust equivalent", name), line)
+            }
+            OxidizeError::InvalidVerbatim { message } => (
+                format!("verbatim item is not valid Rust: {}", message),
+                &None,
+            ),
+        };
+        match line {
+            Some(line) => write!(f, "line {}: {}", line, message),
+            None => f.write_str(&message),
+        }
+    }
+}
+
+impl std::error::Error for OxidizeError {}
+
+/// Layout of oxidized Rust, for projects whose style differs from rustfmt's defaults
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OxidizerConfig {
+    /// One level of indentation, e.g. four spaces or a tab
+    pub indent: String,
+    /// Whether the output ends with a newline
+    pub trailing_newline: bool,
+    /// Where opening braces of blocks go
+    pub brace_style: BraceStyle,
+}
+
+impl Default for OxidizerConfig {
+    fn default() -> Self {
+        Self {
+            indent: "    ".to_string(),
+            trailing_newline: true,
+            brace_style: BraceStyle::default(),
+        }
+    }
+}
+
+/// Placement of a block's opening brace
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BraceStyle {
+    /// `fn f() {` (rustfmt's style)
+    #[default]
+    SameLine,
+    /// The brace on its own line, aligned with the line that opens the block
+    NextLine,
+}
+
+/// Rust edition targeted by oxidized code
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Edition {
+    E2015,
+    E2018,
+    E2021,
+    #[default]
+    E2024,
+}
+
+impl Edition {
+    /// Whether `word` is a keyword in this edition that Iron allows as an identifier
+    fn reserves(self, word: &str) -> bool {
+        match word {
+            "async" | "await" | "dyn" | "try" => self >= Edition::E2018,
+            "gen" => self >= Edition::E2024,
+            _ => false,
+        }
+    }
+}
+
+impl std::str::FromStr for Edition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "2015" => Ok(Edition::E2015),
+            "2018" => Ok(Edition::E2018),
+            "2021" => Ok(Edition::E2021),
+            "2024" => Ok(Edition::E2024),
+            _ => Err(format!(
+                "unknown edition '{}' (expected 2015, 2018, 2021, or 2024)",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Edition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let year = match self {
+            Edition::E2015 => "2015",
+            Edition::E2018 => "2018",
+            Edition::E2021 => "2021",
+            Edition::E2024 => "2024",
+        };
+        f.write_str(year)
+    }
 }
 
 impl Oxidizer {
@@ -14,17 +162,233 @@ impl Oxidizer {
         Self {
             output: String::new(),
             indent_level: 0,
+            comments: Vec::new(),
+            std_imports: BTreeSet::new(),
+            infer_closure_param_types: false,
+            signatures: HashMap::new(),
+            edition: Edition::default(),
+            where_clauses: false,
+            warnings: Vec::new(),
+            line: None,
+            config: OxidizerConfig::default(),
+            types: TypeDictionary::default(),
+            error: None,
+            symbols: SymbolTable::new(),
         }
     }
 
-    pub fn oxidize(&mut self, file: &IronFile) -> String {
-        for (i, item) in file.items.iter().enumerate() {
-            if i > 0 {
+    /// Write the Rust types of the Iron type names in `types`
+    pub fn with_types(mut self, types: TypeDictionary) -> Self {
+        self.types = types;
+        self
+    }
+
+    /// Lay out the output with the given indentation, final newline, and brace style
+    pub fn with_config(mut self, config: OxidizerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Target a Rust edition: names that became keywords are written as raw identifiers,
+    /// and 2015 output leaves trait objects without `dyn`
+    pub fn with_edition(mut self, edition: Edition) -> Self {
+        self.edition = edition;
+        self
+    }
+
+    /// Fill in untyped closure parameters when the closure is passed straight to a
+    /// function of the same file whose parameter is a `function taking ...` type
+    pub fn with_closure_param_inference(mut self, enabled: bool) -> Self {
+        self.infer_closure_param_types = enabled;
+        self
+    }
+
+    /// Forget the previous file and its warnings, keeping the settings and buffers, so
+    /// one oxidizer can oxidize many files; without it each output repeats the last
+    pub fn reset(&mut self) {
+        self.output.clear();
+        self.indent_level = 0;
+        self.comments.clear();
+        self.std_imports.clear();
+        self.signatures.clear();
+        self.warnings.clear();
+        self.line = None;
+        self.error = None;
+        self.symbols.clear();
+    }
+
+    pub fn oxidize(&mut self, file: &IronFile) -> Result<String, OxidizeError> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("oxidize_ast", items = file.items.len()).entered();
+        if self.infer_closure_param_types {
+            let mut seen = HashMap::new();
+            Self::collect_signatures(&file.items, &mut seen);
+            // A name defined twice (e.g. in two modules) is ambiguous, so it is left out
+            self.signatures = seen
+                .into_iter()
+                .filter_map(|(name, sig)| Some((name, sig?)))
+                .collect();
+        }
+        self.warnings.extend(file.warnings.iter().cloned());
+        self.oxidize_items(&file.items);
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+
+        let (code, parse_error) = format_rust(&self.output, &self.comments);
+        if let Some(error) = parse_error {
+            self.line = None;
+            self.warn(format!(
+                "generated Rust does not parse ({}), so it was left unformatted",
+                error
+            ));
+        }
+        // Parser and oxidizer warnings interleave by line; file-wide ones go last
+        self.warnings
+            .sort_by_key(|warning| warning.line.unwrap_or(usize::MAX));
+        Ok(apply_layout(&code, &self.config))
+    }
+
+    /// Whether an expression can be assigned to
+    fn is_place(expr: &IronExpr) -> bool {
+        match expr {
+            IronExpr::Identifier(_) | IronExpr::FieldAccess { .. } | IronExpr::Index { .. } => true,
+            IronExpr::Unary {
+                op: IronUnaryOp::Deref,
+                ..
+            } => true,
+            IronExpr::Group(inner) => Self::is_place(inner),
+            _ => false,
+        }
+    }
+
+    /// Problems worked around while oxidizing, such as dropped bounds or closure modifiers
+    pub fn warnings(&self) -> &[IronWarning] {
+        &self.warnings
+    }
+
+    /// The names oxidized so far, with the Iron spelling each had
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Record an error; oxidation carries on so the output stays well-formed, but only
+    /// the first error is reported
+    fn fail(&mut self, error: OxidizeError) {
+        self.error.get_or_insert(error);
+    }
+
+    fn unsupported(&mut self, construct: &str) {
+        self.fail(OxidizeError::Unsupported {
+            construct: construct.to_string(),
+            line: self.line,
+        });
+    }
+
+    fn warn(&mut self, message: String) {
+        self.warnings.push(IronWarning {
+            line: self.line,
+            message,
+        });
+    }
+
+    /// Rust struct and enum generics are emitted without their bounds
+    fn warn_dropped_bounds(&mut self, generics: &[IronGeneric], kind: &str, name: &str) {
+        for generic in generics.iter().filter(|generic| !generic.bounds.is_empty()) {
+            self.warn(format!(
+                "bounds on generic type {} of {} {} were dropped",
+                generic.name, kind, name
+            ));
+        }
+    }
+
+    fn oxidize_items(&mut self, items: &[IronItem]) {
+        let outer_imports = std::mem::take(&mut self.std_imports);
+        let start = self.output.len();
+
+        for (i, item) in items.iter().enumerate() {
+            // A comment stays directly above the item it introduces
+            if i > 0 && !matches!(items[i - 1], IronItem::Comment(_)) {
                 self.output.push_str("\n\n");
             }
             self.oxidize_item(item);
         }
-        self.output.clone()
+
+        // Mapped std types ("hash map", ...) need a `use` in the scope that names them
+        let used = std::mem::replace(&mut self.std_imports, outer_imports);
+        let imported = Self::imported_names(items);
+        let mut block = String::new();
+        for path in used {
+            if !imported.contains(type_name_of(path)) {
+                block.push_str(&"    ".repeat(self.indent_level));
+                block.push_str(&format!("use {};\n", path));
+            }
+        }
+        if !block.is_empty() {
+            block.push('\n');
+            self.output.insert_str(start, &block);
+        }
+    }
+
+    /// Names already brought into scope by the imports among `items`
+    fn imported_names(items: &[IronItem]) -> BTreeSet<String> {
+        fn collect(tree: &IronUseTree, names: &mut BTreeSet<String>) {
+            match tree {
+                IronUseTree::Path { rest, .. } => collect(rest, names),
+                IronUseTree::Name { name, alias } => {
+                    names.insert(alias.clone().unwrap_or_else(|| name.clone()));
+                }
+                IronUseTree::Group(entries) => {
+                    for entry in entries {
+                        collect(entry, names);
+                    }
+                }
+            }
+        }
+
+        let mut names = BTreeSet::new();
+        for item in items {
+            match item {
+                IronItem::Import(import) => collect(&import.tree, &mut names),
+                // Glob and `pub use` imports are carried as verbatim items
+                IronItem::Verbatim(verbatim) if verbatim.rust.contains("use ") => {
+                    for (_, path) in STD_TYPE_PATHS {
+                        let name = type_name_of(path);
+                        if verbatim.rust.contains(name) {
+                            names.insert(name.to_string());
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        names
+    }
+
+    /// Render function generic bounds as `where` clauses rather than inline
+    /// (`fn f<T>() where T: A + B` instead of `fn f<T: A + B>()`)
+    pub fn with_where_clauses(mut self, enabled: bool) -> Self {
+        self.where_clauses = enabled;
+        self
+    }
+
+    fn collect_signatures(
+        items: &[IronItem],
+        signatures: &mut HashMap<String, Option<Vec<IronType>>>,
+    ) {
+        for item in items {
+            match item {
+                IronItem::Function(func) => {
+                    let params = func.params.iter().map(|param| param.ty.clone()).collect();
+                    signatures
+                        .entry(func.name.clone())
+                        .and_modify(|sig| *sig = None)
+                        .or_insert(Some(params));
+                }
+                IronItem::Module(module) => Self::collect_signatures(&module.items, signatures),
+                _ => {}
+            }
+        }
     }
 
     fn oxidize_item(&mut self, item: &IronItem) {
@@ -35,47 +399,46 @@ impl Oxidizer {
             IronItem::Static(stat) => self.oxidize_static(stat),
             IronItem::Const(cnst) => self.oxidize_const(cnst),
             IronItem::TypeAlias(alias) => self.oxidize_type_alias(alias),
-            IronItem::Verbatim(item) => self.oxidize_verbatim_item(item),
+            IronItem::Module(module) => self.oxidize_module(module),
+            IronItem::Import(import) => self.oxidize_import(import),
+            IronItem::Verbatim(verbatim) => self.oxidize_verbatim_item(verbatim),
+            IronItem::Comment(text) => {
+                self.write_indent();
+                self.oxidize_comment(text);
+            }
+        }
+        #[cfg(feature = "tracing")]
+        if !matches!(item, IronItem::Comment(_)) {
+            tracing::debug!(line = ?self.line, "item oxidized");
         }
     }
 
     fn oxidize_function(&mut self, func: &IronFunction) {
+        self.line = Some(func.line);
+
         // Function signature
+        self.write_indent();
         self.output.push_str("fn ");
-        self.output.push_str(&func.name);
+        self.push_identifier(&func.name);
+        self.symbols.declare(&func.name);
+        // Parameters are declared in a scope around the body's
+        self.symbols.enter_scope();
 
         // Generics
-        if !func.generics.is_empty() {
-            self.output.push_str("<");
-            for (i, generic) in func.generics.iter().enumerate() {
-                if i > 0 {
-                    self.output.push_str(", ");
-                }
-                self.output.push_str(&generic.name);
-                if !generic.bounds.is_empty() {
-                    self.output.push_str(": ");
-                    for (j, bound) in generic.bounds.iter().enumerate() {
-                        if j > 0 {
-                            self.output.push_str(" + ");
-                        }
-                        self.output.push_str(&bound.trait_name);
-                    }
-                }
-            }
-            self.output.push_str(">");
-        }
+        self.oxidize_generic_params(&func.generics, !self.where_clauses);
 
         // Parameters
-        self.output.push_str("(");
+        self.output.push('(');
         for (i, param) in func.params.iter().enumerate() {
             if i > 0 {
                 self.output.push_str(", ");
             }
-            self.output.push_str(&param.name);
+            self.push_identifier(&param.name);
+            self.symbols.declare(&param.name);
             self.output.push_str(": ");
             self.oxidize_type(&param.ty);
         }
-        self.output.push_str(")");
+        self.output.push(')');
 
         // Return type
         if let Some(ret) = &func.return_type {
@@ -83,32 +446,48 @@ impl Oxidizer {
             self.oxidize_type(ret);
         }
 
+        if self.where_clauses {
+            self.oxidize_where_clause(&func.generics);
+        }
+
         // Body
         self.output.push_str(" {\n");
         self.indent_level += 1;
-        let body_len = func.body.len();
-        for (i, stmt) in func.body.iter().enumerate() {
-            let is_last = i == body_len - 1;
-            self.oxidize_statement(stmt, is_last);
-        }
+        self.oxidize_statements(&func.body, true);
         self.indent_level -= 1;
+        self.write_indent();
         self.output.push_str("}\n");
+        self.symbols.exit_scope();
     }
 
     fn oxidize_struct(&mut self, strct: &IronStruct) {
+        self.line = Some(strct.line);
+        self.write_indent();
         self.output.push_str("struct ");
         self.output.push_str(&strct.name);
+        self.symbols.declare(&strct.name);
 
         // Generics
-        if !strct.generics.is_empty() {
-            self.output.push_str("<");
-            for (i, generic) in strct.generics.iter().enumerate() {
+        self.warn_dropped_bounds(&strct.generics, "structure", &strct.name);
+        self.oxidize_generic_params(&strct.generics, false);
+
+        // Tuple structs carry positional fields named field0, field1, ...
+        let is_tuple = !strct.fields.is_empty()
+            && strct
+                .fields
+                .iter()
+                .enumerate()
+                .all(|(i, field)| Self::tuple_field_index(&field.name) == Some(i));
+        if is_tuple {
+            self.output.push('(');
+            for (i, field) in strct.fields.iter().enumerate() {
                 if i > 0 {
                     self.output.push_str(", ");
                 }
-                self.output.push_str(&generic.name);
+                self.oxidize_type(&field.ty);
             }
-            self.output.push_str(">");
+            self.output.push_str(");\n");
+            return;
         }
 
         // Fields
@@ -116,30 +495,35 @@ impl Oxidizer {
         self.indent_level += 1;
         for field in &strct.fields {
             self.write_indent();
-            self.output.push_str(&field.name);
+            self.push_identifier(&field.name);
             self.output.push_str(": ");
             self.oxidize_type(&field.ty);
             self.output.push_str(",\n");
         }
         self.indent_level -= 1;
+        self.write_indent();
         self.output.push_str("}\n");
     }
 
+    /// Position of a tuple field written as `field<N>`
+    fn tuple_field_index(name: &str) -> Option<usize> {
+        let digits = name.strip_prefix("field")?;
+        if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        digits.parse().ok()
+    }
+
     fn oxidize_enum(&mut self, enm: &IronEnum) {
+        self.line = Some(enm.line);
+        self.write_indent();
         self.output.push_str("enum ");
         self.output.push_str(&enm.name);
+        self.symbols.declare(&enm.name);
 
         // Generics
-        if !enm.generics.is_empty() {
-            self.output.push_str("<");
-            for (i, generic) in enm.generics.iter().enumerate() {
-                if i > 0 {
-                    self.output.push_str(", ");
-                }
-                self.output.push_str(&generic.name);
-            }
-            self.output.push_str(">");
-        }
+        self.warn_dropped_bounds(&enm.generics, "enumeration", &enm.name);
+        self.oxidize_generic_params(&enm.generics, false);
 
         // Variants
         self.output.push_str(" {\n");
@@ -151,9 +535,9 @@ impl Oxidizer {
             if let Some(data) = &variant.data {
                 match data {
                     IronVariantData::Type(ty) => {
-                        self.output.push_str("(");
+                        self.output.push('(');
                         self.oxidize_type(ty);
-                        self.output.push_str(")");
+                        self.output.push(')');
                     }
                     IronVariantData::Fields(fields) => {
                         self.output.push_str(" {");
@@ -161,21 +545,24 @@ impl Oxidizer {
                             if i > 0 {
                                 self.output.push_str(", ");
                             }
-                            self.output.push_str(&field.name);
+                            self.push_identifier(&field.name);
                             self.output.push_str(": ");
                             self.oxidize_type(&field.ty);
                         }
-                        self.output.push_str("}");
+                        self.output.push('}');
                     }
                 }
             }
             self.output.push_str(",\n");
         }
         self.indent_level -= 1;
+        self.write_indent();
         self.output.push_str("}\n");
     }
 
     fn oxidize_static(&mut self, stat: &IronStatic) {
+        self.line = Some(stat.line);
+        self.write_indent();
         self.output.push_str("static ");
         if stat.mutable {
             self.output.push_str("mut ");
@@ -189,6 +576,8 @@ impl Oxidizer {
     }
 
     fn oxidize_const(&mut self, cnst: &IronConst) {
+        self.line = Some(cnst.line);
+        self.write_indent();
         self.output.push_str("const ");
         self.output.push_str(&cnst.name);
         self.output.push_str(": ");
@@ -199,65 +588,148 @@ impl Oxidizer {
     }
 
     fn oxidize_type_alias(&mut self, alias: &IronTypeAlias) {
+        self.line = Some(alias.line);
+        self.write_indent();
         self.output.push_str("type ");
         self.output.push_str(&alias.name);
 
-        if !alias.generics.is_empty() {
-            self.output.push_str("<");
-            for (i, generic) in alias.generics.iter().enumerate() {
-                if i > 0 {
-                    self.output.push_str(", ");
+        // Where clauses on type aliases are not enforced, so bounds stay inline
+        self.oxidize_generic_params(&alias.generics, true);
+
+        self.output.push_str(" = ");
+        self.oxidize_type(&alias.ty);
+        self.output.push_str(";\n");
+    }
+
+    /// Write `<T: A + B, U>`, leaving bounds out when they go in a where clause
+    fn oxidize_generic_params(&mut self, generics: &[IronGeneric], with_bounds: bool) {
+        if generics.is_empty() {
+            return;
+        }
+        self.output.push('<');
+        for (i, generic) in generics.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
+            }
+            self.output.push_str(&generic.name);
+            if with_bounds && !generic.bounds.is_empty() {
+                self.output.push_str(": ");
+                self.oxidize_bounds(&generic.bounds);
+            }
+        }
+        self.output.push('>');
+    }
+
+    /// Write ` where T: A + B, U: C` for the bounded generics
+    fn oxidize_where_clause(&mut self, generics: &[IronGeneric]) {
+        let bounded: Vec<&IronGeneric> = generics
+            .iter()
+            .filter(|generic| !generic.bounds.is_empty())
+            .collect();
+        if bounded.is_empty() {
+            return;
+        }
+        self.output.push_str(" where ");
+        for (i, generic) in bounded.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(", ");
+            }
+            self.output.push_str(&generic.name);
+            self.output.push_str(": ");
+            self.oxidize_bounds(&generic.bounds);
+        }
+    }
+
+    fn oxidize_bounds(&mut self, bounds: &[IronBound]) {
+        for (i, bound) in bounds.iter().enumerate() {
+            if i > 0 {
+                self.output.push_str(" + ");
+            }
+            self.output.push_str(&bound.trait_name);
+        }
+    }
+
+    fn oxidize_module(&mut self, module: &IronModule) {
+        self.write_indent();
+        self.output.push_str("mod ");
+        self.output.push_str(&module.name);
+        self.output.push_str(" {\n");
+        self.indent_level += 1;
+        self.oxidize_items(&module.items);
+        self.indent_level -= 1;
+        self.write_indent();
+        self.output.push_str("}\n");
+    }
+
+    fn oxidize_import(&mut self, import: &IronImport) {
+        self.write_indent();
+        self.output.push_str("use ");
+        self.oxidize_use_tree(&import.tree);
+        self.output.push_str(";\n");
+    }
+
+    fn oxidize_use_tree(&mut self, tree: &IronUseTree) {
+        match tree {
+            IronUseTree::Path { segment, rest } => {
+                self.output.push_str(segment);
+                self.output.push_str("::");
+                self.oxidize_use_tree(rest);
+            }
+            IronUseTree::Name { name, alias } => {
+                self.output.push_str(name);
+                if let Some(alias) = alias {
+                    self.output.push_str(" as ");
+                    self.output.push_str(alias);
                 }
-                self.output.push_str(&generic.name);
-                if !generic.bounds.is_empty() {
-                    self.output.push_str(": ");
-                    for (j, bound) in generic.bounds.iter().enumerate() {
-                        if j > 0 {
-                            self.output.push_str(" + ");
-                        }
-                        self.output.push_str(&bound.trait_name);
+            }
+            IronUseTree::Group(entries) => {
+                self.output.push('{');
+                for (i, entry) in entries.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
                     }
+                    self.oxidize_use_tree(entry);
                 }
+                self.output.push('}');
             }
-            self.output.push_str(">");
         }
+    }
 
-        self.output.push_str(" = ");
-        self.oxidize_type(&alias.ty);
-        self.output.push_str(";\n");
+    /// Comments are written as marker macro calls so they survive pretty-printing;
+    /// `format_rust` turns them back into `//` lines
+    fn oxidize_comment(&mut self, text: &str) {
+        self.output
+            .push_str(&format!("{}!({});\n", COMMENT_MARKER, self.comments.len()));
+        self.comments.push(text.to_string());
     }
 
-    fn oxidize_verbatim_item(&mut self, item: &str) {
-        self.output.push_str(item);
-        self.output.push_str("\n");
+    fn oxidize_verbatim_item(&mut self, verbatim: &IronVerbatim) {
+        self.line = Some(verbatim.line);
+        if let Err(error) = syn::parse_file(&verbatim.rust) {
+            self.fail(OxidizeError::InvalidVerbatim {
+                message: error.to_string(),
+            });
+        }
+        self.write_indent();
+        self.output.push_str(&verbatim.rust);
+        self.output.push('\n');
     }
 
     fn oxidize_type(&mut self, ty: &IronType) {
         match ty {
             IronType::Named(name) => {
-                // Map Iron type names back to Rust
-                let rust_name = match name.as_str() {
-                    "boolean" => "bool".to_string(),
-                    "character" => "char".to_string(),
-                    "string" => "String".to_string(),
-                    "string slice" => "str".to_string(),
-                    "list" => "Vec".to_string(),
-                    "optional" => "Option".to_string(),
-                    "result" => "Result".to_string(),
-                    "hash map" => "HashMap".to_string(),
-                    "box" => "Box".to_string(),
-                    "reference counted" => "Rc".to_string(),
-                    "atomic reference counted" => "Arc".to_string(),
-                    "unit" => "()".to_string(),
-                    "error" => "dyn std::error::Error".to_string(),
-                    "std::error::Error" => "dyn std::error::Error".to_string(),
-                    "std::fmt::Display" => "dyn std::fmt::Display".to_string(),
-                    _ => name.to_string(),
-                };
+                if matches!(name.as_str(), "unknown" | "unknown_type") {
+                    self.fail(OxidizeError::UnknownType {
+                        name: name.clone(),
+                        line: self.line,
+                    });
+                }
+                self.note_std_import(name);
+                let rust_name = self.rust_type_name(name);
                 self.output.push_str(&rust_name);
             }
             IronType::Reference(inner) => {
-                self.output.push_str("&");
+                self.output.push('&');
                 self.oxidize_type(inner);
             }
             IronType::MutableReference(inner) => {
@@ -275,45 +747,45 @@ impl Oxidizer {
             IronType::Optional(inner) => {
                 self.output.push_str("std::option::Option<");
                 self.oxidize_type(inner);
-                self.output.push_str(">");
+                self.output.push('>');
             }
             IronType::Result(ok, err) => {
                 self.output.push_str("std::result::Result<");
                 self.oxidize_type(ok);
                 self.output.push_str(", ");
                 self.oxidize_type(err);
-                self.output.push_str(">");
+                self.output.push('>');
             }
             IronType::List(inner) => {
                 self.output.push_str("Vec<");
                 self.oxidize_type(inner);
-                self.output.push_str(">");
+                self.output.push('>');
             }
             IronType::BoxType(inner) => {
                 self.output.push_str("Box<");
                 self.oxidize_type(inner);
-                self.output.push_str(">");
+                self.output.push('>');
             }
             IronType::Tuple(types) => {
-                self.output.push_str("(");
+                self.output.push('(');
                 for (i, ty) in types.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
                     self.oxidize_type(ty);
                 }
-                self.output.push_str(")");
+                self.output.push(')');
             }
             IronType::Array(inner) => {
-                self.output.push_str("[");
+                self.output.push('[');
                 self.oxidize_type(inner);
-                self.output.push_str("]");
+                self.output.push(']');
             }
             IronType::Slice(inner) => {
                 // Slice is just [T], the reference is handled by Reference/MutableReference
-                self.output.push_str("[");
+                self.output.push('[');
                 self.oxidize_type(inner);
-                self.output.push_str("]");
+                self.output.push(']');
             }
             IronType::Function(params, ret) => {
                 self.output.push_str("fn(");
@@ -323,18 +795,100 @@ impl Oxidizer {
                     }
                     self.oxidize_type(param);
                 }
-                self.output.push_str(")");
+                self.output.push(')');
                 self.output.push_str(" -> ");
                 self.oxidize_type(ret);
             }
             IronType::Generic(name, _bounds) => {
                 self.output.push_str(name);
             }
+            IronType::Inferred => {
+                // Closure parameters skip the annotation before getting here
+                self.unsupported("inferred type outside a closure parameter");
+                self.output.push('_');
+            }
+            IronType::Path { name, args } => {
+                self.note_std_import(name);
+                let rust_name = self.rust_type_name(name);
+                self.output.push_str(&rust_name);
+                self.output.push('<');
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.oxidize_type(arg);
+                }
+                self.output.push('>');
+            }
+        }
+    }
+
+    /// Record the `use` needed by a mapped std type outside the prelude
+    fn note_std_import(&mut self, name: &str) {
+        if let Some((_, path)) = STD_TYPE_PATHS.iter().find(|(iron, _)| *iron == name) {
+            self.std_imports.insert(path);
+        }
+    }
+
+    /// Map an Iron type name back to Rust
+    fn rust_type_name(&self, name: &str) -> String {
+        if let Some(rust) = self.types.rust_name(name) {
+            return rust.to_string();
+        }
+        let dyn_prefix = if self.edition >= Edition::E2018 {
+            "dyn "
+        } else {
+            ""
+        };
+        match name {
+            "boolean" => "bool".to_string(),
+            "character" => "char".to_string(),
+            "string" => "String".to_string(),
+            "string slice" => "str".to_string(),
+            "list" => "Vec".to_string(),
+            "optional" => "Option".to_string(),
+            "result" => "Result".to_string(),
+            "hash map" => "HashMap".to_string(),
+            "box" => "Box".to_string(),
+            "reference counted" => "Rc".to_string(),
+            "atomic reference counted" => "Arc".to_string(),
+            "unit" => "()".to_string(),
+            "error" | "std::error::Error" => format!("{}std::error::Error", dyn_prefix),
+            "std::fmt::Display" => format!("{}std::fmt::Display", dyn_prefix),
+            _ => name.to_string(),
+        }
+    }
+
+    /// Oxidize a statement list in a scope of its own; when the block is used as a value,
+    /// its last non-comment statement is the tail and keeps no semicolon
+    fn oxidize_statements(&mut self, stmts: &[IronStmt], value: bool) {
+        self.symbols.enter_scope();
+        let tail = stmts
+            .iter()
+            .rposition(|stmt| !matches!(stmt, IronStmt::Comment(_)))
+            .filter(|_| value);
+        match tail {
+            // Nothing may follow a tail expression, so its trailing comments move above it
+            Some(tail) if matches!(stmts[tail], IronStmt::Expr(_)) => {
+                for stmt in stmts[..tail].iter().chain(&stmts[tail + 1..]) {
+                    self.oxidize_statement(stmt, false);
+                }
+                self.oxidize_statement(&stmts[tail], true);
+            }
+            _ => {
+                for (i, stmt) in stmts.iter().enumerate() {
+                    self.oxidize_statement(stmt, Some(i) == tail);
+                }
+            }
         }
+        self.symbols.exit_scope();
     }
 
     fn oxidize_statement(&mut self, stmt: &IronStmt, is_last: bool) {
-        self.write_indent();
+        // Items indent themselves
+        if !matches!(stmt, IronStmt::Item(_)) {
+            self.write_indent();
+        }
 
         match stmt {
             IronStmt::Let {
@@ -346,22 +900,37 @@ impl Oxidizer {
                 if *mutable {
                     self.output.push_str("mut ");
                 }
-                self.output.push_str(name);
+                self.push_identifier(name);
+                self.symbols.declare(name);
                 self.output.push_str(" = ");
                 self.oxidize_expr(value);
                 self.output.push_str(";\n");
             }
             IronStmt::Assign { target, value } => {
+                if !Self::is_place(target) {
+                    self.unsupported("assignment to an expression that is not a place");
+                }
                 self.oxidize_expr(target);
                 self.output.push_str(" = ");
                 self.oxidize_expr(value);
                 self.output.push_str(";\n");
             }
+            IronStmt::CompoundAssign { target, op, value } => {
+                if !Self::is_place(target) {
+                    self.unsupported("assignment to an expression that is not a place");
+                }
+                self.oxidize_expr(target);
+                self.output.push(' ');
+                self.oxidize_binary_op(op);
+                self.output.push_str("= ");
+                self.oxidize_expr(value);
+                self.output.push_str(";\n");
+            }
             IronStmt::Expr(expr) => {
                 self.oxidize_expr(expr);
                 if is_last {
                     // Tail expression - no semicolon
-                    self.output.push_str("\n");
+                    self.output.push('\n');
                 } else {
                     self.output.push_str(";\n");
                 }
@@ -369,11 +938,18 @@ impl Oxidizer {
             IronStmt::Return(expr) => {
                 self.output.push_str("return");
                 if let Some(val) = expr {
-                    self.output.push_str(" ");
+                    self.output.push(' ');
                     self.oxidize_expr(val);
                 }
                 self.output.push_str(";\n");
             }
+            IronStmt::Comment(text) => self.oxidize_comment(text),
+            IronStmt::Item(item) => {
+                // Keep reporting problems at the enclosing item once the local one is done
+                let line = self.line;
+                self.oxidize_item(item);
+                self.line = line;
+            }
             IronStmt::Break => {
                 self.output.push_str("break;\n");
             }
@@ -385,40 +961,16 @@ impl Oxidizer {
                 then_block,
                 else_block,
             } => {
-                self.output.push_str("if ");
-                self.oxidize_expr(condition);
-                self.output.push_str(" {\n");
-                self.indent_level += 1;
-                let then_len = then_block.len();
-                for (i, s) in then_block.iter().enumerate() {
-                    self.oxidize_statement(s, i == then_len - 1);
-                }
-                self.indent_level -= 1;
-                self.write_indent();
-                self.output.push_str("}");
-
-                if let Some(else_blk) = else_block {
-                    self.output.push_str(" else {\n");
-                    self.indent_level += 1;
-                    let else_len = else_blk.len();
-                    for (i, s) in else_blk.iter().enumerate() {
-                        self.oxidize_statement(s, i == else_len - 1);
-                    }
-                    self.indent_level -= 1;
-                    self.write_indent();
-                    self.output.push_str("}");
-                }
-                self.output.push_str("\n");
+                // Only a tail if yields its branches' values; elsewhere they are statements
+                self.oxidize_if_stmt(condition, then_block, else_block.as_deref(), is_last);
+                self.output.push('\n');
             }
             IronStmt::While { condition, body } => {
                 self.output.push_str("while ");
                 self.oxidize_expr(condition);
                 self.output.push_str(" {\n");
                 self.indent_level += 1;
-                let body_len = body.len();
-                for (i, s) in body.iter().enumerate() {
-                    self.oxidize_statement(s, i == body_len - 1);
-                }
+                self.oxidize_statements(body, false);
                 self.indent_level -= 1;
                 self.write_indent();
                 self.output.push_str("}\n");
@@ -429,15 +981,23 @@ impl Oxidizer {
                 body,
             } => {
                 self.output.push_str("for ");
-                self.output.push_str(var);
+                self.push_identifier(var);
                 self.output.push_str(" in ");
                 self.oxidize_expr(iterator);
                 self.output.push_str(" {\n");
                 self.indent_level += 1;
-                let body_len = body.len();
-                for (i, s) in body.iter().enumerate() {
-                    self.oxidize_statement(s, i == body_len - 1);
-                }
+                self.symbols.enter_scope();
+                self.symbols.declare(var);
+                self.oxidize_statements(body, false);
+                self.symbols.exit_scope();
+                self.indent_level -= 1;
+                self.write_indent();
+                self.output.push_str("}\n");
+            }
+            IronStmt::Loop { body } => {
+                self.output.push_str("loop {\n");
+                self.indent_level += 1;
+                self.oxidize_statements(body, false);
                 self.indent_level -= 1;
                 self.write_indent();
                 self.output.push_str("}\n");
@@ -461,15 +1021,58 @@ impl Oxidizer {
         }
     }
 
+    /// Write an if statement, folding a lone nested if in the else block into `else if`
+    fn oxidize_if_stmt(
+        &mut self,
+        condition: &IronExpr,
+        then_block: &[IronStmt],
+        else_block: Option<&[IronStmt]>,
+        value: bool,
+    ) {
+        self.output.push_str("if ");
+        self.oxidize_expr(condition);
+        self.output.push_str(" {\n");
+        self.indent_level += 1;
+        self.oxidize_statements(then_block, value);
+        self.indent_level -= 1;
+        self.write_indent();
+        self.output.push('}');
+
+        match else_block {
+            Some(
+                [
+                    IronStmt::If {
+                        condition,
+                        then_block,
+                        else_block,
+                    },
+                ],
+            ) => {
+                self.output.push_str(" else ");
+                self.oxidize_if_stmt(condition, then_block, else_block.as_deref(), value);
+            }
+            Some(else_blk) => {
+                self.output.push_str(" else {\n");
+                self.indent_level += 1;
+                self.oxidize_statements(else_blk, value);
+                self.indent_level -= 1;
+                self.write_indent();
+                self.output.push('}');
+            }
+            None => {}
+        }
+    }
+
     fn oxidize_expr(&mut self, expr: &IronExpr) {
         match expr {
             IronExpr::Identifier(name) => {
-                self.output.push_str(name);
+                self.push_identifier(name);
             }
             IronExpr::String(s) => {
-                self.output.push_str("\"");
-                self.output.push_str(s);
-                self.output.push_str("\"");
+                self.output.push_str(&format!("{:?}", s));
+            }
+            IronExpr::Char(ch) => {
+                self.output.push_str(&format!("{:?}", ch));
             }
             IronExpr::Integer(n) => {
                 self.output.push_str(n);
@@ -482,43 +1085,66 @@ impl Oxidizer {
             }
             IronExpr::Binary { left, op, right } => {
                 self.oxidize_expr(left);
-                self.output.push_str(" ");
+                self.output.push(' ');
                 self.oxidize_binary_op(op);
-                self.output.push_str(" ");
+                self.output.push(' ');
                 self.oxidize_expr(right);
             }
             IronExpr::Unary { op, expr } => {
                 self.oxidize_unary_op(op);
-                self.output.push_str(" ");
-                self.oxidize_expr(expr);
-            }
-            IronExpr::Call { func, args } => {
-                self.oxidize_expr(func);
-                self.output.push_str("(");
+                if matches!(expr.as_ref(), IronExpr::Binary { .. }) {
+                    self.output.push('(');
+                    self.oxidize_expr(expr);
+                    self.output.push(')');
+                } else {
+                    self.oxidize_expr(expr);
+                }
+            }
+            IronExpr::Call { func, args } => {
+                self.oxidize_expr(func);
+                self.output.push('(');
+                let signature = match &**func {
+                    IronExpr::Identifier(name) => self.signatures.get(name).cloned(),
+                    _ => None,
+                };
                 for (i, arg) in args.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    self.oxidize_expr(arg);
+                    match signature.as_ref().and_then(|sig| sig.get(i)) {
+                        Some(expected) => self.oxidize_argument(arg, expected),
+                        None => self.oxidize_element(arg),
+                    }
                 }
-                self.output.push_str(")");
+                self.output.push(')');
             }
             IronExpr::MethodCall {
                 receiver,
                 method,
                 args,
             } => {
-                self.oxidize_expr(receiver);
-                self.output.push_str(".");
-                self.output.push_str(method);
-                self.output.push_str("(");
+                // A method binds tighter than a minus sign, so -5.abs() would be -(5.abs())
+                let negative_literal = matches!(
+                    receiver.as_ref(),
+                    IronExpr::Integer(n) | IronExpr::Float(n) if n.starts_with('-')
+                );
+                if negative_literal {
+                    self.output.push('(');
+                    self.oxidize_expr(receiver);
+                    self.output.push(')');
+                } else {
+                    self.oxidize_expr(receiver);
+                }
+                self.output.push('.');
+                self.push_identifier(method);
+                self.output.push('(');
                 for (i, arg) in args.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    self.oxidize_expr(arg);
+                    self.oxidize_element(arg);
                 }
-                self.output.push_str(")");
+                self.output.push(')');
             }
             IronExpr::AssociatedFunctionCall {
                 type_name,
@@ -528,14 +1154,14 @@ impl Oxidizer {
                 self.output.push_str(type_name);
                 self.output.push_str("::");
                 self.output.push_str(function);
-                self.output.push_str("(");
+                self.output.push('(');
                 for (i, arg) in args.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    self.oxidize_expr(arg);
+                    self.oxidize_element(arg);
                 }
-                self.output.push_str(")");
+                self.output.push(')');
             }
             IronExpr::Macro {
                 name,
@@ -548,80 +1174,94 @@ impl Oxidizer {
                     if !args.is_empty() {
                         self.output.push_str(args);
                     }
-                    self.output.push_str("]");
+                    self.output.push(']');
                 } else {
                     self.output.push_str("!(");
                     if !args.is_empty() {
                         self.output.push_str(args);
                     }
-                    self.output.push_str(")");
+                    self.output.push(')');
                 }
             }
             IronExpr::FieldAccess { base, field } => {
                 self.oxidize_expr(base);
-                self.output.push_str(".");
-                self.output.push_str(field);
+                self.output.push('.');
+                match Self::tuple_field_index(field) {
+                    Some(index) => self.output.push_str(&index.to_string()),
+                    None => self.push_identifier(field),
+                }
             }
             IronExpr::Try { expr } => {
                 self.oxidize_expr(expr);
-                self.output.push_str("?");
+                self.output.push('?');
             }
             IronExpr::Some(expr) => {
                 self.output.push_str("Some(");
-                self.oxidize_expr(expr);
-                self.output.push_str(")");
+                self.oxidize_element(expr);
+                self.output.push(')');
             }
             IronExpr::None => {
                 self.output.push_str("None");
             }
             IronExpr::Ok(expr) => {
                 self.output.push_str("Ok(");
-                self.oxidize_expr(expr);
-                self.output.push_str(")");
+                self.oxidize_element(expr);
+                self.output.push(')');
             }
             IronExpr::Err(expr) => {
                 self.output.push_str("Err(");
-                self.oxidize_expr(expr);
-                self.output.push_str(")");
+                self.oxidize_element(expr);
+                self.output.push(')');
             }
             IronExpr::Tuple(elems) => {
-                self.output.push_str("(");
+                self.output.push('(');
                 for (i, elem) in elems.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    self.oxidize_expr(elem);
+                    self.oxidize_element(elem);
                 }
-                self.output.push_str(")");
+                self.output.push(')');
             }
             IronExpr::Array(elems) => {
-                self.output.push_str("[");
+                self.output.push('[');
                 for (i, elem) in elems.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    self.oxidize_expr(elem);
+                    self.oxidize_element(elem);
+                }
+                self.output.push(']');
+            }
+            IronExpr::TupleStruct { name, args } => {
+                self.push_identifier(name);
+                self.output.push('(');
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.oxidize_element(arg);
                 }
-                self.output.push_str("]");
+                self.output.push(')');
             }
             IronExpr::Struct { name, fields } => {
-                self.output.push_str(name);
+                self.push_identifier(name);
                 self.output.push_str(" {");
                 for (i, (field, expr)) in fields.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    self.output.push_str(&field.name);
+                    self.push_identifier(&field.name);
                     self.output.push_str(": ");
-                    self.oxidize_expr(expr);
+                    self.oxidize_element(expr);
                 }
-                self.output.push_str("}");
+                self.output.push('}');
             }
             IronExpr::Index { base, index } => {
                 self.oxidize_expr(base);
-                self.output.push_str("[");
-                self.oxidize_expr(index);
-                self.output.push_str("]");
+                self.output.push('[');
+                self.oxidize_element(index);
+                self.output.push(']');
             }
             IronExpr::Range {
                 start,
@@ -641,26 +1281,150 @@ impl Oxidizer {
                 }
             }
             IronExpr::Closure { params, body } => {
-                self.output.push_str("|");
+                self.output.push('|');
                 for (i, param) in params.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    self.output.push_str(&param.name);
+                    self.push_identifier(&param.name);
+                    // Unknown types are left to inference rather than guessed
+                    if !matches!(param.ty, IronType::Inferred) {
+                        self.output.push_str(": ");
+                        self.oxidize_type(&param.ty);
+                    }
                 }
                 self.output.push_str("| {\n");
                 self.indent_level += 1;
-                let body_len = body.len();
-                for (i, stmt) in body.iter().enumerate() {
-                    self.oxidize_statement(stmt, i == body_len - 1);
-                }
+                self.oxidize_statements(body, true);
                 self.indent_level -= 1;
                 self.write_indent();
-                self.output.push_str("}");
+                self.output.push('}');
+            }
+            IronExpr::If {
+                condition,
+                then_block,
+                else_block,
+            } => {
+                self.oxidize_if_expr(condition, then_block, else_block.as_deref());
+            }
+            IronExpr::Group(inner) if Self::is_primary(inner) => self.oxidize_expr(inner),
+            IronExpr::Group(inner) => {
+                self.output.push('(');
+                self.oxidize_expr(inner);
+                self.output.push(')');
+            }
+        }
+    }
+
+    /// Oxidize a whole argument, element, field value, or block value, which commas or
+    /// brackets already delimit, so a group around it needs no parentheses
+    fn oxidize_element(&mut self, expr: &IronExpr) {
+        match expr {
+            IronExpr::Group(inner) => self.oxidize_element(inner),
+            _ => self.oxidize_expr(expr),
+        }
+    }
+
+    /// Whether an expression reads the same in Rust with or without parentheses around
+    /// it, wherever it appears
+    ///
+    /// Field accesses are left out, as `(f.g)()` calls a field but `f.g()` a method, and
+    /// so are literals, which may be negative or take a field.
+    fn is_primary(expr: &IronExpr) -> bool {
+        matches!(
+            expr,
+            IronExpr::Identifier(_)
+                | IronExpr::String(_)
+                | IronExpr::Char(_)
+                | IronExpr::Boolean(_)
+                | IronExpr::Call { .. }
+                | IronExpr::MethodCall { .. }
+                | IronExpr::AssociatedFunctionCall { .. }
+                | IronExpr::Macro { .. }
+                | IronExpr::Index { .. }
+                | IronExpr::Try { .. }
+                | IronExpr::Some(_)
+                | IronExpr::None
+                | IronExpr::Ok(_)
+                | IronExpr::Err(_)
+                | IronExpr::Tuple(_)
+                | IronExpr::Array(_)
+                | IronExpr::TupleStruct { .. }
+                | IronExpr::Group(_)
+        )
+    }
+
+    /// Oxidize a call argument, typing an untyped closure from the parameter it fills
+    fn oxidize_argument(&mut self, arg: &IronExpr, expected: &IronType) {
+        match (arg, expected) {
+            (IronExpr::Closure { params, body }, IronType::Function(types, _))
+                if params.len() == types.len() =>
+            {
+                let params = params
+                    .iter()
+                    .zip(types)
+                    .map(|(param, ty)| IronParam {
+                        name: param.name.clone(),
+                        ty: match param.ty {
+                            IronType::Inferred => ty.clone(),
+                            _ => param.ty.clone(),
+                        },
+                    })
+                    .collect();
+                self.oxidize_expr(&IronExpr::Closure {
+                    params,
+                    body: body.clone(),
+                });
             }
+            _ => self.oxidize_element(arg),
         }
     }
 
+    fn oxidize_if_expr(
+        &mut self,
+        condition: &IronExpr,
+        then_block: &[IronStmt],
+        else_block: Option<&[IronStmt]>,
+    ) {
+        self.output.push_str("if ");
+        self.oxidize_expr(condition);
+        self.output.push(' ');
+        self.oxidize_value_block(then_block);
+
+        if let Some(else_blk) = else_block {
+            self.output.push_str(" else ");
+            if let [
+                IronStmt::Expr(IronExpr::If {
+                    condition,
+                    then_block,
+                    else_block,
+                }),
+            ] = else_blk
+            {
+                self.oxidize_if_expr(condition, then_block, else_block.as_deref());
+            } else {
+                self.oxidize_value_block(else_blk);
+            }
+        }
+    }
+
+    /// Write a block whose last statement is its value, inline when it is a lone expression
+    fn oxidize_value_block(&mut self, block: &[IronStmt]) {
+        if let [IronStmt::Expr(expr)] = block {
+            self.output.push_str("{ ");
+            self.oxidize_element(expr);
+            self.output.push_str(" }");
+            return;
+        }
+
+        self.output.push_str("{\n");
+        self.indent_level += 1;
+        self.oxidize_statements(block, true);
+        self.indent_level -= 1;
+        self.write_indent();
+        self.output.push('}');
+    }
+
     fn oxidize_binary_op(&mut self, op: &IronBinaryOp) {
         let op_str = match op {
             IronBinaryOp::Add => "+",
@@ -697,36 +1461,46 @@ impl Oxidizer {
     fn oxidize_pattern(&mut self, pattern: &IronPattern) {
         match pattern {
             IronPattern::Identifier(name) => {
-                self.output.push_str(name);
+                self.push_identifier(name);
             }
             IronPattern::Wildcard => {
-                self.output.push_str("_");
+                self.output.push('_');
             }
             IronPattern::Literal(expr) => {
+                if !matches!(
+                    expr,
+                    IronExpr::Integer(_)
+                        | IronExpr::Float(_)
+                        | IronExpr::String(_)
+                        | IronExpr::Char(_)
+                        | IronExpr::Boolean(_)
+                ) {
+                    self.unsupported("non-literal expression in a pattern");
+                }
                 self.oxidize_expr(expr);
             }
             IronPattern::Tuple(patterns) => {
-                self.output.push_str("(");
+                self.output.push('(');
                 for (i, pat) in patterns.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
                     self.oxidize_pattern(pat);
                 }
-                self.output.push_str(")");
+                self.output.push(')');
             }
             IronPattern::Struct { name, fields } => {
-                self.output.push_str(name);
+                self.push_identifier(name);
                 self.output.push_str(" {");
                 for (i, (field, pat)) in fields.iter().enumerate() {
                     if i > 0 {
                         self.output.push_str(", ");
                     }
-                    self.output.push_str(&field.name);
+                    self.push_identifier(&field.name);
                     self.output.push_str(": ");
                     self.oxidize_pattern(pat);
                 }
-                self.output.push_str("}");
+                self.output.push('}');
             }
             IronPattern::Variant {
                 enum_name,
@@ -737,14 +1511,27 @@ impl Oxidizer {
                 self.output.push_str("::");
                 self.output.push_str(variant_name);
                 if let Some(d) = data {
-                    self.output.push_str("(");
+                    self.output.push('(');
                     self.oxidize_pattern(d);
-                    self.output.push_str(")");
+                    self.output.push(')');
                 }
             }
         }
     }
 
+    /// Write a name, escaping path segments that are keywords in the target edition
+    fn push_identifier(&mut self, name: &str) {
+        for (i, segment) in name.split("::").enumerate() {
+            if i > 0 {
+                self.output.push_str("::");
+            }
+            if self.edition.reserves(segment) {
+                self.output.push_str("r#");
+            }
+            self.output.push_str(segment);
+        }
+    }
+
     fn write_indent(&mut self) {
         for _ in 0..self.indent_level {
             self.output.push_str("    ");
@@ -752,6 +1539,202 @@ impl Oxidizer {
     }
 }
 
+/// Iron type names that map to std types outside the prelude, with their import paths
+const STD_TYPE_PATHS: [(&str, &str); 3] = [
+    ("hash map", "std::collections::HashMap"),
+    ("reference counted", "std::rc::Rc"),
+    ("atomic reference counted", "std::sync::Arc"),
+];
+
+/// Re-indent formatted code (which uses four spaces per level) and place braces per the
+/// config. Lines that continue a multi-line string literal are left untouched.
+fn apply_layout(code: &str, config: &OxidizerConfig) -> String {
+    const STANDARD_INDENT: &str = "    ";
+
+    let in_string = string_continuation_lines(code);
+    let mut lines: Vec<String> = Vec::new();
+    for (line, in_string) in code.lines().zip(in_string) {
+        if in_string {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let body = line.trim_start_matches(' ');
+        let levels = (line.len() - body.len()) / STANDARD_INDENT.len();
+        let extra = (line.len() - body.len()) % STANDARD_INDENT.len();
+        let indent =
+            |levels: usize| format!("{}{}", config.indent.repeat(levels), " ".repeat(extra));
+
+        if config.brace_style == BraceStyle::NextLine && !body.starts_with("//") {
+            let mut body = body;
+            // `} else {` closes one block and opens the next
+            if let Some(rest) = body.strip_prefix("} ") {
+                lines.push(format!("{}}}", indent(levels)));
+                body = rest;
+            }
+            if let Some(head) = body.strip_suffix(" {") {
+                lines.push(format!("{}{}", indent(levels), head));
+                lines.push(format!("{}{{", indent(levels)));
+            } else {
+                lines.push(format!("{}{}", indent(levels), body));
+            }
+            continue;
+        }
+
+        lines.push(format!("{}{}", indent(levels), body));
+    }
+
+    let mut output = lines.join("\n");
+    if config.trailing_newline && !output.is_empty() {
+        output.push('\n');
+    }
+    output
+}
+
+/// For each line of `code`, whether it starts inside a string literal
+fn string_continuation_lines(code: &str) -> Vec<bool> {
+    let mut flags = Vec::new();
+    let mut chars = code.chars().peekable();
+    // Closing delimiter of the string being read: `"` plus any raw-string hashes
+    let mut open: Option<String> = None;
+    let mut at_line_start = true;
+
+    while let Some(ch) = chars.next() {
+        if at_line_start {
+            flags.push(open.is_some());
+            at_line_start = false;
+        }
+        match (&open, ch) {
+            (_, '\n') => at_line_start = true,
+            (Some(close), '\\') if close == "\"" => {
+                // Skip the escaped character, unless it is a line break
+                if chars.peek() != Some(&'\n') {
+                    chars.next();
+                }
+            }
+            (Some(close), '"') => {
+                let hashes = close.len() - 1;
+                let mut matched = 0;
+                while matched < hashes && chars.peek() == Some(&'#') {
+                    chars.next();
+                    matched += 1;
+                }
+                if matched == hashes {
+                    open = None;
+                }
+            }
+            (Some(_), _) => {}
+            (None, '"') => open = Some("\"".to_string()),
+            (None, 'r') if matches!(chars.peek(), Some('"' | '#')) => {
+                let mut hashes = 0;
+                while chars.peek() == Some(&'#') {
+                    chars.next();
+                    hashes += 1;
+                }
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    open = Some(format!("\"{}", "#".repeat(hashes)));
+                }
+            }
+            (None, '\'') => {
+                // A char literal ('x', '\n', '"'), not a lifetime
+                let mut lookahead = chars.clone();
+                match lookahead.next() {
+                    Some('\\') => {
+                        chars.next();
+                        chars.next();
+                        while chars.next().is_some_and(|c| c != '\'') {}
+                    }
+                    Some(_) if lookahead.next() == Some('\'') => {
+                        chars.next();
+                        chars.next();
+                    }
+                    _ => {}
+                }
+            }
+            (None, _) => {}
+        }
+    }
+    flags
+}
+
+/// The last segment of a std path
+fn type_name_of(path: &str) -> &str {
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+/// Pretty-print generated Rust with prettyplease, keeping the hand-written layout if
+/// the code does not parse, and restore the comments carried by marker macros. The
+/// parse error, if any, is returned alongside.
+fn format_rust(code: &str, comments: &[String]) -> (String, Option<syn::Error>) {
+    let file = match syn::parse_file(code) {
+        Ok(file) => file,
+        Err(error) => return (restore_comments(code, comments), Some(error)),
+    };
+    if file.items.is_empty() {
+        return (
+            restore_comments(&prettyplease::unparse(&file), comments),
+            None,
+        );
+    }
+
+    // prettyplease runs items together, so print them one at a time with blank lines
+    // between, except after a comment introducing the next item and within a run of imports
+    let mut formatted = String::with_capacity(code.len());
+    for (i, item) in file.items.iter().enumerate() {
+        let previous = i.checked_sub(1).map(|prev| &file.items[prev]);
+        let import_run = previous.is_some_and(|prev| matches!(prev, syn::Item::Use(_)))
+            && matches!(item, syn::Item::Use(_));
+        let separate = previous.is_some_and(|prev| !is_comment_marker(prev)) && !import_run;
+        if separate {
+            formatted.push('\n');
+        }
+        let single = syn::File {
+            shebang: None,
+            attrs: if i == 0 {
+                file.attrs.clone()
+            } else {
+                Vec::new()
+            },
+            items: vec![item.clone()],
+        };
+        formatted.push_str(&prettyplease::unparse(&single));
+    }
+    (restore_comments(&formatted, comments), None)
+}
+
+fn is_comment_marker(item: &syn::Item) -> bool {
+    matches!(item, syn::Item::Macro(mac) if mac.mac.path.is_ident(COMMENT_MARKER))
+}
+
+/// Replace `__redox_comment!(N);` lines with the `//` comment they stand for
+fn restore_comments(code: &str, comments: &[String]) -> String {
+    let mut output = String::with_capacity(code.len());
+    for line in code.lines() {
+        let text = line
+            .trim()
+            .strip_prefix(COMMENT_MARKER)
+            .and_then(|rest| rest.strip_prefix("!("))
+            .and_then(|rest| rest.strip_suffix(");"))
+            .and_then(|index| index.parse::<usize>().ok())
+            .and_then(|index| comments.get(index));
+        match text {
+            Some(text) => {
+                let indent = &line[..line.len() - line.trim_start().len()];
+                output.push_str(indent);
+                output.push_str("//");
+                if !text.is_empty() {
+                    output.push(' ');
+                    output.push_str(text);
+                }
+            }
+            None => output.push_str(line),
+        }
+        output.push('\n');
+    }
+    output
+}
+
 impl Default for Oxidizer {
     fn default() -> Self {
         Self::new()
@@ -774,9 +1757,443 @@ end function"#;
         let ast = parser.parse().unwrap();
 
         let mut oxidizer = Oxidizer::new();
-        let rust = oxidizer.oxidize(&ast);
+        let rust = oxidizer.oxidize(&ast).unwrap();
 
         assert!(rust.contains("fn hello()"));
         assert!(rust.contains("return 42"));
     }
+
+    #[test]
+    fn test_oxidize_nested_module() {
+        let iron_input = r#"module outer
+begin
+    function helper
+    begin
+        return 1
+    end function
+
+    module inner
+    begin
+        type Count as i32
+    end module
+end module"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let mut oxidizer = Oxidizer::new();
+        let rust = oxidizer.oxidize(&ast).unwrap();
+
+        assert!(rust.starts_with("mod outer {\n    fn helper() {\n        return 1;\n    }\n"));
+        assert!(rust.contains("    mod inner {\n        type Count = i32;\n    }\n"));
+        assert!(rust.ends_with("}\n"));
+    }
+
+    #[test]
+    fn test_oxidize_imports() {
+        let iron_input = r#"import std collections HashMap
+import std fmt containing user_self and Display as Show"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let mut oxidizer = Oxidizer::new();
+        let rust = oxidizer.oxidize(&ast).unwrap();
+
+        assert!(rust.contains("use std::collections::HashMap;\n"));
+        assert!(rust.contains("use std::fmt::{self, Display as Show};\n"));
+    }
+
+    #[test]
+    fn test_oxidize_method_chains() {
+        let iron_input = r#"function chains
+    takes s of string and items of list of i32
+    returns usize
+begin
+    define t as call method len on call method to_string on call method trim on s
+    define n as call method max on group call method min on call method len on s with 3 end group with t
+    define m as call method count on call method iter on items
+    group call method pow on call helper with 2 end group plus field count of call method unwrap on m
+end function"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let mut oxidizer = Oxidizer::new();
+        let rust = oxidizer.oxidize(&ast).unwrap();
+
+        assert!(rust.contains("let t = s.trim().to_string().len();\n"));
+        assert!(rust.contains("let n = s.len().min(3).max(t);\n"));
+        assert!(rust.contains("let m = items.iter().count();\n"));
+        assert!(rust.contains("helper().pow(2) + m.unwrap().count"));
+    }
+
+    #[test]
+    fn test_oxidize_generic_type_arguments() {
+        let iron_input = r#"function lookup
+    takes cache of Cache of string and list of Wrapper of boolean
+    returns Cache of string and list of Wrapper of boolean
+begin
+    return cache
+end function"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let mut oxidizer = Oxidizer::new();
+        let rust = oxidizer.oxidize(&ast).unwrap();
+
+        assert!(rust.contains("cache: Cache<String, Vec<Wrapper<bool>>>"));
+        assert!(rust.contains("-> Cache<String, Vec<Wrapper<bool>>>"));
+    }
+
+    #[test]
+    fn test_oxidize_comments() {
+        let iron_input = r#"note that adds one
+function bump
+    takes n of i32
+    returns i32
+begin
+    note that the increment
+    define m as n plus 1 note that trailing
+    if m greater than 10 then
+    begin
+        return 10
+    end if
+    note that before otherwise
+    otherwise
+    begin
+        m
+    end if
+    note that after the value
+end function"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let mut oxidizer = Oxidizer::new();
+        let rust = oxidizer.oxidize(&ast).unwrap();
+
+        assert!(rust.starts_with("// adds one\nfn bump"));
+        assert!(rust.contains("    // the increment\n    // trailing\n    let m = n + 1;\n"));
+        assert!(rust.contains("} else {"));
+        assert!(rust.contains("        m\n"));
+        assert!(rust.contains("    // after the value\n}"));
+    }
+
+    #[test]
+    fn test_oxidize_output_is_pretty_printed() {
+        let iron_input = r#"function check
+    takes flag of boolean
+    returns boolean
+begin
+    not flag
+end function
+
+function other
+begin
+    return
+end function"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let mut oxidizer = Oxidizer::new();
+        let rust = oxidizer.oxidize(&ast).unwrap();
+
+        assert_eq!(
+            rust,
+            "fn check(flag: bool) -> bool {\n    !flag\n}\n\nfn other() {\n    return;\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_oxidize_tail_expressions_in_nested_blocks() {
+        let iron_input = r#"function f
+    takes c of boolean
+    returns i32
+begin
+    define mutable n as 0
+    if c then
+    begin
+        call g
+    end if
+    while n less than 3 repeat
+    begin
+        call g
+    end while
+    define x as if c then
+    begin
+        define y as 2
+        y plus 1
+    end if
+    otherwise
+    begin
+        call g
+    end if
+    if c then
+    begin
+        x
+    end if
+    otherwise
+    begin
+        n
+    end if
+end function"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let mut oxidizer = Oxidizer::new();
+        let rust = oxidizer.oxidize(&ast).unwrap();
+
+        assert!(rust.contains("    if c {\n        g();\n    }\n"));
+        assert!(rust.contains("    while n < 3 {\n        g();\n    }\n"));
+        assert!(rust.contains("        y + 1\n    } else {\n        g()\n    };\n"));
+        assert!(rust.contains("    if c { x } else { n }\n}"));
+    }
+
+    #[test]
+    fn test_oxidize_imports_for_mapped_std_types() {
+        let iron_input = r#"import std rc Rc
+
+function count
+    takes words of hash map from string to reference counted string
+    returns atomic reference counted i32
+begin
+    return call associated function new on Arc with 1
+end function
+
+module cache
+begin
+    structure Entry with fields
+        values of hash map
+    end structure
+end module"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let mut oxidizer = Oxidizer::new();
+        let rust = oxidizer.oxidize(&ast).unwrap();
+
+        assert!(rust.starts_with(
+            "use std::collections::HashMap;\nuse std::sync::Arc;\nuse std::rc::Rc;\n\nfn count"
+        ));
+        assert!(rust.contains("words: HashMap<String, Rc<String>>"));
+        assert!(rust.contains("mod cache {\n    use std::collections::HashMap;\n"));
+        assert_eq!(rust.matches("use std::rc::Rc;").count(), 1);
+    }
+
+    #[test]
+    fn test_oxidize_closure_param_types() {
+        let iron_input = r#"function apply
+    takes x of i32 and f of function taking i32 returning i32
+    returns i32
+begin
+    call f with x
+end function
+
+function run
+    returns i32
+begin
+    define typed as closure with parameters n of i64 and body n plus 1
+    define untyped as closure with parameters n and body n plus 1
+    call apply with 3 and closure with parameters n and body n times 2
+end function"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let rust = Oxidizer::new().oxidize(&ast).unwrap();
+        assert!(rust.contains("let typed = |n: i64| { n + 1 };"));
+        assert!(rust.contains("let untyped = |n| { n + 1 };"));
+        assert!(rust.contains("apply(3, |n| { n * 2 })"));
+
+        let rust = Oxidizer::new()
+            .with_closure_param_inference(true)
+            .oxidize(&ast)
+            .unwrap();
+        assert!(rust.contains("let untyped = |n| { n + 1 };"));
+        assert!(rust.contains("apply(3, |n: i32| { n * 2 })"));
+    }
+
+    #[test]
+    fn test_oxidize_for_edition() {
+        let iron_input = r#"function gen
+    takes async of reference to error
+    returns i32
+begin
+    define try as call method await on async
+    field dyn of try
+end function"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let rust = Oxidizer::new().oxidize(&ast).unwrap();
+        assert!(rust.contains("fn r#gen(r#async: &dyn std::error::Error) -> i32"));
+        assert!(rust.contains("let r#try = r#async.r#await();"));
+        assert!(rust.contains("r#try.r#dyn"));
+
+        let rust = Oxidizer::new()
+            .with_edition(Edition::E2021)
+            .oxidize(&ast)
+            .unwrap();
+        assert!(rust.contains("fn gen(r#async: &dyn std::error::Error) -> i32"));
+
+        let rust = Oxidizer::new()
+            .with_edition(Edition::E2015)
+            .oxidize(&ast)
+            .unwrap();
+        assert!(rust.contains("fn gen(async: &std::error::Error) -> i32"));
+        assert!(rust.contains("let try = async.await();"));
+
+        assert_eq!("2018".parse::<Edition>(), Ok(Edition::E2018));
+        assert!("2020".parse::<Edition>().is_err());
+    }
+
+    #[test]
+    fn test_oxidize_where_clauses() {
+        let iron_input = r#"function show with generic type T implementing Display and Clone with generic type U
+    takes value of T and other of U
+    returns string
+begin
+    call method to_string on value
+end function"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let rust = Oxidizer::new().oxidize(&ast).unwrap();
+        assert!(rust.contains("fn show<T: Display + Clone, U>(value: T, other: U) -> String {"));
+
+        let rust = Oxidizer::new()
+            .with_where_clauses(true)
+            .oxidize(&ast)
+            .unwrap();
+        assert!(rust.contains(
+            "fn show<T, U>(value: T, other: U) -> String\nwhere\n    T: Display + Clone,\n{"
+        ));
+    }
+
+    #[test]
+    fn test_oxidize_collects_warnings() {
+        let iron_input = r#"structure Holder with generic type T implementing Display with fields
+    value of T
+end structure
+
+function run
+    note that no place for this comment
+    returns i32
+begin
+    define g as closure move with body 1
+    call g
+end function"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let mut oxidizer = Oxidizer::new();
+        oxidizer.oxidize(&ast).unwrap();
+
+        let warnings: Vec<String> = oxidizer.warnings().iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "line 1: bounds on generic type T of structure Holder were dropped",
+                "line 6: comment dropped: comments here have no Rust equivalent",
+                "line 9: 'move' on closures is not supported yet and was dropped",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_oxidize_with_layout_config() {
+        let iron_input = r#"function pick
+    takes flag of boolean
+    returns string
+begin
+    if flag then
+    begin
+        return "a {"
+    end if
+    otherwise
+    begin
+        return text begin
+            one {
+              two
+            text end
+    end if
+end function"#;
+
+        let mut parser = IronParser::new(iron_input);
+        let ast = parser.parse().unwrap();
+
+        let config = OxidizerConfig {
+            indent: "\t".to_string(),
+            trailing_newline: false,
+            brace_style: BraceStyle::NextLine,
+        };
+        let rust = Oxidizer::new().with_config(config).oxidize(&ast).unwrap();
+
+        assert_eq!(
+            rust,
+            "fn pick(flag: bool) -> String\n{\n\tif flag\n\t{\n\t\treturn \"a {\";\n\t}\n\telse\n\t{\n\t\treturn \"one {\\n  two\";\n\t}\n}"
+        );
+    }
+
+    #[test]
+    fn test_string_continuation_lines() {
+        let code = "let a = \"x {\n    y\";\nlet b = r#\"\"\n\"#;\nlet c = '\"';\nfn f<'a>() {\n}";
+        assert_eq!(
+            string_continuation_lines(code),
+            vec![false, true, false, true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn test_oxidize_errors() {
+        let oxidize = |iron: &str| Oxidizer::new().oxidize(&IronParser::new(iron).parse().unwrap());
+
+        let error =
+            oxidize("function f\n    takes x of unknown_type\nbegin\n    return\nend function")
+                .unwrap_err();
+        assert_eq!(
+            error,
+            OxidizeError::UnknownType {
+                name: "unknown_type".to_string(),
+                line: Some(1),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "line 1: type 'unknown_type' has no Rust equivalent"
+        );
+
+        let error = oxidize("verbatim item \"impl {\"").unwrap_err();
+        assert!(matches!(error, OxidizeError::InvalidVerbatim { .. }));
+
+        let file = IronFile {
+            items: vec![IronItem::Function(IronFunction {
+                line: 4,
+                name: "f".to_string(),
+                generics: Vec::new(),
+                params: Vec::new(),
+                return_type: Some(IronType::Inferred),
+                body: vec![IronStmt::Assign {
+                    target: IronExpr::Integer("1".to_string()),
+                    value: IronExpr::Integer("2".to_string()),
+                }],
+            })],
+            warnings: Vec::new(),
+        };
+        assert_eq!(
+            Oxidizer::new().oxidize(&file),
+            Err(OxidizeError::Unsupported {
+                construct: "inferred type outside a closure parameter".to_string(),
+                line: Some(4),
+            })
+        );
+    }
 }
@@ -0,0 +1,53 @@
+//! A worker pool for running one task over many inputs, shared by the batch APIs and
+//! the command line
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Run `task` on each input with up to `jobs` threads, returning the results in input
+/// order
+///
+/// Each thread makes its own state with `init` and hands it to every task it runs, so
+/// buffers such as a parser's are reused from one input to the next.
+///
+/// # Example
+///
+/// ```rust
+/// use std::num::NonZeroUsize;
+///
+/// let jobs = NonZeroUsize::new(2).unwrap();
+/// let lengths = redox::parallel::map_in_order(&["a", "bcd", ""], jobs, || (), |_, s| s.len());
+///
+/// assert_eq!(lengths, [1, 3, 0]);
+/// ```
+pub fn map_in_order<I: Sync, S, T: Send>(
+    inputs: &[I],
+    jobs: NonZeroUsize,
+    init: impl Fn() -> S + Sync,
+    task: impl Fn(&mut S, &I) -> T + Sync,
+) -> Vec<T> {
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, T)> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.get().min(inputs.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut state = init();
+                    let mut done = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(input) = inputs.get(index) else {
+                            break done;
+                        };
+                        done.push((index, task(&mut state, input)));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap())
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
@@ -2,18 +2,167 @@
 //!
 //! This module implements the visitor pattern to traverse Rust syntax trees
 //! and convert them to Iron code using the emitter.
-
-use crate::emitter::IronEmitter;
-use crate::keywords::sanitize_identifier;
-use crate::mappings::{map_binary_op, map_fn_arg, map_return_type, map_type_to_iron, map_unary_op};
+//!
+//! Reduction writes Iron text directly rather than building an Iron syntax tree for
+//! the printer. Each top-level item is read back strictly once written, and an item
+//! that does not parse is carried as verbatim Rust instead. The corpus tests hold
+//! [`crate::print_iron`] of the read-back tree to the same text.
+
+use crate::cache::{CacheProvider, CachedItem, Position, absolute, cache_key, relative};
+use crate::emitter::{
+    Indentation, IronEmitter, LayoutPolicy, Origin, format_string_literal, quote_char, quote_string,
+};
+use crate::iron_ast::RustSpan;
+use crate::iron_parser::{self, ParseMode};
+use crate::iron_tokenizer::{Token, Tokenizer};
+use crate::mappings::{
+    MappingHook, map_binary_op, map_compound_assign, map_fn_arg, map_macro_with_hooks,
+    map_return_type, map_type_with_hooks, map_unary_op,
+};
+use crate::symbols::SymbolTable;
 use quote::ToTokens;
+use std::sync::Arc;
+use syn::spanned::Spanned;
 use syn::visit::Visit;
-use syn::{Attribute, Expr, File, GenericParam, Item, Member, Pat, Stmt};
+use syn::{Attribute, Expr, File, FnArg, GenericParam, Item, Member, Pat, Stmt};
+
+/// An item with no Iron form, which strict reduction refuses to carry as verbatim Rust
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnsupportedItem {
+    /// What the item is, such as "impl Point"
+    pub item: String,
+    /// Start of the item in the Rust source, as (line, 1-based column)
+    pub start: (usize, usize),
+    /// End of the item in the Rust source, as (line, 1-based column just past it)
+    pub end: (usize, usize),
+}
+
+/// What reduction does with an item that has no Iron form
+///
+/// Items are carried as verbatim Rust by default, which oxidizes back unchanged but
+/// leaves Rust in the Iron. Dataset builders can leave them out or map them instead.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum FallbackPolicy {
+    /// Carry the item as verbatim Rust
+    #[default]
+    Verbatim,
+    /// Leave the item out, with a comment naming it in its place
+    Placeholder,
+    /// Fail, naming each such item and its location, as strict reduction does
+    Error,
+    /// Write the Iron the mapping hooks give for the item
+    /// ([`MappingHook::map_unsupported_item`]), carrying items none of them maps, or
+    /// whose Iron does not parse, as verbatim Rust
+    Hook,
+}
+
+impl std::str::FromStr for FallbackPolicy {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "verbatim" => Ok(FallbackPolicy::Verbatim),
+            "placeholder" => Ok(FallbackPolicy::Placeholder),
+            "error" => Ok(FallbackPolicy::Error),
+            "hook" => Ok(FallbackPolicy::Hook),
+            _ => Err(format!(
+                "unknown fallback '{}' (expected verbatim, placeholder, error, or hook)",
+                name
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for FallbackPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FallbackPolicy::Verbatim => "verbatim",
+            FallbackPolicy::Placeholder => "placeholder",
+            FallbackPolicy::Error => "error",
+            FallbackPolicy::Hook => "hook",
+        })
+    }
+}
+
+/// How much of each call's Iron is spelled out
+///
+/// Every profile reads back to the same Rust, so a dataset can trade tokens for
+/// explicitness to suit the model it trains:
+///
+/// | Profile | Method call | Associated function | Function call |
+/// |---|---|---|---|
+/// | `Terse` | `call push on v with x` | `call function new on Vec` | `call f with x` |
+/// | `Standard` | `call method push on v with x` | `call associated function new on Vec` | `call f with x` |
+/// | `ExtraVerbose` | `call method push on v with parameters x` | `call associated function new on Vec` | `call function f with parameters x` |
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Verbosity {
+    /// The shortest forms the Iron parser accepts
+    Terse,
+    /// The forms Iron has always used
+    #[default]
+    Standard,
+    /// Every call names what is called and introduces its arguments
+    ExtraVerbose,
+}
+
+impl std::str::FromStr for Verbosity {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "terse" => Ok(Verbosity::Terse),
+            "standard" => Ok(Verbosity::Standard),
+            "extra-verbose" => Ok(Verbosity::ExtraVerbose),
+            _ => Err(format!(
+                "unknown verbosity '{}' (expected terse, standard, or extra-verbose)",
+                name
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Verbosity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Verbosity::Terse => "terse",
+            Verbosity::Standard => "standard",
+            Verbosity::ExtraVerbose => "extra-verbose",
+        })
+    }
+}
+
+impl std::fmt::Display for UnsupportedItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {} has no Iron form and would be emitted verbatim",
+            self.start.0, self.start.1, self.item
+        )
+    }
+}
 
 /// Parser that visits Rust AST and emits Iron code
 pub struct IronParser {
     emitter: IronEmitter,
-    errors: Vec<String>,
+    errors: Vec<UnsupportedItem>,
+    /// Reject items with no Iron form, whatever the fallback policy
+    strict: bool,
+    /// What to do with items with no Iron form
+    fallback: FallbackPolicy,
+    /// Start (line, 1-based column) of each item carried as verbatim Rust
+    verbatim_items: Vec<(usize, usize)>,
+    /// Custom mappings asked before the built-in ones
+    hooks: Vec<Arc<dyn MappingHook>>,
+    /// Type names of several words the hooks write, for reading emitted Iron back
+    type_phrases: Vec<String>,
+    /// Where the Iron of top-level items is looked up before, and stored after, reducing
+    cache: Option<Arc<dyn CacheProvider>>,
+    /// How much of each call is spelled out
+    verbosity: Verbosity,
+    /// Start each top-level item with a note of the Rust line it comes from
+    provenance: bool,
+    /// Path of the Rust source, named in provenance notes
+    source_path: Option<String>,
 }
 
 impl IronParser {
@@ -22,32 +171,138 @@ impl IronParser {
         Self {
             emitter: IronEmitter::new(),
             errors: Vec::new(),
+            strict: false,
+            fallback: FallbackPolicy::Verbatim,
+            verbatim_items: Vec::new(),
+            hooks: Vec::new(),
+            type_phrases: Vec::new(),
+            cache: None,
+            verbosity: Verbosity::Standard,
+            provenance: false,
+            source_path: None,
         }
     }
 
+    /// Make every item that would be emitted verbatim an error, with its name and location
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Handle items with no Iron form as `fallback` says; strict mode overrides it
+    pub fn with_fallback(mut self, fallback: FallbackPolicy) -> Self {
+        self.fallback = fallback;
+        self
+    }
+
+    /// Ask `hooks`, in order, for custom Iron before mapping types and macros as usual
+    pub fn with_hooks(mut self, hooks: Vec<Arc<dyn MappingHook>>) -> Self {
+        self.type_phrases = hooks.iter().flat_map(|hook| hook.type_phrases()).collect();
+        self.hooks = hooks;
+        self
+    }
+
+    /// Look up the Iron of each top-level item in `cache` before reducing it, and store
+    /// what was reduced
+    pub fn with_cache(mut self, cache: Option<Arc<dyn CacheProvider>>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Spell out calls as `verbosity` says
+    pub fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Indent each nested block of the Iron by `indentation`
+    pub fn with_indentation(mut self, indentation: Indentation) -> Self {
+        self.emitter.set_indentation(indentation);
+        self
+    }
+
+    /// Wrap Iron lines longer than `max_width` characters onto `continued` lines
+    pub fn with_max_width(mut self, max_width: Option<usize>) -> Self {
+        self.emitter.set_max_width(max_width);
+        self
+    }
+
+    /// Leave blank lines where `layout` says
+    pub fn with_layout(mut self, layout: LayoutPolicy) -> Self {
+        self.emitter.set_layout(layout);
+        self
+    }
+
+    /// Start each top-level item with `note that this comes from line N of PATH`, so the
+    /// Iron can be traced back to the Rust without a source map
+    pub fn with_provenance(mut self, provenance: bool) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    /// Name `source_path` in provenance notes; without it they give only the line
+    pub fn with_source_path(mut self, source_path: Option<String>) -> Self {
+        self.source_path = source_path;
+        self
+    }
+
+    /// Forget the previous input, keeping the settings and buffers, so one parser can
+    /// reduce many files; without it each parse appends to the last
+    pub fn reset(&mut self) {
+        self.emitter.clear();
+        self.errors.clear();
+        self.verbatim_items.clear();
+    }
+
     /// Parse a Rust file and return Iron code
-    pub fn parse_file(&mut self, file: &File) -> Result<String, Vec<String>> {
+    pub fn parse_file(&mut self, file: &File) -> Result<String, Vec<UnsupportedItem>> {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("emit", items = file.items.len()).entered();
         self.visit_file(file);
 
         if self.errors.is_empty() {
             // Clone the emitter output without consuming it
-            Ok(self.emitter.clone_output())
+            Ok(self.emitter.finished_output())
         } else {
             Err(self.errors.clone())
         }
     }
 
+    /// Reduce one top-level item to Iron, for callers that reduce a file item by item,
+    /// noting its provenance and going through the cache as a whole file does
+    pub fn parse_item(&mut self, item: &Item) -> Result<String, Vec<UnsupportedItem>> {
+        if self.provenance {
+            self.write_provenance(item);
+        }
+        match self.cache.clone() {
+            Some(cache) if self.emitter.at_line_start() => {
+                self.emit_cached_item(item, cache.as_ref())
+            }
+            _ => self.emit_checked_item(item),
+        }
+
+        if self.errors.is_empty() {
+            Ok(self.emitter.finished_output())
+        } else {
+            Err(self.errors.clone())
+        }
+    }
+
+    /// The Iron emitted so far, which after a failed parse is that of every item that
+    /// did not fail
+    pub fn output(&self) -> String {
+        self.emitter.finished_output()
+    }
+
     /// Process attributes (comments and doc comments)
     fn process_attributes(&mut self, attrs: &[Attribute]) {
         for attr in attrs {
-            if attr.path().is_ident("doc") {
-                if let Ok(syn::Meta::NameValue(meta)) = attr.parse_args::<syn::Meta>() {
-                    if let syn::Expr::Lit(expr_lit) = meta.value {
-                        if let syn::Lit::Str(lit_str) = expr_lit.lit {
-                            self.emitter.write_comment(&lit_str.value());
-                        }
-                    }
-                }
+            if attr.path().is_ident("doc")
+                && let Ok(syn::Meta::NameValue(meta)) = attr.parse_args::<syn::Meta>()
+                && let syn::Expr::Lit(expr_lit) = meta.value
+                && let syn::Lit::Str(lit_str) = expr_lit.lit
+            {
+                self.emitter.write_comment(&lit_str.value());
             }
         }
     }
@@ -70,10 +325,234 @@ impl IronParser {
         }
     }
 
+    /// The identifiers reduced so far, with the Iron spelling each was given
+    pub fn symbols(&self) -> &SymbolTable {
+        self.emitter.symbols()
+    }
+
+    /// Where the items emitted as verbatim Rust so far start, as (line, 1-based column)
+    pub fn verbatim_items(&self) -> &[(usize, usize)] {
+        &self.verbatim_items
+    }
+
+    /// The (1-based Iron line, origin) of each run of output lines emitted for one Rust
+    /// item or statement
+    pub fn origins(&self) -> Vec<(usize, Origin)> {
+        self.emitter.origins()
+    }
+
+    /// The fallback policy in effect, which strict mode makes `Error`
+    fn fallback(&self) -> FallbackPolicy {
+        match self.strict {
+            true => FallbackPolicy::Error,
+            false => self.fallback,
+        }
+    }
+
+    /// Whether `iron` reads back as Iron, with no character the tokenizer would skip
+    fn parses(&self, iron: &str) -> bool {
+        let skips = Tokenizer::new(iron)
+            .with_symbols(true)
+            .tokenize()
+            .iter()
+            .any(|token| matches!(token, Token::Symbol(_)));
+        !skips
+            && iron_parser::IronParser::new(iron)
+                .with_mode(ParseMode::Strict)
+                .with_type_names(&self.type_phrases)
+                .parse()
+                .is_ok()
+    }
+
+    /// Emit an item with no Iron form as the fallback policy says
     fn emit_verbatim_item(&mut self, item: &Item) {
+        let start = item.span().start();
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            item = %describe_item(item),
+            fallback = %self.fallback(),
+            "item has no Iron form"
+        );
+        match self.fallback() {
+            FallbackPolicy::Verbatim => {}
+            FallbackPolicy::Placeholder => {
+                self.emitter.write_comment(&format!(
+                    "{} has no Iron form and was left out",
+                    describe_item(item)
+                ));
+                self.emitter.separate();
+                return;
+            }
+            FallbackPolicy::Error => {
+                let end = item.span().end();
+                self.errors.push(UnsupportedItem {
+                    item: describe_item(item),
+                    start: (start.line, start.column + 1),
+                    end: (end.line, end.column + 1),
+                });
+                return;
+            }
+            FallbackPolicy::Hook => {
+                let iron = self
+                    .hooks
+                    .iter()
+                    .find_map(|hook| hook.map_unsupported_item(item))
+                    .filter(|iron| self.parses(iron));
+                if let Some(iron) = iron {
+                    self.emitter.write_line(iron.trim_end_matches('\n'));
+                    self.emitter.separate();
+                    return;
+                }
+            }
+        }
+
+        self.verbatim_items.push((start.line, start.column + 1));
         let rust_item = item.to_token_stream().to_string();
         self.emitter.write_verbatim_item(&rust_item);
-        self.emitter.write_empty_line();
+        self.emitter.separate();
+    }
+
+    /// Emit a top-level item, falling back to verbatim Rust if its Iron does not parse
+    /// back, so everything reduction writes can be oxidized
+    fn emit_checked_item(&mut self, item: &Item) {
+        let checkpoint = self.emitter.checkpoint();
+        let (errors, verbatim_items) = (self.errors.len(), self.verbatim_items.len());
+        self.visit_item(item);
+
+        if !self.parses(self.emitter.output_since(&checkpoint)) {
+            self.emitter.rollback(checkpoint);
+            self.errors.truncate(errors);
+            self.verbatim_items.truncate(verbatim_items);
+            let outer = self.emitter.set_origin(Some(origin_of(
+                item_span(item),
+                item.span(),
+                describe_item(item),
+            )));
+            self.emit_verbatim_item(item);
+            self.emitter.set_origin(outer);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            item = %describe_item(item),
+            iron_lines = self.emitter.output_since(&checkpoint).lines().count(),
+            "item emitted"
+        );
+    }
+
+    /// Note the Rust line `item` comes from, as a line of its own that the item's Iron
+    /// (and its cache entry) does not include
+    fn write_provenance(&mut self, item: &Item) {
+        let line = item_span(item).start().line;
+        let note = match &self.source_path {
+            Some(path) => format!("this comes from line {} of {}", line, path),
+            None => format!("this comes from line {}", line),
+        };
+        self.emitter.write_comment_line(&note);
+    }
+
+    /// Emit a top-level item from the cache, or reduce it and store it there
+    ///
+    /// The entry keeps the item's origins, verbatim items, and renamed and declared names,
+    /// with positions relative to where it starts, and a hit replays them where it starts
+    /// now.
+    fn emit_cached_item(&mut self, item: &Item, cache: &dyn CacheProvider) {
+        let key = cache_key(
+            item,
+            self.fallback(),
+            self.verbosity,
+            self.emitter.indentation(),
+            self.emitter.max_width(),
+            self.emitter.layout(),
+        );
+        let start = item.span().start();
+        let start = (start.line, start.column + 1);
+        if let Some(cached) = cache.get(&key).and_then(|entry| CachedItem::decode(&entry)) {
+            #[cfg(feature = "tracing")]
+            tracing::debug!(item = %describe_item(item), "item from cache");
+            let origins = cached
+                .origins
+                .into_iter()
+                .map(|(offset, origin)| (offset, moved_origin(origin, start, absolute)))
+                .collect();
+            self.emitter.write_item(&cached.iron, origins);
+            self.verbatim_items.extend(
+                cached
+                    .verbatim_items
+                    .into_iter()
+                    .map(|position| absolute(start, position)),
+            );
+            let symbols = self.emitter.symbols_mut();
+            for name in &cached.renamed {
+                symbols.record(name);
+            }
+            for name in &cached.declared {
+                symbols.declare(name);
+            }
+            return;
+        }
+
+        let checkpoint = self.emitter.checkpoint();
+        let (errors, verbatim_items) = (self.errors.len(), self.verbatim_items.len());
+        self.emit_checked_item(item);
+        if self.errors.len() == errors && self.emitter.at_line_start() {
+            let cached = CachedItem {
+                iron: self.emitter.output_since(&checkpoint).to_string(),
+                origins: self
+                    .emitter
+                    .origins_since(&checkpoint)
+                    .into_iter()
+                    .map(|(offset, origin)| (offset, moved_origin(origin, start, relative)))
+                    .collect(),
+                verbatim_items: self.verbatim_items[verbatim_items..]
+                    .iter()
+                    .map(|&position| relative(start, position))
+                    .collect(),
+                renamed: self.emitter.renamed_since(&checkpoint),
+                declared: self.emitter.declared_since(&checkpoint),
+            };
+            cache.put(&key, &cached.encode());
+        }
+    }
+
+    /// Format a use tree as an Iron import path, or `None` when it needs verbatim fallback
+    ///
+    /// Globs and groups nested inside other groups have no Iron spelling yet.
+    fn format_use_tree(&mut self, tree: &syn::UseTree, in_group: bool) -> Option<String> {
+        match tree {
+            syn::UseTree::Path(use_path) => {
+                let rest = self.format_use_tree(&use_path.tree, in_group)?;
+                Some(format!(
+                    "{} {}",
+                    self.emitter
+                        .symbols_mut()
+                        .sanitize(&use_path.ident.to_string()),
+                    rest
+                ))
+            }
+            syn::UseTree::Name(use_name) => Some(
+                self.emitter
+                    .symbols_mut()
+                    .sanitize(&use_name.ident.to_string()),
+            ),
+            syn::UseTree::Rename(use_rename) => Some(format!(
+                "{} as {}",
+                self.emitter
+                    .symbols_mut()
+                    .sanitize(&use_rename.ident.to_string()),
+                self.emitter
+                    .symbols_mut()
+                    .sanitize(&use_rename.rename.to_string())
+            )),
+            syn::UseTree::Group(use_group) if !in_group => {
+                let entries = use_group
+                    .items
+                    .iter()
+                    .map(|item| self.format_use_tree(item, true))
+                    .collect::<Option<Vec<_>>>()?;
+                Some(format!("containing {}", entries.join(" and ")))
+            }
+            _ => None,
+        }
     }
 
     fn type_contains_impl_trait(ty: &syn::Type) -> bool {
@@ -124,17 +603,10 @@ impl IronParser {
             || body_tokens.contains("if let")
             || body_tokens.contains("while let")
             || body_tokens.contains("match ")
-    }
-}
-
-impl<'ast> Visit<'ast> for IronParser {
-    fn visit_file(&mut self, file: &'ast File) {
-        for item in &file.items {
-            self.visit_item(item);
-        }
+            || uses_loop_labels(&item_fn.block)
     }
 
-    fn visit_item(&mut self, item: &'ast Item) {
+    fn emit_item(&mut self, item: &Item) {
         match item {
             Item::Fn(item_fn) => {
                 if Self::fn_signature_contains_impl_trait(&item_fn.sig)
@@ -159,16 +631,19 @@ impl<'ast> Visit<'ast> for IronParser {
                             GenericParam::Type(type_param) => {
                                 let name = type_param.ident.to_string();
                                 if type_param.bounds.is_empty() {
-                                    format!("with generic type {}", sanitize_identifier(&name))
+                                    format!(
+                                        "with generic type {}",
+                                        self.emitter.symbols_mut().sanitize(&name)
+                                    )
                                 } else {
                                     let bounds: Vec<String> = type_param
                                         .bounds
                                         .iter()
-                                        .map(|b| Self::format_type_param_bound(b))
+                                        .map(Self::format_type_param_bound)
                                         .collect();
                                     format!(
                                         "with generic type {} implementing {}",
-                                        sanitize_identifier(&name),
+                                        self.emitter.symbols_mut().sanitize(&name),
                                         bounds.join(" and ")
                                     )
                                 }
@@ -185,11 +660,15 @@ impl<'ast> Visit<'ast> for IronParser {
                 };
 
                 // Process parameters
-                let params: Vec<(String, String)> =
-                    item_fn.sig.inputs.iter().filter_map(map_fn_arg).collect();
+                let params: Vec<(String, String)> = item_fn
+                    .sig
+                    .inputs
+                    .iter()
+                    .filter_map(|arg| map_fn_arg(arg, &self.hooks))
+                    .collect();
 
                 // Process return type
-                let return_type = map_return_type(&item_fn.sig.output);
+                let return_type = map_return_type(&item_fn.sig.output, &self.hooks);
 
                 // Get function name
                 let fn_name = item_fn.sig.ident.to_string();
@@ -202,13 +681,22 @@ impl<'ast> Visit<'ast> for IronParser {
                     &return_type,
                 );
 
-                // Emit function body
+                // Emit function body, its parameters declared in the body's scope
                 self.emitter.begin_block();
+                for arg in &item_fn.sig.inputs {
+                    if let FnArg::Typed(pat_type) = arg
+                        && let Pat::Ident(pat_ident) = &*pat_type.pat
+                    {
+                        self.emitter
+                            .symbols_mut()
+                            .declare(&pat_ident.ident.to_string());
+                    }
+                }
                 for stmt in &item_fn.block.stmts {
                     self.visit_stmt(stmt);
                 }
                 self.emitter.end_block("function");
-                self.emitter.write_empty_line();
+                self.emitter.separate();
             }
 
             Item::Struct(item_struct) => {
@@ -248,14 +736,14 @@ impl<'ast> Visit<'ast> for IronParser {
                         for field in &fields_named.named {
                             if let Some(ident) = &field.ident {
                                 let field_name = ident.to_string();
-                                let field_type = map_type_to_iron(&field.ty);
+                                let field_type = map_type_with_hooks(&field.ty, &self.hooks);
                                 self.emitter.write_struct_field(&field_name, &field_type);
                             }
                         }
                     }
                     syn::Fields::Unnamed(fields_unnamed) => {
                         for (idx, field) in fields_unnamed.unnamed.iter().enumerate() {
-                            let field_type = map_type_to_iron(&field.ty);
+                            let field_type = map_type_with_hooks(&field.ty, &self.hooks);
                             self.emitter
                                 .write_struct_field(&format!("field{}", idx), &field_type);
                         }
@@ -267,7 +755,7 @@ impl<'ast> Visit<'ast> for IronParser {
 
                 self.emitter.dedent();
                 self.emitter.write_line("end structure");
-                self.emitter.write_empty_line();
+                self.emitter.separate();
             }
 
             Item::Enum(item_enum) => {
@@ -311,14 +799,15 @@ impl<'ast> Visit<'ast> for IronParser {
                         }
                         syn::Fields::Unnamed(fields_unnamed) => {
                             if fields_unnamed.unnamed.len() == 1 {
-                                let ty = map_type_to_iron(&fields_unnamed.unnamed[0].ty);
+                                let ty =
+                                    map_type_with_hooks(&fields_unnamed.unnamed[0].ty, &self.hooks);
                                 self.emitter
                                     .write_enum_variant_with_data(&variant_name, &ty);
                             } else {
                                 let types: Vec<String> = fields_unnamed
                                     .unnamed
                                     .iter()
-                                    .map(|f| map_type_to_iron(&f.ty))
+                                    .map(|f| map_type_with_hooks(&f.ty, &self.hooks))
                                     .collect();
                                 self.emitter.write_enum_variant_with_data(
                                     &variant_name,
@@ -331,9 +820,9 @@ impl<'ast> Visit<'ast> for IronParser {
                                 .named
                                 .iter()
                                 .filter_map(|f| {
-                                    f.ident
-                                        .as_ref()
-                                        .map(|ident| (ident.to_string(), map_type_to_iron(&f.ty)))
+                                    f.ident.as_ref().map(|ident| {
+                                        (ident.to_string(), map_type_with_hooks(&f.ty, &self.hooks))
+                                    })
                                 })
                                 .collect();
                             self.emitter
@@ -344,13 +833,13 @@ impl<'ast> Visit<'ast> for IronParser {
 
                 self.emitter.dedent();
                 self.emitter.write_line("end enumeration");
-                self.emitter.write_empty_line();
+                self.emitter.separate();
             }
 
             Item::Static(item_static) => {
                 self.process_attributes(&item_static.attrs);
                 let name = item_static.ident.to_string();
-                let ty = map_type_to_iron(&item_static.ty);
+                let ty = map_type_with_hooks(&item_static.ty, &self.hooks);
 
                 // Check mutability - StaticMutability is not an Option, it's an enum
                 let is_mut = matches!(&item_static.mutability, syn::StaticMutability::Mut(_));
@@ -366,13 +855,13 @@ impl<'ast> Visit<'ast> for IronParser {
                 self.emitter.begin_block();
                 self.visit_expr(&item_static.expr);
                 self.emitter.end_block("static");
-                self.emitter.write_empty_line();
+                self.emitter.separate();
             }
 
             Item::Const(item_const) => {
                 self.process_attributes(&item_const.attrs);
                 let name = item_const.ident.to_string();
-                let ty = map_type_to_iron(&item_const.ty);
+                let ty = map_type_with_hooks(&item_const.ty, &self.hooks);
 
                 self.emitter
                     .write_line(&format!("constant {} of {}", name, ty));
@@ -380,13 +869,13 @@ impl<'ast> Visit<'ast> for IronParser {
                 self.emitter.begin_block();
                 self.visit_expr(&item_const.expr);
                 self.emitter.end_block("constant");
-                self.emitter.write_empty_line();
+                self.emitter.separate();
             }
 
             Item::Type(item_type) => {
                 self.process_attributes(&item_type.attrs);
                 let name = item_type.ident.to_string();
-                let ty = map_type_to_iron(&item_type.ty);
+                let ty = map_type_with_hooks(&item_type.ty, &self.hooks);
 
                 let generics_str = if item_type.generics.params.is_empty() {
                     None
@@ -403,7 +892,7 @@ impl<'ast> Visit<'ast> for IronParser {
                                     let bounds: Vec<String> = type_param
                                         .bounds
                                         .iter()
-                                        .map(|b| Self::format_type_param_bound(b))
+                                        .map(Self::format_type_param_bound)
                                         .collect();
                                     format!(
                                         "with generic type {} implementing {}",
@@ -423,21 +912,29 @@ impl<'ast> Visit<'ast> for IronParser {
                     }
                 };
 
+                let name = self.emitter.symbols_mut().sanitize(&name);
                 if let Some(generics_str) = generics_str {
-                    self.emitter.write_line(&format!(
-                        "type {} {} as {}",
-                        sanitize_identifier(&name),
-                        generics_str,
-                        ty
-                    ));
+                    self.emitter
+                        .write_line(&format!("type {} {} as {}", name, generics_str, ty));
                 } else {
-                    self.emitter.write_line(&format!(
-                        "type {} as {}",
-                        sanitize_identifier(&name),
-                        ty
-                    ));
+                    self.emitter.write_line(&format!("type {} as {}", name, ty));
+                }
+                self.emitter.separate();
+            }
+
+            Item::Use(item_use)
+                if matches!(item_use.vis, syn::Visibility::Inherited)
+                    && item_use.leading_colon.is_none()
+                    && matches!(item_use.tree, syn::UseTree::Path(_)) =>
+            {
+                match self.format_use_tree(&item_use.tree, false) {
+                    Some(path) => {
+                        self.process_attributes(&item_use.attrs);
+                        self.emitter.write_import(&path);
+                        self.emitter.separate();
+                    }
+                    None => self.emit_verbatim_item(item),
                 }
-                self.emitter.write_empty_line();
             }
 
             _ => {
@@ -447,7 +944,7 @@ impl<'ast> Visit<'ast> for IronParser {
         }
     }
 
-    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+    fn emit_stmt(&mut self, stmt: &Stmt) {
         match stmt {
             Stmt::Local(local) => {
                 if let Some(init) = &local.init {
@@ -481,6 +978,7 @@ impl<'ast> Visit<'ast> for IronParser {
             }
 
             Stmt::Item(item) => {
+                self.emitter.separate();
                 self.visit_item(item);
             }
 
@@ -496,6 +994,9 @@ impl<'ast> Visit<'ast> for IronParser {
                     Expr::If(if_expr) => {
                         self.emit_if_statement(if_expr);
                     }
+                    Expr::Loop(loop_expr) => {
+                        self.emit_loop(loop_expr);
+                    }
                     _ => {
                         let expr_str = self.expr_to_string(expr);
                         if !expr_str.is_empty() {
@@ -505,12 +1006,54 @@ impl<'ast> Visit<'ast> for IronParser {
                 }
             }
 
-            Stmt::Macro(_stmt_macro) => {
-                // Macros are not expanded in v0.1
-                self.emitter.write_line("macro definition not expanded");
+            Stmt::Macro(stmt_macro) => {
+                let iron = self.macro_to_string(&stmt_macro.mac);
+                self.emitter.write_line(&iron);
             }
         }
     }
+}
+
+impl<'ast> Visit<'ast> for IronParser {
+    fn visit_file(&mut self, file: &'ast File) {
+        for item in &file.items {
+            if self.provenance {
+                self.write_provenance(item);
+            }
+            match self.cache.clone() {
+                Some(cache) if self.emitter.at_line_start() => {
+                    self.emit_cached_item(item, cache.as_ref())
+                }
+                _ => self.emit_checked_item(item),
+            }
+        }
+    }
+
+    fn visit_item(&mut self, item: &'ast Item) {
+        let outer = self.emitter.set_origin(Some(origin_of(
+            item_span(item),
+            item.span(),
+            describe_item(item),
+        )));
+        self.emit_item(item);
+        self.emitter.set_origin(outer);
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        // Items set their own origin
+        let outer = match stmt {
+            Stmt::Item(_) => None,
+            _ => Some(self.emitter.set_origin(Some(origin_of(
+                stmt.span(),
+                stmt.span(),
+                describe_stmt(stmt),
+            )))),
+        };
+        self.emit_stmt(stmt);
+        if let Some(outer) = outer {
+            self.emitter.set_origin(outer);
+        }
+    }
 
     fn visit_expr(&mut self, expr: &'ast Expr) {
         let expr_str = self.expr_to_string(expr);
@@ -522,14 +1065,26 @@ impl<'ast> Visit<'ast> for IronParser {
 
 impl IronParser {
     /// Convert an expression to its Iron string representation
-    fn expr_to_string(&self, expr: &Expr) -> String {
+    fn expr_to_string(&mut self, expr: &Expr) -> String {
         match expr {
             Expr::Lit(expr_lit) => match &expr_lit.lit {
-                syn::Lit::Str(s) => format!("\"{}\"", s.value()),
+                syn::Lit::Str(s) => format_string_literal(&s.value()),
                 syn::Lit::ByteStr(_) => "byte string".to_string(),
                 syn::Lit::Byte(_) => "byte literal".to_string(),
-                syn::Lit::Char(c) => format!("'{}'", c.value()),
-                syn::Lit::Int(i) => i.base10_digits().to_string(),
+                syn::Lit::Char(c) => quote_char(c.value()),
+                syn::Lit::Int(i) => {
+                    // Keep hexadecimal, octal, and binary literals in their original radix
+                    let text = i.token().to_string();
+                    let digits = &text[..text.len() - i.suffix().len()];
+                    if digits.starts_with("0x")
+                        || digits.starts_with("0o")
+                        || digits.starts_with("0b")
+                    {
+                        digits.to_string()
+                    } else {
+                        i.base10_digits().to_string()
+                    }
+                }
                 syn::Lit::Float(f) => f.base10_digits().to_string(),
                 syn::Lit::Bool(b) => b.value.to_string(),
                 syn::Lit::Verbatim(_) => "verbatim".to_string(),
@@ -538,26 +1093,39 @@ impl IronParser {
 
             Expr::Path(expr_path) => {
                 if let Some(ident) = expr_path.path.get_ident() {
-                    sanitize_identifier(&ident.to_string())
+                    self.emitter.symbols_mut().sanitize(&ident.to_string())
                 } else {
                     expr_path
                         .path
                         .segments
                         .iter()
-                        .map(|s| sanitize_identifier(&s.ident.to_string()))
+                        .map(|s| self.emitter.symbols_mut().sanitize(&s.ident.to_string()))
                         .collect::<Vec<_>>()
                         .join(" ")
                 }
             }
 
             Expr::Binary(expr_binary) => {
-                let left = self.expr_to_string(&expr_binary.left);
-                let op = map_binary_op(&expr_binary.op);
+                let mut left = self.expr_to_string(&expr_binary.left);
+                if Self::is_open_ended(&expr_binary.left) {
+                    left = format!("group {} end group", left);
+                }
                 let right = self.expr_to_string(&expr_binary.right);
+                if let Some(stmt) = map_compound_assign(&expr_binary.op, &left, &right) {
+                    return stmt;
+                }
+                let op = map_binary_op(&expr_binary.op);
                 format!("{} {} {}", left, op, right)
             }
 
             Expr::Unary(expr_unary) => {
+                // Negative number literals have their own spelling
+                if let (syn::UnOp::Neg(_), Expr::Lit(expr_lit)) =
+                    (&expr_unary.op, &*expr_unary.expr)
+                    && matches!(expr_lit.lit, syn::Lit::Int(_) | syn::Lit::Float(_))
+                {
+                    return format!("negative {}", self.expr_to_string(&expr_unary.expr));
+                }
                 let op = map_unary_op(&expr_unary.op);
                 let operand = self.expr_to_string(&expr_unary.expr);
                 format!("{} {}", op, operand)
@@ -571,39 +1139,28 @@ impl IronParser {
                         // Associated function: T::method() or Type::method()
                         let type_name = segments[..segments.len() - 1]
                             .iter()
-                            .map(|s| s.ident.to_string())
+                            .map(|s| self.emitter.symbols_mut().sanitize(&s.ident.to_string()))
                             .collect::<Vec<_>>()
-                            .join("::");
+                            .join(" ");
                         let method_name = &segments.last().unwrap().ident.to_string();
-                        let args: Vec<String> = expr_call
-                            .args
-                            .iter()
-                            .map(|arg| self.expr_to_string(arg))
-                            .collect();
-
-                        if args.is_empty() {
-                            return format!(
-                                "call associated function {} on {}",
-                                sanitize_identifier(method_name),
-                                sanitize_identifier(&type_name)
-                            );
-                        } else {
-                            return format!(
-                                "call associated function {} on {} with {}",
-                                sanitize_identifier(method_name),
-                                sanitize_identifier(&type_name),
-                                args.join(" and ")
-                            );
-                        }
+                        let args = self.list_to_strings(&expr_call.args);
+                        let call = match self.verbosity {
+                            Verbosity::Terse => "call function",
+                            _ => "call associated function",
+                        };
+
+                        return format!(
+                            "{} {} on {}{}",
+                            call,
+                            self.emitter.symbols_mut().sanitize(method_name),
+                            type_name,
+                            self.call_args(&args)
+                        );
                     }
                 }
 
                 let func = self.expr_to_string(&expr_call.func);
-                let args: Vec<String> = expr_call
-                    .args
-                    .iter()
-                    .map(|arg| self.expr_to_string(arg))
-                    .collect();
+                let args = self.list_to_strings(&expr_call.args);
 
                 if func == "Some" {
                     if let Some(arg) = args.first() {
@@ -625,46 +1182,48 @@ impl IronParser {
                     } else {
                         "error".to_string()
                     }
+                } else if !args.is_empty() && Self::is_tuple_constructor(&expr_call.func) {
+                    format!("create {} with {}", func, args.join(" and "))
+                } else if self.verbosity == Verbosity::ExtraVerbose {
+                    format!("call function {}{}", func, self.call_args(&args))
                 } else {
-                    format!("call {} with {}", func, args.join(" and "))
+                    format!("call {}{}", func, self.call_args(&args))
                 }
             }
 
             Expr::MethodCall(expr_method) => {
-                let receiver = self.expr_to_string(&expr_method.receiver);
-                let method = sanitize_identifier(&expr_method.method.to_string());
-                let args: Vec<String> = expr_method
-                    .args
-                    .iter()
-                    .map(|arg| self.expr_to_string(arg))
-                    .collect();
-
-                if args.is_empty() {
-                    format!("call method {} on {}", method, receiver)
-                } else {
-                    format!(
-                        "call method {} on {} with {}",
-                        method,
-                        receiver,
-                        args.join(" and ")
-                    )
-                }
+                let receiver = self.operand_to_string(&expr_method.receiver);
+                let method = self
+                    .emitter
+                    .symbols_mut()
+                    .sanitize(&expr_method.method.to_string());
+                let args = self.list_to_strings(&expr_method.args);
+
+                let call = match self.verbosity {
+                    Verbosity::Terse => "call",
+                    _ => "call method",
+                };
+                format!(
+                    "{} {} on {}{}",
+                    call,
+                    method,
+                    receiver,
+                    self.call_args(&args)
+                )
             }
 
             Expr::Field(expr_field) => {
-                let base = self.expr_to_string(&expr_field.base);
+                let base = self.operand_to_string(&expr_field.base);
                 let field_name = match &expr_field.member {
-                    Member::Named(ident) => sanitize_identifier(&ident.to_string()),
+                    Member::Named(ident) => self.emitter.symbols_mut().sanitize(&ident.to_string()),
                     Member::Unnamed(idx) => format!("field{}", idx.index),
                 };
                 format!("field {} of {}", field_name, base)
             }
 
-            Expr::If(_expr_if) => {
-                // Handle if expressions - this is tricky in the visitor pattern
-                // For now, return a placeholder
-                "if expression".to_string()
-            }
+            Expr::If(expr_if) => self
+                .if_expr_to_string(expr_if)
+                .unwrap_or_else(|| "if expression".to_string()),
 
             Expr::Match(_expr_match) => {
                 // Handle match expressions
@@ -694,20 +1253,12 @@ impl IronParser {
             }
 
             Expr::Tuple(expr_tuple) => {
-                let elems: Vec<String> = expr_tuple
-                    .elems
-                    .iter()
-                    .map(|e| self.expr_to_string(e))
-                    .collect();
+                let elems = self.list_to_strings(&expr_tuple.elems);
                 format!("tuple of {}", elems.join(" and "))
             }
 
             Expr::Array(expr_array) => {
-                let elems: Vec<String> = expr_array
-                    .elems
-                    .iter()
-                    .map(|e| self.expr_to_string(e))
-                    .collect();
+                let elems = self.list_to_strings(&expr_array.elems);
                 format!("array of {}", elems.join(" and "))
             }
 
@@ -719,7 +1270,9 @@ impl IronParser {
                 format!("set {} equal to {}", left, right)
             }
 
-            Expr::Paren(expr_paren) => self.expr_to_string(&expr_paren.expr),
+            Expr::Paren(expr_paren) => {
+                format!("group {} end group", self.expr_to_string(&expr_paren.expr))
+            }
 
             Expr::Try(expr_try) => {
                 let inner = self.expr_to_string(&expr_try.expr);
@@ -735,17 +1288,17 @@ impl IronParser {
                         Pat::Ident(pat_ident) => {
                             let name = pat_ident.ident.to_string();
                             if pat_ident.mutability.is_some() {
-                                format!("mutable {}", sanitize_identifier(&name))
+                                format!("mutable {}", self.emitter.symbols_mut().sanitize(&name))
                             } else {
-                                sanitize_identifier(&name)
+                                self.emitter.symbols_mut().sanitize(&name)
                             }
                         }
                         Pat::Type(pat_type) => {
                             // Handle typed parameter: |x: i32|
                             if let Pat::Ident(pat_ident) = &*pat_type.pat {
                                 let name = pat_ident.ident.to_string();
-                                let ty = map_type_to_iron(&pat_type.ty);
-                                format!("{} of {}", sanitize_identifier(&name), ty)
+                                let ty = map_type_with_hooks(&pat_type.ty, &self.hooks);
+                                format!("{} of {}", self.emitter.symbols_mut().sanitize(&name), ty)
                             } else {
                                 "param".to_string()
                             }
@@ -763,61 +1316,83 @@ impl IronParser {
 
                 // Handle closure body
                 let body_str = match &*expr_closure.body {
-                    Expr::Block(block) => {
-                        // Multi-statement closure body
-                        let stmts: Vec<String> = block
-                            .block
-                            .stmts
-                            .iter()
-                            .map(|stmt| self.stmt_to_string(stmt))
-                            .collect();
-                        stmts.join(" ")
-                    }
+                    Expr::Block(block) => match self.block_value_to_string(&block.block) {
+                        Some(value) => value,
+                        None => {
+                            // Multi-statement closure body
+                            let stmts: Vec<String> = block
+                                .block
+                                .stmts
+                                .iter()
+                                .map(|stmt| {
+                                    format!(
+                                        "{}{}",
+                                        self.emitter.indentation().unit(),
+                                        self.stmt_to_string(stmt)
+                                    )
+                                })
+                                .collect();
+                            format!("\nbegin\n{}\nend closure", stmts.join("\n"))
+                        }
+                    },
                     expr => {
                         // Single expression closure body
                         self.expr_to_string(expr)
                     }
                 };
 
+                let body_separator = if body_str.starts_with('\n') { "" } else { " " };
                 if params.is_empty() {
-                    format!("{}closure with body {}", move_prefix, body_str)
+                    format!(
+                        "{}closure with body{}{}",
+                        move_prefix, body_separator, body_str
+                    )
                 } else {
                     format!(
-                        "{}closure with parameters {} and body {}",
+                        "{}closure with parameters {} and body{}{}",
                         move_prefix,
                         params.join(" and "),
+                        body_separator,
                         body_str
                     )
                 }
             }
 
             Expr::Index(expr_index) => {
-                let base = self.expr_to_string(&expr_index.expr);
-                let idx = self.expr_to_string(&expr_index.index);
+                let base = self.operand_to_string(&expr_index.expr);
+                let idx = self.operand_to_string(&expr_index.index);
                 format!("index {} at {}", base, idx)
             }
 
             Expr::Struct(expr_struct) => {
-                let name = expr_struct
+                // Path segments (`Shape::Circle`) are written space-separated
+                let ident_name = expr_struct
                     .path
-                    .get_ident()
-                    .map(|i| i.to_string())
-                    .unwrap_or_else(|| "unknown".to_string());
-                let ident_name = sanitize_identifier(&name);
+                    .segments
+                    .iter()
+                    .map(|s| self.emitter.symbols_mut().sanitize(&s.ident.to_string()))
+                    .collect::<Vec<_>>()
+                    .join(" ");
 
                 if expr_struct.fields.is_empty() {
                     format!("create {}", ident_name)
                 } else {
+                    let last = expr_struct.fields.len() - 1;
                     let fields: Vec<String> = expr_struct
                         .fields
                         .iter()
-                        .map(|field| {
+                        .enumerate()
+                        .map(|(i, field)| {
                             let field_name = match &field.member {
                                 syn::Member::Named(ident) => ident.to_string(),
-                                syn::Member::Unnamed(_) => "field".to_string(),
+                                syn::Member::Unnamed(idx) => format!("field{}", idx.index),
                             };
-                            let value = self.expr_to_string(&field.expr);
-                            format!("{} of {}", sanitize_identifier(&field_name), value)
+                            let value = self.list_element_to_string(&field.expr, i == last);
+                            format!(
+                                "{} of {}",
+                                self.emitter.symbols_mut().sanitize(&field_name),
+                                value
+                            )
                         })
                         .collect();
                     format!("create {} with {}", ident_name, fields.join(" and "))
@@ -844,43 +1419,168 @@ impl IronParser {
                 }
             }
 
-            Expr::Macro(expr_macro) => {
-                // Extract macro name
-                let name = expr_macro
-                    .mac
-                    .path
-                    .get_ident()
-                    .map(|i| i.to_string())
-                    .unwrap_or_else(|| "unknown".to_string());
+            Expr::Macro(expr_macro) => self.macro_to_string(&expr_macro.mac),
 
-                // Extract macro arguments as raw tokens
-                let args = expr_macro.mac.tokens.to_string();
+            _ => {
+                format!("unsupported expression: {:?}", expr)
+            }
+        }
+    }
 
-                // Check delimiter type (brackets [] vs parentheses ())
-                let uses_brackets =
-                    matches!(expr_macro.mac.delimiter, syn::MacroDelimiter::Bracket(_));
-                let bracket_suffix = if uses_brackets { " bracket" } else { "" };
+    /// Convert a macro invocation to Iron, its arguments kept as Rust tokens
+    fn macro_to_string(&mut self, mac: &syn::Macro) -> String {
+        if let Some(iron) = map_macro_with_hooks(mac, &self.hooks) {
+            return iron;
+        }
 
-                if args.is_empty() {
-                    format!("macro {}{}", sanitize_identifier(&name), bracket_suffix)
-                } else {
-                    format!(
-                        "macro {} with {}{}",
-                        sanitize_identifier(&name),
-                        args, // Don't sanitize macro args, preserve exact syntax
-                        bracket_suffix
-                    )
-                }
-            }
+        // Extract macro name
+        let name = mac
+            .path
+            .get_ident()
+            .map(|i| i.to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        // Extract macro arguments as raw tokens
+        let args = macro_args(mac.tokens.clone());
+
+        // Check delimiter type (brackets [] vs parentheses ())
+        let uses_brackets = matches!(mac.delimiter, syn::MacroDelimiter::Bracket(_));
+        let bracket_suffix = if uses_brackets { " bracket" } else { "" };
+
+        if args.is_empty() {
+            format!(
+                "macro {}{}",
+                self.emitter.symbols_mut().sanitize(&name),
+                bracket_suffix
+            )
+        } else {
+            format!(
+                "macro {} with {}{}",
+                self.emitter.symbols_mut().sanitize(&name),
+                args, // Don't sanitize macro args, preserve exact syntax
+                bracket_suffix
+            )
+        }
+    }
 
-            _ => {
-                format!("unsupported expression: {:?}", expr)
+    /// Whether a call target names a tuple struct (a single capitalized path segment)
+    fn is_tuple_constructor(func: &Expr) -> bool {
+        match func {
+            Expr::Path(expr_path) => {
+                expr_path.path.segments.len() == 1
+                    && expr_path.path.segments[0]
+                        .ident
+                        .to_string()
+                        .starts_with(|c: char| c.is_ascii_uppercase())
             }
+            _ => false,
+        }
+    }
+
+    /// Convert the base of a field access, index, or method call
+    ///
+    /// Iron operands bind tighter than binary operators and trailing argument
+    /// lists, so anything but a plain path, literal, or argument-free access is
+    /// wrapped in a group.
+    fn operand_to_string(&mut self, expr: &Expr) -> String {
+        let rendered = self.expr_to_string(expr);
+        let is_atomic = match expr {
+            Expr::Path(_) | Expr::Lit(_) | Expr::Paren(_) | Expr::Field(_) | Expr::Index(_) => true,
+            Expr::MethodCall(expr_method) => expr_method.args.is_empty(),
+            Expr::Call(expr_call) => expr_call.args.is_empty(),
+            _ => false,
+        };
+        if is_atomic {
+            rendered
+        } else {
+            format!("group {} end group", rendered)
+        }
+    }
+
+    /// The arguments of a call after its callee: nothing, or ` with` and the list
+    fn call_args(&self, args: &[String]) -> String {
+        match (args.is_empty(), self.verbosity) {
+            (true, _) => String::new(),
+            (false, Verbosity::ExtraVerbose) => format!(" with parameters {}", args.join(" and ")),
+            (false, _) => format!(" with {}", args.join(" and ")),
+        }
+    }
+
+    /// Convert an `and`-separated list (arguments, fields, elements)
+    fn list_to_strings<'a>(&mut self, exprs: impl IntoIterator<Item = &'a Expr>) -> Vec<String> {
+        let exprs: Vec<&Expr> = exprs.into_iter().collect();
+        let last = exprs.len().saturating_sub(1);
+        exprs
+            .iter()
+            .enumerate()
+            .map(|(i, expr)| self.list_element_to_string(expr, i == last))
+            .collect()
+    }
+
+    /// Convert an element of an `and`-separated list
+    ///
+    /// A bare logical and would read as a list separator, and an open-ended element
+    /// would swallow the elements after it, so both are wrapped in a group.
+    fn list_element_to_string(&mut self, expr: &Expr, last: bool) -> String {
+        let rendered = self.expr_to_string(expr);
+        let is_logical_and = matches!(expr, Expr::Binary(expr_binary) if matches!(expr_binary.op, syn::BinOp::And(_)));
+        if is_logical_and || (!last && Self::is_open_ended(expr)) {
+            format!("group {} end group", rendered)
+        } else {
+            rendered
+        }
+    }
+
+    /// Whether the Iron rendering of `expr` ends in a tail that would absorb a
+    /// following operator or list element (call arguments, closure bodies, ...)
+    fn is_open_ended(expr: &Expr) -> bool {
+        match expr {
+            Expr::Call(expr_call) => !expr_call.args.is_empty(),
+            Expr::MethodCall(expr_method) => !expr_method.args.is_empty(),
+            Expr::Binary(expr_binary) => Self::is_open_ended(&expr_binary.right),
+            Expr::Unary(expr_unary) => Self::is_open_ended(&expr_unary.expr),
+            Expr::Closure(_) | Expr::Macro(_) | Expr::If(_) | Expr::Range(_) => true,
+            Expr::Return(_) | Expr::Reference(_) | Expr::Cast(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Convert a value-position if expression to the inline Iron form
+    ///
+    /// Returns `None` when a branch holds statements rather than a single value.
+    fn if_expr_to_string(&mut self, expr_if: &syn::ExprIf) -> Option<String> {
+        let condition = self.expr_to_string(&expr_if.cond);
+        let mut then_value = self.block_value_to_string(&expr_if.then_branch)?;
+        // A bare nested if would take this one's `otherwise` as its own
+        if let [Stmt::Expr(Expr::If(_), None)] = expr_if.then_branch.stmts.as_slice() {
+            then_value = format!("group {} end group", then_value);
+        }
+
+        let Some((_, else_branch)) = &expr_if.else_branch else {
+            return Some(format!("if {} then {}", condition, then_value));
+        };
+
+        let else_value = match &**else_branch {
+            Expr::If(nested_if) => self.if_expr_to_string(nested_if)?,
+            Expr::Block(block) => self.block_value_to_string(&block.block)?,
+            other => self.expr_to_string(other),
+        };
+
+        Some(format!(
+            "if {} then {} otherwise {}",
+            condition, then_value, else_value
+        ))
+    }
+
+    fn block_value_to_string(&mut self, block: &syn::Block) -> Option<String> {
+        match block.stmts.as_slice() {
+            [Stmt::Expr(expr, None)] => Some(self.expr_to_string(expr)),
+            _ => None,
         }
     }
 
     /// Convert a statement to string representation for closure bodies
-    fn stmt_to_string(&self, stmt: &Stmt) -> String {
+    fn stmt_to_string(&mut self, stmt: &Stmt) -> String {
         match stmt {
             Stmt::Local(local) => {
                 if let Some(init) = &local.init {
@@ -891,11 +1591,15 @@ impl IronParser {
                             if pat_ident.mutability.is_some() {
                                 format!(
                                     "define mutable {} as {}",
-                                    sanitize_identifier(&name),
+                                    self.emitter.symbols_mut().sanitize(&name),
                                     value
                                 )
                             } else {
-                                format!("define {} as {}", sanitize_identifier(&name), value)
+                                format!(
+                                    "define {} as {}",
+                                    self.emitter.symbols_mut().sanitize(&name),
+                                    value
+                                )
                             }
                         }
                         _ => "statement".to_string(),
@@ -923,8 +1627,9 @@ impl IronParser {
         // Emit the for header
         self.emitter.write_for_header(&var_name, &iterator);
 
-        // Emit the body
+        // Emit the body, the loop variable declared in its scope
         self.emitter.begin_block();
+        self.emitter.symbols_mut().declare(&var_name);
         for stmt in &for_loop.body.stmts {
             self.visit_stmt(stmt);
         }
@@ -947,6 +1652,17 @@ impl IronParser {
         self.emitter.end_while();
     }
 
+    /// Emit an infinite loop
+    fn emit_loop(&mut self, loop_expr: &syn::ExprLoop) {
+        self.emitter.write_loop_header();
+
+        self.emitter.begin_block();
+        for stmt in &loop_expr.body.stmts {
+            self.visit_stmt(stmt);
+        }
+        self.emitter.end_loop();
+    }
+
     /// Emit an if statement
     fn emit_if_statement(&mut self, if_expr: &syn::ExprIf) {
         // Get the condition
@@ -954,7 +1670,11 @@ impl IronParser {
 
         // Emit the if header
         self.emitter.write_if_header(&condition);
+        self.emit_if_branches(if_expr);
+    }
 
+    /// Emit the then block and any else-if / else cascade of an if statement
+    fn emit_if_branches(&mut self, if_expr: &syn::ExprIf) {
         // Emit the then block
         self.emitter.begin_block();
         for stmt in &if_expr.then_branch.stmts {
@@ -964,20 +1684,24 @@ impl IronParser {
 
         // Handle else branch if present
         if let Some((_, else_branch)) = &if_expr.else_branch {
-            self.emitter.write_else();
-            self.emitter.begin_block();
             // The else branch can be another if or a block
             match &**else_branch {
                 Expr::If(nested_if) => {
-                    self.emit_if_statement(nested_if);
+                    let condition = self.expr_to_string(&nested_if.cond);
+                    self.emitter.write_else_if(&condition);
+                    self.emit_if_branches(nested_if);
                 }
                 Expr::Block(block) => {
+                    self.emitter.write_else();
+                    self.emitter.begin_block();
                     for stmt in &block.block.stmts {
                         self.visit_stmt(stmt);
                     }
                     self.emitter.end_if();
                 }
                 _ => {
+                    self.emitter.write_else();
+                    self.emitter.begin_block();
                     let else_str = self.expr_to_string(else_branch);
                     if !else_str.is_empty() {
                         self.emitter.write_line(&else_str);
@@ -994,3 +1718,210 @@ impl Default for IronParser {
         Self::new()
     }
 }
+/// The kind and name of an item, for error messages ("function main", "impl Display for Point")
+pub(crate) fn describe_item(item: &Item) -> String {
+    match (item, item_kind(item)) {
+        (Item::Impl(item_impl), _) => {
+            let self_ty = item_impl.self_ty.to_token_stream();
+            match &item_impl.trait_ {
+                Some((_, path, _)) => format!("impl {} for {}", path.to_token_stream(), self_ty),
+                None => format!("impl {}", self_ty),
+            }
+        }
+        (Item::Macro(_), ("macro invocation", Some(path))) => {
+            format!("macro invocation {}!", path)
+        }
+        (_, (kind, Some(name))) => format!("{} {}", kind, name),
+        (_, (kind, None)) => kind.to_string(),
+    }
+}
+
+/// The kind of an item, such as "function" or "impl", and the name it declares; an
+/// impl is named by its self type and a macro invocation by its path
+pub(crate) fn item_kind(item: &Item) -> (&'static str, Option<String>) {
+    let named = |kind, ident: &syn::Ident| (kind, Some(ident.to_string()));
+    match item {
+        Item::Fn(item_fn) => named("function", &item_fn.sig.ident),
+        Item::Struct(item_struct) => named("struct", &item_struct.ident),
+        Item::Enum(item_enum) => named("enum", &item_enum.ident),
+        Item::Union(item_union) => named("union", &item_union.ident),
+        Item::Trait(item_trait) => named("trait", &item_trait.ident),
+        Item::TraitAlias(item_alias) => named("trait alias", &item_alias.ident),
+        Item::Type(item_type) => named("type alias", &item_type.ident),
+        Item::Const(item_const) => named("constant", &item_const.ident),
+        Item::Static(item_static) => named("static", &item_static.ident),
+        Item::Mod(item_mod) => named("module", &item_mod.ident),
+        Item::ExternCrate(item_extern) => named("extern crate", &item_extern.ident),
+        Item::Impl(item_impl) => (
+            "impl",
+            Some(item_impl.self_ty.to_token_stream().to_string()),
+        ),
+        Item::Macro(item_macro) => match &item_macro.ident {
+            Some(ident) => named("macro", ident),
+            None => (
+                "macro invocation",
+                Some(item_macro.mac.path.to_token_stream().to_string()),
+            ),
+        },
+        Item::Use(_) => ("use declaration", None),
+        Item::ForeignMod(_) => ("extern block", None),
+        _ => ("item", None),
+    }
+}
+
+/// Where an item is in the Rust source, from its first token past any attributes
+pub(crate) fn rust_span(item: &Item) -> RustSpan {
+    let origin = origin_of(item_span(item), item.span(), String::new());
+    RustSpan {
+        start: (origin.line, origin.column),
+        end: (origin.end_line, origin.end_column),
+    }
+}
+
+/// The tokens of macro arguments as one line of text, with string literals in the form
+/// the Iron tokenizer reads back to the same value: line breaks, which would end the
+/// line, and escapes it does not know are rewritten
+fn macro_args(tokens: proc_macro2::TokenStream) -> String {
+    fn requote(tokens: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        tokens
+            .into_iter()
+            .map(|token| match token {
+                proc_macro2::TokenTree::Group(group) => {
+                    let mut requoted =
+                        proc_macro2::Group::new(group.delimiter(), requote(group.stream()));
+                    requoted.set_span(group.span());
+                    proc_macro2::TokenTree::Group(requoted)
+                }
+                proc_macro2::TokenTree::Literal(literal) => {
+                    match syn::parse_str::<syn::LitStr>(&literal.to_string()) {
+                        Ok(string) if string.suffix().is_empty() => {
+                            let quoted = quote_string(&string.value());
+                            match quoted.parse::<proc_macro2::Literal>() {
+                                Ok(requoted) => proc_macro2::TokenTree::Literal(requoted),
+                                Err(_) => proc_macro2::TokenTree::Literal(literal),
+                            }
+                        }
+                        _ => proc_macro2::TokenTree::Literal(literal),
+                    }
+                }
+                token => token,
+            })
+            .collect()
+    }
+
+    requote(tokens).to_string()
+}
+
+/// Whether a block labels a loop or block, or breaks with a label or a value, none of
+/// which Iron's `exit loop` and `continue loop` can say
+fn uses_loop_labels(block: &syn::Block) -> bool {
+    struct LabelFinder(bool);
+
+    impl<'ast> Visit<'ast> for LabelFinder {
+        fn visit_label(&mut self, _label: &'ast syn::Label) {
+            self.0 = true;
+        }
+
+        fn visit_expr_break(&mut self, expr_break: &'ast syn::ExprBreak) {
+            self.0 |= expr_break.label.is_some() || expr_break.expr.is_some();
+            syn::visit::visit_expr_break(self, expr_break);
+        }
+
+        fn visit_expr_continue(&mut self, expr_continue: &'ast syn::ExprContinue) {
+            self.0 |= expr_continue.label.is_some();
+        }
+    }
+
+    let mut finder = LabelFinder(false);
+    finder.visit_block(block);
+    finder.0
+}
+
+/// The span of an item from its first token after any attributes, so doc comments do not
+/// move where it starts
+fn item_span(item: &Item) -> proc_macro2::Span {
+    let mut tokens = item.to_token_stream().into_iter().peekable();
+    while let Some(proc_macro2::TokenTree::Punct(punct)) = tokens.peek() {
+        if punct.as_char() != '#' {
+            break;
+        }
+        tokens.next();
+        if let Some(proc_macro2::TokenTree::Punct(bang)) = tokens.peek()
+            && bang.as_char() == '!'
+        {
+            tokens.next();
+        }
+        tokens.next();
+    }
+    tokens
+        .next()
+        .map_or_else(|| item.span(), |token| token.span())
+}
+
+/// A statement's kind for provenance, such as "let binding x" or "for loop"
+fn describe_stmt(stmt: &Stmt) -> String {
+    match stmt {
+        Stmt::Local(local) => match &local.pat {
+            Pat::Ident(pat_ident) => format!("let binding {}", pat_ident.ident),
+            Pat::Type(pat_type) => match &*pat_type.pat {
+                Pat::Ident(pat_ident) => format!("let binding {}", pat_ident.ident),
+                _ => "let binding".to_string(),
+            },
+            _ => "let binding".to_string(),
+        },
+        Stmt::Item(item) => describe_item(item),
+        Stmt::Expr(expr, _) => match expr {
+            Expr::ForLoop(_) => "for loop".to_string(),
+            Expr::While(_) => "while loop".to_string(),
+            Expr::Loop(_) => "loop".to_string(),
+            Expr::If(_) => "if expression".to_string(),
+            Expr::Match(_) => "match expression".to_string(),
+            Expr::Return(_) => "return".to_string(),
+            Expr::Assign(_) => "assignment".to_string(),
+            Expr::Call(_) => "call".to_string(),
+            Expr::MethodCall(method_call) => format!("method call {}", method_call.method),
+            Expr::Macro(expr_macro) => {
+                format!(
+                    "macro invocation {}!",
+                    expr_macro.mac.path.to_token_stream()
+                )
+            }
+            _ => "expression".to_string(),
+        },
+        Stmt::Macro(stmt_macro) => format!(
+            "macro invocation {}!",
+            stmt_macro.mac.path.to_token_stream()
+        ),
+    }
+}
+
+/// The origin of a construct starting at `start` (past any attributes) and ending where
+/// `whole` does
+/// `origin` with its positions moved by `move_position` against `start`, to store it
+/// relative to an item's start or replay it where the item is now
+fn moved_origin(
+    origin: Origin,
+    start: Position,
+    move_position: fn(Position, Position) -> Position,
+) -> Origin {
+    let (line, column) = move_position(start, (origin.line, origin.column));
+    let (end_line, end_column) = move_position(start, (origin.end_line, origin.end_column));
+    Origin {
+        line,
+        column,
+        end_line,
+        end_column,
+        construct: origin.construct,
+    }
+}
+
+fn origin_of(start: proc_macro2::Span, whole: proc_macro2::Span, construct: String) -> Origin {
+    let (start, end) = (start.start(), whole.end());
+    Origin {
+        line: start.line,
+        column: start.column + 1,
+        end_line: end.line,
+        end_column: end.column + 1,
+        construct,
+    }
+}
@@ -0,0 +1,380 @@
+//! Structural differences between two Rust files
+//!
+//! Compares the syntax trees of code before and after a round trip through Iron item by
+//! item, so a report says what changed (a missing item, a changed signature, an altered
+//! literal) where a text diff would only show changed lines.
+
+use std::fmt;
+
+use quote::ToTokens;
+use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
+use syn::{Attribute, File, ImplItem, Item, Lit};
+
+use crate::parser::describe_item;
+
+/// One way the round-tripped code differs from the original
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    /// An item of the original with no counterpart in the round-tripped code
+    MissingItem(String),
+    /// An item only the round-tripped code has
+    AddedItem(String),
+    /// Attributes, doc comments included, that changed on an item
+    ChangedAttributes {
+        item: String,
+        original: String,
+        roundtripped: String,
+    },
+    /// A function signature that changed
+    ChangedSignature {
+        item: String,
+        original: String,
+        roundtripped: String,
+    },
+    /// A literal that came back with another value or suffix
+    AlteredLiteral {
+        item: String,
+        original: String,
+        roundtripped: String,
+    },
+    /// The first statement of a function body that changed other than in its literals,
+    /// empty where one body has fewer statements
+    ChangedBody {
+        item: String,
+        original: String,
+        roundtripped: String,
+    },
+    /// Any other change to an item, such as to the fields of a struct
+    ChangedItem {
+        item: String,
+        original: String,
+        roundtripped: String,
+    },
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (item, what, original, roundtripped) = match self {
+            Difference::MissingItem(item) => return write!(f, "{} is missing", item),
+            Difference::AddedItem(item) => return write!(f, "{} was added", item),
+            Difference::ChangedAttributes {
+                item,
+                original,
+                roundtripped,
+            } => (item, "attributes", original, roundtripped),
+            Difference::ChangedSignature {
+                item,
+                original,
+                roundtripped,
+            } => (item, "signature", original, roundtripped),
+            Difference::AlteredLiteral {
+                item,
+                original,
+                roundtripped,
+            } => (item, "literal", original, roundtripped),
+            Difference::ChangedBody {
+                item,
+                original,
+                roundtripped,
+            } => (item, "statement", original, roundtripped),
+            Difference::ChangedItem {
+                item,
+                original,
+                roundtripped,
+            } => (item, "definition", original, roundtripped),
+        };
+        write!(
+            f,
+            "{}: {} `{}` became `{}`",
+            item, what, original, roundtripped
+        )
+    }
+}
+
+/// The structural differences between an original Rust file and its round trip, in the
+/// order of the original's items, followed by any items added
+///
+/// Items are matched by kind and name; functions of impl blocks and items of inline
+/// modules are compared one by one. Layout and non-doc comments are not part of a
+/// syntax tree, so they never differ.
+///
+/// # Example
+///
+/// ```rust
+/// use redox::semantic_diff::{semantic_diff, Difference};
+///
+/// let original = syn::parse_file("fn f() -> u8 { 1 }\nfn g() {}").unwrap();
+/// let roundtripped = syn::parse_file("fn f() -> u8 { 2 }").unwrap();
+///
+/// assert_eq!(
+///     semantic_diff(&original, &roundtripped),
+///     [
+///         Difference::AlteredLiteral {
+///             item: "function f".to_string(),
+///             original: "1".to_string(),
+///             roundtripped: "2".to_string(),
+///         },
+///         Difference::MissingItem("function g".to_string()),
+///     ]
+/// );
+/// ```
+pub fn semantic_diff(original: &File, roundtripped: &File) -> Vec<Difference> {
+    let mut originals = Vec::new();
+    collect_entries(&original.items, "", &mut originals);
+    let mut roundtrips = Vec::new();
+    collect_entries(&roundtripped.items, "", &mut roundtrips);
+
+    let mut differences = Vec::new();
+    let mut matched = vec![false; roundtrips.len()];
+    for (name, item) in &originals {
+        let counterpart = roundtrips
+            .iter()
+            .enumerate()
+            .position(|(index, (other, _))| !matched[index] && other == name);
+        match counterpart {
+            Some(index) => {
+                matched[index] = true;
+                diff_item(name, item, &roundtrips[index].1, &mut differences);
+            }
+            None => differences.push(Difference::MissingItem(name.clone())),
+        }
+    }
+    for ((name, _), matched) in roundtrips.iter().zip(matched) {
+        if !matched {
+            differences.push(Difference::AddedItem(name.clone()));
+        }
+    }
+    differences
+}
+
+/// Flatten items into named entries, giving functions of impl blocks and items of inline
+/// modules entries of their own and leaving them out of their container's
+fn collect_entries(items: &[Item], prefix: &str, entries: &mut Vec<(String, Item)>) {
+    for item in items {
+        let name = format!("{}{}", prefix, entry_name(item));
+        match item {
+            Item::Impl(item_impl) => {
+                let mut container = item_impl.clone();
+                container.items.retain(|impl_item| {
+                    let ImplItem::Fn(method) = impl_item else {
+                        return true;
+                    };
+                    let method = Item::Fn(syn::ItemFn {
+                        attrs: method.attrs.clone(),
+                        vis: method.vis.clone(),
+                        sig: method.sig.clone(),
+                        block: Box::new(method.block.clone()),
+                    });
+                    entries.push((format!("{}: {}", name, describe_item(&method)), method));
+                    false
+                });
+                entries.push((name, Item::Impl(container)));
+            }
+            Item::Mod(item_mod) if item_mod.content.is_some() => {
+                let mut container = item_mod.clone();
+                let (_, children) = container.content.as_mut().unwrap();
+                let children = std::mem::take(children);
+                entries.push((name.clone(), Item::Mod(container)));
+                collect_entries(&children, &format!("{}: ", name), entries);
+            }
+            _ => entries.push((name, item.clone())),
+        }
+    }
+}
+
+fn entry_name(item: &Item) -> String {
+    match item {
+        Item::Use(item_use) => format!("use {}", item_use.tree.to_token_stream()),
+        _ => describe_item(item),
+    }
+}
+
+/// Record how a matched pair of items differs, from attributes down to statements
+fn diff_item(name: &str, original: &Item, roundtripped: &Item, differences: &mut Vec<Difference>) {
+    if original == roundtripped {
+        return;
+    }
+    let (mut original, mut roundtripped) = (original.clone(), roundtripped.clone());
+    let item = name.to_string();
+
+    let (original_attrs, roundtripped_attrs) =
+        (take_attrs(&mut original), take_attrs(&mut roundtripped));
+    if original_attrs != roundtripped_attrs {
+        differences.push(Difference::ChangedAttributes {
+            item: item.clone(),
+            original: tokens(&original_attrs),
+            roundtripped: tokens(&roundtripped_attrs),
+        });
+    }
+
+    if let (Item::Fn(original_fn), Item::Fn(roundtripped_fn)) = (&original, &mut roundtripped)
+        && original_fn.sig != roundtripped_fn.sig
+    {
+        differences.push(Difference::ChangedSignature {
+            item: item.clone(),
+            original: original_fn.sig.to_token_stream().to_string(),
+            roundtripped: roundtripped_fn.sig.to_token_stream().to_string(),
+        });
+        roundtripped_fn.sig = original_fn.sig.clone();
+    }
+
+    // Literals are compared pairwise when both sides have the same number of them
+    let (original_lits, roundtripped_lits) = (literals(&original), literals(&roundtripped));
+    if original_lits.len() == roundtripped_lits.len() {
+        for (original_lit, roundtripped_lit) in original_lits.iter().zip(&roundtripped_lits) {
+            if original_lit != roundtripped_lit {
+                differences.push(Difference::AlteredLiteral {
+                    item: item.clone(),
+                    original: original_lit.clone(),
+                    roundtripped: roundtripped_lit.clone(),
+                });
+            }
+        }
+    }
+
+    let (mut original_shape, mut roundtripped_shape) = (original.clone(), roundtripped.clone());
+    BlankLiterals.visit_item_mut(&mut original_shape);
+    BlankLiterals.visit_item_mut(&mut roundtripped_shape);
+    if original_shape == roundtripped_shape && original_lits.len() == roundtripped_lits.len() {
+        return;
+    }
+
+    match (
+        &original,
+        &roundtripped,
+        &original_shape,
+        &roundtripped_shape,
+    ) {
+        (
+            Item::Fn(original),
+            Item::Fn(roundtripped),
+            Item::Fn(original_shape),
+            Item::Fn(roundtripped_shape),
+        ) => {
+            let (original_stmts, shape_stmts) =
+                (&original.block.stmts, &original_shape.block.stmts);
+            let (roundtripped_stmts, roundtripped_shape_stmts) =
+                (&roundtripped.block.stmts, &roundtripped_shape.block.stmts);
+            let first = (0..original_stmts.len().max(roundtripped_stmts.len()))
+                .find(|&index| shape_stmts.get(index) != roundtripped_shape_stmts.get(index))
+                // Same statements with a different number of literals
+                .unwrap_or(0);
+            let statement = |stmts: &[syn::Stmt]| {
+                stmts
+                    .get(first)
+                    .map(|stmt| stmt.to_token_stream().to_string())
+                    .unwrap_or_default()
+            };
+            differences.push(Difference::ChangedBody {
+                item,
+                original: statement(original_stmts),
+                roundtripped: statement(roundtripped_stmts),
+            });
+        }
+        _ => differences.push(Difference::ChangedItem {
+            item,
+            original: original.to_token_stream().to_string(),
+            roundtripped: roundtripped.to_token_stream().to_string(),
+        }),
+    }
+}
+
+/// Remove and return the outer attributes of an item
+fn take_attrs(item: &mut Item) -> Vec<Attribute> {
+    let attrs = match item {
+        Item::Const(item) => &mut item.attrs,
+        Item::Enum(item) => &mut item.attrs,
+        Item::ExternCrate(item) => &mut item.attrs,
+        Item::Fn(item) => &mut item.attrs,
+        Item::ForeignMod(item) => &mut item.attrs,
+        Item::Impl(item) => &mut item.attrs,
+        Item::Macro(item) => &mut item.attrs,
+        Item::Mod(item) => &mut item.attrs,
+        Item::Static(item) => &mut item.attrs,
+        Item::Struct(item) => &mut item.attrs,
+        Item::Trait(item) => &mut item.attrs,
+        Item::TraitAlias(item) => &mut item.attrs,
+        Item::Type(item) => &mut item.attrs,
+        Item::Union(item) => &mut item.attrs,
+        Item::Use(item) => &mut item.attrs,
+        _ => return Vec::new(),
+    };
+    std::mem::take(attrs)
+}
+
+fn tokens(attrs: &[Attribute]) -> String {
+    attrs
+        .iter()
+        .map(|attr| attr.to_token_stream().to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The literals of an item, in source order
+fn literals(item: &Item) -> Vec<String> {
+    struct Literals(Vec<String>);
+
+    impl Visit<'_> for Literals {
+        fn visit_lit(&mut self, lit: &Lit) {
+            self.0.push(lit.to_token_stream().to_string());
+        }
+    }
+
+    let mut literals = Literals(Vec::new());
+    literals.visit_item(item);
+    literals.0
+}
+
+/// Replaces every literal with `false`, so items can be compared apart from them
+struct BlankLiterals;
+
+impl VisitMut for BlankLiterals {
+    fn visit_lit_mut(&mut self, lit: &mut Lit) {
+        *lit = Lit::Bool(syn::LitBool::new(false, lit.span()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(original: &str, roundtripped: &str) -> Vec<String> {
+        let original = syn::parse_file(original).unwrap();
+        let roundtripped = syn::parse_file(roundtripped).unwrap();
+        semantic_diff(&original, &roundtripped)
+            .iter()
+            .map(Difference::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn test_semantic_diff_names_each_change() {
+        let original = "/// Docs\nfn f(x: i32) -> i32 {\n    let y = x + 1;\n    y\n}\n\n\
+            struct S {\n    a: u8,\n}\n\n\
+            impl S {\n    fn get(&self) -> u8 {\n        self.a\n    }\n}\n\n\
+            mod m {\n    const C: &str = \"c\";\n}\n\n\
+            use std::fmt;\nuse std::io;\n";
+        let roundtripped = "fn f(x: i64) -> i32 {\n    let y = (x + 2);\n    y\n}\n\n\
+            struct S {\n    a: u16,\n}\n\n\
+            impl S {}\n\n\
+            mod m {\n    const C: &str = \"d\";\n}\n\n\
+            use std::io;\nuse std::fmt;\nfn extra() {}\n";
+        assert_eq!(
+            diff(original, roundtripped),
+            [
+                "function f: attributes `# [doc = \" Docs\"]` became ``",
+                "function f: signature `fn f (x : i32) -> i32` became `fn f (x : i64) -> i32`",
+                "function f: literal `1` became `2`",
+                "function f: statement `let y = x + 1 ;` became `let y = (x + 2) ;`",
+                "struct S: definition `struct S { a : u8 , }` became `struct S { a : u16 , }`",
+                "impl S: function get is missing",
+                "module m: constant C: literal `\"c\"` became `\"d\"`",
+                "function extra was added",
+            ]
+        );
+
+        // Layout and plain comments are not differences
+        assert!(diff("fn f() {\n    // one\n    g(1)\n}\n", "fn f() { g(1) }").is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+//! Source maps between Rust and its Iron
+//!
+//! Records which Iron lines each Rust construct was reduced to, so a position in one
+//! can be looked up in the other: a diagnostic on edited Iron can be projected onto the
+//! Rust it came from, and a Rust span can be found in the Iron.
+
+use std::cmp::Reverse;
+use std::ops::RangeInclusive;
+
+use crate::emitter::Origin;
+
+/// A Rust construct and the Iron lines it was reduced to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    /// Start of the construct in the Rust source, as (line, 1-based column)
+    pub rust_start: (usize, usize),
+    /// End of the construct in the Rust source, as (line, 1-based column just past it)
+    pub rust_end: (usize, usize),
+    /// 1-based Iron lines of the construct, including those of constructs nested in it
+    pub iron_lines: RangeInclusive<usize>,
+    /// What the construct is, such as "function add" or "let binding"
+    pub construct: String,
+}
+
+impl Mapping {
+    fn contains_rust(&self, start: (usize, usize), end: (usize, usize)) -> bool {
+        self.rust_start <= start && end <= self.rust_end
+    }
+
+    fn iron_len(&self) -> usize {
+        self.iron_lines.end() - self.iron_lines.start()
+    }
+}
+
+/// Mappings between Rust constructs and Iron lines, in Iron order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    /// Build a source map from the origins reduction recorded (see
+    /// [`crate::transpile_with_origins`]) for Iron of `iron_line_count` lines
+    ///
+    /// A construct whose lines are interrupted by nested ones, such as a function around
+    /// its statements, gets one mapping spanning all of them.
+    pub fn from_origins(origins: &[(usize, Origin)], iron_line_count: usize) -> Self {
+        let mut mappings: Vec<Mapping> = Vec::new();
+        for (index, (line, origin)) in origins.iter().enumerate() {
+            let last = origins
+                .get(index + 1)
+                .map_or(iron_line_count, |(next, _)| next - 1)
+                .max(*line);
+            let rust_start = (origin.line, origin.column);
+            let rust_end = (origin.end_line, origin.end_column);
+            let existing = mappings.iter_mut().rev().find(|mapping| {
+                mapping.rust_start == rust_start
+                    && mapping.rust_end == rust_end
+                    && mapping.construct == origin.construct
+            });
+            match existing {
+                Some(mapping) => mapping.iron_lines = *mapping.iron_lines.start()..=last,
+                None => mappings.push(Mapping {
+                    rust_start,
+                    rust_end,
+                    iron_lines: *line..=last,
+                    construct: origin.construct.clone(),
+                }),
+            }
+        }
+        SourceMap { mappings }
+    }
+
+    /// Every mapping, in the order their Iron lines start
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    /// The innermost construct an Iron line was reduced from
+    pub fn rust_for_iron_line(&self, line: usize) -> Option<&Mapping> {
+        self.mappings
+            .iter()
+            .filter(|mapping| mapping.iron_lines.contains(&line))
+            .min_by_key(|mapping| mapping.iron_len())
+    }
+
+    /// The Iron lines of the innermost construct containing a Rust span, such as one of
+    /// a `syn` node
+    pub fn iron_lines_for_span(&self, span: proc_macro2::Span) -> Option<RangeInclusive<usize>> {
+        let (start, end) = (span.start(), span.end());
+        self.iron_lines_for_rust((start.line, start.column + 1), (end.line, end.column + 1))
+    }
+
+    /// The Iron lines of the innermost construct containing the Rust source from `start`
+    /// to `end`, each a (line, 1-based column)
+    pub fn iron_lines_for_rust(
+        &self,
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> Option<RangeInclusive<usize>> {
+        self.mappings
+            .iter()
+            .filter(|mapping| mapping.contains_rust(start, end))
+            .max_by_key(|mapping| (mapping.rust_start, Reverse(mapping.rust_end)))
+            .map(|mapping| mapping.iron_lines.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_source_map_maps_both_ways() {
+        let rust = "fn main() {\n    let x = 1;\n    let y = x;\n}\n";
+        let (iron, map) = crate::transpile_with_source_map(rust).unwrap();
+        let lines: Vec<&str> = iron.lines().collect();
+        assert_eq!(lines[2], "    define x as 1");
+
+        let function = &map.mappings()[0];
+        assert_eq!(function.construct, "function main");
+        assert_eq!(function.rust_start, (1, 1));
+        assert_eq!(function.rust_end, (4, 2));
+        assert_eq!(function.iron_lines, 1..=lines.len());
+
+        let binding = map.rust_for_iron_line(3).unwrap();
+        assert_eq!(binding.construct, "let binding x");
+        assert_eq!(binding.rust_start, (2, 5));
+        assert_eq!(binding.iron_lines, 3..=3);
+        assert_eq!(map.rust_for_iron_line(lines.len()), Some(function));
+        assert_eq!(map.rust_for_iron_line(lines.len() + 1), None);
+
+        let file = syn::parse_file(rust).unwrap();
+        let syn::Item::Fn(main) = &file.items[0] else {
+            panic!("expected a function");
+        };
+        let span = syn::spanned::Spanned::span(&main.block.stmts[1]);
+        assert_eq!(map.iron_lines_for_span(span), Some(4..=4));
+        assert_eq!(map.iron_lines_for_rust((3, 13), (3, 14)), Some(4..=4));
+        assert_eq!(
+            map.iron_lines_for_rust((1, 4), (1, 8)),
+            Some(1..=lines.len())
+        );
+        assert_eq!(map.iron_lines_for_rust((9, 1), (9, 2)), None);
+    }
+}
@@ -0,0 +1,273 @@
+//! Identifiers shared by reduction and oxidation
+//!
+//! A [`SymbolTable`] interns each identifier once, works out its Iron spelling once (a
+//! `user_` prefix where the Rust name collides with an Iron keyword, see
+//! [`crate::keywords`]), records each name that was renamed that way, and tracks the
+//! names each nested scope declares. Reduction keeps one in its emitter and oxidation one
+//! in the oxidizer. Both record names by their Rust spelling, so the table of a file and
+//! that of its round trip can be compared.
+//!
+//! Every indented block of Iron, and every block of the Rust written for it, is a scope.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::keywords::sanitize_identifier;
+
+/// An interned identifier, valid for the table that made it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+/// A point in a table's records that it can be rolled back to
+#[derive(Debug, Clone, Copy)]
+pub struct SymbolCheckpoint {
+    renamed: usize,
+    uses: usize,
+    declarations: usize,
+    scopes: usize,
+}
+
+/// Interned identifiers, their Iron spellings, the names renamed to get them, and the
+/// names declared in each open scope
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    names: Vec<Arc<str>>,
+    ids: HashMap<Arc<str>, Symbol>,
+    /// Iron spelling of each name, or `None` if it is spelled as in Rust
+    iron: Vec<Option<Arc<str>>>,
+    /// Names whose Iron spelling differs, in the order they were first recorded
+    renamed: Vec<Symbol>,
+    /// Each recording of a name whose Iron spelling differs, in order
+    renamed_uses: Vec<Symbol>,
+    /// Names declared in the open scopes, innermost last
+    declarations: Vec<Symbol>,
+    /// Where each open scope's declarations start, innermost last
+    scopes: Vec<usize>,
+}
+
+impl SymbolTable {
+    /// Create an empty table, at file scope
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The symbol of `name`, interning it if it is new
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(&symbol) = self.ids.get(name) {
+            return symbol;
+        }
+        let symbol = Symbol(self.names.len() as u32);
+        let name: Arc<str> = Arc::from(name);
+        let iron = sanitize_identifier(&name);
+        self.iron.push((iron != *name).then(|| Arc::from(iron)));
+        self.names.push(name.clone());
+        self.ids.insert(name, symbol);
+        symbol
+    }
+
+    /// The symbol of `name`, if it has been interned
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.ids.get(name).copied()
+    }
+
+    /// The Rust spelling of `symbol`
+    pub fn name(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.0 as usize]
+    }
+
+    /// The Iron spelling of `symbol`
+    pub fn iron_name(&self, symbol: Symbol) -> &str {
+        let index = symbol.0 as usize;
+        self.iron[index].as_ref().unwrap_or(&self.names[index])
+    }
+
+    /// Number of names interned
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Whether no names are interned
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The Iron spelling of the Rust identifier `name`, recording it if it was renamed
+    pub fn sanitize(&mut self, name: &str) -> String {
+        let symbol = self.record(name);
+        self.iron_name(symbol).to_string()
+    }
+
+    /// Intern `name`, noting it as renamed the first time its Iron spelling is used
+    pub fn record(&mut self, name: &str) -> Symbol {
+        let symbol = self.intern(name);
+        if self.iron[symbol.0 as usize].is_some() {
+            if !self.renamed.contains(&symbol) {
+                self.renamed.push(symbol);
+            }
+            self.renamed_uses.push(symbol);
+        }
+        symbol
+    }
+
+    /// Each (Rust, Iron) pair of names renamed to avoid an Iron keyword, in the order
+    /// they were first met
+    pub fn renamings(&self) -> Vec<(String, String)> {
+        self.renamed
+            .iter()
+            .map(|&symbol| {
+                (
+                    self.name(symbol).to_string(),
+                    self.iron_name(symbol).to_string(),
+                )
+            })
+            .collect()
+    }
+
+    /// Open a scope inside the current one
+    pub fn enter_scope(&mut self) {
+        self.scopes.push(self.declarations.len());
+    }
+
+    /// Close the innermost scope, forgetting what it declared; file scope stays open
+    pub fn exit_scope(&mut self) {
+        if let Some(start) = self.scopes.pop() {
+            self.declarations.truncate(start);
+        }
+    }
+
+    /// Number of scopes open inside file scope
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Declare `name` in the innermost scope, recording it if it was renamed
+    pub fn declare(&mut self, name: &str) -> Symbol {
+        let symbol = self.record(name);
+        self.declarations.push(symbol);
+        symbol
+    }
+
+    /// Whether `name` is declared in any open scope
+    pub fn is_declared(&self, name: &str) -> bool {
+        self.get(name)
+            .is_some_and(|symbol| self.declarations.contains(&symbol))
+    }
+
+    /// Whether `name` is declared in the innermost scope, so declaring it again shadows
+    /// a name of the same block
+    pub fn is_declared_in_scope(&self, name: &str) -> bool {
+        let start = self.scopes.last().copied().unwrap_or(0);
+        self.get(name)
+            .is_some_and(|symbol| self.declarations[start..].contains(&symbol))
+    }
+
+    /// The Rust spelling of each name declared since `checkpoint` that is still in scope
+    pub fn declared_since(&self, checkpoint: SymbolCheckpoint) -> Vec<String> {
+        self.declarations[checkpoint.declarations.min(self.declarations.len())..]
+            .iter()
+            .map(|&symbol| self.name(symbol).to_string())
+            .collect()
+    }
+
+    /// The Rust spelling of each renamed name recorded since `checkpoint`, once each,
+    /// whether or not it had been recorded before
+    pub fn renamed_since(&self, checkpoint: SymbolCheckpoint) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for &symbol in &self.renamed_uses[checkpoint.uses..] {
+            let name = self.name(symbol);
+            if !names.iter().any(|known| known == name) {
+                names.push(name.to_string());
+            }
+        }
+        names
+    }
+
+    /// Mark the current renamings, declarations, and scopes
+    pub fn checkpoint(&self) -> SymbolCheckpoint {
+        SymbolCheckpoint {
+            renamed: self.renamed.len(),
+            uses: self.renamed_uses.len(),
+            declarations: self.declarations.len(),
+            scopes: self.scopes.len(),
+        }
+    }
+
+    /// Forget what was recorded since `checkpoint`; interned names are kept
+    ///
+    /// Declarations made before `checkpoint` in scopes closed since are not restored.
+    pub fn rollback(&mut self, checkpoint: SymbolCheckpoint) {
+        self.renamed.truncate(checkpoint.renamed);
+        self.renamed_uses.truncate(checkpoint.uses);
+        self.declarations.truncate(checkpoint.declarations);
+        self.scopes.truncate(checkpoint.scopes);
+    }
+
+    /// Forget the renamings, declarations, and scopes of the previous file, keeping the
+    /// interned names and their spellings for the next
+    pub fn clear(&mut self) {
+        self.renamed.clear();
+        self.renamed_uses.clear();
+        self.declarations.clear();
+        self.scopes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbol_table_interns_and_scopes() {
+        let mut table = SymbolTable::new();
+        let count = table.intern("count");
+        assert_eq!(table.intern("count"), count);
+        assert_eq!(table.get("count"), Some(count));
+        assert_eq!(table.get("total"), None);
+        assert_eq!(table.name(count), "count");
+        assert_eq!(table.iron_name(count), "count");
+
+        assert_eq!(table.sanitize("function"), "user_function");
+        assert_eq!(table.sanitize("user_self"), "user_user_self");
+        assert_eq!(table.sanitize("function"), "user_function");
+        assert_eq!(table.sanitize("user_id"), "user_id");
+        assert_eq!(
+            table.renamings(),
+            [
+                ("function".to_string(), "user_function".to_string()),
+                ("user_self".to_string(), "user_user_self".to_string())
+            ]
+        );
+
+        let checkpoint = table.checkpoint();
+        table.record("loop");
+        table.record("function");
+        table.record("loop");
+        assert_eq!(table.renamings().len(), 3);
+        assert_eq!(table.renamed_since(checkpoint), ["loop", "function"]);
+        table.rollback(checkpoint);
+        assert_eq!(table.renamings().len(), 2);
+        assert!(table.get("loop").is_some());
+
+        table.declare("count");
+        table.enter_scope();
+        let checkpoint = table.checkpoint();
+        table.declare("loop");
+        assert!(table.is_declared("count") && table.is_declared("loop"));
+        assert!(!table.is_declared_in_scope("count"));
+        assert_eq!(table.declared_since(checkpoint), ["loop"]);
+        assert_eq!(table.depth(), 1);
+        table.rollback(checkpoint);
+        assert!(!table.is_declared("loop"));
+        assert_eq!(table.renamings().len(), 2);
+        table.declare("loop");
+        table.exit_scope();
+        assert!(!table.is_declared("loop"));
+        assert!(table.is_declared_in_scope("count"));
+        table.exit_scope();
+        assert_eq!(table.depth(), 0);
+
+        table.clear();
+        assert!(table.renamings().is_empty() && !table.is_declared("count"));
+        assert_eq!(table.get("count"), Some(count));
+    }
+}
@@ -0,0 +1,273 @@
+//! Profiles of the symbols validation prohibits
+//!
+//! Iron spells out what Rust writes with symbols, and which leftover symbols matter
+//! depends on the model the Iron is for. A [`ValidationProfile`] names the prohibited
+//! symbols, each with the rule it breaks, and whether those in comments, strings, and
+//! verbatim blocks are reported. Besides the built-in profiles, one can be read from
+//! TOML or JSON with the `files` feature:
+//!
+//! ```toml
+//! name = "small-model"
+//! literals = false
+//!
+//! [symbols]
+//! "::" = "path-separator"
+//! "->" = "arrow"
+//! ```
+//!
+//! Symbols are matched in the symbol tokens of the Iron tokenizer, never inside words or
+//! numbers, and longer symbols first, so `::` is one symbol rather than two colons.
+
+#[cfg(feature = "files")]
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::TranspileError;
+use crate::iron_tokenizer::{Token, Tokenizer};
+
+/// Names of the built-in profiles, the default first
+pub const BUILT_IN: [&str; 2] = ["standard", "code"];
+
+/// The symbols of the standard profile, with the rule each breaks
+const STANDARD: [(&str, &str); 6] = [
+    ("::", "path-separator"),
+    ("&", "ampersand"),
+    ("-", "minus-sign"),
+    (">", "angle-bracket"),
+    ("<", "angle-bracket"),
+    ("*", "asterisk"),
+];
+
+/// The symbols prohibited in Iron, and where they count
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationProfile {
+    name: String,
+    /// Each symbol with the rule it breaks, longest first
+    symbols: Vec<(String, String)>,
+    /// Report symbols in comments, strings, and verbatim blocks, as warnings
+    literals: bool,
+}
+
+impl Default for ValidationProfile {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl ValidationProfile {
+    /// A profile named `name` that prohibits nothing yet
+    pub fn new(name: &str) -> Self {
+        ValidationProfile {
+            name: name.to_string(),
+            symbols: Vec::new(),
+            literals: true,
+        }
+    }
+
+    /// Rust's path, reference, arrow, comparison, and pointer symbols, reported as
+    /// warnings in comments, strings, and verbatim blocks
+    pub fn standard() -> Self {
+        let mut profile = Self::new("standard");
+        for (symbol, rule) in STANDARD {
+            profile.insert(symbol, rule).unwrap();
+        }
+        profile
+    }
+
+    /// The standard symbols in code only, leaving comments, strings, and verbatim
+    /// blocks alone
+    pub fn code() -> Self {
+        ValidationProfile {
+            name: "code".to_string(),
+            literals: false,
+            ..Self::standard()
+        }
+    }
+
+    /// The built-in profile named `name`
+    pub fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "standard" => Some(Self::standard()),
+            "code" => Some(Self::code()),
+            _ => None,
+        }
+    }
+
+    /// Read a profile from a `.toml` or `.json` file
+    #[cfg(feature = "files")]
+    pub fn load(path: &Path) -> Result<Self, TranspileError> {
+        let text = std::fs::read_to_string(path).map_err(|e| {
+            TranspileError::config_source(format!("Failed to read {}: {}", path.display(), e), e)
+        })?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml(&text),
+            Some("json") => Self::from_json(&text),
+            _ => Err(TranspileError::config(format!(
+                "{}: a validation profile must be a .toml or .json file",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Read a profile from TOML with a `[symbols]` table
+    #[cfg(feature = "files")]
+    pub fn from_toml(text: &str) -> Result<Self, TranspileError> {
+        let value: serde_json::Value = toml::from_str(text).map_err(|e| {
+            TranspileError::config_source(format!("Invalid validation profile: {}", e), e)
+        })?;
+        Self::from_value(&value)
+    }
+
+    /// Read a profile from JSON with a `symbols` object
+    #[cfg(feature = "files")]
+    pub fn from_json(text: &str) -> Result<Self, TranspileError> {
+        let value: serde_json::Value = serde_json::from_str(text).map_err(|e| {
+            TranspileError::config_source(format!("Invalid validation profile: {}", e), e)
+        })?;
+        Self::from_value(&value)
+    }
+
+    #[cfg(feature = "files")]
+    fn from_value(value: &serde_json::Value) -> Result<Self, TranspileError> {
+        let symbols = match value.get("symbols") {
+            Some(serde_json::Value::Object(symbols)) => symbols,
+            Some(_) => {
+                return Err(TranspileError::config(
+                    "Validation profile 'symbols' is not a table",
+                ));
+            }
+            None => {
+                return Err(TranspileError::config(
+                    "Validation profile has no 'symbols' table",
+                ));
+            }
+        };
+        let name = match value.get("name") {
+            Some(name) => name.as_str().ok_or_else(|| {
+                TranspileError::config("Validation profile 'name' is not a string")
+            })?,
+            None => "custom",
+        };
+
+        let mut profile = Self::new(name);
+        if let Some(literals) = value.get("literals") {
+            profile.literals = literals.as_bool().ok_or_else(|| {
+                TranspileError::config("Validation profile 'literals' is not a boolean")
+            })?;
+        }
+        for (symbol, rule) in symbols {
+            let rule = rule.as_str().ok_or_else(|| {
+                TranspileError::config(format!(
+                    "Validation profile symbol '{}' has no rule",
+                    symbol
+                ))
+            })?;
+            profile.insert(symbol, rule)?;
+        }
+        Ok(profile)
+    }
+
+    /// Prohibit `symbol`, reporting it as breaking `rule`
+    ///
+    /// The symbol must be characters the tokenizer reads as one symbol: no letters,
+    /// digits, whitespace, quotes, or commas.
+    pub fn insert(&mut self, symbol: &str, rule: &str) -> Result<(), TranspileError> {
+        let tokens = Tokenizer::new(symbol).with_symbols(true).tokenize();
+        if !matches!(&tokens[..], [Token::Symbol(text), Token::EndOfFile] if text == symbol) {
+            return Err(TranspileError::config(format!(
+                "'{}' is not a symbol: use characters outside Iron's words, numbers, and strings",
+                symbol
+            )));
+        }
+        if self.symbols.iter().any(|(existing, _)| existing == symbol) {
+            return Err(TranspileError::config(format!(
+                "'{}' is already prohibited",
+                symbol
+            )));
+        }
+        self.symbols.push((symbol.to_string(), rule.to_string()));
+        self.symbols
+            .sort_by_key(|(symbol, _)| std::cmp::Reverse(symbol.len()));
+        Ok(())
+    }
+
+    /// The profile's name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Each prohibited symbol with the rule it breaks, longest first
+    pub fn symbols(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.symbols
+            .iter()
+            .map(|(symbol, rule)| (symbol.as_str(), rule.as_str()))
+    }
+
+    /// Whether symbols in comments, strings, and verbatim blocks are reported
+    pub fn checks_literals(&self) -> bool {
+        self.literals
+    }
+
+    /// The prohibited symbol `text` starts with, if any, with its rule
+    pub fn symbol_at(&self, text: &str) -> Option<(&str, &str)> {
+        self.symbols().find(|(symbol, _)| text.starts_with(symbol))
+    }
+}
+
+impl FromStr for ValidationProfile {
+    type Err = TranspileError;
+
+    /// A built-in profile by name, or one read from a `.toml` or `.json` file with the
+    /// `files` feature
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Self::built_in(s) {
+            Some(profile) => Ok(profile),
+            #[cfg(feature = "files")]
+            None if s.ends_with(".toml") || s.ends_with(".json") => Self::load(Path::new(s)),
+            None => Err(TranspileError::config(format!(
+                "unknown validation profile '{}' (expected {}, or a .toml or .json file)",
+                s,
+                BUILT_IN.join(", ")
+            ))),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "files"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation_profiles() {
+        let standard = ValidationProfile::standard();
+        assert_eq!(standard.symbol_at("::x"), Some(("::", "path-separator")));
+        assert_eq!(standard.symbol_at("->"), Some(("-", "minus-sign")));
+        assert_eq!(standard.symbol_at(":"), None);
+        assert!(!ValidationProfile::code().checks_literals());
+        assert_eq!("code".parse().ok(), Some(ValidationProfile::code()));
+        assert!("gpt".parse::<ValidationProfile>().is_err());
+
+        let profile = ValidationProfile::from_toml(
+            "name = \"small-model\"\nliterals = false\n\n[symbols]\n\"-\" = \"minus-sign\"\n\"->\" = \"arrow\"\n",
+        )
+        .unwrap();
+        assert_eq!(profile.name(), "small-model");
+        assert!(!profile.checks_literals());
+        assert_eq!(profile.symbol_at("->"), Some(("->", "arrow")));
+        let json = ValidationProfile::from_json(
+            r#"{"name": "small-model", "literals": false, "symbols": {"->": "arrow", "-": "minus-sign"}}"#,
+        );
+        assert_eq!(json.unwrap(), profile);
+
+        let mut profile = profile;
+        for symbol in ["", "a", "1", "- >", "\"", "->"] {
+            assert!(profile.insert(symbol, "rule").is_err(), "{:?}", symbol);
+        }
+        assert!(ValidationProfile::from_toml("literals = true\n").is_err());
+        assert!(ValidationProfile::from_json(r#"{"symbols": {"&": 1}}"#).is_err());
+
+        let error = ValidationProfile::from_toml("[symbols").unwrap_err();
+        assert_eq!(error.code(), "invalid-config");
+        assert!(std::error::Error::source(&error).is_some());
+    }
+}
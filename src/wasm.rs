@@ -0,0 +1,200 @@
+//! WebAssembly bindings, behind the `wasm` feature
+//!
+//! `transpile`, `oxidize`, and `validate` for JavaScript. Each takes source text and
+//! returns a plain object rather than throwing, so a playground can show errors and
+//! warnings next to the input:
+//!
+//! ```js
+//! transpile("fn main( {}")
+//! // { ok: false, output: null, warnings: [],
+//! //   error: { code: "parse-error", message: "...", line: 1, column: 8 } }
+//!
+//! validate("function f\nbegin\n    return a::b\nend function\n")
+//! // { valid: false, violations: [{ line: 3, column: 13, symbol: "::",
+//! //   rule: "path-separator", snippet: "a::b", severity: "error" }] }
+//! ```
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::{OxidizeOptions, Severity, TranspileError};
+
+/// What a conversion produced, as handed to JavaScript
+#[derive(Debug, Serialize)]
+pub struct Outcome {
+    pub ok: bool,
+    /// The converted code, when conversion succeeded
+    pub output: Option<String>,
+    pub error: Option<ErrorInfo>,
+    pub warnings: Vec<WarningInfo>,
+}
+
+/// A failed conversion
+#[derive(Debug, Serialize)]
+pub struct ErrorInfo {
+    /// `parse-error`, `unsupported-syntax`, or `internal-error`
+    pub code: &'static str,
+    /// The message without its location
+    pub message: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+/// A problem oxidation worked around
+#[derive(Debug, Serialize)]
+pub struct WarningInfo {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// Prohibited symbols found in Iron, as handed to JavaScript
+#[derive(Debug, Serialize)]
+pub struct Validation {
+    pub valid: bool,
+    pub violations: Vec<Violation>,
+}
+
+/// One prohibited symbol
+#[derive(Debug, Serialize)]
+pub struct Violation {
+    pub line: usize,
+    pub column: usize,
+    pub symbol: String,
+    pub rule: String,
+    pub snippet: String,
+    /// `error`, or `warning` inside a comment, string, or verbatim block
+    pub severity: &'static str,
+}
+
+impl Outcome {
+    fn new(result: Result<String, TranspileError>, warnings: Vec<WarningInfo>) -> Self {
+        match result {
+            Ok(output) => Outcome {
+                ok: true,
+                output: Some(output),
+                error: None,
+                warnings,
+            },
+            Err(error) => {
+                let location = error.location();
+                Outcome {
+                    ok: false,
+                    output: None,
+                    error: Some(ErrorInfo {
+                        code: error.code(),
+                        message: error.detail(),
+                        line: location.map(|(line, _)| line),
+                        column: location.and_then(|(_, column)| column),
+                    }),
+                    warnings,
+                }
+            }
+        }
+    }
+}
+
+/// Reduce Rust to Iron, for [`transpile`]
+pub fn transpile_outcome(source: &str) -> Outcome {
+    Outcome::new(crate::transpile(source), Vec::new())
+}
+
+/// Oxidize Iron to Rust with any warnings, for [`oxidize`]
+pub fn oxidize_outcome(source: &str) -> Outcome {
+    match crate::oxidize_with_warnings(source, &OxidizeOptions::default()) {
+        Ok((rust, warnings)) => Outcome::new(
+            Ok(rust),
+            warnings
+                .into_iter()
+                .map(|warning| WarningInfo {
+                    line: warning.line,
+                    message: warning.message,
+                })
+                .collect(),
+        ),
+        Err(error) => Outcome::new(Err(error), Vec::new()),
+    }
+}
+
+/// Check Iron for prohibited symbols, for [`validate`]
+pub fn validation(source: &str) -> Validation {
+    let report = crate::validate_iron_report(source);
+    Validation {
+        valid: report.is_valid(),
+        violations: report
+            .violations
+            .into_iter()
+            .map(|symbol| Violation {
+                line: symbol.line,
+                column: symbol.column,
+                symbol: symbol.symbol,
+                rule: symbol.rule,
+                snippet: symbol.snippet,
+                severity: match symbol.severity {
+                    Severity::Error => "error",
+                    Severity::Warning => "warning",
+                },
+            })
+            .collect(),
+    }
+}
+
+fn to_js(value: &impl Serialize) -> JsValue {
+    serde_wasm_bindgen::to_value(value).unwrap_or_else(|e| JsValue::from_str(&e.to_string()))
+}
+
+/// Reduce Rust source to Iron: `{ ok, output, error, warnings }`
+#[wasm_bindgen]
+pub fn transpile(source: &str) -> JsValue {
+    to_js(&transpile_outcome(source))
+}
+
+/// Oxidize Iron source to Rust: `{ ok, output, error, warnings }`
+#[wasm_bindgen]
+pub fn oxidize(source: &str) -> JsValue {
+    to_js(&oxidize_outcome(source))
+}
+
+/// Find the Rust symbols in Iron source: `{ valid, violations }`
+#[wasm_bindgen]
+pub fn validate(source: &str) -> JsValue {
+    to_js(&validation(source))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcomes_serialize_for_javascript() {
+        let outcome = serde_json::to_value(transpile_outcome("fn one() {}")).unwrap();
+        assert_eq!(outcome["ok"], true);
+        assert!(
+            outcome["output"]
+                .as_str()
+                .unwrap()
+                .starts_with("function one")
+        );
+        assert!(outcome["error"].is_null());
+
+        let outcome = serde_json::to_value(transpile_outcome("fn main( {}")).unwrap();
+        assert_eq!(outcome["ok"], false);
+        assert!(outcome["output"].is_null());
+        assert_eq!(outcome["error"]["code"], "parse-error");
+        assert_eq!(outcome["error"]["line"], 1);
+
+        let outcome = oxidize_outcome("function f\nbegin\n    return 1\nend function\n");
+        assert_eq!(
+            outcome.output.as_deref(),
+            Some("fn f() {\n    return 1;\n}\n")
+        );
+
+        let validation = serde_json::to_value(validation(
+            "function f\nbegin\n    return a::b\nend function\n",
+        ))
+        .unwrap();
+        assert_eq!(validation["valid"], false);
+        assert_eq!(validation["violations"][0]["rule"], "path-separator");
+        assert_eq!(validation["violations"][0]["snippet"], "a::b");
+        assert_eq!(validation["violations"][0]["severity"], "error");
+    }
+}
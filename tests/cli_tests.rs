@@ -0,0 +1,1613 @@
+//! Tests for the `redox` command line interface
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::process::{Command, Stdio};
+
+fn redox() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_redox"))
+}
+
+#[test]
+fn test_reduce_directory_mirrors_tree() {
+    let input = tempfile::tempdir().unwrap();
+    let output = tempfile::tempdir().unwrap();
+    fs::create_dir_all(input.path().join("nested/deeper")).unwrap();
+    fs::write(input.path().join("lib.rs"), "fn one() -> i32 { 1 }\n").unwrap();
+    fs::write(
+        input.path().join("nested/deeper/two.rs"),
+        "fn two() -> i32 { 2 }\n",
+    )
+    .unwrap();
+    fs::write(input.path().join("nested/notes.txt"), "not rust\n").unwrap();
+
+    let status = redox()
+        .arg("reduce")
+        .arg(input.path())
+        .arg("--out-dir")
+        .arg(output.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let lib = fs::read_to_string(output.path().join("lib.iron")).unwrap();
+    assert!(lib.contains("function one"));
+    let two = fs::read_to_string(output.path().join("nested/deeper/two.iron")).unwrap();
+    assert!(two.contains("function two"));
+    assert!(!output.path().join("nested/notes.iron").exists());
+
+    // The glob is matched against paths relative to the input directory
+    let filtered = tempfile::tempdir().unwrap();
+    let status = redox()
+        .arg("reduce")
+        .arg(input.path())
+        .args(["--glob", "nested/**/*.rs", "--out-dir"])
+        .arg(filtered.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(filtered.path().join("nested/deeper/two.iron").exists());
+    assert!(!filtered.path().join("lib.iron").exists());
+
+    // A file named directly and through its directory is written to both places
+    let repeated = tempfile::tempdir().unwrap();
+    let status = redox()
+        .arg("reduce")
+        .arg(input.path())
+        .arg(input.path().join("nested/deeper/two.rs"))
+        .arg("--out-dir")
+        .arg(repeated.path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(repeated.path().join("nested/deeper/two.iron").exists());
+    assert!(repeated.path().join("two.iron").exists());
+}
+
+#[test]
+fn test_parallel_reduce_reports_failures_in_order() {
+    let input = tempfile::tempdir().unwrap();
+    let output = tempfile::tempdir().unwrap();
+    for i in 0..8 {
+        let source = if i % 3 == 0 {
+            "fn (".to_string()
+        } else {
+            format!("fn f{}() -> i32 {{ {} }}\n", i, i)
+        };
+        fs::write(input.path().join(format!("f{}.rs", i)), source).unwrap();
+    }
+
+    let result = redox()
+        .arg("reduce")
+        .arg(input.path())
+        .args(["--jobs", "4", "--out-dir"])
+        .arg(output.path())
+        .output()
+        .unwrap();
+    assert!(!result.status.success());
+
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    let failed: Vec<_> = stderr
+        .lines()
+        .filter_map(|line| line.split(".rs:").next()?.rsplit('/').next())
+        .filter(|name| name.starts_with('f'))
+        .collect();
+    assert_eq!(failed, ["f0", "f3", "f6"], "{}", stderr);
+    assert!(
+        stderr.contains("3 of 8 files failed to reduce"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("  reduced          5 files\n"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.contains("  failed           3 files\n"),
+        "{}",
+        stderr
+    );
+    // Output is not a terminal, so there is no progress line
+    assert!(!stderr.contains('\r'), "{}", stderr);
+
+    let result = redox()
+        .arg("reduce")
+        .arg(input.path())
+        .args(["--quiet", "--out-dir"])
+        .arg(output.path())
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(!stderr.contains("files\n"), "{}", stderr);
+    assert_eq!(stderr.lines().count(), 4, "{}", stderr);
+    let five = fs::read_to_string(output.path().join("f5.iron")).unwrap();
+    assert!(five.contains("function f5"));
+}
+
+#[test]
+fn test_stdin_and_stdout_pipes() {
+    let run = |args: &[&str], stdin: &str| {
+        let mut child = redox()
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(stdin.as_bytes())
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+        assert!(output.status.success(), "redox {:?} failed", args);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    let iron = run(&["reduce", "-"], "fn one() -> i32 { 1 }\n");
+    assert!(iron.contains("function one"));
+
+    let rust = run(&["oxidize", "-", "--output", "-"], &iron);
+    assert_eq!(rust, "fn one() -> i32 {\n    1\n}\n");
+
+    assert!(run(&["validate", "-"], &iron).contains("Valid Iron code"));
+}
+
+#[test]
+fn test_roundtrip_command_reports_stages() {
+    let dir = tempfile::tempdir().unwrap();
+    let same = dir.path().join("same.rs");
+    fs::write(&same, "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n").unwrap();
+
+    let output = redox()
+        .args(["roundtrip", "--compile"])
+        .arg(&same)
+        .output()
+        .unwrap();
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{}", report);
+    assert!(report.contains("reduce: ok"));
+    assert!(report.contains("compile roundtrip: ok"));
+    assert!(report.ends_with("result: identical\n"));
+
+    // Parentheses dropped on the way back show up in the diff
+    let changed = dir.path().join("changed.rs");
+    fs::write(
+        &changed,
+        "fn h(x: i32) -> i32 {\n    x\n}\n\nfn f() -> i32 {\n    (h(1)) + 1\n}\n",
+    )
+    .unwrap();
+    let output = redox().arg("roundtrip").arg(&changed).output().unwrap();
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(report.contains("result: differs\n--- original\n+++ roundtrip\n"));
+
+    let output = redox()
+        .args(["roundtrip", "--semantic"])
+        .arg(&changed)
+        .output()
+        .unwrap();
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(!output.status.success());
+    assert!(
+        report.ends_with(
+            "result: differs\n  function f: statement `(h (1)) + 1` became `h (1) + 1`\n"
+        )
+    );
+
+    // The same stages as one JSON object
+    let output = redox()
+        .args(["--format", "json", "roundtrip", "--semantic", "--show-iron"])
+        .arg(&changed)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["reduce"]["ok"], true);
+    assert!(report["iron"].as_str().unwrap().starts_with("function h"));
+    assert_eq!(report["oxidize"]["warnings"], serde_json::json!([]));
+    assert_eq!(report["identical"], false);
+    assert_eq!(
+        report["differences"],
+        serde_json::json!(["function f: statement `(h (1)) + 1` became `h (1) + 1`"])
+    );
+    assert_eq!(report["passed"], false);
+
+    let output = redox()
+        .args(["--format", "json", "roundtrip"])
+        .arg(&changed)
+        .output()
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert!(
+        report["diff"]
+            .as_str()
+            .unwrap()
+            .starts_with("--- original\n+++ roundtrip\n")
+    );
+
+    let output = redox()
+        .args(["--format", "json", "roundtrip", "--compile"])
+        .arg(&same)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["compile"]["roundtrip"]["ok"], true);
+    assert_eq!(report["identical"], true);
+    assert_eq!(report["passed"], true);
+}
+
+#[test]
+fn test_stats_command_counts_items() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("shapes.rs"),
+        "struct Point {\n    x: i32,\n}\n\nimpl Point {\n    fn x(&self) -> i32 {\n        self.x\n    }\n}\n\nfn origin() -> i32 {\n    0\n}\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("broken.rs"), "fn (").unwrap();
+
+    let output = redox().arg("stats").arg(dir.path()).output().unwrap();
+    assert!(output.status.success());
+    let report = String::from_utf8(output.stdout).unwrap();
+
+    assert!(report.contains("broken.rs: failed"), "{}", report);
+    assert!(
+        report.contains("shapes.rs: 3 items (fn 1, impl 1, struct 1), 1 verbatim"),
+        "{}",
+        report
+    );
+    assert!(
+        report.contains("total: 2 files (1 failed), 3 items"),
+        "{}",
+        report
+    );
+}
+
+#[test]
+fn test_json_diagnostics() {
+    let dir = tempfile::tempdir().unwrap();
+    let broken = dir.path().join("broken.iron");
+    fs::write(
+        &broken,
+        "function f\nbegin\n    define x as\nend function\n",
+    )
+    .unwrap();
+    let symbols = dir.path().join("symbols.iron");
+    fs::write(&symbols, "define x as 1\ncall a::b with x\n").unwrap();
+
+    let output = redox()
+        .args(["oxidize", "--format", "json"])
+        .arg(&broken)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let error: serde_json::Value = serde_json::from_str(stderr.trim()).unwrap();
+    assert_eq!(error["file"], broken.display().to_string());
+    assert_eq!(error["line"], 3);
+    assert_eq!(error["column"], 16);
+    assert_eq!(error["severity"], "error");
+    assert_eq!(error["code"], "parse-error");
+
+    let output = redox()
+        .args(["validate", "--format", "json"])
+        .arg(&symbols)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let result: serde_json::Value = serde_json::from_str(stdout.trim()).unwrap();
+    assert_eq!(result["valid"], false);
+    assert_eq!(result["diagnostics"][0]["line"], 2);
+    assert_eq!(result["diagnostics"][0]["column"], 7);
+    assert_eq!(result["diagnostics"][0]["code"], "prohibited-symbol");
+}
+
+#[test]
+fn test_validate_profile() {
+    let dir = tempfile::tempdir().unwrap();
+    let iron = dir.path().join("arrows.iron");
+    fs::write(&iron, "note that a - b\ncall f with a -> b\n").unwrap();
+    let profile = dir.path().join("arrows.toml");
+    fs::write(
+        &profile,
+        "name = \"arrows\"\n\n[symbols]\n\"->\" = \"arrow\"\n",
+    )
+    .unwrap();
+
+    // The comment's hyphen is only a warning by default, and not checked by `code`
+    let output = redox().arg("validate").arg(&iron).output().unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("warning"), "{}", stderr);
+    let output = redox()
+        .args(["validate", "--profile", "code"])
+        .arg(&iron)
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(!stderr.contains("warning"), "{}", stderr);
+    assert!(
+        stderr.contains("prohibited symbol '-' in '->'"),
+        "{}",
+        stderr
+    );
+
+    let output = redox()
+        .args(["validate", "--profile"])
+        .arg(&profile)
+        .arg(&iron)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("prohibited symbol '->'"), "{}", stderr);
+    assert!(!stderr.contains("'-' "), "{}", stderr);
+
+    let status = redox()
+        .args(["validate", "--profile", "nonsense", "-"])
+        .status()
+        .unwrap();
+    assert!(!status.success());
+}
+
+#[test]
+fn test_fmt_command() {
+    let dir = tempfile::tempdir().unwrap();
+    let messy = dir.path().join("messy.iron");
+    fs::write(&messy, "Function One\nBegin\n\n  Return 1\nEnd  Function\n").unwrap();
+
+    let output = redox().arg("fmt").arg(&messy).output().unwrap();
+    assert!(
+        !output.status.success(),
+        "keywords must be lowercase by default"
+    );
+
+    let output = redox()
+        .arg("fmt")
+        .arg(&messy)
+        .arg("--case-insensitive-keywords")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let formatted = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        formatted,
+        "function One\nbegin\n    return 1\nend function\n"
+    );
+
+    let status = redox()
+        .args(["fmt", "--check"])
+        .arg(&messy)
+        .status()
+        .unwrap();
+    assert!(!status.success());
+    let tidy = dir.path().join("tidy.iron");
+    fs::write(&tidy, &formatted).unwrap();
+    let status = redox()
+        .args(["fmt", "--check"])
+        .arg(&tidy)
+        .status()
+        .unwrap();
+    assert!(status.success());
+}
+
+#[test]
+fn test_check_command_reports_grammar_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let nonsense = dir.path().join("nonsense.iron");
+    fs::write(
+        &nonsense,
+        "function f\nbegin\n    define x as\nend function\n",
+    )
+    .unwrap();
+
+    // Free of prohibited symbols, so only a full parse catches it
+    let status = redox().arg("validate").arg(&nonsense).status().unwrap();
+    assert!(status.success());
+
+    let output = redox().arg("check").arg(&nonsense).output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("nonsense.iron:3:16: error: Unexpected token NewLine"),
+        "{}",
+        stderr
+    );
+
+    let valid = dir.path().join("valid.iron");
+    fs::write(&valid, "function f\nbegin\n    return 1\nend function\n").unwrap();
+    let output = redox().arg("check").arg(&valid).output().unwrap();
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .contains("Valid Iron code")
+    );
+}
+
+#[test]
+fn test_cargo_redox_follows_module_tree() {
+    let project = tempfile::tempdir().unwrap();
+    let root = project.path();
+    fs::create_dir_all(root.join("src/shapes")).unwrap();
+    fs::write(
+        root.join("Cargo.toml"),
+        "[package]\nname = \"demo\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    fs::write(
+        root.join("src/lib.rs"),
+        "mod shapes;\n#[path = \"extra.rs\"]\nmod renamed;\npub fn top() -> i32 { 1 }\n",
+    )
+    .unwrap();
+    fs::write(root.join("src/shapes.rs"), "mod circle;\n").unwrap();
+    fs::write(
+        root.join("src/shapes/circle.rs"),
+        "fn area() -> i32 { 3 }\n",
+    )
+    .unwrap();
+    fs::write(root.join("src/extra.rs"), "fn extra() {}\n").unwrap();
+    // Not reachable through a mod declaration
+    fs::write(root.join("src/orphan.rs"), "fn orphan() {}\n").unwrap();
+
+    let out = root.join("iron");
+    let status = Command::new(env!("CARGO_BIN_EXE_cargo-redox"))
+        .arg("redox")
+        .arg("--manifest-path")
+        .arg(root.join("Cargo.toml"))
+        .arg("--out-dir")
+        .arg(&out)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let circle = fs::read_to_string(out.join("src/shapes/circle.iron")).unwrap();
+    assert!(circle.contains("function area"));
+    assert!(out.join("src/extra.iron").exists());
+    assert!(!out.join("src/orphan.iron").exists());
+
+    let manifest = fs::read_to_string(out.join("redox-manifest.json")).unwrap();
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).unwrap();
+    let modules: Vec<_> = manifest["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|file| {
+            (
+                file["source"].as_str().unwrap(),
+                file["module"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        modules,
+        [
+            ("src/extra.rs", "crate::renamed"),
+            ("src/lib.rs", "crate"),
+            ("src/shapes/circle.rs", "crate::shapes::circle"),
+            ("src/shapes.rs", "crate::shapes"),
+        ]
+    );
+}
+
+#[test]
+fn test_diff_command_aligns_items() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("input.rs");
+    fs::write(
+        &input,
+        "// Adds\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nstruct P {\n    x: i32,\n}\n",
+    )
+    .unwrap();
+
+    let output = redox()
+        .arg("diff")
+        .arg(&input)
+        .args(["--width", "63"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let view = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<_> = view.lines().collect();
+    assert_eq!(lines[0], format!("{:30} │ function add", "// Adds"));
+    assert_eq!(lines[3], format!("{:30} │ begin", "}"));
+    assert_eq!(lines[6], format!("{}─┼─{}", "─".repeat(30), "─".repeat(30)));
+    assert_eq!(
+        lines[7],
+        format!("{:30} │ structure P with fields", "struct P {")
+    );
+
+    let output = redox()
+        .arg("diff")
+        .arg(&input)
+        .arg("--unified")
+        .output()
+        .unwrap();
+    let view = String::from_utf8(output.stdout).unwrap();
+    assert!(view.starts_with("@@ line 1 (4 Rust, 6 Iron) @@\n- // Adds\n"));
+    assert!(view.contains("@@ line 6 (3 Rust, 3 Iron) @@\n- struct P {\n"));
+    assert!(view.contains("+ end structure\n"));
+
+    let output = redox()
+        .args(["--format", "json", "diff"])
+        .arg(&input)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let diff: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(diff["items"][1]["line"], 6);
+    assert_eq!(
+        diff["items"][1]["rust"],
+        serde_json::json!(["struct P {", "    x: i32,", "}"])
+    );
+    assert_eq!(diff["items"][1]["iron"][2], "end structure");
+}
+
+#[test]
+fn test_reduce_files_into_out_dir_with_extension() {
+    let input = tempfile::tempdir().unwrap();
+    let output = tempfile::tempdir().unwrap();
+    fs::create_dir_all(input.path().join("lib")).unwrap();
+    fs::write(input.path().join("main.rs"), "fn main() {}\n").unwrap();
+    fs::write(input.path().join("lib/util.rs"), "fn util() {}\n").unwrap();
+
+    let result = redox()
+        .arg("reduce")
+        .arg(input.path().join("main.rs"))
+        .arg(input.path().join("lib"))
+        .args(["--extension", "fe", "--out-dir"])
+        .arg(output.path())
+        .output()
+        .unwrap();
+    assert!(result.status.success(), "{:?}", result);
+    assert!(
+        fs::read_to_string(output.path().join("main.fe"))
+            .unwrap()
+            .contains("function main")
+    );
+    assert!(output.path().join("util.fe").is_file());
+
+    // Two inputs with one output name, and an output on top of an input, are refused
+    // before anything is written
+    fs::write(input.path().join("util.rs"), "fn other() {}\n").unwrap();
+    let result = redox()
+        .arg("reduce")
+        .arg(input.path().join("util.rs"))
+        .arg(input.path().join("lib/util.rs"))
+        .arg("--out-dir")
+        .arg(output.path())
+        .output()
+        .unwrap();
+    assert!(!result.status.success());
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(stderr.contains("would be written to"), "{}", stderr);
+
+    let result = redox()
+        .arg("reduce")
+        .arg(input.path().join("main.rs"))
+        .args(["--extension", "rs", "--out-dir"])
+        .arg(input.path())
+        .output()
+        .unwrap();
+    assert!(!result.status.success());
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(
+        stderr.contains("would overwrite an input file"),
+        "{}",
+        stderr
+    );
+    assert_eq!(
+        fs::read_to_string(input.path().join("main.rs")).unwrap(),
+        "fn main() {}\n"
+    );
+
+    let result = redox()
+        .arg("reduce")
+        .arg(input.path().join("main.rs"))
+        .arg(input.path().join("util.rs"))
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8(result.stderr).unwrap();
+    assert!(stderr.contains("needs --out-dir"), "{}", stderr);
+}
+
+#[test]
+fn test_markdown_code_blocks_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let notes = dir.path().join("notes.md");
+    fs::write(
+        &notes,
+        "# Adding\n\n```rust\nfn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n```\n\n```sh\nls -l\n```\n",
+    )
+    .unwrap();
+
+    let output = redox()
+        .args(["reduce", "--markdown"])
+        .arg(&notes)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let reduced = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        reduced.starts_with("# Adding\n\n```iron\nfunction add\n"),
+        "{}",
+        reduced
+    );
+    assert!(reduced.contains("end function\n```\n\n```sh\nls -l\n```\n"));
+
+    let iron_notes = dir.path().join("notes.iron.md");
+    fs::write(&iron_notes, &reduced).unwrap();
+    let output = redox()
+        .args(["oxidize", "--markdown"])
+        .arg(&iron_notes)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let oxidized = String::from_utf8(output.stdout).unwrap();
+    assert!(oxidized.contains("```rust\nfn add(a: i32, b: i32) -> i32 {\n"));
+
+    // A block that does not reduce is kept and reported, and the rest still converts
+    fs::write(&notes, "```rust\nfn (\n```\n\n```rust\nfn one() {}\n```\n").unwrap();
+    let output = redox()
+        .args(["reduce", "--markdown"])
+        .arg(&notes)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let converted = String::from_utf8(output.stdout).unwrap();
+    assert!(converted.starts_with("```rust\nfn (\n```\n\n```iron\n"));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("code block at line 1:"), "{}", stderr);
+}
+
+#[test]
+fn test_completions_and_man_page() {
+    let output = redox().args(["completions", "bash"]).output().unwrap();
+    assert!(output.status.success());
+    let script = String::from_utf8(output.stdout).unwrap();
+    assert!(script.contains("_redox()"));
+    assert!(script.contains("reduce"));
+
+    let output = redox().arg("man").output().unwrap();
+    assert!(output.status.success());
+    let page = String::from_utf8(output.stdout).unwrap();
+    assert!(page.contains(".TH redox 1"));
+    assert!(page.contains("oxidize"));
+
+    // Both stay out of the help text
+    let output = redox().arg("--help").output().unwrap();
+    let help = String::from_utf8(output.stdout).unwrap();
+    assert!(!help.contains("completions"));
+}
+
+#[test]
+fn test_exit_codes_distinguish_failures() {
+    let dir = tempfile::tempdir().unwrap();
+    let status = |args: &[&str], name: &str, content: &str| {
+        let path = dir.path().join(name);
+        fs::write(&path, content).unwrap();
+        redox().args(args).arg(&path).output().unwrap()
+    };
+
+    let output = status(&["reduce"], "broken.rs", "fn f( {");
+    assert_eq!(output.status.code(), Some(3));
+    let output = status(&["reduce", "--strict"], "trait.rs", "trait T {}\n");
+    assert_eq!(output.status.code(), Some(4));
+    let output = status(
+        &["validate"],
+        "symbol.iron",
+        "function f\nbegin\n    a & b\nend function\n",
+    );
+    assert_eq!(output.status.code(), Some(5));
+    let missing = dir.path().join("missing.rs");
+    let output = redox().arg("reduce").arg(&missing).output().unwrap();
+    assert_eq!(output.status.code(), Some(7));
+
+    // A symbol in a comment is only a warning, which fails the run on request
+    let commented = "function f\nbegin\n    note that a -> b\n    1\nend function\n";
+    let output = status(&["validate"], "comment.iron", commented);
+    assert!(output.status.success());
+    let warnings = String::from_utf8(output.stderr).unwrap();
+    assert!(warnings.contains("comment.iron:3:17: warning: prohibited symbol '-'"));
+    let output = status(
+        &["validate", "--warnings-as-errors"],
+        "comment.iron",
+        commented,
+    );
+    assert_eq!(output.status.code(), Some(6));
+}
+
+#[test]
+fn test_explain_command_annotates_origins() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("input.rs");
+    fs::write(
+        &input,
+        "fn add(a: i32, b: i32) -> i32 {\n    let total = a + b;\n    total\n}\n",
+    )
+    .unwrap();
+
+    let output = redox().arg("explain").arg(&input).output().unwrap();
+    assert!(output.status.success());
+    let explained = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        explained,
+        "note that line 1, function add: fn add(a: i32, b: i32) -> i32 {\n\
+         function add\n    \
+             takes a of i32 and b of i32\n    \
+             returns i32\n\
+         begin\n    \
+             note that line 2, let binding total: let total = a + b;\n    \
+             define total as a plus b\n    \
+             note that line 3, expression: total\n    \
+             total\n\
+         end function\n"
+    );
+
+    let output = redox()
+        .args(["--format", "json", "explain"])
+        .arg(&input)
+        .output()
+        .unwrap();
+    let explained: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(explained["origins"][1]["iron_line"], 5);
+    assert_eq!(explained["origins"][1]["rust_line"], 2);
+    assert_eq!(explained["origins"][1]["rust_column"], 5);
+    assert_eq!(explained["origins"][1]["construct"], "let binding total");
+}
+
+#[test]
+fn test_coverage_command_splits_native_and_verbatim() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("input.rs");
+    fs::write(
+        &input,
+        "fn one() -> i32 {\n    1\n}\n\ntrait Shape {\n    fn area(&self) -> i32 {\n        2\n    }\n}\n",
+    )
+    .unwrap();
+
+    let output = redox().arg("coverage").arg(&input).output().unwrap();
+    assert!(output.status.success());
+    let report = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        report.contains("items: 1 of 2 native (50.0%)"),
+        "{}",
+        report
+    );
+    assert!(
+        report.contains("expressions: 1 of 2 native (50.0%)"),
+        "{}",
+        report
+    );
+    let trait_line = report
+        .lines()
+        .find(|line| line.trim_start().starts_with("trait"))
+        .unwrap();
+    assert_eq!(
+        trait_line.split_whitespace().collect::<Vec<_>>(),
+        ["trait", "0", "/", "1", "0.0%"]
+    );
+}
+
+#[test]
+fn test_dataset_command_exports_item_pairs() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("input.rs"),
+        "fn one() -> i32 {\n    1\n}\n\ntrait Shape {}\n",
+    )
+    .unwrap();
+    let out = dir.path().join("pairs.jsonl");
+
+    let status = redox()
+        .arg("dataset")
+        .arg(dir.path())
+        .arg("--out")
+        .arg(&out)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let records: Vec<serde_json::Value> = fs::read_to_string(&out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["kind"], "fn");
+    assert_eq!(records[0]["line"], 1);
+    assert_eq!(records[0]["rust"], "fn one() -> i32 {\n    1\n}");
+    assert!(
+        records[0]["iron"]
+            .as_str()
+            .unwrap()
+            .starts_with("function one")
+    );
+    assert_eq!(records[0]["rust_tokens"], 9);
+    assert_eq!(records[0]["verbatim"], false);
+    assert_eq!(records[1]["kind"], "trait");
+    assert_eq!(records[1]["verbatim"], true);
+}
+
+/// Send one HTTP request to a `redox serve` process, returning the status line and body
+fn http_request(address: &str, method: &str, path: &str, body: &str) -> (String, String) {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        method,
+        path,
+        address,
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    (head.lines().next().unwrap().to_string(), body.to_string())
+}
+
+#[test]
+fn test_serve_answers_json_endpoints() {
+    let mut server = redox()
+        .args(["serve", "--port", "0"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut banner = String::new();
+    BufReader::new(server.stderr.take().unwrap())
+        .read_line(&mut banner)
+        .unwrap();
+    let address = banner.trim().trim_start_matches("Listening on http://");
+
+    let (status, body) = http_request(
+        address,
+        "POST",
+        "/reduce",
+        r#"{"source": "fn one() -> i32 { 1 }"}"#,
+    );
+    assert!(status.contains("200"), "{}", status);
+    let reduced: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let iron = reduced["iron"].as_str().unwrap();
+    assert!(iron.starts_with("function one\n"));
+
+    let request = serde_json::json!({ "source": iron });
+    let (status, body) = http_request(address, "POST", "/oxidize", &request.to_string());
+    assert!(status.contains("200"), "{}", status);
+    let oxidized: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(
+        oxidized["rust"]
+            .as_str()
+            .unwrap()
+            .contains("fn one() -> i32")
+    );
+
+    let request = serde_json::json!({ "source": "function f\nbegin\nend if\n", "file": "f.iron" });
+    let (status, body) = http_request(address, "POST", "/validate", &request.to_string());
+    assert!(status.contains("200"), "{}", status);
+    let validated: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(validated["valid"], false);
+    assert_eq!(validated["diagnostics"][0]["file"], "f.iron");
+    assert_eq!(validated["diagnostics"][0]["line"], 3);
+
+    let (status, body) = http_request(address, "POST", "/oxidize", &request.to_string());
+    assert!(status.contains("422"), "{}", status);
+    let failed: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(failed["error"]["code"], "parse-error");
+
+    let (status, _) = http_request(address, "POST", "/transmute", "{}");
+    assert!(status.contains("404"), "{}", status);
+    let (status, _) = http_request(address, "POST", "/reduce", "{}");
+    assert!(status.contains("400"), "{}", status);
+    let (status, _) = http_request(address, "GET", "/reduce", "");
+    assert!(status.contains("405"), "{}", status);
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+#[test]
+fn test_serve_refuses_oversized_bodies() {
+    let mut server = redox()
+        .args(["serve", "--port", "0", "--jobs", "1"])
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut banner = String::new();
+    BufReader::new(server.stderr.take().unwrap())
+        .read_line(&mut banner)
+        .unwrap();
+    let address = banner.trim().trim_start_matches("Listening on http://");
+
+    // Refused from the header alone; the server answers once the client stops sending
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "POST /reduce HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        address,
+        64 * 1024 * 1024
+    )
+    .unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+    assert!(response.contains("byte limit"), "{}", response);
+
+    let (status, _) = http_request(address, "POST", "/reduce", r#"{"source": "fn f() {}"}"#);
+    assert!(status.contains("200"), "{}", status);
+
+    server.kill().unwrap();
+    server.wait().unwrap();
+}
+
+#[test]
+fn test_serve_daemon_answers_json_rpc() {
+    let frame = |message: serde_json::Value| {
+        let body = message.to_string();
+        format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+    };
+    let input = [
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "reduce",
+            "params": { "source": "fn one() -> i32 { 1 }" },
+        }),
+        // Cancelling a request that is not pending is not answered
+        serde_json::json!({ "jsonrpc": "2.0", "method": "$/cancelRequest", "params": { "id": 9 } }),
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": "check",
+            "method": "validate",
+            "params": { "source": "function f\nbegin\nend if\n" },
+        }),
+        serde_json::json!({ "jsonrpc": "2.0", "id": 3, "method": "transmute", "params": {} }),
+    ]
+    .into_iter()
+    .map(frame)
+    .collect::<String>();
+    // Bad frames are answered and skipped: a missing or malformed length, and last, as
+    // it takes the rest of the input, one over the size limit
+    let input = format!(
+        "X-Note: none\r\n\r\nContent-Length: many\r\n\r\n{}Content-Length: 999999999999\r\n\r\n{{}}",
+        input
+    );
+
+    let mut child = redox()
+        .args(["serve", "--daemon"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    // Requests run concurrently, so answers are matched up by id
+    let output = String::from_utf8(output.stdout).unwrap();
+    let mut answers = std::collections::HashMap::new();
+    let mut bad_frames = 0;
+    for message in output.split("Content-Length: ").skip(1) {
+        let (_, body) = message.split_once("\r\n\r\n").unwrap();
+        let answer: serde_json::Value = serde_json::from_str(body).unwrap();
+        if answer["id"].is_null() {
+            assert_eq!(answer["error"]["code"], -32700);
+            bad_frames += 1;
+        } else {
+            answers.insert(answer["id"].to_string(), answer);
+        }
+    }
+    assert_eq!(bad_frames, 3);
+    assert_eq!(answers.len(), 3);
+    assert!(
+        answers["1"]["result"]["iron"]
+            .as_str()
+            .unwrap()
+            .starts_with("function one\n")
+    );
+    assert_eq!(answers["\"check\""]["result"]["valid"], false);
+    assert_eq!(answers["\"check\""]["result"]["diagnostics"][0]["line"], 3);
+    assert_eq!(answers["3"]["error"]["code"], -32601);
+}
+
+#[test]
+fn test_serve_daemon_refuses_a_pending_id() {
+    // Reducing a large source keeps the first request pending while the second is read
+    let source: String = (0..2000)
+        .map(|n| format!("fn f{}(x: i32) -> i32 {{ x + {} }}\n", n, n))
+        .collect();
+    let input: String = [source.as_str(), "fn one() -> i32 { 1 }"]
+        .into_iter()
+        .map(|source| {
+            let body = serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 7,
+                "method": "reduce",
+                "params": { "source": source },
+            })
+            .to_string();
+            format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+        })
+        .collect();
+
+    let mut child = redox()
+        .args(["serve", "--daemon", "--jobs", "1"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    let output = String::from_utf8(output.stdout).unwrap();
+    let answers: Vec<serde_json::Value> = output
+        .split("Content-Length: ")
+        .skip(1)
+        .map(|message| serde_json::from_str(message.split_once("\r\n\r\n").unwrap().1).unwrap())
+        .collect();
+    assert_eq!(answers.len(), 2, "{}", output);
+    assert_eq!(answers[0]["id"], 7);
+    assert_eq!(answers[0]["error"]["code"], -32600);
+    assert!(
+        answers[1]["result"]["iron"]
+            .as_str()
+            .unwrap()
+            .starts_with("function f0\n")
+    );
+}
+
+#[test]
+fn test_reduce_check_reports_stale_outputs() {
+    let dir = tempfile::tempdir().unwrap();
+    let src = dir.path().join("src");
+    let iron = dir.path().join("iron");
+    fs::create_dir(&src).unwrap();
+    fs::write(src.join("a.rs"), "fn a() {}\n").unwrap();
+    fs::write(src.join("b.rs"), "fn b() {}\n").unwrap();
+    let check = || {
+        redox()
+            .args(["--format", "json", "reduce"])
+            .arg(&src)
+            .arg("--out-dir")
+            .arg(&iron)
+            .arg("--check")
+            .output()
+            .unwrap()
+    };
+
+    // Nothing is written, and missing outputs count as changes
+    let output = check();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!iron.exists());
+
+    let status = redox()
+        .arg("reduce")
+        .arg(&src)
+        .arg("--out-dir")
+        .arg(&iron)
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(check().status.success());
+
+    fs::write(
+        iron.join("b.iron"),
+        "function b\nbegin\n    1\nend function\n",
+    )
+    .unwrap();
+    let output = check();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let diagnostics: Vec<serde_json::Value> = stderr
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(diagnostics[0]["code"], "out-of-date");
+    assert!(diagnostics[0]["file"].as_str().unwrap().ends_with("b.rs"));
+    assert_eq!(diagnostics[1]["summary"]["changed"], 1);
+    assert_eq!(diagnostics[1]["summary"]["unchanged"], 1);
+    assert_eq!(
+        fs::read_to_string(iron.join("b.iron")).unwrap(),
+        "function b\nbegin\n    1\nend function\n"
+    );
+}
+
+#[test]
+fn test_oxidize_verify_reports_compiler_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let good = dir.path().join("good.iron");
+    let bad = dir.path().join("bad.iron");
+    let output = dir.path().join("bad.rs");
+    fs::write(
+        &good,
+        "function f\nbegin\n    define x as 1\nend function\n",
+    )
+    .unwrap();
+    fs::write(
+        &bad,
+        "function f\n    returns i32\nbegin\n    return \"s\"\nend function\n",
+    )
+    .unwrap();
+
+    let result = redox()
+        .arg("oxidize")
+        .arg(&good)
+        .args(["--verify", "--compile"])
+        .output()
+        .unwrap();
+    assert!(result.status.success());
+    assert!(String::from_utf8_lossy(&result.stdout).contains("let x = 1;"));
+
+    // The type error parses, so only compiling catches it
+    let status = redox()
+        .arg("oxidize")
+        .arg(&bad)
+        .arg("--verify")
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let result = redox()
+        .args(["--format", "json", "oxidize"])
+        .arg(&bad)
+        .args(["--verify", "--compile", "-o"])
+        .arg(&output)
+        .output()
+        .unwrap();
+    assert_eq!(result.status.code(), Some(5));
+    assert!(!output.exists());
+    let diagnostic: serde_json::Value = serde_json::from_slice(&result.stderr).unwrap();
+    assert_eq!(diagnostic["code"], "verify-error");
+    let message = diagnostic["message"].as_str().unwrap();
+    assert!(message.contains("does not compile"));
+    assert!(message.contains("E0308"));
+}
+
+#[test]
+fn test_batch_manifest_reports_each_file() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("one.rs"), "fn one() -> i32 { 1 }\n").unwrap();
+    fs::write(
+        dir.path().join("two.iron"),
+        "function two\nbegin\n    define x as 2\nend function\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("bad.iron"), "fn bad() {}\n").unwrap();
+    let manifest = dir.path().join("jobs.toml");
+    fs::write(
+        &manifest,
+        r#"
+[defaults]
+edition = 2021
+
+[[files]]
+input = "one.rs"
+output = "out/one.iron"
+
+[[files]]
+command = "oxidize"
+input = "two.iron"
+
+[[files]]
+command = "validate"
+input = "bad.iron"
+
+[[files]]
+input = "missing.rs"
+"#,
+    )
+    .unwrap();
+
+    let output = redox()
+        .args(["--quiet", "batch"])
+        .arg(&manifest)
+        .output()
+        .unwrap();
+    // The missing input fails the run, but only after every file is reported
+    assert_eq!(output.status.code(), Some(1));
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let files = report["files"].as_array().unwrap();
+    assert_eq!(files[0]["status"], "ok");
+    assert_eq!(files[1]["status"], "ok");
+    assert_eq!(files[2]["status"], "invalid");
+    assert_eq!(files[3]["status"], "failed");
+    assert_eq!(files[3]["diagnostics"][0]["severity"], "error");
+    assert_eq!(report["summary"]["ok"], 2);
+    assert_eq!(report["summary"]["total"], 4);
+    assert!(
+        fs::read_to_string(dir.path().join("out/one.iron"))
+            .unwrap()
+            .contains("function one")
+    );
+    assert!(
+        fs::read_to_string(dir.path().join("two.rs"))
+            .unwrap()
+            .contains("let x = 2;")
+    );
+}
+
+#[test]
+#[cfg(feature = "tokens")]
+fn test_tokens_command_compares_tokenizers() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("input.rs");
+    fs::write(&input, "fn one() {}\n\nfn two() -> i32 {\n    2\n}\n").unwrap();
+    // A word-level tokenizer that knows only `fn`, so every other word is one token
+    let tokenizer = dir.path().join("tokenizer.json");
+    fs::write(
+        &tokenizer,
+        r#"{"version":"1.0","truncation":null,"padding":null,"added_tokens":[],"normalizer":null,"pre_tokenizer":{"type":"Whitespace"},"post_processor":null,"decoder":null,"model":{"type":"WordLevel","vocab":{"[UNK]":0,"fn":1},"unk_token":"[UNK]"}}"#,
+    )
+    .unwrap();
+
+    let output = redox()
+        .args(["--format", "json", "tokens"])
+        .arg(&input)
+        .args(["--tokenizer", "cl100k", "--tokenizer"])
+        .arg(&tokenizer)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let counts = report["counts"].as_array().unwrap();
+    assert_eq!(counts.len(), 2);
+    assert_eq!(counts[0]["tokenizer"], "cl100k");
+    for counts in counts {
+        assert!(counts["rust"].as_u64().unwrap() > 0);
+        assert!(counts["iron"].as_u64().unwrap() > 0);
+        let items = counts["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[1]["line"], 3);
+    }
+    // `fn one() {}` splits on punctuation into `fn`, `one`, `()`, `{}`
+    assert_eq!(counts[1]["items"][0]["rust"], 4);
+
+    let output = redox()
+        .arg("tokens")
+        .arg(&input)
+        .args(["--tokenizer", "missing.json"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Failed to load tokenizer 'missing.json'")
+    );
+}
+
+#[test]
+fn test_types_dictionary_names_project_types_both_ways() {
+    let dir = tempfile::tempdir().unwrap();
+    let types = dir.path().join("types.json");
+    fs::write(&types, r#"{"types": {"UserId": "user identifier"}}"#).unwrap();
+    let input = dir.path().join("ids.rs");
+    fs::write(&input, "fn next(id: UserId) -> UserId {\n    id\n}\n").unwrap();
+
+    let output = redox()
+        .arg("reduce")
+        .arg(&input)
+        .arg("--types")
+        .arg(&types)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let iron = String::from_utf8(output.stdout).unwrap();
+    assert!(iron.contains("takes id of user identifier\n    returns user identifier\n"));
+
+    let reduced = dir.path().join("ids.iron");
+    fs::write(&reduced, &iron).unwrap();
+    let output = redox()
+        .arg("oxidize")
+        .arg(&reduced)
+        .arg("--types")
+        .arg(&types)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let rust = String::from_utf8(output.stdout).unwrap();
+    assert!(rust.contains("fn next(id: UserId) -> UserId"));
+
+    fs::write(&types, r#"{"types": {"Text": "string"}}"#).unwrap();
+    let output = redox()
+        .arg("reduce")
+        .arg(&input)
+        .arg("--types")
+        .arg(&types)
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("'string' is already an Iron type"));
+}
+
+#[test]
+fn test_reduce_header_is_checked_when_parsing() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("one.rs");
+    fs::write(&input, "fn one() -> i32 {\n    1\n}\n").unwrap();
+
+    let output = redox()
+        .arg("reduce")
+        .arg(&input)
+        .arg("--header")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let iron = String::from_utf8(output.stdout).unwrap();
+    assert!(iron.starts_with("iron specification 1, generated by redox "));
+
+    let reduced = dir.path().join("one.iron");
+    fs::write(&reduced, &iron).unwrap();
+    let check = |path: &std::path::Path| {
+        redox()
+            .arg("check")
+            .arg(path)
+            .args(["--parse-mode", "strict"])
+            .output()
+            .unwrap()
+    };
+    assert!(check(&reduced).status.success());
+
+    fs::write(&reduced, iron.replace("returns i32", "returns i64")).unwrap();
+    let output = check(&reduced);
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.contains("edited after it was generated"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn test_reduce_verbosity_reads_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("main.rs");
+    fs::write(&input, "fn main() {\n    v.push(len(s))\n}\n").unwrap();
+
+    let reduce = |verbosity: &str| {
+        let output = redox()
+            .arg("reduce")
+            .arg(&input)
+            .args(["--verbosity", verbosity])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let terse = reduce("terse");
+    assert!(terse.contains("    call push on v with call len with s\n"));
+    let verbose = reduce("extra-verbose");
+    assert!(verbose.contains(
+        "    call method push on v with parameters call function len with parameters s\n"
+    ));
+
+    for iron in [terse, verbose] {
+        let reduced = dir.path().join("main.iron");
+        fs::write(&reduced, iron).unwrap();
+        let output = redox().arg("oxidize").arg(&reduced).output().unwrap();
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap(),
+            "fn main() {\n    v.push(len(s))\n}\n"
+        );
+    }
+}
+
+#[test]
+fn test_reduce_indent_reads_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("main.rs");
+    let source =
+        "fn main() {\n    for x in xs {\n        if x {\n            f(x);\n        }\n    }\n}\n";
+    fs::write(&input, source).unwrap();
+
+    let output = redox()
+        .arg("reduce")
+        .arg(&input)
+        .args(["--indent", "tab"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let iron = String::from_utf8(output.stdout).unwrap();
+    assert!(iron.contains("\n\t\t\tcall"), "{}", iron);
+    assert!(!iron.contains("    "), "{}", iron);
+
+    let reduced = dir.path().join("main.iron");
+    fs::write(&reduced, iron).unwrap();
+    let output = redox().arg("oxidize").arg(&reduced).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), source);
+
+    let output = redox()
+        .arg("reduce")
+        .arg(&input)
+        .args(["--indent", "0"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_reduce_max_width_reads_back() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("main.rs");
+    let source = "fn main() {\n    let total = items.iter().map(|item| item.price * item.quantity).sum();\n}\n";
+    fs::write(&input, source).unwrap();
+
+    let output = redox()
+        .arg("reduce")
+        .arg(&input)
+        .args(["--max-width", "50", "--validate"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let iron = String::from_utf8(output.stdout).unwrap();
+    assert!(iron.contains("\n        continued "), "{}", iron);
+    assert!(iron.lines().all(|line| line.len() <= 50), "{}", iron);
+
+    let reduced = dir.path().join("main.iron");
+    fs::write(&reduced, iron).unwrap();
+    let output = redox().arg("oxidize").arg(&reduced).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "fn main() {\n    let total = items.iter().map(|item| { item.price * item.quantity }).sum();\n}\n"
+    );
+}
+
+#[test]
+fn test_reduce_provenance_names_file_and_line() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("lib.rs");
+    fs::write(&input, "fn one() {}\n\nfn two() {}\n").unwrap();
+
+    let output = redox()
+        .arg("reduce")
+        .arg(&input)
+        .args(["--provenance", "--validate"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let iron = String::from_utf8(output.stdout).unwrap();
+    let note = |line| {
+        format!(
+            "note that this comes from line {} of {}\n",
+            line,
+            input.display()
+        )
+    };
+    assert!(
+        iron.starts_with(&format!("{}function one\n", note(1))),
+        "{}",
+        iron
+    );
+    assert!(
+        iron.contains(&format!("\n\n{}function two\n", note(3))),
+        "{}",
+        iron
+    );
+}
+
+#[test]
+fn test_reduce_spacing_flags() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("main.rs");
+    fs::write(
+        &input,
+        "fn one() {}\n\nfn main() {\n    let x = 1;\n    fn two() {}\n}\n",
+    )
+    .unwrap();
+
+    let output = redox()
+        .arg("reduce")
+        .arg(&input)
+        .args(["--item-spacing", "0", "--block-spacing", "2"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "function one\n\
+         begin\n\
+         end function\n\
+         function main\n\
+         begin\n    \
+             define x as 1\n\
+         \n\
+         \n    \
+             function two\n    \
+             begin\n    \
+             end function\n\
+         end function\n"
+    );
+}
+
+#[test]
+fn test_reduce_fallback_placeholder_leaves_items_out() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("bits.rs");
+    fs::write(&input, "union Bits {\n    n: u32,\n}\n\nfn one() {}\n").unwrap();
+
+    let output = redox()
+        .arg("reduce")
+        .arg(&input)
+        .args(["--fallback", "placeholder", "--validate"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let iron = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        iron.starts_with("note that union Bits has no Iron form and was left out\n"),
+        "{}",
+        iron
+    );
+    assert!(!iron.contains("verbatim"));
+
+    let output = redox()
+        .arg("reduce")
+        .arg(&input)
+        .args(["--fallback", "error"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_reduce_cache_dir_reuses_unchanged_items() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("items.rs");
+    fs::write(
+        &input,
+        "fn one() -> i32 {\n    1\n}\n\nconst TWO: i32 = 2;\n",
+    )
+    .unwrap();
+    let cache = dir.path().join("cache");
+    let reduce = || {
+        redox()
+            .arg("reduce")
+            .arg(&input)
+            .arg("--cache-dir")
+            .arg(&cache)
+            .output()
+            .unwrap()
+    };
+    let entries = || fs::read_dir(cache.join("default")).unwrap().count();
+
+    let first = reduce();
+    assert!(first.status.success());
+    assert_eq!(entries(), 2);
+
+    let again = reduce();
+    assert_eq!(again.stdout, first.stdout);
+    assert_eq!(entries(), 2);
+
+    fs::write(
+        &input,
+        "fn one() -> i32 {\n    1\n}\n\nconst TWO: i32 = 3;\n",
+    )
+    .unwrap();
+    let edited = reduce();
+    assert!(
+        String::from_utf8(edited.stdout)
+            .unwrap()
+            .contains("    3\n")
+    );
+    assert_eq!(entries(), 3);
+}
@@ -116,6 +116,24 @@ fn make_closure() -> i32 {
     test_roundtrip_content(code).expect("Closures should round-trip");
 }
 
+#[test]
+fn test_block_closure_roundtrip() {
+    let code = r#"
+fn make_closure() -> i32 {
+    let f = |x| {
+        let doubled = x * 2;
+        doubled + 1
+    };
+    f(20)
+}
+"#;
+    test_roundtrip_content(code).expect("Block-bodied closures should round-trip");
+
+    let iron = redox::transpile(code).expect("Block-bodied closure should reduce");
+    assert!(iron.contains("closure with parameters x and body\n"));
+    assert!(iron.contains("end closure"));
+}
+
 #[test]
 fn test_type_alias_roundtrip() {
     let code = r#"
@@ -154,9 +172,475 @@ fn use_counter() -> i32 {
     test_roundtrip_content(code).expect("Impl blocks should round-trip");
 }
 
+#[test]
+fn test_unary_operators_roundtrip() {
+    let code = r#"
+fn unary_ops(flag: bool, value: i32, ptr: &i32) -> bool {
+    let negated = -value;
+    let inverted = !flag;
+    inverted && negated < *ptr
+}
+"#;
+    test_roundtrip_content(code).expect("Unary operators should round-trip");
+}
+
+#[test]
+fn test_if_expression_value_roundtrip() {
+    let code = r#"
+fn sign(n: i32) -> i32 {
+    let y = if n < 0 { -1 } else if n == 0 { 0 } else { 1 };
+    y
+}
+"#;
+    test_roundtrip_content(code).expect("If expressions as values should round-trip");
+
+    let iron = redox::transpile(code).expect("If expression should reduce");
+    assert!(iron.contains(
+        "define y as if n less than 0 then negative 1 otherwise if n equal to 0 then 0 otherwise 1"
+    ));
+}
+
+#[test]
+fn test_nested_if_expression_keeps_its_else() {
+    let code = r#"
+fn x() {}
+
+fn y() {}
+
+fn pick(a: bool, b: bool) {
+    let v = if a { if b { x() } } else { y() };
+    v
+}
+"#;
+    test_roundtrip_content(code).expect("Nested if expressions should round-trip");
+
+    let iron = redox::transpile(code).expect("Nested if expression should reduce");
+    assert!(
+        iron.contains("define v as if a then group if b then call x end group otherwise call y")
+    );
+
+    let rust = redox::oxidize(&iron).expect("Nested if expression should oxidize");
+    assert!(rust.contains("} else {\n        y()\n    };"), "{}", rust);
+}
+
+#[test]
+fn test_else_if_chain_roundtrip() {
+    let code = r#"
+fn classify(n: i32) -> i32 {
+    if n < 0 {
+        return 1;
+    } else if n == 0 {
+        return 2;
+    } else if n < 10 {
+        return 3;
+    } else {
+        return 4;
+    }
+}
+"#;
+    test_roundtrip_content(code).expect("Else-if chains should round-trip");
+
+    let iron = redox::transpile(code).expect("Else-if chain should reduce");
+    assert!(iron.contains("otherwise if n equal to 0 then"));
+    assert!(iron.contains("otherwise if n less than 10 then"));
+
+    let rust = redox::oxidize(&iron).expect("Else-if chain should oxidize");
+    assert!(rust.contains("} else if n == 0 {"));
+    assert!(rust.contains("} else if n < 10 {"));
+}
+
+#[test]
+fn test_loop_forever_roundtrip() {
+    let code = r#"
+fn count_up(limit: i32) -> i32 {
+    let mut n = 0;
+    loop {
+        if n > limit {
+            break;
+        }
+        n = n + 1;
+    }
+    n
+}
+"#;
+    test_roundtrip_content(code).expect("Infinite loops should round-trip");
+
+    let iron = redox::transpile(code).expect("Loop should reduce");
+    assert!(iron.contains("loop forever\n    begin"));
+    assert!(iron.contains("end loop"));
+}
+
+#[test]
+fn test_field_access_precedence_roundtrip() {
+    let code = r#"
+struct Counter {
+    count: i32,
+}
+
+fn total(c: Counter, items: Vec<i32>, i: usize) -> i32 {
+    c.count + items[i + 1] * 2
+}
+"#;
+    test_roundtrip_content(code).expect("Field access precedence should round-trip");
+
+    let iron = redox::transpile(code).expect("Field access should reduce");
+    assert!(iron.contains("field count of c plus index items at group i plus 1 end group times 2"));
+}
+
+#[test]
+fn test_assignment_places_roundtrip() {
+    let code = r#"
+struct Counter {
+    count: i32,
+}
+
+fn reset(total: &mut i32) -> i32 {
+    let mut c = Counter { count: 5 };
+    let mut items = vec![1, 2, 3];
+    c.count = 0;
+    items[1] = c.count;
+    *total = items[1];
+    c.count += 2;
+    c.count
+}
+"#;
+    test_roundtrip_content(code).expect("Place assignments should round-trip");
+
+    let iron = redox::transpile(code).expect("Place assignments should reduce");
+    assert!(iron.contains("set field count of c equal to 0"));
+    assert!(iron.contains("set index items at 1 equal to field count of c"));
+}
+
+#[test]
+fn test_method_chain_roundtrip() {
+    let code = r#"
+fn helper() -> usize {
+    2
+}
+
+fn chain(items: Vec<i32>, s: String) -> usize {
+    let n = items.iter().map(|x| x + 1).filter(|x| *x > 2).count();
+    let t = s.trim().to_string().len();
+    let m = helper().max(n.min(t));
+    helper().pow(2) + m
+}
+"#;
+    test_roundtrip_content(code).expect("Method chains should round-trip");
+
+    let iron = redox::transpile(code).expect("Method chains should reduce");
+    assert!(iron.contains("call method len on call method to_string on call method trim on s"));
+    assert!(iron.contains("call method max on call helper with call method min on n with t"));
+    assert!(iron.contains("group call method pow on call helper with 2 end group plus m"));
+}
+
+#[test]
+fn test_tuple_struct_roundtrip() {
+    let code = r#"
+struct Wrapper(i32);
+
+struct Pair(i32, bool);
+
+fn bump(w: Wrapper) -> Pair {
+    Pair(w.0 + 1, true)
+}
+
+fn make() -> Wrapper {
+    Wrapper(42)
+}
+"#;
+    test_roundtrip_content(code).expect("Tuple structs should round-trip");
+
+    let iron = redox::transpile(code).expect("Tuple structs should reduce");
+    assert!(iron.contains("create Pair with field field0 of w plus 1 and true"));
+    assert!(iron.contains("create Wrapper with 42"));
+}
+
+#[test]
+fn test_radix_literals_roundtrip() {
+    let code = r#"
+const MASK: u32 = 0xFF;
+
+fn flags(x: u32) -> u32 {
+    x & 0o755 | 0b1010_0101 | MASK
+}
+"#;
+    test_roundtrip_content(code).expect("Radix literals should round-trip");
+
+    let rust = redox::oxidize(&redox::transpile(code).unwrap()).unwrap();
+    assert!(rust.contains("0xFF"));
+    assert!(rust.contains("0o755"));
+    assert!(rust.contains("0b1010_0101"));
+}
+
+#[test]
+fn test_unicode_identifiers_roundtrip() {
+    let code = r#"
+struct Größe {
+    länge: i32,
+}
+
+fn café(naïve: i32) -> i32 {
+    let größe = Größe { länge: naïve };
+    let 変数 = größe.länge + 1;
+    変数
+}
+"#;
+    test_roundtrip_content(code).expect("Unicode identifiers should round-trip");
+
+    let iron = redox::transpile(code).expect("Unicode identifiers should reduce");
+    assert!(iron.contains("define 変数 as field länge of größe plus 1"));
+}
+
+#[test]
+fn test_text_block_roundtrip() {
+    let code = r#"
+struct S;
+
+impl S {
+    fn greet(&self) -> String {
+        "hi \"there\"\n".to_string()
+    }
+}
+
+fn poem() -> String {
+    let p = "roses\n  are \"red\"\n";
+    let q = "text end\nis not a terminator here";
+    p.to_string() + q
+}
+"#;
+    test_roundtrip_content(code).expect("Text blocks should round-trip");
+
+    let iron = redox::transpile(code).expect("Text blocks should reduce");
+    assert!(iron.contains("verbatim begin\nimpl S"));
+    assert!(iron.contains("}\nverbatim end\n"));
+    assert!(iron.contains("define p as text begin\n    roses\n      are \"red\"\n"));
+    assert!(iron.contains("define q as \"text end\\nis not a terminator here\""));
+}
+
+#[test]
+fn test_compound_assignment_roundtrip() {
+    let code = r#"
+fn mix(y: i32) -> i32 {
+    let mut x = 10;
+    x += y;
+    x -= 1;
+    x *= 2;
+    x /= 3;
+    x %= 5;
+    x <<= 1;
+    x ^= y;
+    x
+}
+"#;
+    test_roundtrip_content(code).expect("Compound assignments should round-trip");
+
+    let iron = redox::transpile(code).expect("Compound assignments should reduce");
+    assert!(iron.contains("increase x by y"));
+    assert!(iron.contains("update x with shift left 1"));
+}
+
+#[test]
+fn test_static_and_const_initializers_roundtrip() {
+    let code = r#"
+struct Point {
+    x: i32,
+    y: i32,
+}
+
+const ORIGIN: Point = Point { x: 1, y: 2 };
+
+static LIMIT: i32 = 40 + 2;
+
+fn limit() -> i32 {
+    LIMIT
+}
+"#;
+    test_roundtrip_content(code).expect("Static and const initializers should round-trip");
+
+    let rust = redox::oxidize(&redox::transpile(code).expect("Items should reduce"))
+        .expect("Items should oxidize");
+    assert!(rust.contains("const ORIGIN: Point = Point { x: 1, y: 2 };"));
+    assert!(rust.contains("static LIMIT: i32 = 40 + 2;"));
+}
+
+#[test]
+fn test_bitwise_operators_roundtrip() {
+    let code = r#"
+fn pack(a: u32, b: u32) -> u32 {
+    let low = a & 255;
+    let high = b << 8;
+    let flipped = low ^ high;
+    flipped | a >> 4
+}
+"#;
+    test_roundtrip_content(code).expect("Bitwise operators should round-trip");
+
+    let rust = redox::oxidize(&redox::transpile(code).expect("Bitwise ops should reduce"))
+        .expect("Bitwise ops should oxidize");
+    assert!(rust.contains("let low = a & 255;"));
+    assert!(rust.contains("let high = b << 8;"));
+    assert!(rust.contains("let flipped = low ^ high;"));
+    assert!(rust.contains("flipped | a >> 4"));
+}
+
+#[test]
+fn test_grouped_expressions_roundtrip() {
+    let code = r#"
+fn both(a: bool, b: bool) -> bool {
+    a
+}
+
+fn grouped(a: i32, b: i32, c: i32) -> bool {
+    let scaled = (a + b) * c;
+    both(a > 0 && b > 0, !(scaled < c || c == 0))
+}
+"#;
+    test_roundtrip_content(code).expect("Grouped expressions should round-trip");
+
+    let iron = redox::transpile(code).expect("Grouped expressions should reduce");
+    assert!(iron.contains("define scaled as group a plus b end group times c"));
+    assert!(iron.contains("call both with group a greater than 0 and b greater than 0 end group"));
+
+    let rust = redox::oxidize(&iron).expect("Grouped expressions should oxidize");
+    assert!(rust.contains("let scaled = (a + b) * c;"));
+    assert!(rust.contains("both(a > 0 && b > 0, !(scaled < c || c == 0))"));
+}
+
+#[test]
+fn test_grouped_arguments_roundtrip_unchanged() {
+    let code = r#"
+fn g(x: i32) -> i32 {
+    x
+}
+
+fn h(x: i32, y: i32) -> i32 {
+    x + y
+}
+
+fn k(a: i32, b: i32, c: bool) -> Vec<bool> {
+    vec![c]
+}
+
+fn mixed(a: i32, b: i32) -> Vec<bool> {
+    let list = [h(a, b), g(a), a + b];
+    k(a + b, h(a, b), a > 0 && b > 0)
+}
+"#;
+    test_roundtrip_content(code).expect("Grouped arguments should round-trip");
+
+    let iron = redox::transpile(code).expect("Grouped arguments should reduce");
+    assert!(
+        iron.contains("call k with a plus b and group call h with a and b end group and group")
+    );
+
+    let rust = redox::oxidize(&iron).expect("Grouped arguments should oxidize");
+    assert!(rust.contains("let list = [h(a, b), g(a), a + b];"));
+    assert!(rust.contains("    k(a + b, h(a, b), a > 0 && b > 0)\n"));
+}
+
+#[test]
+fn test_nested_calls_roundtrip_unchanged() {
+    let code = r#"
+fn g(x: usize) -> usize {
+    x
+}
+
+fn f(x: usize, y: usize) -> usize {
+    x + y
+}
+
+fn nested(x: usize, y: usize, s: String) -> usize {
+    let n = f(g(x), y);
+    s.trim().len().min(3).max(n)
+}
+"#;
+    test_roundtrip_content(code).expect("Nested calls should round-trip");
+
+    let iron = redox::transpile(code).expect("Nested calls should reduce");
+    assert!(iron.contains("define n as call f with group call g with x end group and y"));
+
+    let rust = redox::oxidize(&iron).expect("Nested calls should oxidize");
+    assert!(rust.contains("let n = f(g(x), y);"));
+    assert!(rust.contains("s.trim().len().min(3).max(n)"));
+}
+
+#[test]
+fn test_use_declarations_roundtrip() {
+    let code = r#"
+use std::collections::HashMap;
+use std::fmt::{self, Display as Show};
+use std::error::Error as StdError;
+
+fn answer() -> i32 {
+    42
+}
+"#;
+    test_roundtrip_content(code).expect("Use declarations should round-trip");
+
+    let iron = redox::transpile(code).expect("Use declarations should reduce");
+    assert!(iron.contains("import std collections HashMap"));
+    assert!(iron.contains("import std fmt containing user_self and Display as Show"));
+    assert!(!iron.contains("verbatim"));
+}
+
 // ============== CORPUS TESTS ==============
 // Tests using real extracted standard library code
 
+#[test]
+fn test_corpus_canonical_form_is_stable() {
+    let mut unstable = Vec::new();
+    for dir in ["tests/corpus/std", "tests/corpus/anyhow"] {
+        let dir = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), dir);
+        for entry in std::fs::read_dir(&dir).expect("Corpus directory should exist") {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "rs") {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            let Ok(canonical) = redox::canonicalize(&source) else {
+                continue;
+            };
+            match redox::canonicalize(&canonical) {
+                Ok(again) if again == canonical => {}
+                _ => unstable.push(path.display().to_string()),
+            }
+        }
+    }
+    assert!(
+        unstable.is_empty(),
+        "Canonical form changed on a second pass:\n{}",
+        unstable.join("\n")
+    );
+}
+
+#[test]
+fn test_corpus_printed_tree_matches_transpile() {
+    // The crate's own sources cover chars, text blocks and nested items
+    let mut mismatched = Vec::new();
+    for dir in ["tests/corpus/std", "tests/corpus/anyhow", "src"] {
+        let dir = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), dir);
+        for entry in std::fs::read_dir(&dir).expect("Corpus directory should exist") {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "rs") {
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            let (Ok(iron), Ok(tree)) = (redox::transpile(&source), redox::reduce_to_ast(&source))
+            else {
+                continue;
+            };
+            if redox::print_iron(&tree) != iron {
+                mismatched.push(path.display().to_string());
+            }
+        }
+    }
+    assert!(
+        mismatched.is_empty(),
+        "Printed reduce_to_ast tree differs from transpile output:\n{}",
+        mismatched.join("\n")
+    );
+}
+
 #[test]
 fn test_vec_basic_corpus_compiles() {
     // For now, just verify the corpus file exists and can be read
@@ -179,3 +663,145 @@ fn test_vec_basic_corpus_compiles() {
     // Don't assert - this is just to see what works
     let _ = iron;
 }
+
+#[test]
+fn test_mapped_std_types_roundtrip() {
+    let code = r#"
+use std::collections::HashMap;
+use std::rc::Rc;
+
+fn lookup(counts: HashMap<String, i32>, shared: Rc<String>) -> HashMap<String, i32> {
+    counts
+}
+"#;
+    test_roundtrip_content(code).expect("Mapped std types should round-trip");
+
+    let iron = redox::transpile(code).expect("Mapped std types should reduce");
+    assert!(iron.contains("hash map from string to i32"));
+    assert!(iron.contains("reference counted string"));
+
+    // Without the imports, oxidation adds them back
+    let iron = iron.replace("import std collections HashMap", "");
+    let iron = iron.replace("import std rc Rc", "");
+    let rust = redox::oxidize(&iron).expect("Mapped std types should oxidize");
+    assert!(rust.starts_with("use std::collections::HashMap;\nuse std::rc::Rc;\n\nfn lookup"));
+}
+
+#[test]
+fn test_typed_closure_params_roundtrip() {
+    let code = r#"
+fn scale(values: Vec<i64>) -> Vec<i64> {
+    let factor = |x: i64| x * 2;
+    values.into_iter().map(factor).collect()
+}
+"#;
+    test_roundtrip_content(code).expect("Typed closure parameters should round-trip");
+
+    let rust = redox::oxidize(&redox::transpile(code).expect("Closure should reduce"))
+        .expect("Closure should oxidize");
+    assert!(rust.contains("|x: i64|"));
+}
+
+#[test]
+fn test_user_prefixed_names_roundtrip() {
+    let code = r#"
+fn lookup(user_id: u32, user_self: u32) -> u32 {
+    let function = user_id + user_self;
+    function
+}
+"#;
+    test_roundtrip_content(code).expect("Prefixed names should round-trip");
+
+    let rust = redox::oxidize(&redox::transpile(code).expect("Names should reduce"))
+        .expect("Names should oxidize");
+    assert!(rust.contains("fn lookup(user_id: u32, user_self: u32) -> u32"));
+    assert!(rust.contains("let function = user_id + user_self;"));
+}
+
+#[test]
+fn test_enum_variant_construction_roundtrip() {
+    let code = r#"
+enum Shape {
+    Circle { radius: f64 },
+    Square(f64),
+}
+
+fn make(round: bool) -> Shape {
+    if round { Shape::Circle { radius: 1.0 } } else { Shape::Square(2.0) }
+}
+"#;
+    test_roundtrip_content(code).expect("Enum variant construction should round-trip");
+
+    let iron = redox::transpile(code).expect("Variants should reduce");
+    assert!(iron.contains("create Shape Circle with radius of 1.0"));
+}
+
+#[test]
+fn test_negative_literals_roundtrip() {
+    let code = r#"
+const LOW: i32 = -5;
+const HALF: f64 = -0.5;
+
+fn shift(x: i32) -> i32 {
+    let offsets = [-1, -2];
+    let scaled = x * -3 + offsets[0] + LOW;
+    -scaled
+}
+"#;
+    test_roundtrip_content(code).expect("Negative literals should round-trip");
+
+    let iron = redox::transpile(code).expect("Negative literals should reduce");
+    assert!(iron.contains("negative 0.5"));
+    assert!(iron.contains("array of negative 1 and negative 2"));
+    assert!(iron.contains("x times negative 3"));
+    assert!(iron.contains("negate scaled"));
+    assert!(redox::validate_iron(&iron));
+}
+
+#[test]
+fn test_nested_items_roundtrip() {
+    let code = r#"
+fn outer(x: i32) -> i32 {
+    const LIMIT: i32 = 10;
+    struct Pair {
+        a: i32,
+        b: i32,
+    }
+    fn helper(v: i32) -> i32 {
+        v * 2
+    }
+    let p = Pair { a: x, b: LIMIT };
+    helper(p.a) + p.b
+}
+"#;
+    test_roundtrip_content(code).expect("Nested items should round-trip");
+
+    let rust = redox::oxidize(&redox::transpile(code).expect("Nested items should reduce"))
+        .expect("Nested items should oxidize");
+    assert!(rust.contains("    const LIMIT: i32 = 10;\n    struct Pair {"));
+    assert!(rust.contains("    fn helper(v: i32) -> i32 {\n        v * 2\n    }\n"));
+}
+
+#[test]
+fn test_labeled_loops_roundtrip() {
+    let code = r#"
+fn find(limit: i32) -> i32 {
+    let mut n = 0;
+    'outer: loop {
+        loop {
+            n += 1;
+            if n > limit {
+                break 'outer;
+            }
+        }
+    }
+    n
+}
+"#;
+    test_roundtrip_content(code).expect("Labeled loops should round-trip");
+
+    let rust = redox::oxidize(&redox::transpile(code).expect("Labeled loops should reduce"))
+        .expect("Labeled loops should oxidize");
+    assert!(rust.contains("'outer: loop {"));
+    assert!(rust.contains("break 'outer;"));
+}